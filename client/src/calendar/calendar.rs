@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use bansheelong_shared_ui::style;
 use bansheelong_types::{ Date, IO, Weekday };
-use chrono::{ Datelike, Local, Timelike };
+use chrono::{ Local, Timelike };
 use iced_native::alignment;
 use iced_native::layout;
 use iced_native::renderer;
@@ -22,12 +22,18 @@ pub static ITEM_MARGIN_RIGHT: Vector = Vector::new(15.0, 0.0);
 pub static ITEM_PADDING_LEFT_BOTTOM: Vector = Vector::new(5.0, 0.0);
 pub static ITEM_PADDING_RIGHT_TOP: Vector = Vector::new(5.0, 1.0);
 pub static Y_OFFSET: f32 = 5.0;
+pub static DAY_LABEL_HEIGHT: f32 = 20.0;
+pub static DAY_SPACING: f32 = 2.0;
+pub static HEADER_HEIGHT: f32 = 42.0; // day label and planned meal band
+pub static TRACKED_WIDTH: f32 = 4.0;
 
 #[derive(Debug)]
 pub struct Calendar<Renderer: text::Renderer> {
+	days: u16,
 	font: Renderer::Font,
 	item_size: Option<u16>,
 	size: Option<u16>,
+	start_date: Date,
 	todos: Option<Arc<IO>>,
 	width: Length,
 }
@@ -35,14 +41,22 @@ pub struct Calendar<Renderer: text::Renderer> {
 impl<Renderer: text::Renderer> Calendar<Renderer> {
 	pub fn new(io: Option<Arc<IO>>) -> Self {
 		Self {
+			days: 1,
 			font: Default::default(),
 			item_size: None,
 			size: None,
+			start_date: Date::today(),
 			todos: io,
 			width: Length::Shrink,
 		}
 	}
 
+	// how many days to show side by side, starting at the start date
+	pub fn days(mut self, days: u16) -> Self {
+		self.days = days.max(1);
+		self
+	}
+
 	pub fn font(mut self, font: impl Into<Renderer::Font>) -> Self {
 		self.font = font.into();
 		self
@@ -58,10 +72,105 @@ impl<Renderer: text::Renderer> Calendar<Renderer> {
 		self
 	}
 
+	pub fn start_date(mut self, date: Date) -> Self {
+		self.start_date = date;
+		self
+	}
+
 	pub fn width(mut self, width: Length) -> Self {
 		self.width = width;
 		self
 	}
+
+	// draws as much of the text as fits in the bounds, ending it in ... if it had to be cut
+	fn draw_truncated(&self, renderer: &mut Renderer, style: &renderer::Style, text: &str, bounds: Rectangle) {
+		let size = self.item_size.unwrap_or(renderer.default_size());
+		let characters = text.chars().collect::<Vec<char>>();
+		if characters.is_empty() {
+			return;
+		}
+
+		let mut string = text.to_string();
+		let mut string_width = 0.0;
+		let mut string_height = 0.0;
+
+		let mut collector = String::from(characters[0]);
+		for i in 1..=characters.len() {
+			let truncated = if i == characters.len() {
+				collector.clone()
+			} else {
+				collector.clone() + &String::from("...")
+			};
+
+			let (width, height) = renderer.measure(
+				&truncated,
+				size,
+				self.font.clone(),
+				Size::new(
+					bounds.width - ITEM_PADDING_RIGHT_TOP.x - ITEM_PADDING_LEFT_BOTTOM.x,
+					bounds.height * 2.0
+				)
+			);
+
+			if height > bounds.height - ITEM_PADDING_LEFT_BOTTOM.y {
+				collector.pop();
+				string = collector.clone() + &String::from("...");
+				break;
+			}
+
+			string_width = width;
+			string_height = height;
+
+			if i < characters.len() {
+				collector.push(characters[i]);
+			}
+		}
+
+		let text_node = &layout::Node::new(Size::new(string_width, string_height));
+		let text_layout = layout::Layout::with_offset(
+			Vector::new(
+				bounds.x + ITEM_PADDING_LEFT_BOTTOM.x,
+				bounds.y + ITEM_PADDING_RIGHT_TOP.y
+			),
+			&text_node
+		);
+
+		iced_native::widget::text::draw(
+			renderer,
+			style,
+			text_layout,
+			&string,
+			self.font.clone(),
+			self.item_size,
+			None,
+			alignment::Horizontal::Left,
+			alignment::Vertical::Top
+		);
+	}
+}
+
+fn get_height() -> f32 {
+	(END_TIME - START_TIME + 1) as f32 * TEXT_SPACING.y + Y_OFFSET + HEADER_HEIGHT
+}
+
+fn get_day_label(date: Date, days: u16) -> String {
+	let weekday = match date.weekday() {
+		Some(Weekday::Monday) => "m",
+		Some(Weekday::Tuesday) => "t",
+		Some(Weekday::Wednesday) => "w",
+		Some(Weekday::Thursday) => "th",
+		Some(Weekday::Friday) => "f",
+		Some(Weekday::Saturday) => "s",
+		Some(Weekday::Sunday) => "su",
+		None => "",
+	};
+
+	// a week of columns is too narrow for the whole date
+	if days >= 7 {
+		format!("{}{}", weekday, date.day)
+	} else {
+		format!("{} {}/{}", weekday, date.month, date.day)
+	}
 }
 
 impl<Message, Renderer> Widget<Message, Renderer> for Calendar<Renderer>
@@ -73,7 +182,7 @@ where
 	}
 
 	fn height(&self) -> Length {
-		Length::Units((END_TIME - START_TIME + 1) as u16 * TEXT_SPACING.y as u16 + Y_OFFSET as u16)
+		Length::Units(get_height() as u16)
 	}
 
 	fn layout(
@@ -81,8 +190,7 @@ where
 		_renderer: &Renderer,
 		limits: &layout::Limits
 	) -> layout::Node {
-		let height = (END_TIME - START_TIME + 1) as u16 * TEXT_SPACING.y as u16 + Y_OFFSET as u16;
-		let size = limits.width(self.width).height(Length::Units(height)).resolve(Size::ZERO);
+		let size = limits.width(self.width).height(Length::Units(get_height() as u16)).resolve(Size::ZERO);
 		layout::Node::new(Size::new(size.width, size.height))
 	}
 
@@ -103,20 +211,8 @@ where
 		};
 
 		let time = Local::now();
-		let weekday = match time.weekday() {
-			chrono::Weekday::Mon => { Weekday::Monday },
-			chrono::Weekday::Tue => { Weekday::Tuesday },
-			chrono::Weekday::Wed => { Weekday::Wednesday },
-			chrono::Weekday::Thu => { Weekday::Thursday },
-			chrono::Weekday::Fri => { Weekday::Friday },
-			chrono::Weekday::Sat => { Weekday::Saturday },
-			chrono::Weekday::Sun => { Weekday::Sunday },
-		};
-		let current_date = Some(Date {
-			day: time.day() as u8,
-			month: time.month() as u8,
-			year: (time.year() % 100) as u8,
-		});
+		let today = Date::today();
+		let top = Y_OFFSET + HEADER_HEIGHT;
 
 		// draw background
 		renderer.fill_quad(
@@ -153,7 +249,7 @@ where
 				let text_layout = layout::Layout::with_offset(
 					Vector::new(
 						layout.position().x + TEXT_MARGIN.x,
-						layout.position().y + TEXT_MARGIN.y + Y_OFFSET
+						layout.position().y + TEXT_MARGIN.y + top
 					) + time_to_position(i, 0),
 					&text_node
 				);
@@ -172,128 +268,171 @@ where
 			}
 		}
 
-		let mut items = Vec::new();
-		if self.todos.is_some() && self.todos.as_ref().unwrap().todos_database.mapping.get(&current_date).is_some() {
-			for item in self.todos.as_ref().unwrap().todos_database.mapping.get(&current_date).unwrap().items.iter() {
-				items.push(item.clone());
-			}
-		}
+		let day_width = (layout.bounds().width - ITEM_MARGIN_LEFT.x - ITEM_MARGIN_RIGHT.x) / self.days as f32;
+		for index in 0..self.days {
+			let date = self.start_date.add_days(index as i64);
+			let day_x = layout.bounds().x + ITEM_MARGIN_LEFT.x + day_width * index as f32;
 
-		if self.todos.is_some() && self.todos.as_ref().unwrap().todos_database.mapping.get(&None).is_some() {
-			for item in self.todos.as_ref().unwrap().todos_database.mapping.get(&None).unwrap().items.iter() {
-				if item.time.is_some() && item.time.unwrap().day.is_some() && item.time.unwrap().day.unwrap() == weekday {
-					items.push(item.clone());
-				}
+			// day header, with today picked out
+			let label = get_day_label(date, self.days);
+			let size = self.size.unwrap_or(renderer.default_size());
+			let (width, height) = renderer.measure(&label, size, self.font.clone(), Size::new(day_width, DAY_LABEL_HEIGHT));
+			iced_native::widget::text::draw(
+				renderer,
+				style,
+				layout::Layout::with_offset(
+					Vector::new(day_x + ITEM_PADDING_LEFT_BOTTOM.x, layout.bounds().y + Y_OFFSET),
+					&layout::Node::new(Size::new(width, height))
+				),
+				&label,
+				self.font.clone(),
+				self.size,
+				Some(if date == today { style::TEXT_COLOR } else { style::DIM_TEXT_COLOR }),
+				alignment::Horizontal::Left,
+				alignment::Vertical::Top
+			);
+
+			// planned meals sit in a band under the header, leftovers included
+			let meal = self.todos.as_ref().and_then(|io| {
+				io.meals_database.planned_meal_mapping.values()
+					.find(|meal| meal.date <= date && date <= meal.last_covered_date())
+			});
+
+			if let Some(meal) = meal {
+				let bounds = Rectangle {
+					x: day_x,
+					y: layout.bounds().y + Y_OFFSET + DAY_LABEL_HEIGHT,
+					width: day_width - DAY_SPACING,
+					height: HEADER_HEIGHT - DAY_LABEL_HEIGHT - DAY_SPACING,
+				};
+
+				renderer.fill_quad(
+					iced_native::renderer::Quad {
+						bounds,
+						border_radius: 0.0,
+						border_width: 0.0,
+						border_color: Color::TRANSPARENT,
+					},
+					Background::Color(style::BACKGROUND_LIGHT_PURPLE)
+				);
+
+				self.draw_truncated(renderer, style, &meal.recipe.name, bounds);
 			}
-		}
 
-		if items.len() > 0 {
-			let mut color_index = 0;
-			for item in items {
-				let time = if item.time.is_none() {
-					continue;
+			// overlapping items are laid out side by side within the day
+			let scheduled_items = match self.todos.as_ref() {
+				Some(io) => io.todos_database.schedule(date),
+				None => Vec::new(),
+			};
+
+			for (color_index, scheduled) in scheduled_items.iter().enumerate() {
+				let time = scheduled.time;
+
+				// the calendar starts at 1am, and midnight is at the bottom of it
+				let (start_hour, start_minute) = if (time.start_hour as HourMinute) < START_TIME {
+					(START_TIME as u8, 0)
 				} else {
-					item.time.unwrap()
+					(time.start_hour, time.start_minute)
+				};
+
+				if (time.end_hour, time.end_minute) <= (start_hour, start_minute) {
+					continue;
+				}
+
+				let column_width = (day_width - DAY_SPACING) / scheduled.columns as f32;
+				let start_time = time_to_position(start_hour as HourMinute, start_minute as HourMinute);
+				let end_time = time_to_position(time.end_hour as HourMinute, time.end_minute as HourMinute);
+				let bounds = Rectangle {
+					x: day_x + column_width * scheduled.column as f32,
+					y: layout.bounds().y + start_time.y + top,
+					width: column_width,
+					height: end_time.y - start_time.y,
 				};
 
-				let start_time = time_to_position(time.start_hour as HourMinute, time.start_minute as HourMinute) + ITEM_MARGIN_LEFT;
-				let end_time = time_to_position(time.end_hour as HourMinute, time.end_minute as HourMinute) + ITEM_MARGIN_LEFT;
-				let item_width = layout.bounds().width - ITEM_MARGIN_LEFT.x - ITEM_MARGIN_RIGHT.x;
-				let item_height = end_time.y - start_time.y;
 				renderer.fill_quad(
 					iced_native::renderer::Quad {
-						bounds: Rectangle {
-							x: layout.bounds().x + start_time.x,
-							y: layout.bounds().y + start_time.y + Y_OFFSET,
-							width: item_width,
-							height: item_height,
-						},
+						bounds,
 						border_radius: 0.0,
 						border_width: 0.0,
 						border_color: Color::TRANSPARENT,
 					},
 					Background::Color(style::TODO_COLORS[color_index % style::TODO_COLORS.len()])
 				);
-				color_index += 1;
 
-				// figure out string truncation
-				let size = self.item_size.unwrap_or(renderer.default_size());
-				let mut string = if VALID_STARTING_CHARACTERS.contains(&item.description.chars().nth(0).unwrap())
-					&& item.description.chars().nth(1).unwrap() == ' '
+				let description = &scheduled.item.description;
+				let string = if VALID_STARTING_CHARACTERS.contains(&description.chars().nth(0).unwrap())
+					&& description.chars().nth(1) == Some(' ')
 				{
-					String::from(&item.description[2..])
+					&description[2..]
+				} else {
+					description.as_str()
+				};
+
+				self.draw_truncated(renderer, style, string, bounds);
+			}
+
+			// tracked time runs down the right edge of the day, next to what was planned
+			let sessions = match self.todos.as_ref() {
+				Some(io) => io.todos_database.get_sessions(date, time.naive_local()),
+				None => Vec::new(),
+			};
+
+			for (_, tracked) in sessions.iter() {
+				let (start_hour, start_minute) = if (tracked.start_hour as HourMinute) < START_TIME {
+					(START_TIME as u8, 0)
 				} else {
-					item.description
+					(tracked.start_hour, tracked.start_minute)
 				};
-				let mut string_width = 0.0;
-				let mut string_height = 0.0;
-
-				let mut collector = String::from(string.chars().nth(0).unwrap());
-				for i in 1..string.len() {
-					let truncated = collector.clone() + &String::from("...");
-					let (width, height) = renderer.measure(
-						&truncated,
-						size,
-						self.font.clone(),
-						Size::new(
-							item_width - ITEM_PADDING_RIGHT_TOP.x - ITEM_PADDING_LEFT_BOTTOM.x,
-							item_height * 2.0
-						)
-					);
-					
-					if height > item_height - ITEM_PADDING_LEFT_BOTTOM.y {
-						collector.pop();
-						string = collector.clone() + &String::from("...");
-						break;
-					}
-
-					string_width = width;
-					string_height = height;
-					
-					collector.extend(string.chars().nth(i));
+
+				if (tracked.end_hour, tracked.end_minute) <= (start_hour, start_minute) {
+					continue;
 				}
-				
-				// draw the truncated text
-				let text_node = &layout::Node::new(Size::new(string_width, string_height));
-				let text_layout = layout::Layout::with_offset(
-					Vector::new(
-						layout.bounds().x + start_time.x + ITEM_PADDING_LEFT_BOTTOM.x,
-						layout.bounds().y + start_time.y + ITEM_PADDING_RIGHT_TOP.y + Y_OFFSET
-					),
-					&text_node
-				);
 
-				iced_native::widget::text::draw(
-					renderer,
-					style,
-					text_layout,
-					&string,
-					self.font.clone(),
-					self.item_size,
-					None,
-					alignment::Horizontal::Left,
-					alignment::Vertical::Top
+				let start_time = time_to_position(start_hour as HourMinute, start_minute as HourMinute);
+				let end_time = time_to_position(tracked.end_hour as HourMinute, tracked.end_minute as HourMinute);
+				renderer.fill_quad(
+					iced_native::renderer::Quad {
+						bounds: Rectangle {
+							x: day_x + day_width - DAY_SPACING - TRACKED_WIDTH,
+							y: layout.bounds().y + start_time.y + top,
+							width: TRACKED_WIDTH,
+							height: (end_time.y - start_time.y).max(1.0),
+						},
+						border_radius: 0.0,
+						border_width: 0.0,
+						border_color: Color::TRANSPARENT,
+					},
+					Background::Color(style::TEXT_COLOR)
 				);
 			}
 		}
 
-		// time line
-		let current_hour = time.hour() as HourMinute;
-		let current_minute = time.minute() as HourMinute;
-		renderer.fill_quad(
-			iced_native::renderer::Quad {
-				bounds: Rectangle {
-					x: layout.bounds().x + time_to_position(current_hour, current_minute).x,
-					y: layout.bounds().y + time_to_position(current_hour, current_minute).y + Y_OFFSET,
-					width: layout.bounds().width,
-					height: 1.0,
+		// time line, across today's column if it is showing
+		let shown = (0..self.days as i64).find(|index| self.start_date.add_days(*index) == today);
+		if let Some(index) = shown {
+			let current_hour = time.hour() as HourMinute;
+			let current_minute = time.minute() as HourMinute;
+			let (x, width) = if self.days == 1 {
+				(layout.bounds().x, layout.bounds().width)
+			} else {
+				(layout.bounds().x + ITEM_MARGIN_LEFT.x + day_width * index as f32, day_width)
+			};
+
+			renderer.fill_quad(
+				iced_native::renderer::Quad {
+					bounds: Rectangle {
+						x,
+						y: layout.bounds().y + time_to_position(current_hour, current_minute).y + top,
+						width,
+						height: 1.0,
+					},
+					border_radius: 0.0,
+					border_width: 0.0,
+					border_color: Color::TRANSPARENT,
 				},
-				border_radius: 0.0,
-				border_width: 0.0,
-				border_color: Color::TRANSPARENT,
-			},
-			Background::Color(style::BACKGROUND_LIGHT_PURPLE)
-		);
+				Background::Color(style::BACKGROUND_LIGHT_PURPLE)
+			);
+		}
 	}
 }
 
@@ -1,27 +1,68 @@
 use std::sync::Arc;
 use std::time::{ Duration, Instant };
 
-use bansheelong_shared_ui::style;
-use bansheelong_types::IO;
+use bansheelong_shared_ui::{ constants, style };
+use bansheelong_types::{ Date, IO, Weekday };
 use chrono::{ Local, Timelike };
-use iced::scrollable;
-use iced::{ Command, Element, Length, Scrollable };
+use iced::{ button, scrollable };
+use iced::{ Alignment, Button, Column, Command, Element, Length, Row, Scrollable, Text, alignment };
 
 use crate::state::WINDOW_STATE;
 
 use super::calendar;
 
+// the views the day count button cycles through. a week always starts on monday
+const DAY_COUNTS: [u16; 3] = [1, 3, 7];
+
+// the first day shown when the view has this many days and includes today
+fn get_start_date(days: u16) -> Date {
+	let today = Date::today();
+	if days < 7 {
+		return today;
+	}
+
+	let mut start = today;
+	while start.weekday() != Some(Weekday::Monday) {
+		start = start.add_days(-1);
+	}
+
+	start
+}
+
+fn navigation_button<'a>(state: &'a mut button::State, icon: &str, message: Message) -> Button<'a, Message> {
+	Button::new(
+		state,
+		Text::new(icon)
+			.width(Length::Units(30))
+			.horizontal_alignment(alignment::Horizontal::Center)
+			.size(30)
+			.font(constants::ICONS)
+	)
+		.on_press(message)
+		.padding(0)
+		.style(style::DarkButton)
+}
+
 #[derive(Debug)]
 pub struct View {
 	database: Option<Arc<IO>>,
+	days: u16,
 	last_interaction: Option<Instant>,
 	scrollable_state: scrollable::State,
+	start_date: Date,
+
+	days_state: button::State,
+	next_state: button::State,
+	previous_state: button::State,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
+	Next,
+	Previous,
 	Scroll(f32),
 	Tick,
+	ToggleDays,
 	Update(Option<Arc<IO>>),
 }
 
@@ -29,27 +70,51 @@ impl View {
 	pub fn new() -> Self {
 		View {
 			database: None,
+			days: DAY_COUNTS[0],
 			last_interaction: None,
 			scrollable_state: scrollable::State::new(),
+			start_date: Date::today(),
+
+			days_state: button::State::new(),
+			next_state: button::State::new(),
+			previous_state: button::State::new(),
 		}
 	}
 
 	pub fn update(&mut self, message: Message) -> Command<Message> {
 		match message {
+			Message::Next => {
+				self.last_interaction = Some(Instant::now());
+				self.start_date = self.start_date.add_days(self.days as i64);
+			},
+			Message::Previous => {
+				self.last_interaction = Some(Instant::now());
+				self.start_date = self.start_date.add_days(-(self.days as i64));
+			},
+			Message::ToggleDays => {
+				self.last_interaction = Some(Instant::now());
+
+				let index = DAY_COUNTS.iter().position(|days| *days == self.days).unwrap_or(0);
+				self.days = DAY_COUNTS[(index + 1) % DAY_COUNTS.len()];
+				self.start_date = get_start_date(self.days);
+			},
 			Message::Scroll(_) => {
 				self.last_interaction = Some(Instant::now());
 				self.scrollable_state.set_force_disable(false);
 			},
 			Message::Tick => {
 				if self.last_interaction.is_none() || Instant::now() - self.last_interaction.unwrap() > Duration::from_secs(120) {
+					// wander back to the days around today once left alone
+					self.start_date = get_start_date(self.days);
+
 					let time = Local::now();
 					let current_hour = time.hour() as i32;
 					let current_minute = time.minute() as i32;
 					let current_seconds = time.second() as i32;
 
-					let height = (calendar::END_TIME - calendar::START_TIME + 1) as f32 * calendar::TEXT_SPACING.y + calendar::Y_OFFSET + 20.0 - WINDOW_STATE.height as f32;
+					let height = (calendar::END_TIME - calendar::START_TIME + 1) as f32 * calendar::TEXT_SPACING.y + calendar::Y_OFFSET + calendar::HEADER_HEIGHT + 20.0 - WINDOW_STATE.height as f32;
 					let time_height
-						= calendar::TEXT_SPACING.y * (current_hour - calendar::START_TIME) as f32 + calendar::TEXT_SPACING.y * (current_minute as f32 / 60.0)  + calendar::TEXT_SPACING.y * (current_seconds as f32 / 60.0 / 60.0) + calendar::Y_OFFSET + 20.0 - (WINDOW_STATE.height / 2) as f32;
+						= calendar::TEXT_SPACING.y * (current_hour - calendar::START_TIME) as f32 + calendar::TEXT_SPACING.y * (current_minute as f32 / 60.0)  + calendar::TEXT_SPACING.y * (current_seconds as f32 / 60.0 / 60.0) + calendar::Y_OFFSET + calendar::HEADER_HEIGHT + 20.0 - (WINDOW_STATE.height / 2) as f32;
 
 					self.scrollable_state.snap_to(time_height / height);
 				} else if self.last_interaction.is_some()
@@ -68,16 +133,48 @@ impl View {
 	}
 
 	pub fn view(&mut self) -> Element<Message> {
-		Scrollable::new(&mut self.scrollable_state)
+		let days_text = if self.days >= 7 {
+			String::from("week")
+		} else {
+			format!("{} day", self.days)
+		};
+
+		Column::new()
 			.width(Length::Units(355))
 			.height(Length::Fill)
-			.padding([20, 15, 20, 5])
-			.style(style::TodoScrollable)
 			.push(
-				calendar::Calendar::new(self.database.clone())
+				Row::new()
+					.padding([10, 15, 0, 5])
+					.align_items(Alignment::Center)
+					.push(navigation_button(&mut self.previous_state, "\u{e408}", Message::Previous))
+					.push(
+						Button::new(
+							&mut self.days_state,
+							Text::new(days_text)
+								.width(Length::Fill)
+								.horizontal_alignment(alignment::Horizontal::Center)
+						)
+							.on_press(Message::ToggleDays)
+							.width(Length::Fill)
+							.padding(0)
+							.style(style::DarkButton)
+					)
+					.push(navigation_button(&mut self.next_state, "\u{e409}", Message::Next))
+			)
+			.push(
+				Scrollable::new(&mut self.scrollable_state)
 					.width(Length::Fill)
+					.height(Length::Fill)
+					.padding([10, 15, 20, 5])
+					.style(style::TodoScrollable)
+					.push(
+						calendar::Calendar::new(self.database.clone())
+							.days(self.days)
+							.start_date(self.start_date)
+							.width(Length::Fill)
+					)
+					.on_scroll(move |offset| Message::Scroll(offset))
 			)
-			.on_scroll(move |offset| Message::Scroll(offset))
 			.into()
 	}
 }
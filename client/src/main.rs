@@ -1,6 +1,7 @@
 mod calendar;
 mod flavor;
 mod menu;
+mod snapshots;
 mod state;
 mod storage;
 mod todos;
@@ -9,8 +10,8 @@ mod weather;
 use std::sync::Arc;
 use std::time::{ Duration, Instant };
 
-use bansheelong_types::{ Date, Error, IO, MealsDatabase, PlannedMeal, PlannedMealsWriteLog, Resource, TodosDatabase, WriteDatabase, get_todos_host, get_todos_path, get_todos_port, read_database, write_database };
-use bansheelong_shared_ui::{ meals, style, ws };
+use bansheelong_types::{ Date, Error, IO, MealsDatabase, PlannedMeal, PlannedMealsWriteLog, Resource, TodoCompletion, TodosDatabase, TrackingEvent, WriteDatabase, get_todos_host, get_todos_path, get_todos_port, read_database, write_database };
+use bansheelong_shared_ui::{ meals, reminders, style, ws };
 use iced::alignment;
 use iced::executor;
 use iced::{ Application, Column, Command, Container, Element, Length, Row, Settings, Subscription, Text };
@@ -18,6 +19,7 @@ use iced::{ Application, Column, Command, Container, Element, Length, Row, Setti
 struct Window {
 	flavor: flavor::View,
 	menu: menu::View,
+	reminders: reminders::View,
 	storage: storage::View,
 	weather: weather::View,
 
@@ -29,15 +31,18 @@ struct Window {
 #[derive(Debug)]
 enum Message {
 	AddPlannedMeal(PlannedMeal),
+	CompleteTodo(TodoCompletion),
 	FetchedTodos(Result<(TodosDatabase, MealsDatabase), Error>),
 	FlavorMessage(flavor::Message),
 	MenuMessage(menu::Message),
 	Noop,
 	Refresh,
 	RefreshTodos,
+	ReminderMessage(reminders::Message),
 	RemovePlannedMeal(Date),
 	StorageMessage(storage::Message),
 	Tick,
+	TrackTodo(TrackingEvent),
 	UpdatePlannedMeal(PlannedMeal),
 	WeatherMessage(weather::Message),
 }
@@ -56,6 +61,7 @@ impl Application for Window {
 			Window {
 				flavor: flavor::View::new(),
 				menu: menu::View::new(),
+				reminders: reminders::View::new(),
 				storage: storage::View::new(),
 				weather: weather::View::new(),
 
@@ -94,14 +100,25 @@ impl Application for Window {
 						todos::Message::Update(None)
 					)),
 					ws::Event::Refresh => Self::Message::RefreshTodos,
+					ws::Event::Reminder(reminder) => Self::Message::ReminderMessage(reminders::Message::Received(reminder)),
 				}
 			}),
 			storage::connect().map(|event| {
 				match event {
+					storage::tcp::Event::CommandResponse(response) => Self::Message::StorageMessage(
+						storage::Message::ReceivedCommandResponse(response)
+					),
 					storage::tcp::Event::Error(_) => Self::Message::StorageMessage(storage::Message::Received(None)),
+					storage::tcp::Event::History(history) => Self::Message::StorageMessage(storage::Message::ReceivedHistory(history)),
 					storage::tcp::Event::Ignore => Self::Message::Noop,
 					storage::tcp::Event::InvalidateState => Self::Message::StorageMessage(storage::Message::Received(None)),
 					storage::tcp::Event::Message(data) => Self::Message::StorageMessage(storage::Message::Received(Some(data))),
+					storage::tcp::Event::SnapshotDiff(diff) => Self::Message::MenuMessage(menu::Message::SnapshotsMessage(
+						snapshots::Message::ReceivedDiff(diff)
+					)),
+					storage::tcp::Event::Snapshots(list) => Self::Message::MenuMessage(menu::Message::SnapshotsMessage(
+						snapshots::Message::ReceivedSnapshots(list)
+					)),
 				}
 			}),
 		])
@@ -124,6 +141,8 @@ impl Application for Window {
 							WriteDatabase::Partial {
 								planned_meals_remove_log: &Vec::new(),
 								planned_meals_write_log: &log,
+								todos_complete_log: &Vec::new(),
+								todos_tracking_log: &Vec::new(),
 								todos_write_log: &Vec::new(),
 							},
 							resource
@@ -135,7 +154,28 @@ impl Application for Window {
 					}),
 				])
 			},
-			Self::Message::FetchedTodos(result) => {
+			Self::Message::CompleteTodo(completion) => {
+				let log = self.io.as_ref().complete_todo_log(completion.date, completion.path, completion.completed);
+
+				let resource = self.io.resource.clone();
+				Command::perform(async move {
+					if let Err(error) = write_database(
+						WriteDatabase::Partial {
+							planned_meals_remove_log: &Vec::new(),
+							planned_meals_write_log: &Vec::new(),
+							todos_complete_log: &log,
+							todos_tracking_log: &Vec::new(),
+							todos_write_log: &Vec::new(),
+						},
+						resource
+					).await {
+						eprintln!("{:?}", error);
+					}
+				}, move |()| {
+					Self::Message::Refresh
+				})
+			},
+						Self::Message::FetchedTodos(result) => {
 				if let Err(error) = result {
 					eprintln!("{:?}", error);
 					
@@ -214,6 +254,11 @@ impl Application for Window {
 			Self::Message::RefreshTodos => {
 				Command::perform(read_database(self.io.resource.clone()), Self::Message::FetchedTodos)
 			},
+			Self::Message::ReminderMessage(message) => {
+				self.reminders.update(message).map(move |message| {
+					Self::Message::ReminderMessage(message)
+				})
+			},
 			Self::Message::RemovePlannedMeal(date) => {
 				let log = self.io.as_ref().remove_planned_meal_log(date.clone());
 
@@ -229,6 +274,8 @@ impl Application for Window {
 							WriteDatabase::Partial {
 								planned_meals_remove_log: &log,
 								planned_meals_write_log: &Vec::new(),
+								todos_complete_log: &Vec::new(),
+								todos_tracking_log: &Vec::new(),
 								todos_write_log: &Vec::new(),
 							},
 							resource
@@ -241,15 +288,35 @@ impl Application for Window {
 				])
 			},
 			Self::Message::StorageMessage(message) => {
-				self.storage.update(message).map(move |message| {
-					Self::Message::StorageMessage(message)
-				})
+				// restores come in with the status, but are shown in the snapshot browser
+				let mut commands = Vec::new();
+				if let storage::Message::Received(data) = &message {
+					let restores = data.as_ref().map_or(Vec::new(), |data| data.restores.clone());
+					commands.push(
+						self.menu.update(menu::Message::SnapshotsMessage(
+							snapshots::Message::ReceivedRestores(restores)
+						)).map(move |message| {
+							Self::Message::MenuMessage(message)
+						})
+					);
+				}
+
+				commands.push(
+					self.storage.update(message).map(move |message| {
+						Self::Message::StorageMessage(message)
+					})
+				);
+
+				Command::batch(commands)
 			},
 			Self::Message::Tick => {
 				let mut commands = vec![
 					self.menu.update(menu::Message::Tick).map(move |message| {
 						Self::Message::MenuMessage(message)
 					}),
+					self.reminders.update(reminders::Message::Tick).map(move |message| {
+						Self::Message::ReminderMessage(message)
+					}),
 					self.storage.update(storage::Message::Tick).map(move |message| {
 						Self::Message::StorageMessage(message)
 					}),
@@ -267,6 +334,8 @@ impl Application for Window {
 								WriteDatabase::Partial {
 									planned_meals_remove_log: &Vec::new(),
 									planned_meals_write_log: &log,
+									todos_complete_log: &Vec::new(),
+									todos_tracking_log: &Vec::new(),
 									todos_write_log: &Vec::new(),
 								},
 								resource
@@ -284,6 +353,27 @@ impl Application for Window {
 					Command::batch(commands)
 				}
 			},
+			Self::Message::TrackTodo(event) => {
+				let log = self.io.as_ref().track_log(event);
+
+				let resource = self.io.resource.clone();
+				Command::perform(async move {
+					if let Err(error) = write_database(
+						WriteDatabase::Partial {
+							planned_meals_remove_log: &Vec::new(),
+							planned_meals_write_log: &Vec::new(),
+							todos_complete_log: &Vec::new(),
+							todos_tracking_log: &log,
+							todos_write_log: &Vec::new(),
+						},
+						resource
+					).await {
+						eprintln!("{:?}", error);
+					}
+				}, move |()| {
+					Self::Message::Refresh
+				})
+			},
 			Self::Message::UpdatePlannedMeal(meal) => {
 				self.update_log.append(&mut self.io.as_ref().add_planned_meal_log(meal.clone()));
 				self.last_update_to_log = Instant::now();
@@ -305,7 +395,7 @@ impl Application for Window {
 	}
 
 	fn view(&mut self) -> Element<Self::Message> {
-		Container::new(
+		let content = Container::new(
 			Row::new()
 				.push( // weather
 					self.weather.view().map(move |message| {
@@ -331,6 +421,10 @@ impl Application for Window {
 							Self::Message::RemovePlannedMeal(date.clone())
 						} else if let menu::Message::MealsMessage(meals::Message::APIUpdatePlannedMeal(date)) = &message {
 							Self::Message::UpdatePlannedMeal(date.clone())
+						} else if let menu::Message::TodosMessage(todos::Message::APICompleteTodo(completion)) = &message {
+							Self::Message::CompleteTodo(completion.clone())
+						} else if let menu::Message::TodosMessage(todos::Message::APITrackTodo(event)) = &message {
+							Self::Message::TrackTodo(event.clone())
 						} else {
 							Self::Message::MenuMessage(message)
 						}
@@ -351,8 +445,21 @@ impl Application for Window {
 				)
 		)
 			.width(Length::Fill)
-			.style(style::Container)
-			.into()
+			.style(style::Container);
+
+		// reminders go in a banner across the top until they are snoozed or dismissed
+		if self.reminders.is_showing() {
+			Column::new()
+				.push(
+					self.reminders.view().map(move |message| {
+						Self::Message::ReminderMessage(message)
+					})
+				)
+				.push(content)
+				.into()
+		} else {
+			content.into()
+		}
 	}
 }
 
@@ -11,8 +11,9 @@ lazy_static! {
 		buttons: vec![
 			(String::from("Meal manager"), constants::Menu::Meals),
 			(String::from("Todo manager"), constants::Menu::Todos),
+			(String::from("Snapshot browser"), constants::Menu::Snapshots),
 		],
-		button_count: 2,
+		button_count: 3,
 		button_height: 30,
 		button_spacing: 15,
 		width: 740,
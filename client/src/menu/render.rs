@@ -3,6 +3,7 @@ use iced::{ Command, Container, Element, Length, Row };
 
 use crate::calendar;
 use crate::menu::MENU_STATE;
+use crate::snapshots;
 use crate::state::WINDOW_STATE;
 use crate::todos;
 
@@ -10,6 +11,7 @@ use crate::todos;
 pub struct View {
 	calendar: calendar::View,
 	meals: meals::View,
+	snapshots: snapshots::View,
 	todos: todos::View,
 	
 	menu: constants::Menu,
@@ -19,6 +21,7 @@ pub struct View {
 pub enum Message {
 	CalendarMessage(calendar::Message),
 	MealsMessage(meals::Message),
+	SnapshotsMessage(snapshots::Message),
 	Tick,
 	TodosMessage(todos::Message),
 }
@@ -40,12 +43,24 @@ impl View {
 				WINDOW_STATE,
 				[20, 15, 20, 0]
 			),
+			snapshots: snapshots::View::new(),
 			todos: todos::View::new(),
 			
 			menu: constants::Menu::Todos,
 		}
 	}
 
+	// the snapshot browser hears about being opened from other pages, so it can scroll to the top and fetch the list
+	fn open_snapshots(&mut self, menu: constants::Menu) -> Command<Message> {
+		if menu != constants::Menu::Snapshots {
+			return Command::none();
+		}
+
+		self.snapshots.update(snapshots::Message::MenuChange(menu)).map(move |message| {
+			Message::SnapshotsMessage(message)
+		})
+	}
+
 	pub fn update(&mut self, message: Message) -> Command<Message> {
 		match message {
 			Message::CalendarMessage(message) => {
@@ -54,12 +69,24 @@ impl View {
 				})
 			},
 			Message::MealsMessage(message) => {
+				let mut commands = Vec::new();
 				if let meals::Message::MenuChange(menu) = message {
 					self.menu = menu;
+					commands.push(self.open_snapshots(menu));
 				}
 				
-				self.meals.update(message).map(move |message| {
+				commands.push(self.meals.update(message).map(move |message| {
 					Message::MealsMessage(message)
+				}));
+				Command::batch(commands)
+			},
+			Message::SnapshotsMessage(message) => {
+				if let snapshots::Message::MenuChange(menu) = message {
+					self.menu = menu;
+				}
+
+				self.snapshots.update(message).map(move |message| {
+					Message::SnapshotsMessage(message)
 				})
 			},
 			Message::Tick => {
@@ -70,19 +97,25 @@ impl View {
 					self.meals.update(meals::Message::Tick).map(move |message| {
 						Message::MealsMessage(message)
 					}),
+					self.snapshots.update(snapshots::Message::Tick).map(move |message| {
+						Message::SnapshotsMessage(message)
+					}),
 					self.todos.update(todos::Message::Tick).map(move |message| {
 						Message::TodosMessage(message)
 					}),
 				])
 			},
 			Message::TodosMessage(message) => {
+				let mut commands = Vec::new();
 				if let todos::Message::MenuChange(menu) = message {
 					self.menu = menu;
+					commands.push(self.open_snapshots(menu));
 				}
 				
-				self.todos.update(message).map(move |message| {
+				commands.push(self.todos.update(message).map(move |message| {
 					Message::TodosMessage(message)
-				})
+				}));
+				Command::batch(commands)
 			},
 		}
 	}
@@ -112,6 +145,13 @@ impl View {
 						.width(Length::Units(MENU_STATE.width))
 				)
 			},
+			constants::Menu::Snapshots => {
+				Container::new(
+					self.snapshots.view().map(move |message| {
+						Message::SnapshotsMessage(message)
+					})
+				)
+			},
 		};
 
 		Container::new(menu)
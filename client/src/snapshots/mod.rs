@@ -0,0 +1,4 @@
+pub(crate) mod render;
+
+pub(crate) use render::Message;
+pub(crate) use render::View;
@@ -0,0 +1,475 @@
+use std::time::{ Duration, Instant };
+
+use bansheelong_shared_ui::{ Underline, constants, style };
+use bansheelong_types::{
+	Restore,
+	RestoreRequest,
+	RestoreState,
+	Snapshot,
+	SnapshotChange,
+	SnapshotDiff,
+	SnapshotDiffQuery,
+	SnapshotSource,
+	encode_storage_restore_request,
+	encode_storage_snapshot_diff_query,
+	encode_storage_snapshots_query,
+	get_storage_token,
+};
+use chrono::{ Local, TimeZone };
+use iced::{ Button, Color, Column, Command, Container, Element, Length, Row, Scrollable, Space, Text, alignment, button, scrollable };
+
+use crate::menu::MENU_STATE;
+use crate::state::WINDOW_STATE;
+use crate::storage::tcp::send;
+
+fn format_size(size: Option<u64>) -> String {
+	match size {
+		Some(size) if size >= 1_000_000_000 => format!("{:.1}G", size as f64 / 1_000_000_000.0),
+		Some(size) if size >= 1_000_000 => format!("{:.1}M", size as f64 / 1_000_000.0),
+		Some(size) => format!("{}K", size / 1_000),
+		None => String::from("?"),
+	}
+}
+
+fn format_time(time: i64) -> String {
+	Local.timestamp_opt(time, 0).single().map_or(String::from("?"), |time| time.format("%m/%d/%y %H:%M").to_string())
+}
+
+// sections of the page look like the days on the todo list
+fn get_item(column: Column<Message>) -> Container<Message> {
+	Container::new(
+		Container::new(column)
+			.width(Length::Fill)
+			.style(style::TodoItem)
+			.padding(10)
+	)
+		.width(Length::Fill)
+		.style(style::TodoItemContainer)
+		.padding([0, 0, 10, 0])
+}
+
+#[derive(Debug)]
+pub struct View {
+	button_states: Vec<button::State>,
+	change_button_states: Vec<button::State>,
+	compare_button_state: button::State,
+	diff: Option<SnapshotDiff>,
+	diff_pending: bool,
+	last_interaction: Option<Instant>,
+	refresh_button_state: button::State,
+	restores: Vec<Restore>,
+	scrollable_state: scrollable::State,
+	scroll_position: f32,
+	selected: Vec<String>, // names of up to two snapshots to compare, in the order they were picked
+	snapshot_button_states: Vec<button::State>,
+	snapshots: Option<Vec<Snapshot>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+	Compare,
+	MenuChange(constants::Menu),
+	ReceivedDiff(SnapshotDiff),
+	ReceivedRestores(Vec<Restore>),
+	ReceivedSnapshots(Vec<Snapshot>),
+	Refresh,
+	Restore(RestoreRequest),
+	Scroll(f32),
+	Select(String),
+	Tick,
+}
+
+impl View {
+	pub fn new() -> Self {
+		let scroll_position = MENU_STATE.get_area_size() as f32;
+
+		let mut scrollable_state = scrollable::State::new();
+		scrollable_state.snap_to_absolute(scroll_position);
+		View {
+			button_states: vec![button::State::new(); MENU_STATE.button_count as usize],
+			change_button_states: Vec::new(),
+			compare_button_state: button::State::new(),
+			diff: None,
+			diff_pending: false,
+			last_interaction: None,
+			refresh_button_state: button::State::new(),
+			restores: Vec::new(),
+			scrollable_state,
+			scroll_position,
+			selected: Vec::new(),
+			snapshot_button_states: Vec::new(),
+			snapshots: None,
+		}
+	}
+
+	// the selected snapshots, oldest first
+	fn get_selected(&self) -> Option<(&Snapshot, &Snapshot)> {
+		let snapshots = self.snapshots.as_ref()?;
+		let mut selected = snapshots.iter()
+			.filter(|snapshot| self.selected.contains(&snapshot.name))
+			.collect::<Vec<&Snapshot>>();
+
+		if selected.len() != 2 {
+			return None;
+		}
+
+		selected.sort_by_key(|snapshot| snapshot.created_at);
+		Some((selected[0], selected[1]))
+	}
+
+	pub fn update(&mut self, message: Message) -> Command<Message> {
+		match message {
+			Message::Compare => {
+				if let Some((from, to)) = self.get_selected() {
+					send(encode_storage_snapshot_diff_query(&SnapshotDiffQuery {
+						from: from.name.clone(),
+						path: String::new(),
+						secret: get_storage_token(),
+						to: to.name.clone(),
+					}));
+
+					self.diff = None;
+					self.diff_pending = true;
+				}
+
+				Command::none()
+			},
+			Message::MenuChange(menu) => {
+				self.scrollable_state.snap_to_absolute(MENU_STATE.get_area_size() as f32);
+				self.scroll_position = MENU_STATE.get_area_size() as f32;
+
+				// snapshots come and go with backups, so the list is fetched every time the page is opened
+				if menu == constants::Menu::Snapshots {
+					send(encode_storage_snapshots_query());
+				}
+
+				Command::none()
+			},
+			Message::ReceivedDiff(diff) => {
+				// a diff for snapshots that are not selected anymore is not what the page is showing
+				let is_selected = self.get_selected()
+					.is_some_and(|(from, to)| from.name == diff.query.from && to.name == diff.query.to);
+
+				if is_selected {
+					self.change_button_states = vec![button::State::new(); diff.changes.len()];
+					self.diff = Some(diff);
+					self.diff_pending = false;
+				}
+
+				Command::none()
+			},
+			Message::ReceivedRestores(restores) => {
+				self.restores = restores;
+				Command::none()
+			},
+			Message::ReceivedSnapshots(snapshots) => {
+				// snapshots that went away cannot stay selected
+				self.selected.retain(|name| snapshots.iter().any(|snapshot| &snapshot.name == name));
+				if self.get_selected().is_none() {
+					self.diff = None;
+					self.diff_pending = false;
+				}
+
+				self.snapshot_button_states = vec![button::State::new(); snapshots.len()];
+				self.snapshots = Some(snapshots);
+				Command::none()
+			},
+			Message::Refresh => {
+				send(encode_storage_snapshots_query());
+				Command::none()
+			},
+			Message::Restore(request) => {
+				send(encode_storage_restore_request(&request));
+				Command::none()
+			},
+			Message::Scroll(scroll) => {
+				self.last_interaction = Some(Instant::now());
+				self.scroll_position = scroll;
+				self.scrollable_state.set_force_disable(false);
+				Command::none()
+			},
+			Message::Select(name) => {
+				// picking a third snapshot lets go of the one picked first
+				if let Some(index) = self.selected.iter().position(|selected| selected == &name) {
+					self.selected.remove(index);
+				} else {
+					if self.selected.len() >= 2 {
+						self.selected.remove(0);
+					}
+
+					self.selected.push(name);
+				}
+
+				self.diff = None;
+				self.diff_pending = false;
+				Command::none()
+			},
+			Message::Tick => {
+				if let Some(last_interaction) = self.last_interaction {
+					if Instant::now() - last_interaction > Duration::from_secs(2)
+						&& self.scroll_position < MENU_STATE.get_area_size() as f32
+					{
+						self.scrollable_state.snap_to_absolute(MENU_STATE.get_area_size() as f32);
+						self.scroll_position = MENU_STATE.get_area_size() as f32;
+					}
+
+					if Instant::now() - last_interaction > Duration::from_secs(4) {
+						self.scrollable_state.set_force_disable(true);
+					}
+				}
+
+				Command::none()
+			},
+		}
+	}
+
+	pub fn view(&mut self) -> Element<Message> {
+		let selected = self.get_selected().map(|(from, to)| (from.name.clone(), to.name.clone()));
+
+		let mut scrollable = Scrollable::new(&mut self.scrollable_state)
+			.width(Length::Units(MENU_STATE.width))
+			.height(Length::Fill)
+			.padding([20, 15, 20, 0])
+			.style(style::TodoScrollable)
+			.on_scroll_absolute(move |offset| Message::Scroll(offset))
+			.min_height((MENU_STATE.get_area_size() + WINDOW_STATE.height) as u32);
+
+		// add buttons to top button menu thing
+		scrollable = scrollable.push(
+			self.button_states
+			.iter_mut()
+			.zip(MENU_STATE.buttons.iter())
+			.fold(
+				Column::new()
+					.spacing(MENU_STATE.button_spacing)
+					.padding([0, 0, 20, 0]),
+				|button_column, (state, (name, menu_type))| {
+					if menu_type != &constants::Menu::Snapshots {
+						button_column.push(
+							Button::new(
+								state,
+								Text::new(name.clone())
+									.width(Length::Fill)
+									.horizontal_alignment(alignment::Horizontal::Center)
+							)
+								.style(style::TodoMenuButton)
+								.width(Length::Fill)
+								.height(Length::Units(MENU_STATE.button_height))
+								.on_press(Message::MenuChange(menu_type.clone()))
+						)
+					} else {
+						button_column
+					}
+				}
+			)
+		);
+
+		// tapping two snapshots compares them
+		let mut list = Column::new()
+			.push(
+				Row::new()
+					.push(
+						Container::new(Underline::new("Snapshots"))
+							.width(Length::Fill)
+					)
+					.push(
+						Button::new(&mut self.refresh_button_state, Text::new("\u{e5d5}").font(constants::ICONS))
+							.on_press(Message::Refresh)
+							.style(style::DarkButton)
+							.padding(0)
+					)
+			)
+			.push(
+				Space::new(Length::Units(0), Length::Units(5))
+			)
+			.width(Length::Fill);
+
+		match self.snapshots.as_ref() {
+			Some(snapshots) if !snapshots.is_empty() => {
+				for (snapshot, state) in snapshots.iter().rev().zip(self.snapshot_button_states.iter_mut()) {
+					let is_selected = self.selected.contains(&snapshot.name);
+					let source = match snapshot.source {
+						SnapshotSource::Btrfs => "btrfs",
+						SnapshotSource::Zfs => "zfs",
+					};
+
+					let color = if snapshot.path.is_empty() { style::DIM_TEXT_COLOR } else { style::TEXT_COLOR };
+					let row = Row::new()
+						.push(
+							Text::new(if is_selected { "\u{e2e6}" } else { "\u{e836}" })
+								.font(constants::ICONS)
+								.width(Length::Units(18))
+						)
+						.push(
+							Text::new(format!("{} ", snapshot.name))
+								.font(constants::NOTOSANS_THIN)
+								.color(color)
+								.width(Length::Fill)
+						)
+						.push(
+							Text::new(format!("{} {} {}", format_time(snapshot.created_at), format_size(snapshot.size), source))
+								.font(constants::NOTOSANS_THIN)
+								.color(style::DIM_TEXT_COLOR)
+						);
+
+					// snapshots whose files cannot be reached cannot be compared or restored from
+					list = if snapshot.path.is_empty() {
+						list.push(row)
+					} else {
+						list.push(
+							Button::new(state, row)
+								.on_press(Message::Select(snapshot.name.clone()))
+								.style(style::DarkButton)
+								.padding(0)
+						)
+					};
+				}
+			},
+			Some(_) => {
+				list = list.push(Text::new("No snapshots").font(constants::NOTOSANS_THIN).color(style::DIM_TEXT_COLOR));
+			},
+			None => {
+				list = list.push(Text::new("Asking the storage server...").font(constants::NOTOSANS_THIN).color(style::DIM_TEXT_COLOR));
+			},
+		}
+
+		scrollable = scrollable.push(get_item(list));
+
+		if let Some((from, to)) = selected.as_ref() {
+			scrollable = scrollable.push(
+				Container::new(
+					Button::new(
+						&mut self.compare_button_state,
+						Text::new(format!("Compare {} to {}", from, to))
+							.width(Length::Fill)
+							.horizontal_alignment(alignment::Horizontal::Center)
+					)
+						.style(style::TodoMenuButton)
+						.width(Length::Fill)
+						.height(Length::Units(MENU_STATE.button_height))
+						.on_press(Message::Compare)
+				)
+					.padding([0, 0, 10, 0])
+			);
+		}
+
+		// tapping a change restores the file from whichever snapshot still has it
+		if self.diff_pending || self.diff.is_some() {
+			let mut changes = Column::new()
+				.push(
+					Underline::new("Changes")
+				)
+				.push(
+					Space::new(Length::Units(0), Length::Units(5))
+				)
+				.width(Length::Fill);
+
+			match self.diff.as_ref() {
+				Some(diff) if diff.error.is_some() => {
+					changes = changes.push(
+						Text::new(diff.error.clone().unwrap()).font(constants::NOTOSANS_THIN).color(style::RED_COLOR)
+					);
+				},
+				Some(diff) if diff.changes.is_empty() => {
+					changes = changes.push(Text::new("No changes").font(constants::NOTOSANS_THIN).color(style::DIM_TEXT_COLOR));
+				},
+				Some(diff) => {
+					for ((change, path), state) in diff.changes.iter().zip(self.change_button_states.iter_mut()) {
+						let (character, color, snapshot): (&str, Color, &String) = match change {
+							SnapshotChange::Added => ("+", style::GREEN_COLOR, &diff.query.to),
+							SnapshotChange::Changed => ("~", style::ORANGE_COLOR, &diff.query.to),
+							SnapshotChange::Removed => ("-", style::RED_COLOR, &diff.query.from),
+						};
+
+						let path = if diff.query.path.is_empty() {
+							path.clone()
+						} else {
+							format!("{}/{}", diff.query.path, path)
+						};
+
+						changes = changes.push(
+							Button::new(
+								state,
+								Row::new()
+									.push(
+										Container::new(Text::new(character).color(color))
+											.width(Length::Units(18))
+											.align_x(alignment::Horizontal::Center)
+											.padding([0, 4, 0, 0])
+									)
+									.push(
+										Text::new(format!("{} ", path))
+											.font(constants::NOTOSANS_THIN)
+											.width(Length::Fill)
+									)
+							)
+								.on_press(Message::Restore(RestoreRequest {
+									path,
+									secret: get_storage_token(),
+									snapshot: snapshot.clone(),
+								}))
+								.style(style::DarkButton)
+								.padding(0)
+						);
+					}
+
+					if diff.truncated {
+						changes = changes.push(
+							Text::new(format!("only the first {} changes are shown", diff.changes.len()))
+								.font(constants::NOTOSANS_THIN)
+								.color(style::DIM_TEXT_COLOR)
+						);
+					}
+				},
+				None => {
+					changes = changes.push(Text::new("Comparing...").font(constants::NOTOSANS_THIN).color(style::DIM_TEXT_COLOR));
+				},
+			}
+
+			scrollable = scrollable.push(get_item(changes));
+		}
+
+		// restores the storage server is working on, or finished recently
+		if !self.restores.is_empty() {
+			let mut restores = Column::new()
+				.push(
+					Underline::new("Restores")
+				)
+				.push(
+					Space::new(Length::Units(0), Length::Units(5))
+				)
+				.width(Length::Fill);
+
+			for restore in self.restores.iter().rev() {
+				let (state, color) = match restore.state {
+					RestoreState::Queued => ("queued", style::DIM_TEXT_COLOR),
+					RestoreState::Running => ("copying", style::TEXT_COLOR),
+					RestoreState::Done => ("done", style::GREEN_COLOR),
+					RestoreState::Failed => ("failed", style::RED_COLOR),
+				};
+
+				restores = restores.push(
+					Text::new(format!("{} {} from {}", state, restore.request.path, restore.request.snapshot))
+						.font(constants::NOTOSANS_THIN)
+						.color(color)
+				);
+
+				let detail = match restore.state {
+					RestoreState::Failed => restore.message.clone().unwrap_or_default(),
+					_ => format!("to {}", restore.destination),
+				};
+
+				restores = restores.push(
+					Text::new(detail)
+						.font(constants::NOTOSANS_THIN)
+						.color(style::DIM_TEXT_COLOR)
+						.size(16)
+				);
+			}
+
+			scrollable = scrollable.push(get_item(restores));
+		}
+
+		scrollable.into()
+	}
+}
@@ -1,4 +1,5 @@
 pub(crate) mod render;
+pub(crate) mod sparkline;
 pub(crate) mod tcp;
 
 pub(crate) use tcp::connect;
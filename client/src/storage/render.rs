@@ -1,37 +1,135 @@
+use std::collections::BTreeMap;
+use std::time::{ Duration, Instant };
+
 use bansheelong_shared_ui::style;
-use bansheelong_types::JobStatusFlags;
-use iced::{ Column, Command, Container, Element, Length, Row, Space, Text };
+use bansheelong_types::{
+	BackupJobState,
+	BackupKind,
+	JobStatusFlags,
+	SmartDrive,
+	StorageCommand,
+	StorageCommandRequest,
+	StorageCommandResponse,
+	StorageHistoryReply,
+	encode_storage_command,
+	get_storage_token,
+};
+use iced::{ Button, Color, Column, Command, Container, Element, Length, Row, Space, Text, button };
 
 use super::Data;
+use super::sparkline::Sparkline;
+use super::tcp::send;
+
+// how long the outcome of a command stays up
+const RESPONSE_DURATION: Duration = Duration::from_secs(10);
 
 #[derive(Debug)]
 pub struct View {
+	alert_button_states: Vec<button::State>,
+	backup_button_state: button::State,
 	data: Option<Data>,
 	ellipses: u8,
+	history: Option<StorageHistoryReply>,
+	next_command_id: u64,
+	pending_commands: BTreeMap<u64, String>, // descriptions of commands waiting on a response, by id
+	refresh_button_state: button::State,
+	response: Option<(String, Color, Instant)>,
+	scrub_button_state: button::State,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
+	Command(StorageCommand),
 	Received(Option<Data>),
+	ReceivedCommandResponse(StorageCommandResponse),
+	ReceivedHistory(StorageHistoryReply),
+	Scrub,
 	Tick,
 }
 
 impl View {
 	pub fn new() -> Self {
 		View {
+			alert_button_states: Vec::new(),
+			backup_button_state: button::State::new(),
 			data: None,
 			ellipses: 0,
+			history: None,
+			next_command_id: 0,
+			pending_commands: BTreeMap::new(),
+			refresh_button_state: button::State::new(),
+			response: None,
+			scrub_button_state: button::State::new(),
 		}
 	}
 
+	fn send_command(&mut self, command: StorageCommand) {
+		let id = self.next_command_id;
+		self.next_command_id += 1;
+
+		self.pending_commands.insert(id, command.get_description());
+		send(encode_storage_command(&StorageCommandRequest {
+			command,
+			id,
+			secret: get_storage_token(),
+		}));
+	}
+
 	pub fn update(&mut self, message: Message) -> Command<Message> {
 		match message {
+			Message::Command(command) => {
+				self.send_command(command);
+				Command::none()
+			},
 			Message::Received(data) => {
+				// history from a server we lost touch with is stale too, and it will not answer commands sent before
+				if data.is_none() {
+					self.history = None;
+					self.pending_commands.clear();
+				}
+
+				let alert_count = data.as_ref().map_or(0, |data| data.alerts.len());
+				if alert_count != self.alert_button_states.len() {
+					self.alert_button_states = vec![button::State::new(); alert_count];
+				}
+
 				self.data = data;
 				Command::none()
 			},
+			Message::ReceivedCommandResponse(response) => {
+				if let Some(description) = self.pending_commands.remove(&response.id) {
+					self.response = Some(match response.result {
+						Ok(message) => (message, style::GREEN_COLOR, Instant::now()),
+						Err(error) => (format!("{}: {}", description, error), style::RED_COLOR, Instant::now()),
+					});
+				}
+
+				Command::none()
+			},
+			Message::ReceivedHistory(history) => {
+				self.history = Some(history);
+				Command::none()
+			},
+			Message::Scrub => {
+				// every pool that is not already being scanned
+				let pools = self.data.iter()
+					.flat_map(|data| data.pools.iter())
+					.filter(|pool| !pool.scan.as_ref().is_some_and(|scan| scan.in_progress))
+					.map(|pool| pool.name.clone())
+					.collect::<Vec<String>>();
+
+				for pool in pools {
+					self.send_command(StorageCommand::StartScrub(pool));
+				}
+
+				Command::none()
+			},
 			Message::Tick => {
 				self.ellipses = (self.ellipses + 1) % 5;
+				if self.response.as_ref().is_some_and(|(_, _, time)| time.elapsed() > RESPONSE_DURATION) {
+					self.response = None;
+				}
+
 				Command::none()
 			},
 		}
@@ -49,6 +147,202 @@ impl View {
 			}
 		};
 
+		// drives zfs is unhappy with go underneath, by the serial number on their label
+		let count = |count: Option<u64>| count.map_or(String::from("?"), |count| count.to_string());
+		let mut details = Vec::new();
+
+		// backups the storage server runs itself, while they run or if the last one failed
+		for job in self.data.iter().flat_map(|data| data.jobs.iter()) {
+			match job.state {
+				BackupJobState::Running => details.push((
+					format!(
+						"{} backup{}",
+						job.kind.get_name(),
+						job.progress.map_or(String::new(), |progress| format!(" {:.0}%", progress))
+					),
+					style::TEXT_COLOR,
+				)),
+				BackupJobState::Failed => details.push((
+					format!(
+						"{} backup failed{}",
+						job.kind.get_name(),
+						job.exit_code.map_or(String::new(), |code| format!(" ({})", code))
+					),
+					style::RED_COLOR,
+				)),
+				_ => {},
+			}
+		}
+
+		for pool in self.data.iter().flat_map(|data| data.pools.iter()) {
+			if let Some(scan) = pool.scan.as_ref().filter(|scan| scan.in_progress) {
+				details.push((
+					format!(
+						"{} {:.1}%{}",
+						scan.kind,
+						scan.percent_done.unwrap_or(0.0),
+						scan.time_remaining.as_ref().map_or(String::new(), |remaining| format!(", {} left", remaining))
+					),
+					style::TEXT_COLOR,
+				));
+			}
+
+			for drive in pool.get_failing_drives() {
+				details.push((
+					if drive.state == "ONLINE" {
+						format!(
+							"{} {}r {}w {}c",
+							drive.get_serial(),
+							count(drive.read_errors),
+							count(drive.write_errors),
+							count(drive.checksum_errors)
+						)
+					} else {
+						format!("{} {}", drive.get_serial(), drive.state.to_lowercase())
+					},
+					style::RED_COLOR,
+				));
+			}
+		}
+
+		// drives smart is unhappy with, then how warm the rest are running
+		let smart = self.data.iter().flat_map(|data| data.smart.iter()).collect::<Vec<&SmartDrive>>();
+		for drive in smart.iter() {
+			let mut problems = Vec::new();
+			if drive.is_failing() {
+				problems.push(String::from("failing smart"));
+			}
+
+			if drive.get_bad_sectors() > 0 {
+				problems.push(format!("{} bad sectors", drive.get_bad_sectors()));
+			}
+
+			if !problems.is_empty() {
+				details.push((format!("{} {}", drive.serial, problems.join(", ")), style::RED_COLOR));
+			}
+		}
+
+		let temperatures = smart.iter().filter_map(|drive| drive.temperature);
+		if let (Some(coolest), Some(hottest)) = (temperatures.clone().min(), temperatures.max()) {
+			details.push((
+				if coolest == hottest {
+					format!("drives at {}°C", hottest)
+				} else {
+					format!("drives at {}-{}°C", coolest, hottest)
+				},
+				if hottest > 50 { style::RED_COLOR } else { style::TEXT_COLOR },
+			));
+		}
+
+		// alerts the storage server is raising, until someone acknowledges them
+		let mut alerts = Column::new();
+		let data_alerts = self.data.iter().flat_map(|data| data.alerts.iter());
+		for (alert, state) in data_alerts.zip(self.alert_button_states.iter_mut()) {
+			if alert.acknowledged {
+				continue;
+			}
+
+			alerts = alerts.push(
+				Row::new()
+					.push(
+						Text::new(alert.message.clone())
+							.color(style::RED_COLOR)
+							.width(Length::Fill)
+					)
+					.push(
+						Button::new(state, Text::new("ok"))
+							.on_press(Message::Command(StorageCommand::AcknowledgeAlert(alert.key.clone())))
+							.style(style::DarkButton)
+							.padding([0, 5])
+					)
+			);
+		}
+
+		// commands only go anywhere while we are connected
+		let connected = self.data.is_some();
+		let command_button = |state, label: &str, message: Message| {
+			let button = Button::new(state, Text::new(label.to_string()))
+				.style(style::DarkButton)
+				.padding([0, 5]);
+
+			if connected {
+				button.on_press(message)
+			} else {
+				button
+			}
+		};
+
+		let mut controls = Column::new()
+			.push(
+				Row::new()
+					.push(command_button(&mut self.refresh_button_state, "refresh", Message::Command(StorageCommand::RefreshStatus)))
+					.push(command_button(&mut self.scrub_button_state, "scrub", Message::Scrub))
+					.push(command_button(&mut self.backup_button_state, "backup", Message::Command(StorageCommand::RunBackup(BackupKind::Daily))))
+					.spacing(5)
+			);
+
+		if let Some((response, color, _)) = self.response.as_ref() {
+			controls = controls.push(Text::new(response.clone()).color(*color));
+		}
+
+		// 30 days of pool usage, along with how long until it fills up at the rate it has been going
+		let mut usage = Column::new();
+		if let Some(history) = self.history.as_ref().filter(|history| history.samples.len() > 1) {
+			usage = usage
+				.push(Space::new(Length::Units(0), Length::Units(5)))
+				.push(
+					Sparkline::new(history.samples.iter().map(|sample| sample.used_size as f32).collect())
+						.height(20)
+						.width(Length::Fill)
+				)
+				.push(
+					Text::new(
+						match history.days_until_full {
+							Some(days) if days < 365.0 * 10.0 => format!("full in {} days", days.round() as u64),
+							_ => String::from("not filling up"),
+						}
+					)
+						.color(if history.days_until_full.is_some_and(|days| days < 30.0) {
+							style::RED_COLOR
+						} else {
+							style::DIM_TEXT_COLOR
+						})
+				);
+		}
+
+		// a row for every pool and filesystem the storage server watches, older servers only tell us about two of them
+		let mut targets = Column::new();
+		if let Some(data) = self.data.as_ref() {
+			let rows = if data.usage.is_empty() {
+				vec![
+					(String::from("btrfs"), Some((data.btrfs_used_size, data.btrfs_total_size))),
+					(String::from("pool"), Some((data.used_size, data.total_size))),
+				]
+			} else {
+				data.usage.iter()
+					.map(|usage| {
+						(usage.target.get_name().to_string(), usage.error.is_none().then(|| (usage.used_size, usage.total_size)))
+					})
+					.collect::<Vec<(String, Option<(u64, u64)>)>>()
+			};
+
+			for (name, sizes) in rows {
+				targets = targets.push(
+					Row::new()
+						.push(Text::new(name).width(Length::Fill))
+						.push(
+							Text::new(sizes.map_or(String::from("?"), |(used_size, total_size)| {
+								format!("{}/{}", format_size(used_size), format_size(total_size))
+							}))
+								.color(match sizes {
+									Some((used_size, total_size)) if total_size == 0 || used_size as f64 / total_size as f64 <= 0.9 => style::TEXT_COLOR,
+									_ => style::RED_COLOR,
+								})
+						)
+				);
+			}
+		}
+
 		Container::new(
 			Container::new(
 				Column::new()
@@ -66,44 +360,9 @@ impl View {
 									)
 								)
 							)
-							.push(Space::new(Length::Units(18), Length::Units(0)))
-							.push(
-								Text::new(
-									format!(
-										"{}/{}",
-										if let None = self.data { // used size
-											"0T".to_string()
-										} else {
-											format_size(self.data.as_ref().unwrap().btrfs_used_size)
-										},
-										if let None = self.data { // total size
-											"0T".to_string()
-										} else {
-											format_size(self.data.as_ref().unwrap().btrfs_total_size)
-										}
-									)
-								)
-							)
-							.push(Space::new(Length::Units(18), Length::Units(0)))
-							.push(
-								Text::new(
-									format!(
-										"{}/{}",
-										if let None = self.data { // used size
-											"0T".to_string()
-										} else {
-											format_size(self.data.as_ref().unwrap().used_size)
-										},
-										if let None = self.data { // total size
-											"0T".to_string()
-										} else {
-											format_size(self.data.as_ref().unwrap().total_size)
-										}
-									)
-								)
-							)
 							.width(Length::Fill)
 					)
+					.push(targets)
 					.push(
 						Text::new(
 							if self.data.is_none() {
@@ -141,6 +400,15 @@ impl View {
 							}
 						)
 					)
+					.push(
+						details.into_iter().fold(Column::new(), |column, (detail, color)| {
+							column.push(Text::new(detail).color(color))
+						})
+					)
+					.push(alerts)
+					.push(usage)
+					.push(Space::new(Length::Units(0), Length::Units(5)))
+					.push(controls)
 					.width(Length::Units(240))
 			)
 				.padding(10)
@@ -0,0 +1,118 @@
+use bansheelong_shared_ui::style;
+use iced_native::layout;
+use iced_native::renderer;
+use iced_native::{ Background, Color, Element, Layout, Length, Point, Rectangle, Size, Widget };
+
+pub static BAR_SPACING: f32 = 1.0;
+
+// a bar per value, scaled between the smallest and largest value so small changes in a mostly full pool still show
+#[derive(Debug)]
+pub struct Sparkline {
+	color: Color,
+	height: u16,
+	values: Vec<f32>,
+	width: Length,
+}
+
+impl Sparkline {
+	pub fn new(values: Vec<f32>) -> Self {
+		Self {
+			color: style::TEXT_COLOR,
+			height: 24,
+			values,
+			width: Length::Fill,
+		}
+	}
+
+	pub fn color(mut self, color: Color) -> Self {
+		self.color = color;
+		self
+	}
+
+	pub fn height(mut self, height: u16) -> Self {
+		self.height = height;
+		self
+	}
+
+	pub fn width(mut self, width: Length) -> Self {
+		self.width = width;
+		self
+	}
+}
+
+impl<Message, Renderer> Widget<Message, Renderer> for Sparkline
+where
+	Renderer: renderer::Renderer,
+{
+	fn width(&self) -> Length {
+		self.width
+	}
+
+	fn height(&self) -> Length {
+		Length::Units(self.height)
+	}
+
+	fn layout(
+		&self,
+		_renderer: &Renderer,
+		limits: &layout::Limits
+	) -> layout::Node {
+		let size = limits.width(self.width).height(Length::Units(self.height)).resolve(Size::ZERO);
+		layout::Node::new(Size::new(size.width, size.height))
+	}
+
+	fn draw(
+		&self,
+		renderer: &mut Renderer,
+		_style: &renderer::Style,
+		layout: Layout<'_>,
+		_cursor_position: Point,
+		_viewport: &Rectangle
+	) {
+		if self.values.is_empty() {
+			return;
+		}
+
+		let bounds = layout.bounds();
+		let minimum = self.values.iter().cloned().fold(f32::INFINITY, f32::min);
+		let maximum = self.values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+		let bar_width = (bounds.width / self.values.len() as f32 - BAR_SPACING).max(1.0);
+
+		for (index, value) in self.values.iter().enumerate() {
+			// flat lines sit at half height, everything else gets at least a sliver
+			let fraction = if maximum > minimum {
+				0.1 + 0.9 * (value - minimum) / (maximum - minimum)
+			} else {
+				0.5
+			};
+
+			let height = bounds.height * fraction;
+			renderer.fill_quad(
+				iced_native::renderer::Quad {
+					bounds: Rectangle {
+						x: bounds.x + index as f32 * (bar_width + BAR_SPACING),
+						y: bounds.y + bounds.height - height,
+						width: bar_width,
+						height,
+					},
+					border_radius: 0.0,
+					border_width: 0.0,
+					border_color: Color::TRANSPARENT,
+				},
+				Background::Color(self.color)
+			);
+		}
+	}
+}
+
+impl<'a, Message, Renderer> From<Sparkline>
+	for Element<'a, Message, Renderer>
+where
+	Renderer: renderer::Renderer + 'a,
+{
+	fn from(
+		sparkline: Sparkline,
+	) -> Element<'a, Message, Renderer> {
+		Element::new(sparkline)
+	}
+}
@@ -1,36 +1,72 @@
-use bansheelong_types::{ JobStatusFlags, STORAGE_MESSAGE_COUNT, get_storage_port, get_storage_host };
+use std::sync::Mutex;
+use std::time::{ Instant, SystemTime, UNIX_EPOCH };
+
+use bansheelong_types::{
+	STORAGE_PROTOCOL_MAGIC,
+	Snapshot,
+	SnapshotDiff,
+	StorageCommandResponse,
+	StorageFrame,
+	StorageHistoryQuery,
+	StorageHistoryReply,
+	StorageStatus,
+	decode_storage_frame,
+	encode_storage_hello,
+	encode_storage_history_query,
+	get_storage_port,
+	get_storage_host,
+};
 use iced_native::subscription::{ self, Subscription };
+use lazy_static::lazy_static;
+use tokio::io::AsyncWriteExt;
 use tokio::time::{ Duration, sleep };
 use tokio::net::TcpStream;
+use tokio::sync::Notify;
+
+// the sparkline shows a point a day, so asking once an hour is plenty
+const HISTORY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+lazy_static! {
+	// frames the rest of the client wants sent, written out by the subscription the next time it gets to run
+	static ref OUTBOX: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+	static ref OUTBOX_NOTIFY: Notify = Notify::new();
+}
 
 #[derive(Debug)]
 enum State {
-	Connected(TcpStream, String),
+	Connected(TcpStream, Vec<u8>, Instant), // when history was last asked for
 	Disconnected,
 	WaitToConnect,
 }
 
-#[derive(Clone, Debug)]
-pub struct Data {
-	pub job_flags: JobStatusFlags,
-
-	pub used_size: u64,
-	pub total_size: u64,
-
-	pub btrfs_used_size: u64,
-	pub btrfs_total_size: u64,
-	pub btrfs_backup_count: u64,
-
-	pub dailies: u8,
-	pub weeklies: u8,
-}
+pub type Data = StorageStatus;
 
 #[derive(Clone, Debug)]
 pub enum Event {
+	CommandResponse(StorageCommandResponse),
 	Error(String),
+	History(StorageHistoryReply),
 	Ignore,
 	InvalidateState,
 	Message(Data),
+	SnapshotDiff(SnapshotDiff),
+	Snapshots(Vec<Snapshot>),
+}
+
+// queues a request for the storage server. requests made while disconnected go out once we connect again
+pub fn send(frame: Vec<u8>) {
+	OUTBOX.lock().unwrap().extend(frame);
+	OUTBOX_NOTIFY.notify_one();
+}
+
+// the last 30 days, a sample a day
+fn get_history_query() -> Vec<u8> {
+	let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs() as i64);
+	encode_storage_history_query(&StorageHistoryQuery {
+		from: now - 30 * 24 * 60 * 60,
+		resolution: 24 * 60 * 60,
+		to: now,
+	})
 }
 
 pub fn connect() -> Subscription<Event> {
@@ -41,92 +77,126 @@ pub fn connect() -> Subscription<Event> {
 		State::Disconnected,
 		|state| async move {
 			match state {
-				State::Connected(socket, mut buffer) => { // receive messages in a way that is friendly to iced subscriptions
-					if let Err(error) = socket.readable().await {
-						eprintln!("TCP error {:?}", error);
-						return (Some(Event::Error(String::from("Lost connection"))), State::WaitToConnect);
+				State::Connected(mut socket, mut buffer, mut last_query) => { // receive messages in a way that is friendly to iced subscriptions
+					if last_query.elapsed() >= HISTORY_INTERVAL {
+						if let Err(error) = socket.write_all(&get_history_query()).await {
+							eprintln!("TCP error {:?}", error);
+							return (Some(Event::Error(String::from("Lost connection"))), State::WaitToConnect);
+						}
+
+						last_query = Instant::now();
+					}
+
+					let outbox = std::mem::take(&mut *OUTBOX.lock().unwrap());
+					if !outbox.is_empty() {
+						if let Err(error) = socket.write_all(&outbox).await {
+							eprintln!("TCP error {:?}", error);
+							return (Some(Event::Error(String::from("Lost connection"))), State::WaitToConnect);
+						}
 					}
 
-					let mut packet = Vec::new();
-					match socket.try_read_buf(&mut packet) {
+					// wake up for new requests too, so they do not wait on the next status
+					tokio::select! {
+						result = socket.readable() => {
+							if let Err(error) = result {
+								eprintln!("TCP error {:?}", error);
+								return (Some(Event::Error(String::from("Lost connection"))), State::WaitToConnect);
+							}
+						},
+						_ = OUTBOX_NOTIFY.notified() => {
+							return (Some(Event::Ignore), State::Connected(socket, buffer, last_query));
+						},
+					}
+
+					match socket.try_read_buf(&mut buffer) {
 						Ok(0) => {
 							eprintln!("TCP error lost connection");
 							return (Some(Event::Error(String::from("Lost connection"))), State::WaitToConnect);
 						},
 						Err(ref error) if error.kind() == tokio::io::ErrorKind::WouldBlock => {
 							sleep(Duration::from_secs(1)).await;
-							return (Some(Event::Ignore), State::Connected(socket, buffer));
+							return (Some(Event::Ignore), State::Connected(socket, buffer, last_query));
 						},
 						Err(error) => {
 							eprintln!("TCP error {:?}", error);
 							return (Some(Event::Error(String::from("Lost connection"))), State::WaitToConnect);
 						},
-						Ok(_) => {
-							let message = match String::from_utf8(packet) {
-								Ok(string) => string,
+						Ok(_) => {},
+					}
+
+					// read everything that fully arrived, and keep the last status only. replies are handed over as soon as
+					// they are read, unless there is a status to hand over first, in which case they wait in the buffer
+					let mut latest = None;
+					while !buffer.is_empty() {
+						if buffer[0] == STORAGE_PROTOCOL_MAGIC[0] {
+							match decode_storage_frame(&buffer) {
+								Ok(Some((frame, _))) if latest.is_some() && !matches!(frame, StorageFrame::Status(_)) => break,
+								Ok(Some((frame, used))) => {
+									buffer.drain(..used);
+									let event = match frame {
+										StorageFrame::CommandResponse(response) => Event::CommandResponse(response),
+										StorageFrame::History(history) => Event::History(history),
+										StorageFrame::SnapshotDiff(diff) => Event::SnapshotDiff(diff),
+										StorageFrame::Snapshots(snapshots) => Event::Snapshots(snapshots),
+										StorageFrame::Status(status) => {
+											latest = Some(status);
+											continue;
+										},
+										_ => continue,
+									};
+
+									return (Some(event), State::Connected(socket, buffer, last_query));
+								},
+								Ok(None) => break,
 								Err(error) => {
-									eprintln!("TCP error {:?}", error);
+									eprintln!("TCP error {}", error);
 									return (Some(Event::Error(String::from("Malformed message"))), State::WaitToConnect);
 								},
+							}
+						} else { // servers from before the binary protocol, and the first message after connecting, are text lines
+							let end = match buffer.iter().position(|byte| *byte == b'\n') {
+								Some(end) => end,
+								None => break,
 							};
 
-							buffer.push_str(&message);
-						},
-					}
-
-					if buffer.contains("\n") {
-						let split = buffer.split("\n").filter(|s| s.len() > 0).collect::<Vec<&str>>();
-
-						let last_valid_message = if buffer.ends_with("\n") {
-							split.len() - 1
-						} else {
-							split.len() - 2
-						};
-
-						// parse the last message only
-						let parts: Vec<u64> = split[last_valid_message].split(" ").map(|x| {
-							match x.parse::<u64>() {
-								Err(_) => 0,
-								Ok(value) => value
+							let line = String::from_utf8_lossy(&buffer[..end]).to_string();
+							buffer.drain(..=end);
+							if line.trim().is_empty() {
+								continue;
 							}
-						}).collect();
-
-						if parts.len() != STORAGE_MESSAGE_COUNT as usize {
-							eprintln!("TCP error message not right length");
-							return (Some(Event::Error(String::from("Malformed message"))), State::WaitToConnect);
-						}
 
-						sleep(Duration::from_secs(1)).await;
-
-						return (
-							Some(Event::Message(Data {
-								job_flags: if JobStatusFlags::from_bits(parts[0] as u64).is_none() {
-									JobStatusFlags::GENERAL_ERROR
-								} else {
-									JobStatusFlags::from_bits(parts[0] as u64).unwrap()
+							match StorageStatus::from_legacy_line(&line) {
+								Some(status) => latest = Some(status),
+								None => {
+									eprintln!("TCP error message not right length");
+									return (Some(Event::Error(String::from("Malformed message"))), State::WaitToConnect);
 								},
+							}
+						}
+					}
 
-								used_size: parts[1],
-								total_size: parts[2],
-
-								btrfs_used_size: parts[3],
-								btrfs_total_size: parts[4],
-								btrfs_backup_count: parts[5],
-
-								dailies: parts[6] as u8,
-								weeklies: parts[7] as u8,
-							})),
-							State::Connected(socket, String::new())
-						);
-					} else {
-						return (Some(Event::Ignore), State::Connected(socket, buffer));
+					match latest {
+						Some(status) => {
+							sleep(Duration::from_secs(1)).await;
+							(Some(Event::Message(status)), State::Connected(socket, buffer, last_query))
+						},
+						None => (Some(Event::Ignore), State::Connected(socket, buffer, last_query)),
 					}
 				},
 				State::Disconnected => { // try connecting if we're disconnected
 					match TcpStream::connect(
 						format!("{}:{}", get_storage_host(), get_storage_port())
 					).await {
-						Ok(socket) => (Some(Event::InvalidateState), State::Connected(socket, String::new())),
+						Ok(mut socket) => { // ask for frames and history, older servers ignore this and keep sending text lines
+							let mut hello = encode_storage_hello();
+							hello.extend_from_slice(&get_history_query());
+							if let Err(error) = socket.write_all(&hello).await {
+								eprintln!("TCP error {:?}", error);
+								return (Some(Event::Error(String::from("Could not connect"))), State::WaitToConnect);
+							}
+
+							(Some(Event::InvalidateState), State::Connected(socket, Vec::new(), Instant::now()))
+						},
 						Err(error) => {
 							eprintln!("TCP error {:?}", error);
 							(Some(Event::Error(String::from("Could not connect"))), State::WaitToConnect)
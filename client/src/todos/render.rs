@@ -3,17 +3,48 @@ use std::sync::Arc;
 use std::time::{ Duration, Instant };
 
 use bansheelong_shared_ui::{ Underline, constants, style };
-use bansheelong_types::{ Date, Day, IO, PlannedMeal };
+use bansheelong_types::{ Date, Day, IO, Item, PlannedMeal, Priority, TodoCompletion, TrackingEvent };
 use chrono::{ Datelike, Local, TimeZone, Utc, Weekday };
-use iced::{ Button, Column, Command, Container, Element, Length, Row, Scrollable, Space, Text, alignment, button, scrollable };
+use iced::{ Button, Color, Column, Command, Container, Element, Length, Row, Scrollable, Space, Text, alignment, button, scrollable };
 
 use crate::menu::MENU_STATE;
 use crate::state::{ VALID_STARTING_CHARACTERS, WINDOW_STATE };
 
+fn get_priority_color(item: &Item, current_date: Option<Date>) -> Color {
+	// anything past its due date is as urgent as it gets
+	if item.due.is_some() && item.due < current_date {
+		return style::RED_COLOR;
+	}
+
+	match item.priority {
+		Priority::Low => style::DIM_TEXT_COLOR,
+		Priority::Normal => style::TEXT_COLOR,
+		Priority::High => style::ORANGE_COLOR,
+		Priority::Urgent => style::RED_COLOR,
+	}
+}
+
+// flattens an item's children depth first, along with how deep they are and the path of descriptions to them
+fn get_children<'a>(item: &'a Item, path: Vec<String>, depth: u16, children: &mut Vec<(&'a Item, u16, Vec<String>)>) {
+	for child in item.children.iter() {
+		let mut child_path = path.clone();
+		child_path.push(child.description.clone());
+
+		children.push((child, depth, child_path.clone()));
+		get_children(child, child_path, depth + 1, children);
+	}
+}
+
+fn count_children(item: &Item) -> usize {
+	item.children.iter().map(|child| 1 + count_children(child)).sum()
+}
+
 #[derive(Debug)]
 pub struct View {
 	button_states: Vec<button::State>,
+	child_button_states: Vec<button::State>,
 	database: Option<Arc<IO>>,
+	item_button_states: Vec<button::State>,
 	last_interaction: Option<Instant>,
 	scrollable_state: scrollable::State,
 	scroll_position: f32,
@@ -21,6 +52,8 @@ pub struct View {
 
 #[derive(Debug, Clone)]
 pub enum Message {
+	APICompleteTodo(TodoCompletion),
+	APITrackTodo(TrackingEvent),
 	MenuChange(constants::Menu),
 	Scroll(f32),
 	Tick,
@@ -35,7 +68,9 @@ impl View {
 		scrollable_state.snap_to_absolute(scroll_position);
 		View {
 			button_states: vec![button::State::new(); MENU_STATE.button_count as usize],
+			child_button_states: Vec::new(),
 			database: None,
+			item_button_states: Vec::new(),
 			last_interaction: None,
 			scrollable_state,
 			scroll_position,
@@ -44,6 +79,12 @@ impl View {
 
 	pub fn update(&mut self, message: Message) -> Command<Message> {
 		match message {
+			Message::APICompleteTodo(_) => { // handled by the window
+				Command::none()
+			},
+			Message::APITrackTodo(_) => { // handled by the window
+				Command::none()
+			},
 			Message::MenuChange(_) => {
 				self.scrollable_state.snap_to_absolute(MENU_STATE.get_area_size() as f32);
 				self.scroll_position = MENU_STATE.get_area_size() as f32;
@@ -72,6 +113,23 @@ impl View {
 				Command::none()
 			},
 			Message::Update(io) => {
+				// every child gets its own checkbox button
+				let child_count = io.as_ref().map_or(0, |io| {
+					io.todos_database.mapping.values()
+						.flat_map(|day| day.items.iter())
+						.map(count_children)
+						.sum()
+				});
+
+				// tapping an item starts or stops tracking time against it
+				let item_count = io.as_ref().map_or(0, |io| {
+					io.todos_database.mapping.values()
+						.map(|day| day.items.len())
+						.sum()
+				});
+
+				self.child_button_states = vec![button::State::new(); child_count];
+				self.item_button_states = vec![button::State::new(); item_count];
 				self.database = io;
 				Command::none()
 			},
@@ -188,6 +246,12 @@ impl View {
 			}
 		}
 
+		let now = time.naive_local();
+		let tracked = self.database.as_ref().unwrap().todos_database.get_running_session()
+			.map(|session| session.description.clone());
+
+		let mut child_button_states = self.child_button_states.iter_mut();
+		let mut item_button_states = self.item_button_states.iter_mut();
 		for (date, (day, meal)) in new_mapping.iter() {
 			let mut column = Column::new()
 				.push(
@@ -199,10 +263,12 @@ impl View {
 				.width(Length::Fill);
 			
 			if let Some(day) = day {
+				let items = day.items_by_priority();
+
 				// find the last valid index in the list
 				let mut last_index = -1;
 				let mut index = 0;
-				for item in day.items.iter() {
+				for item in items.iter() {
 					if item.description != "" && !has_time_day(&item) {
 						last_index = index;
 					}
@@ -216,7 +282,7 @@ impl View {
 				index = 0;
 				let mut color_index = 0;
 
-				column = day.items.iter().fold(
+				column = items.iter().fold(
 					column,
 					|acc, item| {
 						index += 1;
@@ -249,26 +315,76 @@ impl View {
 								Container::new(Space::new(Length::Units(0), Length::Units(0)))
 							};
 
-							acc.push(
-								Row::new()
-									.push(
-										circle_or_dash
-									)
-									.push(
-										Text::new(format!(
-											"{} ",
-											if VALID_STARTING_CHARACTERS.contains(&item.description.chars().nth(0).unwrap())
-												&& item.description.chars().nth(1).unwrap() == ' '
-											{
-												String::from(&item.description[2..])
-											} else {
-												item.description.clone()
-											}
-										))
-											.font(constants::NOTOSANS_THIN)
-											.width(Length::Fill)
+							let text = if item.children.is_empty() {
+								format!("{} ", item.get_text())
+							} else {
+								let (done, total) = item.progress();
+								format!("{} ({}/{}) ", item.get_text(), done, total)
+							};
+
+							let is_tracked = tracked.as_ref() == Some(&item.description);
+							let row = Row::new()
+								.push(
+									circle_or_dash
+								)
+								.push(
+									Text::new(text)
+										.font(constants::NOTOSANS_THIN)
+										.color(get_priority_color(item, current_date))
+										.width(Length::Fill)
+								)
+								.push(
+									Text::new(if is_tracked { "\u{e425}" } else { "" })
+										.font(constants::ICONS)
+								);
+
+							let acc = match item_button_states.next() {
+								Some(state) if item.description != "" => acc.push(
+									Button::new(state, row)
+										.on_press(Message::APITrackTodo(if is_tracked {
+											TrackingEvent::stop(now)
+										} else {
+											TrackingEvent::start(item.description.clone(), now)
+										}))
+										.style(style::DarkButton)
+										.padding(0)
+								),
+								_ => acc.push(row),
+							};
+
+							// add children as checkboxes underneath their parent
+							let mut children = Vec::new();
+							get_children(item, vec![item.description.clone()], 1, &mut children);
+
+							children.into_iter()
+								.zip(&mut child_button_states)
+								.fold(acc, |acc, ((child, depth, path), state)| {
+									acc.push(
+										Button::new(
+											state,
+											Row::new()
+												.push(
+													Text::new(if child.is_done() { "\u{e2e6}" } else { "\u{e836}" })
+														.font(constants::ICONS)
+														.width(Length::Units(18))
+												)
+												.push(
+													Text::new(format!("{} ", child.get_text()))
+														.font(constants::NOTOSANS_THIN)
+														.color(get_priority_color(child, current_date))
+														.width(Length::Fill)
+												)
+												.padding([0, 0, 0, 18 * depth])
+										)
+											.on_press(Message::APICompleteTodo(TodoCompletion {
+												completed: !child.completed,
+												date: day.date,
+												path,
+											}))
+											.style(style::DarkButton)
+											.padding(0)
 									)
-							)
+								})
 						}
 					}
 				);
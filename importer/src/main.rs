@@ -0,0 +1,66 @@
+use bansheelong_types::{ IO, Resource, get_todos_host, get_todos_path, get_todos_port, import_recipes };
+
+#[tokio::main]
+async fn main() {
+	let file_name = match std::env::args().nth(1) {
+		Some(file_name) => file_name,
+		None => {
+			eprintln!("usage: bansheelong_importer <recipe or .ics file> [database]");
+			std::process::exit(1);
+		},
+	};
+
+	// import into the todos server unless we're given a local database
+	let resource = Resource {
+		reference: match std::env::args().nth(2) {
+			Some(database) => database,
+			None => format!("https://{}{}{}", get_todos_host(), get_todos_port(), get_todos_path()),
+		},
+	};
+
+	let contents = match std::fs::read_to_string(&file_name) {
+		Ok(contents) => contents,
+		Err(error) => {
+			eprintln!("Could not read '{}': {:?}", file_name, error);
+			std::process::exit(1);
+		},
+	};
+
+	// calendars become todos, anything else is a recipe file
+	if file_name.ends_with(".ics") {
+		let mut io = IO {
+			resource,
+			..IO::default()
+		};
+
+		let result = match io.read_database().await {
+			Ok(_) => io.import_ical(&contents),
+			Err(error) => Err(error),
+		};
+
+		match result {
+			Ok(count) => {
+				if let Err(error) = io.sync().await {
+					eprintln!("{:?}", error);
+					std::process::exit(1);
+				}
+
+				println!("Imported {} events", count);
+			},
+			Err(error) => {
+				eprintln!("{:?}", error);
+				std::process::exit(1);
+			},
+		}
+
+		return;
+	}
+
+	match import_recipes(contents, resource).await {
+		Ok(count) => println!("Imported {} recipes", count),
+		Err(error) => {
+			eprintln!("{:?}", error);
+			std::process::exit(1);
+		},
+	}
+}
@@ -5,12 +5,13 @@ use std::sync::Arc;
 use std::time::{ Duration, Instant };
 
 use bansheelong_types::{ Date, Error, IO, MealsDatabase, PlannedMeal, PlannedMealsWriteLog, Resource, TodosDatabase, WriteDatabase, get_todos_host, get_todos_path, get_todos_port, read_database, write_database };
-use bansheelong_shared_ui::{ meals, style, ws };
+use bansheelong_shared_ui::{ meals, reminders, style, ws };
 use iced::executor;
-use iced::{ Application, Command, Container, Element, Length, Row, Settings, Subscription };
+use iced::{ Application, Column, Command, Container, Element, Length, Row, Settings, Subscription };
 
 struct Window {
 	menu: menu::View,
+	reminders: reminders::View,
 
 	last_update_to_log: Instant,
 	io: Arc<IO>,
@@ -23,6 +24,7 @@ enum Message {
 	FetchedTodos(Result<(TodosDatabase, MealsDatabase), Error>),
 	MenuMessage(menu::Message),
 	Refresh,
+	ReminderMessage(reminders::Message),
 	RemovePlannedMeal(Date),
 	Tick,
 	UpdatePlannedMeal(PlannedMeal),
@@ -41,6 +43,7 @@ impl Application for Window {
 		(
 			Window {
 				menu: menu::View::new(),
+				reminders: reminders::View::new(),
 
 				last_update_to_log: Instant::now(),
 				io: Arc::new(IO {
@@ -72,6 +75,7 @@ impl Application for Window {
 						meals::Message::Update(None)
 					)),
 					ws::Event::Refresh => Self::Message::Refresh,
+					ws::Event::Reminder(reminder) => Self::Message::ReminderMessage(reminders::Message::Received(reminder)),
 				}
 			}),
 		])
@@ -94,6 +98,8 @@ impl Application for Window {
 							WriteDatabase::Partial {
 								planned_meals_remove_log: &Vec::new(),
 								planned_meals_write_log: &log,
+								todos_complete_log: &Vec::new(),
+								todos_tracking_log: &Vec::new(),
 								todos_write_log: &Vec::new(),
 							},
 							resource
@@ -151,6 +157,11 @@ impl Application for Window {
 			Self::Message::Refresh => {
 				Command::perform(read_database(self.io.resource.clone()), Self::Message::FetchedTodos)
 			},
+			Self::Message::ReminderMessage(message) => {
+				self.reminders.update(message).map(move |message| {
+					Self::Message::ReminderMessage(message)
+				})
+			},
 			Self::Message::RemovePlannedMeal(date) => {
 				let log = self.io.as_ref().remove_planned_meal_log(date.clone());
 
@@ -166,6 +177,8 @@ impl Application for Window {
 							WriteDatabase::Partial {
 								planned_meals_remove_log: &log,
 								planned_meals_write_log: &Vec::new(),
+								todos_complete_log: &Vec::new(),
+								todos_tracking_log: &Vec::new(),
 								todos_write_log: &Vec::new(),
 							},
 							resource
@@ -182,6 +195,9 @@ impl Application for Window {
 					self.menu.update(menu::Message::Tick).map(move |message| {
 						Self::Message::MenuMessage(message)
 					}),
+					self.reminders.update(reminders::Message::Tick).map(move |message| {
+						Self::Message::ReminderMessage(message)
+					}),
 				];
 
 				if Instant::now() - self.last_update_to_log > Duration::from_secs(5) && self.update_log.len() > 0 {
@@ -193,6 +209,8 @@ impl Application for Window {
 								WriteDatabase::Partial {
 									planned_meals_remove_log: &Vec::new(),
 									planned_meals_write_log: &log,
+									todos_complete_log: &Vec::new(),
+									todos_tracking_log: &Vec::new(),
 									todos_write_log: &Vec::new(),
 								},
 								resource
@@ -226,7 +244,7 @@ impl Application for Window {
 	}
 
 	fn view(&mut self) -> Element<Self::Message> {
-		Container::new(
+		let content = Container::new(
 			Row::new()
 				.push(
 					self.menu.view().map(move |message| {
@@ -244,8 +262,21 @@ impl Application for Window {
 		)
 			.width(Length::Fill)
 			.padding([0, 5, 0, 20])
-			.style(style::Container)
-			.into()
+			.style(style::Container);
+
+		// reminders go in a banner across the top until they are snoozed or dismissed
+		if self.reminders.is_showing() {
+			Column::new()
+				.push(
+					self.reminders.view().map(move |message| {
+						Self::Message::ReminderMessage(message)
+					})
+				)
+				.push(content)
+				.into()
+		} else {
+			content.into()
+		}
 	}
 }
 
@@ -0,0 +1,330 @@
+use std::sync::Arc;
+use std::convert::Infallible;
+use tokio::sync::{ Mutex, mpsc };
+use warp::Filter;
+use warp::http::{ HeaderMap, Method, StatusCode };
+use warp::hyper::Body;
+
+use crate::types;
+
+use bansheelong_types::{
+	Date,
+	IO,
+	Item,
+	fnv1a,
+	get_todos_secret,
+	item_etag,
+	item_to_ical,
+	item_uid,
+	parse_ical_item,
+	to_ical,
+};
+
+// the principal and calendar home live at the root, the one calendar we have lives under it
+const ROOT: &str = "/caldav/";
+const CALENDAR: &str = "/caldav/todos/";
+
+type Response = warp::reply::Response;
+
+fn respond(status: StatusCode, content_type: &str, body: String) -> Response {
+	warp::http::Response::builder()
+		.status(status)
+		.header("content-type", content_type)
+		.header("dav", "1, calendar-access")
+		.body(Body::from(body))
+		.unwrap()
+}
+
+fn empty(status: StatusCode) -> Response {
+	respond(status, "text/plain", String::new())
+}
+
+// calendar apps only know how to send a username and password, so the password is our secret
+fn is_authorized(authorization: Option<String>) -> bool {
+	let credentials = authorization
+		.as_deref()
+		.and_then(|header| header.strip_prefix("Basic "))
+		.and_then(|encoded| base64::decode(encoded.trim()).ok())
+		.and_then(|decoded| String::from_utf8(decoded).ok());
+
+	match credentials.as_deref().and_then(|credentials| credentials.split_once(':')) {
+		Some((_, password)) => password == get_todos_secret(),
+		None => false,
+	}
+}
+
+fn escape_xml(text: &str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+}
+
+fn get_items(io: &IO) -> Vec<(Option<Date>, &Item)> {
+	io.todos_database.mapping.iter()
+		.flat_map(|(date, day)| day.items.iter().map(move |item| (*date, item)))
+		.filter(|(_, item)| !item.get_text().trim().is_empty())
+		.collect()
+}
+
+// changes whenever any item in the calendar does
+fn get_ctag(items: &[(Option<Date>, &Item)]) -> String {
+	let etags = items.iter()
+		.map(|(date, item)| item_etag(date, item))
+		.collect::<String>();
+
+	format!("{:016x}", fnv1a(etags.as_bytes()))
+}
+
+fn to_response(href: &str, properties: &str) -> String {
+	format!(
+		"<d:response><d:href>{}</d:href><d:propstat><d:prop>{}</d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+		escape_xml(href),
+		properties
+	)
+}
+
+fn multistatus(responses: Vec<String>) -> Response {
+	respond(
+		StatusCode::MULTI_STATUS,
+		"application/xml; charset=utf-8",
+		format!(
+			"<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<d:multistatus xmlns:d=\"DAV:\" xmlns:c=\"urn:ietf:params:xml:ns:caldav\" xmlns:cs=\"http://calendarserver.org/ns/\">{}</d:multistatus>",
+			responses.join("")
+		)
+	)
+}
+
+fn root_response() -> String {
+	to_response(ROOT, &format!(
+		"<d:resourcetype><d:collection/></d:resourcetype>\
+		<d:displayname>bansheelong</d:displayname>\
+		<d:current-user-principal><d:href>{ROOT}</d:href></d:current-user-principal>\
+		<d:principal-URL><d:href>{ROOT}</d:href></d:principal-URL>\
+		<c:calendar-home-set><d:href>{ROOT}</d:href></c:calendar-home-set>"
+	))
+}
+
+fn calendar_response(items: &[(Option<Date>, &Item)]) -> String {
+	let ctag = get_ctag(items);
+	to_response(CALENDAR, &format!(
+		"<d:resourcetype><d:collection/><c:calendar/></d:resourcetype>\
+		<d:displayname>todos</d:displayname>\
+		<d:current-user-principal><d:href>{ROOT}</d:href></d:current-user-principal>\
+		<c:supported-calendar-component-set><c:comp name=\"VEVENT\"/><c:comp name=\"VTODO\"/></c:supported-calendar-component-set>\
+		<cs:getctag>{ctag}</cs:getctag>\
+		<d:sync-token>{ctag}</d:sync-token>"
+	))
+}
+
+fn item_response(date: &Option<Date>, item: &Item, with_data: bool) -> String {
+	let data = if with_data {
+		format!("<c:calendar-data>{}</c:calendar-data>", escape_xml(&item_to_ical(date, item, Date::today())))
+	} else {
+		String::new()
+	};
+
+	to_response(
+		&format!("{}{}.ics", CALENDAR, item_uid(date, item)),
+		&format!(
+			"<d:resourcetype/><d:getcontenttype>text/calendar; charset=utf-8</d:getcontenttype><d:getetag>\"{}\"</d:getetag>{}",
+			item_etag(date, item),
+			data
+		)
+	)
+}
+
+// pulls the hrefs out of a calendar-multiget body, whatever namespace prefix the client picked
+fn get_requested_hrefs(body: &str) -> Vec<String> {
+	let mut hrefs = Vec::new();
+	let mut rest = body;
+	while let Some(start) = rest.find("href>") {
+		rest = &rest[start + 5..];
+		if let Some(end) = rest.find("</") {
+			hrefs.push(rest[..end].trim().replace("&amp;", "&"));
+			rest = &rest[end..];
+		}
+	}
+
+	hrefs
+}
+
+fn get_uid_from_href(href: &str) -> Option<&str> {
+	href.rsplit('/').next()?.strip_suffix(".ics")
+}
+
+// whether an If-Match header lets us change the item, given the item's current etag if it exists
+fn is_match(if_match: &str, etag: Option<String>) -> bool {
+	match etag {
+		Some(etag) => if_match.trim() == "*" || if_match.split(',').any(|tag| {
+			tag.trim().trim_start_matches("W/").trim_matches('"') == etag
+		}),
+		None => false,
+	}
+}
+
+fn with_etag(mut response: Response, date: &Option<Date>, item: &Item) -> Response {
+	response.headers_mut().insert("etag", format!("\"{}\"", item_etag(date, item)).parse().unwrap());
+	response
+}
+
+async fn caldav_endpoint(
+	tail: warp::path::Tail,
+	method: Method,
+	headers: HeaderMap,
+	tx: Arc<Mutex<mpsc::UnboundedSender<types::WSCommand>>>,
+	io: Arc<Mutex<IO>>,
+	body: warp::hyper::body::Bytes
+) -> Result<Response, Infallible> {
+	println!("{} /caldav/{}", method, tail.as_str());
+
+	if method == Method::OPTIONS {
+		let mut response = empty(StatusCode::OK);
+		response.headers_mut().insert("allow", "OPTIONS, PROPFIND, REPORT, GET, PUT, DELETE".parse().unwrap());
+		return Ok(response);
+	}
+
+	let get_header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(String::from);
+
+	if !is_authorized(get_header("authorization")) {
+		let mut response = empty(StatusCode::UNAUTHORIZED);
+		response.headers_mut().insert("www-authenticate", "Basic realm=\"bansheelong\"".parse().unwrap());
+		return Ok(response);
+	}
+
+	let path = tail.as_str().trim_end_matches('/');
+	let uid = path.strip_prefix("todos/").and_then(get_uid_from_href).map(String::from);
+	let body = String::from_utf8_lossy(&body).to_string();
+	let depth = get_header("depth").unwrap_or_else(|| String::from("0"));
+
+	let mut guard = io.lock().await;
+	if let Err(error) = guard.read_database().await {
+		eprintln!(" -> Error on request, {:?}", error);
+		return Ok(respond(StatusCode::INTERNAL_SERVER_ERROR, "text/plain", format!("{:?}", error)));
+	}
+
+	// clients send the etag they last saw so they do not overwrite changes made somewhere else
+	if let (Some(if_match), Some(uid)) = (get_header("if-match"), uid.as_ref()) {
+		let etag = guard.todos_database.find_by_uid(uid).map(|(date, item)| item_etag(&date, item));
+		if (method == Method::PUT || method == Method::DELETE) && !is_match(&if_match, etag) {
+			return Ok(empty(StatusCode::PRECONDITION_FAILED));
+		}
+	}
+
+	let response = match (method.as_str(), path, uid) {
+		("PROPFIND", "", _) => {
+			let mut responses = vec![root_response()];
+			if depth != "0" {
+				responses.push(calendar_response(&get_items(&guard)));
+			}
+
+			multistatus(responses)
+		},
+		("PROPFIND", "todos", _) => {
+			let items = get_items(&guard);
+			let mut responses = vec![calendar_response(&items)];
+			if depth != "0" {
+				responses.extend(items.iter().map(|(date, item)| item_response(date, item, false)));
+			}
+
+			multistatus(responses)
+		},
+		("PROPFIND", _, Some(uid)) => match guard.todos_database.find_by_uid(&uid) {
+			Some((date, item)) => multistatus(vec![item_response(&date, item, false)]),
+			None => empty(StatusCode::NOT_FOUND),
+		},
+		("REPORT", "todos", _) => {
+			let items = get_items(&guard);
+
+			// a multiget asks for specific items, anything else we answer with everything
+			if body.contains("calendar-multiget") {
+				let uids = get_requested_hrefs(&body);
+				multistatus(
+					items.iter()
+						.filter(|(date, item)| {
+							let uid = item_uid(date, item);
+							uids.iter().any(|href| get_uid_from_href(href) == Some(uid.as_str()))
+						})
+						.map(|(date, item)| item_response(date, item, true))
+						.collect()
+				)
+			} else {
+				multistatus(items.iter().map(|(date, item)| item_response(date, item, true)).collect())
+			}
+		},
+		("GET", "todos", _) => respond(
+			StatusCode::OK,
+			"text/calendar; charset=utf-8",
+			to_ical(&guard.todos_database, &guard.meals_database, Date::today())
+		),
+		("GET", _, Some(uid)) => match guard.todos_database.find_by_uid(&uid) {
+			Some((date, item)) => with_etag(
+				respond(StatusCode::OK, "text/calendar; charset=utf-8", item_to_ical(&date, item, Date::today())),
+				&date,
+				item
+			),
+			None => empty(StatusCode::NOT_FOUND),
+		},
+		("PUT", _, Some(uid)) => match parse_ical_item(&body) {
+			Ok((date, item)) => match guard.put_ical_item(&uid, date, item) {
+				Ok(added) => {
+					let status = if added {
+						StatusCode::CREATED
+					} else {
+						StatusCode::NO_CONTENT
+					};
+
+					match guard.todos_database.find_by_uid(&uid) {
+						Some((date, item)) => with_etag(empty(status), &date, item),
+						None => empty(status),
+					}
+				},
+				Err(error) => respond(StatusCode::INTERNAL_SERVER_ERROR, "text/plain", format!("{:?}", error)),
+			},
+			Err(error) => respond(StatusCode::BAD_REQUEST, "text/plain", error.message),
+		},
+		("DELETE", _, Some(uid)) => match guard.delete_ical_item(&uid) {
+			Ok(true) => empty(StatusCode::NO_CONTENT),
+			Ok(false) => empty(StatusCode::NOT_FOUND),
+			Err(error) => respond(StatusCode::INTERNAL_SERVER_ERROR, "text/plain", format!("{:?}", error)),
+		},
+		("PROPFIND", _, _) | ("REPORT", _, _) | ("GET", _, _) => empty(StatusCode::NOT_FOUND),
+		_ => empty(StatusCode::METHOD_NOT_ALLOWED),
+	};
+
+	// anything that changed the todos gets written out and pushed to the other clients
+	if method == Method::PUT || method == Method::DELETE {
+		if let Err(error) = guard.sync().await {
+			eprintln!(" -> Error on request, {:?}", error);
+			return Ok(respond(StatusCode::INTERNAL_SERVER_ERROR, "text/plain", format!("{:?}", error)));
+		}
+
+		if response.status().is_success() {
+			println!(" -> Valid request, changing todos and syncing...");
+			if let Err(error) = tx.lock().await.send(types::WSCommand::Refresh) {
+				eprintln!("WS could not send refresh through http -> ws channel {:?}", error);
+			}
+		}
+	}
+
+	Ok(response)
+}
+
+pub(crate) fn build_caldav(
+	tx: Arc<Mutex<mpsc::UnboundedSender<types::WSCommand>>>,
+	io: Arc<Mutex<IO>>
+) -> impl warp::Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+	// clients that are only given the host look here first
+	let well_known = warp::path!(".well-known" / "caldav")
+		.map(|| warp::redirect::permanent(warp::http::Uri::from_static(ROOT)));
+
+	well_known.or(
+		warp::path("caldav")
+			.and(warp::path::tail())
+			.and(warp::method())
+			.and(warp::header::headers_cloned())
+			.and(warp::any().map(move || tx.clone()))
+			.and(warp::any().map(move || io.clone()))
+			.and(warp::body::bytes())
+			.and_then(caldav_endpoint)
+	)
+}
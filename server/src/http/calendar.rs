@@ -0,0 +1,54 @@
+use std::sync::Arc;
+use std::convert::Infallible;
+use tokio::sync::Mutex;
+use warp::{ Filter, Reply };
+
+use crate::http::{ Response, failed_secret };
+
+use bansheelong_types::{ Date, IO, get_todos_calendar_secret, to_ical };
+
+async fn calendar_endpoint(
+	file_name: String,
+	io: Arc<Mutex<IO>>
+) -> Result<warp::reply::Response, Infallible> {
+	println!("GET /calendar/");
+
+	// calendar apps cannot send our secret header, so it lives in the subscription url instead
+	let secret = get_todos_calendar_secret();
+	if secret.is_empty() || file_name != format!("{}.ics", secret) {
+		return Ok(failed_secret().into_response());
+	}
+
+	let mut guard = io.lock().await;
+	let result = guard.read_database().await;
+	if let Err(error) = result {
+		eprintln!(" -> Error on request, {:?}", error);
+		return Ok(warp::reply::with_status(
+			warp::reply::json(&Response {
+				error: format!("{:?}", error).into(),
+				success: false,
+			}),
+			warp::http::StatusCode::INTERNAL_SERVER_ERROR
+		).into_response());
+	}
+
+	println!(" -> Valid request, exporting todos and planned meals as a calendar...");
+
+	let (todos, meals) = result.unwrap();
+	Ok(warp::reply::with_header(
+		to_ical(todos, meals, Date::today()),
+		"content-type",
+		"text/calendar; charset=utf-8"
+	).into_response())
+}
+
+pub(crate) fn build_calendar(
+	io: Arc<Mutex<IO>>
+) -> impl warp::Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+	warp::get()
+		.and(warp::path("calendar"))
+		.and(warp::path::param::<String>())
+		.and(warp::path::end())
+		.and(warp::any().map(move || io.clone()))
+		.and_then(calendar_endpoint)
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use std::convert::Infallible;
+use tokio::sync::{ Mutex, mpsc };
+use warp::Filter;
+
+use crate::http::{ Response, failed_secret };
+use crate::types;
+
+use bansheelong_types::{ IO, TodoCompletion, get_todos_secret };
+
+async fn complete_todos_endpoint(
+	secret: bool,
+	tx: Arc<Mutex<mpsc::UnboundedSender<types::WSCommand>>>,
+	io: Arc<Mutex<IO>>,
+	completions: Vec<TodoCompletion>
+) -> Result<impl warp::Reply, Infallible> {
+	println!("POST /complete-todos/");
+	
+	if !secret {
+		return Ok(failed_secret());
+	}
+	
+	let mut guard = io.lock().await;
+
+	for completion in completions {
+		if let Err(error) = guard.complete_todo(completion) { // check off todo
+			eprintln!(" -> Error on request, {:?}", error);
+			return Ok(warp::reply::with_status(
+				warp::reply::json(&Response {
+					error: format!("{:?}", error).into(),
+					success: false,
+				}),
+				warp::http::StatusCode::INTERNAL_SERVER_ERROR
+			));
+		}
+	}
+
+	if let Err(error) = guard.sync().await { // sync
+		eprintln!(" -> Error on request, {:?}", error);
+		return Ok(warp::reply::with_status(
+			warp::reply::json(&Response {
+				error: format!("{:?}", error).into(),
+				success: false,
+			}),
+			warp::http::StatusCode::INTERNAL_SERVER_ERROR
+		));
+	}
+
+	println!(" -> Valid request, completing todos and syncing...");
+	if let Err(error) = tx.lock().await.send(types::WSCommand::Refresh) {
+		eprintln!("WS could not send refresh through http -> ws channel {:?}", error);
+	}
+	
+	Ok(warp::reply::with_status(
+		warp::reply::json(&Response {
+			error: None,
+			success: true,
+		}),
+		warp::http::StatusCode::OK
+	))
+}
+
+pub(crate) fn build_complete_todos(
+	tx: Arc<Mutex<mpsc::UnboundedSender<types::WSCommand>>>,
+	io: Arc<Mutex<IO>>
+) -> impl warp::Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+	warp::post()
+		.and(warp::path("complete-todos"))
+		.and(warp::body::content_length_limit(1024 * 100))
+		.and(
+			warp::header::<String>("secret")
+				.map(|token: String| {
+					token == get_todos_secret()
+				})
+		)
+		.and(warp::any().map(move || tx.clone()))
+		.and(warp::any().map(move || io.clone()))
+		.and(warp::body::json())
+		.and_then(complete_todos_endpoint)
+}
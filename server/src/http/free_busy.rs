@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use std::convert::Infallible;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use warp::Filter;
+
+use crate::http::{ Response, failed_secret };
+
+use bansheelong_types::{ Date, IO, get_todos_secret };
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct FreeBusyQuery {
+	day: u8,
+	month: u8,
+	year: u8,
+	start: Option<u8>,
+	end: Option<u8>,
+}
+
+async fn free_busy_endpoint(
+	secret: bool,
+	io: Arc<Mutex<IO>>,
+	query: FreeBusyQuery
+) -> Result<impl warp::Reply, Infallible> {
+	println!("GET /free-busy/");
+	
+	if !secret {
+		return Ok(failed_secret());
+	}
+	
+	let mut guard = io.lock().await;
+	if let Err(error) = guard.read_database().await {
+		eprintln!(" -> Error on request, {:?}", error);
+		return Ok(warp::reply::with_status(
+			warp::reply::json(&Response {
+				error: format!("{:?}", error).into(),
+				success: false,
+			}),
+			warp::http::StatusCode::INTERNAL_SERVER_ERROR
+		));
+	}
+
+	println!(" -> Valid request, finding free and busy time...");
+
+	let date = Date {
+		day: query.day,
+		month: query.month,
+		year: query.year,
+	};
+
+	Ok(warp::reply::with_status(
+		warp::reply::json(&guard.todos_database.free_busy(date, query.start.unwrap_or(0), query.end.unwrap_or(24))),
+		warp::http::StatusCode::OK
+	))
+}
+
+pub(crate) fn build_free_busy(
+	io: Arc<Mutex<IO>>
+) -> impl warp::Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+	warp::get()
+		.and(warp::path("free-busy"))
+		.and(
+			warp::header::<String>("secret")
+				.map(|token: String| {
+					token == get_todos_secret()
+				})
+		)
+		.and(warp::any().map(move || io.clone()))
+		.and(warp::query::<FreeBusyQuery>())
+		.and_then(free_busy_endpoint)
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use std::convert::Infallible;
+use tokio::sync::{ Mutex, mpsc };
+use warp::Filter;
+
+use crate::http::{ Response, failed_secret };
+use crate::types;
+
+use bansheelong_types::{ IO, get_todos_secret, parse_recipes };
+
+async fn import_recipes_endpoint(
+	secret: bool,
+	tx: Arc<Mutex<mpsc::UnboundedSender<types::WSCommand>>>,
+	io: Arc<Mutex<IO>>,
+	body: warp::hyper::body::Bytes
+) -> Result<impl warp::Reply, Infallible> {
+	println!("POST /import-recipes/");
+	
+	if !secret {
+		return Ok(failed_secret());
+	}
+
+	let recipes = match String::from_utf8(body.to_vec()) {
+		Ok(contents) => parse_recipes(&contents),
+		Err(error) => Err(bansheelong_types::Error {
+			message: format!("{:?}", error),
+			..bansheelong_types::Error::default()
+		}),
+	};
+
+	let recipes = match recipes {
+		Ok(recipes) => recipes,
+		Err(error) => {
+			eprintln!(" -> Error on request, {:?}", error);
+			return Ok(warp::reply::with_status(
+				warp::reply::json(&Response {
+					error: format!("{:?}", error).into(),
+					success: false,
+				}),
+				warp::http::StatusCode::BAD_REQUEST
+			));
+		},
+	};
+	
+	let mut guard = io.lock().await;
+	if let Err(error) = guard.sync().await { // make sure we have the recipes before checking for duplicates
+		eprintln!(" -> Error on request, {:?}", error);
+		return Ok(warp::reply::with_status(
+			warp::reply::json(&Response {
+				error: format!("{:?}", error).into(),
+				success: false,
+			}),
+			warp::http::StatusCode::INTERNAL_SERVER_ERROR
+		));
+	}
+
+	let mut count: usize = 0;
+	for recipe in recipes { // add recipes to database
+		if guard.meals_database.recipes.contains(&recipe) {
+			println!(" -> Skipping recipe '{}', it already exists", recipe.name);
+			continue;
+		}
+
+		if let Err(error) = guard.add_recipe(recipe) {
+			eprintln!(" -> Error on request, {:?}", error);
+			return Ok(warp::reply::with_status(
+				warp::reply::json(&Response {
+					error: format!("{:?}", error).into(),
+					success: false,
+				}),
+				warp::http::StatusCode::INTERNAL_SERVER_ERROR
+			));
+		}
+
+		count += 1;
+	}
+
+	if let Err(error) = guard.sync().await { // sync
+		eprintln!(" -> Error on request, {:?}", error);
+		return Ok(warp::reply::with_status(
+			warp::reply::json(&Response {
+				error: format!("{:?}", error).into(),
+				success: false,
+			}),
+			warp::http::StatusCode::INTERNAL_SERVER_ERROR
+		));
+	}
+
+	println!(" -> Valid request, imported {} recipes and syncing...", count);
+	if count > 0 {
+		if let Err(error) = tx.lock().await.send(types::WSCommand::Refresh) {
+			eprintln!("WS could not send refresh through http -> ws channel {:?}", error);
+		}
+	}
+	
+	Ok(warp::reply::with_status(
+		warp::reply::json(&count),
+		warp::http::StatusCode::OK
+	))
+}
+
+pub(crate) fn build_import_recipes(
+	tx: Arc<Mutex<mpsc::UnboundedSender<types::WSCommand>>>,
+	io: Arc<Mutex<IO>>
+) -> impl warp::Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+	warp::post()
+		.and(warp::path("import-recipes"))
+		.and(warp::body::content_length_limit(1024 * 1024 * 5)) // saved web pages are big
+		.and(
+			warp::header::<String>("secret")
+				.map(|token: String| {
+					token == get_todos_secret()
+				})
+		)
+		.and(warp::any().map(move || tx.clone()))
+		.and(warp::any().map(move || io.clone()))
+		.and(warp::body::bytes())
+		.and_then(import_recipes_endpoint)
+}
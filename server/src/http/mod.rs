@@ -1,10 +1,17 @@
 pub(crate) mod add_planned_meals;
 pub(crate) mod add_todos;
 pub(crate) mod add_recipes;
+pub(crate) mod caldav;
+pub(crate) mod calendar;
+pub(crate) mod complete_todos;
 pub(crate) mod failed_secret;
+pub(crate) mod free_busy;
 pub(crate) mod get_database;
+pub(crate) mod import_recipes;
 pub(crate) mod remove_planned_meals;
 pub(crate) mod set_database;
+pub(crate) mod track_todos;
+pub(crate) mod weekly_totals;
 
 pub(crate) use failed_secret::failed_secret;
 
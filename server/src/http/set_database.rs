@@ -22,6 +22,7 @@ async fn set_database_endpoint(
 	
 	let mut guard = io.lock().await;
 	let old_planned_meals = guard.meals_database.planned_meal_mapping.clone();
+	let old_history = guard.meals_database.history.clone();
 
 	guard.meals_database = databases.1;
 
@@ -29,7 +30,22 @@ async fn set_database_endpoint(
 		guard.meals_database.planned_meal_mapping = old_planned_meals;
 	}
 
+	if guard.meals_database.history.is_empty() {
+		guard.meals_database.history = old_history;
+	}
+
+	let old_archive = guard.todos_database.archive.clone();
+	let old_sessions = guard.todos_database.sessions.clone();
 	guard.todos_database = databases.0;
+
+	if guard.todos_database.archive.is_empty() {
+		guard.todos_database.archive = old_archive;
+	}
+
+	if guard.todos_database.sessions.is_empty() {
+		guard.todos_database.sessions = old_sessions;
+	}
+
 	guard.dirty = Dirty::Write;
 	if let Err(error) = guard.sync().await {
 		eprintln!(" -> Error on request, {:?}", error);
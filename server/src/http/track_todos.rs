@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use std::convert::Infallible;
+use tokio::sync::{ Mutex, mpsc };
+use warp::Filter;
+
+use crate::http::{ Response, failed_secret };
+use crate::types;
+
+use bansheelong_types::{ IO, TrackingEvent, get_todos_secret };
+
+async fn track_todos_endpoint(
+	secret: bool,
+	tx: Arc<Mutex<mpsc::UnboundedSender<types::WSCommand>>>,
+	io: Arc<Mutex<IO>>,
+	events: Vec<TrackingEvent>
+) -> Result<impl warp::Reply, Infallible> {
+	println!("POST /track-todos/");
+	
+	if !secret {
+		return Ok(failed_secret());
+	}
+	
+	let mut guard = io.lock().await;
+
+	for event in events {
+		if let Err(error) = guard.track(event) { // start or stop the session
+			eprintln!(" -> Error on request, {:?}", error);
+			return Ok(warp::reply::with_status(
+				warp::reply::json(&Response {
+					error: format!("{:?}", error).into(),
+					success: false,
+				}),
+				warp::http::StatusCode::INTERNAL_SERVER_ERROR
+			));
+		}
+	}
+
+	if let Err(error) = guard.sync().await { // sync
+		eprintln!(" -> Error on request, {:?}", error);
+		return Ok(warp::reply::with_status(
+			warp::reply::json(&Response {
+				error: format!("{:?}", error).into(),
+				success: false,
+			}),
+			warp::http::StatusCode::INTERNAL_SERVER_ERROR
+		));
+	}
+
+	println!(" -> Valid request, tracking todos and syncing...");
+	if let Err(error) = tx.lock().await.send(types::WSCommand::Refresh) {
+		eprintln!("WS could not send refresh through http -> ws channel {:?}", error);
+	}
+	
+	Ok(warp::reply::with_status(
+		warp::reply::json(&Response {
+			error: None,
+			success: true,
+		}),
+		warp::http::StatusCode::OK
+	))
+}
+
+pub(crate) fn build_track_todos(
+	tx: Arc<Mutex<mpsc::UnboundedSender<types::WSCommand>>>,
+	io: Arc<Mutex<IO>>
+) -> impl warp::Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+	warp::post()
+		.and(warp::path("track-todos"))
+		.and(warp::body::content_length_limit(1024 * 100))
+		.and(
+			warp::header::<String>("secret")
+				.map(|token: String| {
+					token == get_todos_secret()
+				})
+		)
+		.and(warp::any().map(move || tx.clone()))
+		.and(warp::any().map(move || io.clone()))
+		.and(warp::body::json())
+		.and_then(track_todos_endpoint)
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use std::convert::Infallible;
+use chrono::Datelike;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use warp::Filter;
+
+use crate::http::{ Response, failed_secret };
+
+use bansheelong_types::{ Date, IO, get_todos_secret };
+
+// any day in the week we want totals for, this week if left out
+#[derive(Debug, Deserialize)]
+pub(crate) struct WeeklyTotalsQuery {
+	day: Option<u8>,
+	month: Option<u8>,
+	year: Option<u8>,
+}
+
+async fn weekly_totals_endpoint(
+	secret: bool,
+	io: Arc<Mutex<IO>>,
+	query: WeeklyTotalsQuery
+) -> Result<impl warp::Reply, Infallible> {
+	println!("GET /weekly-totals/");
+	
+	if !secret {
+		return Ok(failed_secret());
+	}
+	
+	let mut guard = io.lock().await;
+	if let Err(error) = guard.read_database().await {
+		eprintln!(" -> Error on request, {:?}", error);
+		return Ok(warp::reply::with_status(
+			warp::reply::json(&Response {
+				error: format!("{:?}", error).into(),
+				success: false,
+			}),
+			warp::http::StatusCode::INTERNAL_SERVER_ERROR
+		));
+	}
+
+	println!(" -> Valid request, totaling tracked time...");
+
+	let date = match (query.day, query.month, query.year) {
+		(Some(day), Some(month), Some(year)) => Date {
+			day,
+			month,
+			year,
+		},
+		_ => Date::today(),
+	};
+
+	// weeks start on monday
+	let week_start = match date.to_naive() {
+		Some(naive) => date.add_days(-(naive.weekday().num_days_from_monday() as i64)),
+		None => date,
+	};
+
+	Ok(warp::reply::with_status(
+		warp::reply::json(&guard.todos_database.get_weekly_totals(week_start, chrono::Local::now().naive_local())),
+		warp::http::StatusCode::OK
+	))
+}
+
+pub(crate) fn build_weekly_totals(
+	io: Arc<Mutex<IO>>
+) -> impl warp::Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+	warp::get()
+		.and(warp::path("weekly-totals"))
+		.and(
+			warp::header::<String>("secret")
+				.map(|token: String| {
+					token == get_todos_secret()
+				})
+		)
+		.and(warp::any().map(move || io.clone()))
+		.and(warp::query::<WeeklyTotalsQuery>())
+		.and_then(weekly_totals_endpoint)
+}
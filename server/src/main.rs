@@ -3,10 +3,13 @@ mod types;
 mod ws;
 
 use bansheelong_types::{
+	Date,
 	IO,
 	get_static_path,
 	get_todos_https_cert,
 	get_todos_https_key,
+	get_todos_reminders,
+	get_todos_rollover_mode,
 	get_todos_secret,
 	get_todos_server_port,
 };
@@ -25,9 +28,16 @@ use crate::http::{
 	add_planned_meals,
 	add_todos,
 	add_recipes,
+	caldav,
+	calendar,
+	complete_todos,
+	free_busy,
 	get_database,
+	import_recipes,
 	remove_planned_meals,
-	set_database
+	set_database,
+	track_todos,
+	weekly_totals
 };
 use crate::ws::Users;
 
@@ -53,8 +63,15 @@ async fn main() {
 		.or(set_database::build_set_database(tx.clone(), io.clone()))
 		.or(get_database::build_get_database(io.clone()))
 		.or(add_recipes::build_add_recipes(tx.clone(), io.clone()))
+		.or(import_recipes::build_import_recipes(tx.clone(), io.clone()))
 		.or(add_planned_meals::build_add_planned_meals(tx.clone(), io.clone()))
 		.or(remove_planned_meals::build_remove_planned_meals(tx.clone(), io.clone()))
+		.or(complete_todos::build_complete_todos(tx.clone(), io.clone()))
+		.or(calendar::build_calendar(io.clone()))
+		.or(caldav::build_caldav(tx.clone(), io.clone()))
+		.or(free_busy::build_free_busy(io.clone()))
+		.or(track_todos::build_track_todos(tx.clone(), io.clone()))
+		.or(weekly_totals::build_weekly_totals(io.clone()))
 		.or( // set up websocket
 			warp::path("websocket")
 				.and(
@@ -105,7 +122,16 @@ async fn main() {
 							eprintln!("WS Error {:?}", error);
 						}
 					}
-				}
+				},
+				types::WSCommand::Reminder(reminder) => {
+					let text = format!("reminder {}", serde_json::to_string(&reminder).unwrap());
+					let users = users.write().await;
+					for user in users.iter() {
+						if let Err(error) = user.channel.send(Message::text(text.clone())) {
+							eprintln!("WS Error {:?}", error);
+						}
+					}
+				},
 			}
 		}
 	});
@@ -120,5 +146,97 @@ async fn main() {
 		}
 	});
 
-	future::join_all([http_server, ws_message_handler, ws_keep_alive]).await;
+	// planned meal archival async block
+	let archive_io = io.clone();
+	let archive_tx = tx.clone();
+	let planned_meals_archiver = Box::pin(async move {
+		loop {
+			let mut guard = archive_io.lock().await;
+			if let Err(error) = guard.sync().await {
+				eprintln!("Could not sync before archiving planned meals {:?}", error);
+			} else {
+				let count = guard.archive_planned_meals(Date::today());
+				if count > 0 {
+					println!("Archived {} planned meals", count);
+					if let Err(error) = guard.sync().await {
+						eprintln!("Could not sync archived planned meals {:?}", error);
+					} else if let Err(error) = archive_tx.lock().await.send(types::WSCommand::Refresh) {
+						eprintln!("WS could not send refresh through archive -> ws channel {:?}", error);
+					}
+				}
+			}
+			drop(guard);
+
+			tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await;
+		}
+	});
+
+	let rollover_io = io.clone();
+	let rollover_tx = tx.clone();
+	let todos_rollover = Box::pin(async move {
+		loop {
+			let mut guard = rollover_io.lock().await;
+			if let Err(error) = guard.sync().await {
+				eprintln!("Could not sync before rolling over todos {:?}", error);
+			} else {
+				let count = guard.rollover_todos(Date::today(), get_todos_rollover_mode());
+				if count > 0 {
+					println!("Rolled over {} todos", count);
+				}
+
+				if let Err(error) = guard.sync().await {
+					eprintln!("Could not sync rolled over todos {:?}", error);
+				} else if let Err(error) = rollover_tx.lock().await.send(types::WSCommand::Refresh) {
+					eprintln!("WS could not send refresh through rollover -> ws channel {:?}", error);
+				}
+			}
+			drop(guard);
+
+			// run again just after midnight
+			tokio::time::sleep(tokio::time::Duration::from_secs(Date::seconds_until_tomorrow() + 60)).await;
+		}
+	});
+
+	// reminder async block
+	let reminder_io = io.clone();
+	let reminder_tx = tx.clone();
+	let reminder_scheduler = Box::pin(async move {
+		let lead_times = get_todos_reminders();
+		let mut last_check = chrono::Local::now().naive_local();
+		loop {
+			tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+			if lead_times.is_empty() {
+				continue;
+			}
+
+			let now = chrono::Local::now().naive_local();
+
+			let mut guard = reminder_io.lock().await;
+			if let Err(error) = guard.read_database().await {
+				eprintln!("Could not read todos for reminders {:?}", error);
+				continue;
+			}
+
+			let reminders = guard.todos_database.get_reminders(last_check, now, &lead_times);
+			drop(guard);
+
+			for reminder in reminders {
+				println!("Reminding about '{}'", reminder.description);
+				if let Err(error) = reminder_tx.lock().await.send(types::WSCommand::Reminder(reminder)) {
+					eprintln!("WS could not send reminder through reminder -> ws channel {:?}", error);
+				}
+			}
+
+			last_check = now;
+		}
+	});
+
+	future::join_all([
+		http_server,
+		ws_message_handler,
+		ws_keep_alive,
+		planned_meals_archiver,
+		todos_rollover,
+		reminder_scheduler,
+	]).await;
 }
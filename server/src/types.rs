@@ -1,5 +1,8 @@
+use bansheelong_types::Reminder;
+
 #[derive(Debug)]
 pub enum WSCommand {
 	Ping,
 	Refresh,
+	Reminder(Reminder),
 }
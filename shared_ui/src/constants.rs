@@ -31,6 +31,7 @@ pub const ICONS: Font = Font::External {
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Menu {
 	Meals,
+	Snapshots,
 	Todos,
 }
 
@@ -1,5 +1,6 @@
 pub mod constants;
 pub mod meals;
+pub mod reminders;
 pub mod style;
 mod underline;
 pub mod ws;
@@ -40,16 +40,19 @@ pub enum Message {
 
 #[derive(Debug)]
 pub struct PlannedInfo {
+	cooked_state: button::State,
 	image: image::Handle,
 	image_state: image::viewer::State,
 	ingredient_button_states: Vec<button::State>,
 	ingredients_state: scrollable::State,
+	leftovers_state: button::State,
 	mapping: HashMap<Date, PlannedMeal>,
 	meal_button_states: Vec<button::State>,
 	meal_index: Option<Date>,
 	meals_position: f32,
 	meals_state: scrollable::State,
 	remove_meal_state: button::State,
+	skipped_state: button::State,
 	switch_planner_state: button::State,
 }
 
@@ -1,3 +1,4 @@
+use bansheelong_types::MealStatus;
 use iced::{ Button, Column, Container, Length, Row, Scrollable, Space, Text, alignment, image };
 
 use crate::constants;
@@ -318,6 +319,69 @@ impl View {
 					});
 			}
 
+			// add meal status buttons
+			let mut cooked_meal = selected_meal.clone();
+			cooked_meal.status = MealStatus::Cooked;
+
+			let mut skipped_meal = selected_meal.clone();
+			skipped_meal.status = MealStatus::Skipped;
+
+			let mut leftovers_meal = selected_meal.clone();
+			leftovers_meal.status = match selected_meal.status { // cycle through 1-3 days of leftovers
+				MealStatus::Leftovers(days) => MealStatus::Leftovers(days % 3 + 1),
+				_ => MealStatus::Leftovers(1),
+			};
+
+			let status_button = |state, text: String, active: bool, meal| {
+				let button = Button::new(
+					state,
+					Text::new(text)
+						.size(self.props.text_size)
+						.width(Length::Fill)
+						.horizontal_alignment(alignment::Horizontal::Center)
+				)
+					.width(Length::Fill)
+					.height(Length::Units(self.menu_state.button_height))
+					.on_press(Message::APIUpdatePlannedMeal(meal));
+
+				if active {
+					button.style(style::SpecialMenuButton)
+				} else {
+					button.style(style::TodoMenuButton)
+				}
+			};
+
+			information_column = information_column
+				.push(
+					Space::new(Length::Units(0), Length::Units(15))
+				)
+				.push(
+					Row::new()
+						.spacing(10)
+						.push(status_button(
+							&mut self.planned.cooked_state,
+							String::from("Cooked"),
+							selected_meal.status == MealStatus::Cooked,
+							cooked_meal
+						))
+						.push(status_button(
+							&mut self.planned.leftovers_state,
+							if let MealStatus::Leftovers(days) = selected_meal.status {
+								format!("Leftovers ({})", days)
+							} else {
+								String::from("Leftovers")
+							},
+							matches!(selected_meal.status, MealStatus::Leftovers(_)),
+							leftovers_meal
+						))
+						.push(status_button(
+							&mut self.planned.skipped_state,
+							String::from("Skipped"),
+							selected_meal.status == MealStatus::Skipped,
+							skipped_meal
+						))
+				);
+
 			// add remove meal button
 			information_column = information_column
 				.push(
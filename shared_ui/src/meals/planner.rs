@@ -91,6 +91,18 @@ impl View {
 				)
 			);
 
+		// show which recipe we keep coming back to
+		if let Some((name, count)) = self.database.as_ref().unwrap().meals_database.most_cooked_recipe() {
+			scrollable = scrollable
+				.push(
+					Text::new(format!("Most cooked: {} ({}x)", name, count))
+						.size(self.props.text_size)
+						.width(Length::Fill)
+						.horizontal_alignment(alignment::Horizontal::Center)
+				)
+				.push(Space::new(Length::Units(0), Length::Units(10)));
+		}
+
 		// construct recipes list
 		scrollable = self.database.as_ref().unwrap().meals_database.recipes.iter()
 			.zip(self.planner.recipe_button_states.iter_mut())
@@ -57,6 +57,7 @@ impl View {
 			last_interaction: None,
 			menu_state,
 			planned: PlannedInfo {
+				cooked_state: button::State::new(),
 				image: image::Handle::from_path(format!(
 					"{}/data/meals-images/placeholder.png",
 					constants::get_directory()
@@ -64,12 +65,14 @@ impl View {
 				image_state: image::viewer::State::new(),
 				ingredient_button_states: Vec::new(),
 				ingredients_state: scrollable::State::new(),
+				leftovers_state: button::State::new(),
 				mapping: HashMap::new(),
 				meal_button_states: Vec::new(),
 				meal_index: None,
 				meals_state,
 				meals_position: scroll_position,
 				remove_meal_state: button::State::new(),
+				skipped_state: button::State::new(),
 				switch_planner_state: button::State::new(),
 			},
 			planner: PlannerInfo {
@@ -275,6 +275,29 @@ pub(crate) fn get_planner_right_panel<'a, I>(args: PlannerRightPanelArguments<'a
 							.width(Length::Fill)
 							.horizontal_alignment(alignment::Horizontal::Center)
 					)
+					.push(
+						Text::new(
+							match database.meals_database.last_cooked(&selected_recipe.name) {
+								Some(date) => {
+									let count = database.meals_database.times_cooked(&selected_recipe.name);
+									format!(
+										"Cooked {} time{}, last on {}",
+										count,
+										if count != 1 {
+											"s"
+										} else {
+											""
+										},
+										date.to_string()
+									)
+								},
+								None => String::from("Never cooked"),
+							}
+						)
+							.size(props.text_size)
+							.width(Length::Fill)
+							.horizontal_alignment(alignment::Horizontal::Center)
+					)
 					.push(
 						Container::new(
 							Container::new(Text::new(""))
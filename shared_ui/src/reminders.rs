@@ -0,0 +1,156 @@
+use std::process::{ Command, Stdio };
+use std::time::{ Duration, Instant };
+
+use bansheelong_types::{ Reminder, get_desktop_notifications };
+use iced::{ Alignment, Button, Column, Command, Container, Element, Length, Row, Text, alignment, button };
+
+use crate::style;
+
+const SNOOZE_TIME: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug)]
+struct ActiveReminder {
+	dismiss_state: button::State,
+	reminder: Reminder,
+	snooze_state: button::State,
+	snoozed_until: Option<Instant>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+	Dismiss(usize),
+	Received(Reminder),
+	Snooze(usize),
+	Tick,
+}
+
+// shows a reminder through the freedesktop notification service over D-Bus
+fn notify(reminder: &Reminder) {
+	let child = Command::new("gdbus")
+		.arg("call")
+		.arg("--session")
+		.arg("--dest=org.freedesktop.Notifications")
+		.arg("--object-path=/org/freedesktop/Notifications")
+		.arg("--method=org.freedesktop.Notifications.Notify")
+		.arg("bansheelong")
+		.arg("0")
+		.arg("")
+		.arg("bansheelong")
+		.arg(reminder.get_text())
+		.arg("[]")
+		.arg("{}")
+		.arg("-1")
+		.stdout(Stdio::null())
+		.spawn();
+
+	match child {
+		Ok(mut child) => {
+			if let Err(error) = child.wait() {
+				eprintln!("Could not wait for notification {:?}", error);
+			}
+		},
+		Err(error) => eprintln!("Could not send notification {:?}", error),
+	}
+}
+
+#[derive(Debug)]
+pub struct View {
+	reminders: Vec<ActiveReminder>,
+}
+
+impl View {
+	pub fn new() -> Self {
+		View {
+			reminders: Vec::new(),
+		}
+	}
+
+	pub fn update(&mut self, message: Message) -> Command<Message> {
+		match message {
+			Message::Dismiss(index) => {
+				if index < self.reminders.len() {
+					self.reminders.remove(index);
+				}
+			},
+			Message::Received(reminder) => {
+				// the same event at a shorter lead time replaces the old reminder
+				self.reminders.retain(|active| {
+					active.reminder.date != reminder.date || active.reminder.description != reminder.description
+				});
+
+				if get_desktop_notifications() {
+					notify(&reminder);
+				}
+
+				self.reminders.push(ActiveReminder {
+					dismiss_state: button::State::new(),
+					reminder,
+					snooze_state: button::State::new(),
+					snoozed_until: None,
+				});
+			},
+			Message::Snooze(index) => {
+				if let Some(active) = self.reminders.get_mut(index) {
+					active.snoozed_until = Some(Instant::now() + SNOOZE_TIME);
+				}
+			},
+			Message::Tick => {
+				for active in self.reminders.iter_mut() {
+					if active.snoozed_until.is_some_and(|until| Instant::now() >= until) {
+						active.snoozed_until = None;
+					}
+				}
+			},
+		}
+
+		Command::none()
+	}
+
+	pub fn is_showing(&self) -> bool {
+		self.reminders.iter().any(|active| active.snoozed_until.is_none())
+	}
+
+	// a banner with every reminder that is not snoozed
+	pub fn view(&mut self) -> Element<Message> {
+		let column = self.reminders.iter_mut()
+			.enumerate()
+			.filter(|(_, active)| active.snoozed_until.is_none())
+			.fold(Column::new().spacing(5), |column, (index, active)| {
+				column.push(
+					Row::new()
+						.spacing(10)
+						.align_items(Alignment::Center)
+						.push(
+							Text::new(active.reminder.get_text())
+								.width(Length::Fill)
+						)
+						.push(
+							Button::new(
+								&mut active.snooze_state,
+								Text::new("Snooze")
+									.horizontal_alignment(alignment::Horizontal::Center)
+							)
+								.on_press(Message::Snooze(index))
+								.padding([5, 15])
+								.style(style::DarkButton)
+						)
+						.push(
+							Button::new(
+								&mut active.dismiss_state,
+								Text::new("Dismiss")
+									.horizontal_alignment(alignment::Horizontal::Center)
+							)
+								.on_press(Message::Dismiss(index))
+								.padding([5, 15])
+								.style(style::RemoveButton)
+						)
+				)
+			});
+
+		Container::new(column)
+			.width(Length::Fill)
+			.padding(10)
+			.style(style::ReminderBanner)
+			.into()
+	}
+}
@@ -60,6 +60,18 @@ pub const RED_COLOR: Color = Color::from_rgb(
 	0x60 as f32 / 255.0,
 );
 
+pub const ORANGE_COLOR: Color = Color::from_rgb(
+	0xE0 as f32 / 255.0,
+	0xAA as f32 / 255.0,
+	0x59 as f32 / 255.0,
+);
+
+pub const DIM_TEXT_COLOR: Color = Color::from_rgb(
+	0x9A as f32 / 255.0,
+	0x8A as f32 / 255.0,
+	0x96 as f32 / 255.0,
+);
+
 pub const TODO_COLORS: [Color; 4] = [
 	BLUE_COLOR,
 	MAGENTA_COLOR,
@@ -89,6 +101,17 @@ impl container::StyleSheet for VerticalRule {
 	}
 }
 
+pub struct ReminderBanner;
+impl container::StyleSheet for ReminderBanner {
+	fn style(&self) -> container::Style {
+		container::Style {
+			background: Some(Background::Color(BACKGROUND_LIGHT_PURPLE)),
+			text_color: Some(TEXT_COLOR),
+			..container::Style::default()
+		}
+	}
+}
+
 pub struct WeatherContainer;
 impl container::StyleSheet for WeatherContainer {
 	fn style(&self) -> container::Style {
@@ -1,6 +1,6 @@
 use async_tungstenite::tokio::{ ConnectStream, connect_async };
 use async_tungstenite::tungstenite;
-use bansheelong_types::{ get_todos_host, get_todos_secret, get_todos_server_port };
+use bansheelong_types::{ Reminder, get_todos_host, get_todos_secret, get_todos_server_port };
 use futures::StreamExt;
 use iced_native::subscription::{ self, Subscription };
 use tokio::time::{ Duration, sleep };
@@ -18,6 +18,7 @@ pub enum Event {
 	Error(String),
 	InvalidateState,
 	Refresh,
+	Reminder(Reminder),
 }
 
 pub fn connect() -> Subscription<Event> {
@@ -34,6 +35,8 @@ pub fn connect() -> Subscription<Event> {
 						Ok(tungstenite::Message::Text(message)) => {
 							if message == "refresh" {
 								(Some(Event::Refresh), State::Connected(websocket))
+							} else if let Some(Ok(reminder)) = message.strip_prefix("reminder ").map(serde_json::from_str) {
+								(Some(Event::Reminder(reminder)), State::Connected(websocket))
 							} else {
 								(None, State::Connected(websocket))
 							}
@@ -0,0 +1,400 @@
+use std::collections::{ BTreeMap, BTreeSet };
+use std::io::Write;
+
+use chrono::{ Local, TimeZone };
+use serde::{ Serialize, Deserialize };
+
+use bansheelong_types::{
+	Date,
+	Item,
+	JobStatusFlags,
+	PlannedMealsRemoveLog,
+	PlannedMealsWriteLog,
+	Resource,
+	StorageAlert,
+	StorageStatus,
+	TodosCompleteLog,
+	TodosTrackingLog,
+	WriteDatabase,
+	write_database,
+};
+
+use crate::probe::Error;
+
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+
+// everything the rules look at
+pub(crate) struct AlertContext<'a> {
+	pub(crate) last_daily: Option<i64>, // unix seconds
+	pub(crate) now: i64,
+	pub(crate) status: &'a StorageStatus,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum AlertRule {
+	BadSectors(u64), // reallocated and pending sectors a drive can have
+	DriveErrors,
+	NoDailyBackup(i64), // seconds without a new daily
+	NoScrub(i64), // seconds since the last scrub finished
+	PoolDegraded,
+	SmartFailing,
+	StatusErrors,
+	TemperatureAbove(u64), // celsius
+	UsageAbove(f64), // fraction of each pool or filesystem
+}
+
+// keys tell alerts apart, so a second drive failing is its own alert instead of being hidden by the first one
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct Alert {
+	pub(crate) key: String,
+	pub(crate) message: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum AlertEvent {
+	Firing(Alert),
+	Resolved(Alert),
+}
+
+pub(crate) fn get_default_rules() -> Vec<AlertRule> {
+	vec![
+		AlertRule::PoolDegraded,
+		AlertRule::DriveErrors,
+		AlertRule::SmartFailing,
+		AlertRule::BadSectors(0),
+		AlertRule::TemperatureAbove(50),
+		AlertRule::StatusErrors,
+		AlertRule::UsageAbove(0.9),
+		AlertRule::NoDailyBackup(36 * HOUR),
+		AlertRule::NoScrub(35 * DAY),
+	]
+}
+
+impl AlertRule {
+	pub(crate) fn check(&self, context: &AlertContext) -> Vec<Alert> {
+		let status = context.status;
+		let mut alerts = Vec::new();
+		match self {
+			AlertRule::BadSectors(limit) => {
+				for drive in status.smart.iter().filter(|drive| drive.get_bad_sectors() > *limit) {
+					alerts.push(Alert {
+						key: format!("smart-sectors:{}", drive.serial),
+						message: format!(
+							"drive {} has {} reallocated and {} pending sectors",
+							drive.serial,
+							drive.reallocated_sectors.unwrap_or(0),
+							drive.pending_sectors.unwrap_or(0)
+						),
+					});
+				}
+			},
+			AlertRule::DriveErrors => {
+				for drive in status.pools.iter().flat_map(|pool| pool.get_failing_drives()) {
+					let count = |count: Option<u64>| count.map_or(String::from("?"), |count| count.to_string());
+					alerts.push(Alert {
+						key: format!("drive-errors:{}", drive.get_serial()),
+						message: format!(
+							"drive {} is {} with {} read, {} write and {} checksum errors",
+							drive.get_serial(),
+							drive.state.to_lowercase(),
+							count(drive.read_errors),
+							count(drive.write_errors),
+							count(drive.checksum_errors)
+						),
+					});
+				}
+			},
+			AlertRule::NoDailyBackup(limit) => {
+				match context.last_daily {
+					Some(last_daily) if context.now - last_daily <= *limit => {},
+					Some(last_daily) => alerts.push(Alert {
+						key: String::from("no-daily-backup"),
+						message: format!("no new daily backup in {} hours", (context.now - last_daily) / HOUR),
+					}),
+					None => alerts.push(Alert {
+						key: String::from("no-daily-backup"),
+						message: String::from("could not tell when the last daily backup was made"),
+					}),
+				}
+			},
+			AlertRule::NoScrub(limit) => {
+				for pool in status.pools.iter() {
+					// zpool only remembers the last scan, so a running one of either kind counts
+					let scan = pool.scan.as_ref();
+					if scan.is_some_and(|scan| scan.in_progress) {
+						continue;
+					}
+
+					let finished_at = scan.filter(|scan| scan.kind == "scrub").and_then(|scan| scan.finished_at);
+					match finished_at {
+						Some(finished_at) if context.now - finished_at <= *limit => {},
+						Some(finished_at) => alerts.push(Alert {
+							key: format!("no-scrub:{}", pool.name),
+							message: format!("pool {} has not been scrubbed in {} days", pool.name, (context.now - finished_at) / DAY),
+						}),
+						None => alerts.push(Alert {
+							key: format!("no-scrub:{}", pool.name),
+							message: format!("pool {} has no finished scrub", pool.name),
+						}),
+					}
+				}
+			},
+			AlertRule::PoolDegraded => {
+				for pool in status.pools.iter() {
+					if pool.state != "ONLINE" {
+						alerts.push(Alert {
+							key: format!("pool-degraded:{}", pool.name),
+							message: format!("pool {} is {}", pool.name, pool.state.to_lowercase()),
+						});
+					} else if pool.has_data_errors() {
+						alerts.push(Alert {
+							key: format!("pool-degraded:{}", pool.name),
+							message: format!("pool {} has data errors: {}", pool.name, pool.errors),
+						});
+					}
+				}
+			},
+			AlertRule::SmartFailing => {
+				for drive in status.smart.iter().filter(|drive| drive.is_failing()) {
+					// the drive's own verdict is the more serious of the two
+					let message = match drive.self_test.as_ref() {
+						Some(test) if drive.passed != Some(false) && !test.passed => {
+							format!("drive {} failed its last {} self-test: {}", drive.serial, test.kind.to_lowercase(), test.status)
+						},
+						_ => format!("drive {} is failing its SMART health check", drive.serial),
+					};
+
+					alerts.push(Alert {
+						key: format!("smart-failed:{}", drive.serial),
+						message,
+					});
+				}
+			},
+			AlertRule::StatusErrors => {
+				// with no pools to look at, the job flags are all we have to go on
+				if status.pools.is_empty() && status.job_flags.contains(JobStatusFlags::ZPOOL_ERROR) {
+					alerts.push(Alert {
+						key: String::from("status-errors:zpool"),
+						message: String::from("could not read zpool status"),
+					});
+				}
+
+				if status.job_flags.contains(JobStatusFlags::ZPOOL_HARD_DRIVE_PARSE_ERROR) {
+					alerts.push(Alert {
+						key: String::from("status-errors:parse"),
+						message: String::from("could not read the error counts in zpool status"),
+					});
+				}
+
+				if status.job_flags.contains(JobStatusFlags::GENERAL_ERROR) {
+					alerts.push(Alert {
+						key: String::from("status-errors:general"),
+						message: String::from("the storage server reported an error"),
+					});
+				}
+			},
+			AlertRule::TemperatureAbove(limit) => {
+				for drive in status.smart.iter() {
+					if let Some(temperature) = drive.temperature.filter(|temperature| temperature > limit) {
+						alerts.push(Alert {
+							key: format!("smart-temperature:{}", drive.serial),
+							message: format!("drive {} is at {}°C, over the {}°C limit", drive.serial, temperature, limit),
+						});
+					}
+				}
+			},
+			AlertRule::UsageAbove(limit) => {
+				// every target on its own, or the one pool an older status knows about
+				for usage in status.usage.iter().filter(|usage| usage.get_fraction() > *limit) {
+					alerts.push(Alert {
+						key: format!("usage:{}", usage.target.get_name()),
+						message: format!(
+							"{} is {:.0}% full, over the {:.0}% limit",
+							usage.target.get_name(),
+							usage.get_fraction() * 100.0,
+							limit * 100.0
+						),
+					});
+				}
+
+				if status.usage.is_empty() && status.total_size > 0 && status.used_size as f64 / status.total_size as f64 > *limit {
+					alerts.push(Alert {
+						key: String::from("usage"),
+						message: format!(
+							"pool is {:.0}% full, over the {:.0}% limit",
+							status.used_size as f64 / status.total_size as f64 * 100.0,
+							limit * 100.0
+						),
+					});
+				}
+			},
+		}
+
+		alerts
+	}
+}
+
+// keeps track of what is firing, so sinks only hear about an alert when it starts and when it stops. acknowledgements
+// are not saved, so they only last until the server restarts
+#[derive(Debug, Default)]
+pub(crate) struct Alerts {
+	pub(crate) acknowledged: BTreeSet<String>,
+	pub(crate) firing: BTreeMap<String, Alert>,
+	pub(crate) rules: Vec<AlertRule>,
+}
+
+impl Alerts {
+	pub(crate) fn update(&mut self, context: &AlertContext) -> Vec<AlertEvent> {
+		let alerts = self.rules.iter()
+			.flat_map(|rule| rule.check(context))
+			.map(|alert| (alert.key.clone(), alert))
+			.collect::<BTreeMap<String, Alert>>();
+
+		let mut events = Vec::new();
+		for (key, alert) in self.firing.iter() {
+			if !alerts.contains_key(key) {
+				events.push(AlertEvent::Resolved(alert.clone()));
+			}
+		}
+
+		for (key, alert) in alerts.iter() {
+			if !self.firing.contains_key(key) {
+				events.push(AlertEvent::Firing(alert.clone()));
+			}
+		}
+
+		// an alert that fires again after resolving has to be acknowledged again
+		self.acknowledged.retain(|key| alerts.contains_key(key));
+		self.firing = alerts;
+		events
+	}
+
+	pub(crate) fn acknowledge(&mut self, key: &str) -> Result<(), String> {
+		if !self.firing.contains_key(key) {
+			return Err(format!("{} is not firing", key));
+		}
+
+		self.acknowledged.insert(key.to_string());
+		Ok(())
+	}
+
+	// what clients get
+	pub(crate) fn get_storage_alerts(&self) -> Vec<StorageAlert> {
+		self.firing.values()
+			.map(|alert| StorageAlert {
+				acknowledged: self.acknowledged.contains(&alert.key),
+				key: alert.key.clone(),
+				message: alert.message.clone(),
+			})
+			.collect()
+	}
+
+	pub(crate) fn read_firing(&mut self, path: &str) -> Result<(), Error> {
+		let contents = std::fs::read_to_string(path).map_err(|error| Error::LocalInfo(format!("{} read error: {:?}", path, error)))?;
+		self.firing = serde_json::from_str(&contents).map_err(|error| Error::LocalInfo(format!("{} parse error: {:?}", path, error)))?;
+		Ok(())
+	}
+
+	pub(crate) fn write_firing(&self, path: &str) -> Result<(), Error> {
+		std::fs::write(path, serde_json::to_string(&self.firing).unwrap())
+			.map_err(|error| Error::LocalInfo(format!("{} write error: {:?}", path, error)))
+	}
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum AlertSink {
+	MailSpool(String), // path to an mbox file
+	Todos(Resource),
+	Webhook(String),
+}
+
+// an mbox message. lines starting with "From " would start a new message, so they get escaped
+pub(crate) fn get_mail(event: &AlertEvent, user: &str, now: i64) -> String {
+	let (state, alert) = match event {
+		AlertEvent::Firing(alert) => ("firing", alert),
+		AlertEvent::Resolved(alert) => ("resolved", alert),
+	};
+
+	let time = Local.timestamp_opt(now, 0).single().unwrap_or_else(Local::now);
+	let body = format!("{} ({})", alert.message, alert.key)
+		.lines()
+		.map(|line| if line.starts_with("From ") { format!(">{}", line) } else { line.to_string() })
+		.collect::<Vec<String>>()
+		.join("\n");
+
+	format!(
+		"From bansheelong@localhost {}\nFrom: bansheelong <bansheelong@localhost>\nTo: {}\nDate: {}\nSubject: [bansheelong] {}: {}\n\n{}\n\n",
+		time.format("%a %b %e %H:%M:%S %Y"),
+		user,
+		time.to_rfc2822(),
+		state,
+		alert.message,
+		body
+	)
+}
+
+impl AlertSink {
+	pub(crate) async fn send(&self, event: &AlertEvent, now: i64) -> Result<(), Error> {
+		match self {
+			AlertSink::MailSpool(path) => {
+				// spools are named after the user they belong to
+				let user = path.rsplit('/').next().unwrap_or("root");
+				std::fs::OpenOptions::new()
+					.create(true)
+					.append(true)
+					.open(path)
+					.and_then(|mut file| file.write_all(get_mail(event, user, now).as_bytes()))
+					.map_err(|error| Error::Sink(format!("{} write error: {:?}", path, error)))
+			},
+			AlertSink::Todos(resource) => {
+				// todos are for fixing things, there is nothing to do once an alert goes away
+				let alert = match event {
+					AlertEvent::Firing(alert) => alert,
+					AlertEvent::Resolved(_) => return Ok(()),
+				};
+
+				// the priority is read out of the marker, so an item without one comes back as normal priority
+				let todos_write_log = vec![(
+					Item::from_description(format!("! storage: {}", alert.message), None),
+					Some(Date::today()),
+				)];
+
+				write_database(
+					WriteDatabase::Partial {
+						planned_meals_remove_log: &PlannedMealsRemoveLog::default(),
+						planned_meals_write_log: &PlannedMealsWriteLog::default(),
+						todos_complete_log: &TodosCompleteLog::default(),
+						todos_tracking_log: &TodosTrackingLog::default(),
+						todos_write_log: &todos_write_log,
+					},
+					resource.clone()
+				).await.map_err(|error| Error::Sink(format!("todos error: {}", error.message)))
+			},
+			AlertSink::Webhook(url) => {
+				let (state, alert) = match event {
+					AlertEvent::Firing(alert) => ("firing", alert),
+					AlertEvent::Resolved(alert) => ("resolved", alert),
+				};
+
+				let response = reqwest::Client::new()
+					.post(url)
+					.json(&serde_json::json!({
+						"key": alert.key,
+						"message": alert.message,
+						"state": state,
+						"time": now,
+					}))
+					.send()
+					.await
+					.map_err(|error| Error::Sink(format!("{} webhook error: {:?}", url, error)))?;
+
+				if !response.status().is_success() {
+					return Err(Error::Sink(format!("{} webhook returned {}", url, response.status())));
+				}
+
+				Ok(())
+			},
+		}
+	}
+}
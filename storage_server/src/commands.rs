@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use chrono::Local;
+use tokio::sync::{ Mutex, Notify };
+
+use bansheelong_types::{ StorageCommand, StorageCommandRequest, StorageCommandResponse, StorageStatus };
+
+use crate::alerts::Alerts;
+use crate::jobs::{ Jobs, run_job };
+use crate::probe::{ Error, Probe };
+use crate::status::Storage;
+
+// an empty secret would let in anyone who sends one
+pub(crate) fn is_authorized(secret: &str, shared_secret: &str, tokens: &[String]) -> bool {
+	!secret.is_empty() && (secret == shared_secret || tokens.iter().any(|token| token == secret))
+}
+
+impl<P: Probe> Storage<P> {
+	pub(crate) fn start_scrub(&self, pool: &str) -> Result<(), Error> {
+		self.probe.run("zpool", &["scrub", pool]).map(|_| ())
+	}
+}
+
+// everything commands can reach into, shared with the status loop
+pub(crate) struct Commands<P: Probe> {
+	pub(crate) alerts: Arc<Mutex<Alerts>>,
+	pub(crate) jobs: Option<Arc<Mutex<Jobs>>>,
+	pub(crate) message: Arc<Mutex<StorageStatus>>,
+	pub(crate) refresh: Arc<Notify>, // wakes the status loop up early
+	pub(crate) shared_secret: String,
+	pub(crate) storage: Arc<Storage<P>>,
+	pub(crate) tokens: Vec<String>,
+}
+
+impl<P: Probe + Send + Sync + 'static> Commands<P> {
+	pub(crate) async fn run(&self, request: StorageCommandRequest) -> StorageCommandResponse {
+		let result = if is_authorized(&request.secret, &self.shared_secret, &self.tokens) {
+			self.run_command(request.command).await
+		} else {
+			Err(String::from("not authorized"))
+		};
+
+		StorageCommandResponse {
+			id: request.id,
+			result,
+		}
+	}
+
+	async fn run_command(&self, command: StorageCommand) -> Result<String, String> {
+		let result = match command {
+			StorageCommand::AcknowledgeAlert(key) => {
+				self.alerts.lock().await.acknowledge(&key)?;
+				format!("acknowledged {}", key)
+			},
+			StorageCommand::RefreshStatus => String::from("refreshing"),
+			StorageCommand::RunBackup(kind) => {
+				let jobs = self.jobs.as_ref().ok_or_else(|| String::from("the storage server does not run backups"))?;
+				let mut locked = jobs.lock().await;
+				if locked.config.get(kind).is_none() {
+					return Err(format!("there is no {} backup job", kind.get_name()));
+				}
+
+				if let Some(job) = locked.get_running() {
+					return Err(format!("{} is still running", job.name));
+				}
+
+				let name = locked.start(kind, Local::now().naive_local());
+				tokio::spawn(run_job(jobs.clone(), kind, name.clone()));
+				format!("started {}", name)
+			},
+			StorageCommand::StartScrub(pool) => {
+				// only pools zpool told us about, so the name cannot be turned into some other argument
+				let scrubbing = self.message.lock().await.pools.iter()
+					.find(|known| known.name == pool)
+					.map(|known| known.scan.as_ref().is_some_and(|scan| scan.in_progress))
+					.ok_or_else(|| format!("there is no pool named {}", pool))?;
+
+				if scrubbing {
+					return Err(format!("{} is already being scanned", pool));
+				}
+
+				let storage = self.storage.clone();
+				let scrubbed = pool.clone();
+				tokio::task::spawn_blocking(move || storage.start_scrub(&scrubbed))
+					.await
+					.map_err(|error| format!("scrub error: {:?}", error))?
+					.map_err(|error| format!("could not scrub {}: {:?}", pool, error))?;
+
+				format!("started scrubbing {}", pool)
+			},
+		};
+
+		// every command changes something the status shows
+		self.refresh.notify_one();
+		Ok(result)
+	}
+}
@@ -0,0 +1,350 @@
+use std::process::Stdio;
+use std::sync::Arc;
+
+use chrono::{ Datelike, Local, NaiveDateTime, TimeZone, Weekday };
+use serde::Deserialize;
+use tokio::io::{ AsyncBufReadExt, AsyncReadExt, BufReader };
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+use bansheelong_types::{ BackupJob, BackupJobState, BackupKind, JobStatusFlags };
+
+use crate::probe::Error;
+
+// how many finished jobs to remember, on top of the ones whose backups are still around
+const HISTORY_LENGTH: usize = 100;
+
+// commands are the program followed by its arguments, with {name} standing in for the backup's name
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct JobConfig {
+	pub(crate) create: Vec<String>,
+	#[serde(default)]
+	pub(crate) hour: u32, // local time the job starts at
+	pub(crate) keep: usize,
+	#[serde(default)]
+	pub(crate) remove: Vec<String>,
+}
+
+// kinds without a config are left to whatever ran them before
+#[derive(Clone, Debug, Default, Deserialize)]
+pub(crate) struct JobsConfig {
+	pub(crate) daily: Option<JobConfig>,
+	pub(crate) monthly: Option<JobConfig>,
+	pub(crate) weekly: Option<JobConfig>,
+}
+
+impl JobsConfig {
+	pub(crate) fn get(&self, kind: BackupKind) -> Option<&JobConfig> {
+		match kind {
+			BackupKind::Daily => self.daily.as_ref(),
+			BackupKind::Weekly => self.weekly.as_ref(),
+			BackupKind::Monthly => self.monthly.as_ref(),
+		}
+	}
+
+	pub(crate) fn read(path: &str) -> Result<Self, Error> {
+		let contents = std::fs::read_to_string(path).map_err(|error| Error::LocalInfo(format!("{} read error: {:?}", path, error)))?;
+		serde_json::from_str(&contents).map_err(|error| Error::LocalInfo(format!("{} parse error: {:?}", path, error)))
+	}
+}
+
+pub(crate) fn to_timestamp(time: NaiveDateTime) -> i64 {
+	Local.from_local_datetime(&time).earliest().map_or(time.timestamp(), |time| time.timestamp())
+}
+
+// the last time the job should have started, at or before now. weeklies run on sundays and monthlies on the first
+pub(crate) fn get_scheduled_time(kind: BackupKind, hour: u32, now: NaiveDateTime) -> NaiveDateTime {
+	let mut date = now.date();
+	loop {
+		let time = date.and_hms(hour.min(23), 0, 0);
+		let is_scheduled = match kind {
+			BackupKind::Daily => true,
+			BackupKind::Weekly => date.weekday() == Weekday::Sun,
+			BackupKind::Monthly => date.day() == 1,
+		};
+
+		if is_scheduled && time <= now {
+			return time;
+		}
+
+		date = date.pred();
+	}
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Jobs {
+	pub(crate) config: JobsConfig,
+	pub(crate) history: Vec<BackupJob>,
+	pub(crate) path: Option<String>, // where the history is saved
+}
+
+impl Jobs {
+	// jobs that were running when the server went down are not running anymore
+	pub(crate) fn read(config: JobsConfig, path: &str) -> Self {
+		let mut history: Vec<BackupJob> = match std::fs::read_to_string(path) {
+			Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|error| {
+				eprintln!("{} parse error: {:?}", path, error);
+				Vec::new()
+			}),
+			Err(_) => Vec::new(),
+		};
+
+		for job in history.iter_mut().filter(|job| job.is_active()) {
+			job.message = Some(String::from("the storage server stopped during the job"));
+			job.state = BackupJobState::Failed;
+		}
+
+		Jobs {
+			config,
+			history,
+			path: Some(String::from(path)),
+		}
+	}
+
+	pub(crate) fn write(&self) {
+		if let Some(path) = self.path.as_ref() {
+			if let Err(error) = std::fs::write(path, serde_json::to_string(&self.history).unwrap()) {
+				eprintln!("{} write error: {:?}", path, error);
+			}
+		}
+	}
+
+	pub(crate) fn get_running(&self) -> Option<&BackupJob> {
+		self.history.iter().find(|job| job.is_active())
+	}
+
+	fn get_running_mut(&mut self, name: &str) -> Option<&mut BackupJob> {
+		self.history.iter_mut().rev().find(|job| job.is_active() && job.name == name)
+	}
+
+	// one job at a time so they do not fight over the disks. monthlies go first since they come around least often.
+	// a job that failed waits for its next turn instead of being retried over and over
+	pub(crate) fn get_due(&self, now: NaiveDateTime) -> Option<BackupKind> {
+		if self.get_running().is_some() {
+			return None;
+		}
+
+		[BackupKind::Monthly, BackupKind::Weekly, BackupKind::Daily].into_iter().find(|kind| {
+			self.config.get(*kind).is_some_and(|config| {
+				let scheduled = to_timestamp(get_scheduled_time(*kind, config.hour, now));
+				!self.history.iter().any(|job| job.kind == *kind && job.started_at >= scheduled)
+			})
+		})
+	}
+
+	pub(crate) fn start(&mut self, kind: BackupKind, now: NaiveDateTime) -> String {
+		let mut name = format!("{}-{}", kind.get_name(), now.format("%Y-%m-%d"));
+
+		// a backup run by hand on a day one already ran needs a name of its own
+		if self.history.iter().any(|job| job.name == name) {
+			name = format!("{}-{}", name, now.format("%H%M%S"));
+		}
+
+		self.history.push(BackupJob {
+			exit_code: None,
+			finished_at: None,
+			kind,
+			message: None,
+			name: name.clone(),
+			progress: None,
+			removed: false,
+			started_at: to_timestamp(now),
+			state: BackupJobState::Running,
+		});
+
+		self.write();
+		name
+	}
+
+	// backups that are still around, oldest first
+	pub(crate) fn get_kept(&self, kind: BackupKind) -> Vec<&BackupJob> {
+		self.history.iter()
+			.filter(|job| job.kind == kind && job.state == BackupJobState::Succeeded && !job.removed)
+			.collect()
+	}
+
+	// the oldest backups past how many we keep
+	pub(crate) fn get_expired(&self, kind: BackupKind) -> Vec<String> {
+		let keep = self.config.get(kind).map_or(usize::MAX, |config| config.keep);
+		let kept = self.get_kept(kind);
+		kept.iter()
+			.take(kept.len().saturating_sub(keep))
+			.map(|job| job.name.clone())
+			.collect()
+	}
+
+	pub(crate) fn get_last_success(&self, kind: BackupKind) -> Option<i64> {
+		self.history.iter().rev()
+			.find(|job| job.kind == kind && job.state == BackupJobState::Succeeded)
+			.and_then(|job| job.finished_at)
+	}
+
+	// what clients get, the running job and the last run of each kind
+	pub(crate) fn get_recent(&self) -> Vec<BackupJob> {
+		let mut recent = Vec::new();
+		for kind in [BackupKind::Daily, BackupKind::Weekly, BackupKind::Monthly] {
+			if let Some(job) = self.history.iter().rev().find(|job| job.kind == kind) {
+				recent.push(job.clone());
+			}
+		}
+
+		recent
+	}
+
+	pub(crate) fn get_flags(&self) -> JobStatusFlags {
+		self.history.iter()
+			.filter(|job| job.is_active())
+			.fold(JobStatusFlags::IDLE, |flags, job| flags | job.kind.get_flags(job.state))
+	}
+
+	// forgets the oldest jobs, as long as their backups are gone
+	fn trim(&mut self) {
+		let mut extra = self.history.len().saturating_sub(HISTORY_LENGTH);
+		self.history.retain(|job| {
+			if extra > 0 && !job.is_active() && (job.removed || job.state != BackupJobState::Succeeded) {
+				extra -= 1;
+				false
+			} else {
+				true
+			}
+		});
+	}
+}
+
+fn get_command(template: &[String], name: &str) -> Option<Command> {
+	let (program, arguments) = template.split_first()?;
+	let mut command = Command::new(program.replace("{name}", name));
+	command.args(arguments.iter().map(|argument| argument.replace("{name}", name)))
+		.env("BANSHEELONG_BACKUP_NAME", name)
+		.stdin(Stdio::null())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped());
+
+	Some(command)
+}
+
+// runs a job's command. lines like "progress 42.5" on stdout update the job's progress if jobs is given. returns the
+// exit code, along with the last line on stderr
+async fn run_command(
+	template: &[String],
+	name: &str,
+	jobs: Option<&Arc<Mutex<Jobs>>>
+) -> Result<(i32, Option<String>), String> {
+	let mut child = get_command(template, name)
+		.ok_or_else(|| String::from("no command to run"))?
+		.spawn()
+		.map_err(|error| format!("{} spawn error: {:?}", template[0], error))?;
+
+	let stdout = child.stdout.take().unwrap();
+	let mut stderr = child.stderr.take().unwrap();
+
+	// both pipes are read at once so a chatty job cannot fill one up and stall
+	let read_stdout = async {
+		let mut lines = BufReader::new(stdout).lines();
+		while let Ok(Some(line)) = lines.next_line().await {
+			let progress = line.trim().strip_prefix("progress ").and_then(|progress| progress.trim().parse::<f64>().ok());
+			if let (Some(progress), Some(jobs)) = (progress, jobs) {
+				if let Some(job) = jobs.lock().await.get_running_mut(name) {
+					job.progress = Some(progress.clamp(0.0, 100.0));
+				}
+			}
+		}
+	};
+
+	let read_stderr = async {
+		let mut output = String::new();
+		let _ = stderr.read_to_string(&mut output).await;
+		output.lines().rev().find(|line| !line.trim().is_empty()).map(|line| line.trim().to_string())
+	};
+
+	let (_, message) = futures::join!(read_stdout, read_stderr);
+	let status = child.wait().await.map_err(|error| format!("{} wait error: {:?}", template[0], error))?;
+	match status.code() {
+		Some(code) => Ok((code, message)),
+		None => Err(format!("{} was killed", template[0])),
+	}
+}
+
+// runs a job that has already been started, then removes whatever backups it pushed past retention
+pub(crate) async fn run_job(jobs: Arc<Mutex<Jobs>>, kind: BackupKind, name: String) {
+	let config = match jobs.lock().await.config.get(kind).cloned() {
+		Some(config) => config,
+		None => return,
+	};
+
+	let result = run_command(&config.create, &name, Some(&jobs)).await;
+
+	let mut locked = jobs.lock().await;
+	let job = match locked.get_running_mut(&name) {
+		Some(job) => job,
+		None => return,
+	};
+
+	match result {
+		Ok((code, message)) => {
+			job.exit_code = Some(code);
+			job.message = message;
+			if code == 0 {
+				job.progress = Some(100.0);
+				job.state = BackupJobState::Succeeded; // so the new backup counts towards retention
+			} else {
+				job.state = BackupJobState::Failed;
+			}
+		},
+		Err(message) => {
+			job.message = Some(message);
+			job.state = BackupJobState::Failed;
+		},
+	}
+
+	if job.state == BackupJobState::Failed {
+		job.finished_at = Some(Local::now().timestamp());
+		locked.write();
+		return;
+	}
+
+	// without a way to remove backups, retention is left to whoever set up the job
+	let expired = if config.remove.is_empty() { Vec::new() } else { locked.get_expired(kind) };
+	if let Some(job) = locked.history.iter_mut().rev().find(|job| job.name == name) {
+		job.state = BackupJobState::Pruning;
+	}
+
+	locked.write();
+	drop(locked);
+
+	let mut failures = Vec::new();
+	for expired_name in expired.iter() {
+		let result = run_command(&config.remove, expired_name, None).await;
+
+		let mut locked = jobs.lock().await;
+		match result {
+			Ok((0, _)) => {
+				if let Some(job) = locked.history.iter_mut().find(|job| {
+					&job.name == expired_name && job.state == BackupJobState::Succeeded && !job.removed
+				}) {
+					job.removed = true;
+				}
+			},
+			Ok((code, message)) => failures.push(format!(
+				"could not remove {}, exit code {}{}",
+				expired_name,
+				code,
+				message.map_or(String::new(), |message| format!(": {}", message))
+			)),
+			Err(message) => failures.push(format!("could not remove {}: {}", expired_name, message)),
+		}
+	}
+
+	// a backup that was made is still a success, even if an old one could not be removed
+	let mut locked = jobs.lock().await;
+	if let Some(job) = locked.get_running_mut(&name) {
+		job.finished_at = Some(Local::now().timestamp());
+		job.state = BackupJobState::Succeeded;
+		if !failures.is_empty() {
+			job.message = Some(failures.join(", "));
+		}
+	}
+
+	locked.trim();
+	locked.write();
+}
@@ -1,329 +1,180 @@
-use std::path::Path;
-use std::process::{ Command, Stdio };
+mod alerts;
+mod commands;
+mod jobs;
+mod probe;
+mod smart;
+mod snapshots;
+mod status;
+mod tests;
+
 use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::time::{ Instant, SystemTime, UNIX_EPOCH };
 
+use chrono::Local;
 use futures::future;
 
+use tokio::io::AsyncWriteExt;
 use tokio::net::tcp::{ OwnedReadHalf, OwnedWriteHalf };
 use tokio::net::TcpListener;
 use tokio::time::{ Duration, sleep };
-use tokio::sync::Mutex;
-
-use bansheelong_types::{ JobStatusFlags, STORAGE_MESSAGE_COUNT };
-
-#[derive(Debug)]
-enum Error {
-	DiskUsage(String),
-	LocalInfo(String),
-	ZPool(String),
-}
-
-// run a command and return the stdout
-fn run_command(command: &mut Command) -> Result<String, Error> {
-	let child = command.stdout(Stdio::piped())
-		.spawn();
-
-	// make sure the command spawned
-	let child = match child {
-		Err(error) => {
-			return Err(Error::ZPool(format!("child spawn error: {:?}", error)));
-		},
-		Ok(child) => child,
-	};
-
-	// make sure we got an output
-	let child = match child.wait_with_output() {
-		Err(error) => {
-			return Err(Error::ZPool(format!("child wait error: {:?}", error)));
-		},
-		Ok(child) => child,
-	};
-
-	if !child.status.success() {
-		return Err(Error::ZPool(format!("child returned non-zero exit code: {:?}", child.status.code())));
-	}
-
-	// make sure we decode the utf8 correctly
-	match String::from_utf8(child.stdout) {
-		Err(error) => {
-			return Err(Error::ZPool(format!("utf8 decode error: {:?}", error)));
-		},
-		Ok(stdout) => Ok(stdout),
-	}
-}
-
-#[derive(Debug)]
-enum ZPoolStatus {
-	Error,
-	HardDriveReadWriteChecksumError,
-	HardDriveParseError,
-	HardDriveStateError,
-	Scrubbing,
-	Safe,
-}
-
-#[derive(Debug)]
-enum HardDriveStatus {
-	Degraded(i64, i64, i64),
-	Faulted(i64, i64, i64),
-	Offline(i64, i64, i64),
-	Online(i64, i64, i64),
-	Unavailable(i64, i64, i64),
-}
-
-fn get_hard_drive_status(line: &str) -> Option<HardDriveStatus> {
-	let is_hard_drive_line = ["ONLINE", "FAULTED", "DEGRADED", "UNAVAIL", "OFFLINE"].iter()
-		.fold(None, |is_hard_drive_line, item| {
-			if line.to_string().contains(item)
-				&& is_hard_drive_line == None
-				&& !line.to_string().contains("state:")
-			{
-				Some(item)
-			} else {
-				is_hard_drive_line
-			}
-		});
-
-	if is_hard_drive_line != None {
-		let split_line = line.split(" ")
-			.fold(Vec::new(), |mut accum, item| {
-				let mut trimmed = item.replace("\t", "");
-				trimmed = trimmed.trim().to_string();
-				if trimmed.len() == 0 {
-					accum
-				} else {
-					accum.push(trimmed);
-					accum
-				}
-			});
-
-		let read_errors: i64 = split_line[2].parse().unwrap_or(-1);
-		let write_errors: i64 = split_line[3].parse().unwrap_or(-1);
-		let checksum_errors: i64 = split_line[4].parse().unwrap_or(-1);
-
-		match is_hard_drive_line {
-			Some(&"ONLINE") => Some(HardDriveStatus::Online(read_errors, write_errors, checksum_errors)),
-			Some(&"FAULTED") => Some(HardDriveStatus::Faulted(read_errors, write_errors, checksum_errors)),
-			Some(&"DEGRADED") => Some(HardDriveStatus::Degraded(read_errors, write_errors, checksum_errors)),
-			Some(&"UNAVAIL") => Some(HardDriveStatus::Unavailable(read_errors, write_errors, checksum_errors)),
-			Some(&"OFFLINE") => Some(HardDriveStatus::Offline(read_errors, write_errors, checksum_errors)),
-			_ => None,
-		}
+use tokio::sync::{ Mutex, Notify };
+
+use bansheelong_types::{
+	BackupKind,
+	Resource,
+	SnapshotDiff,
+	StorageFrame,
+	StorageHistory,
+	StorageProtocolError,
+	StorageSample,
+	StorageStatus,
+	decode_storage_frame,
+	encode_storage_command_response,
+	encode_storage_history,
+	encode_storage_snapshot_diff,
+	encode_storage_snapshots,
+	encode_storage_status,
+	get_storage_alert_mail_spool,
+	get_storage_alert_todos,
+	get_storage_alert_webhook,
+	get_storage_alerts_path,
+	get_storage_btrfs_path,
+	get_storage_history_path,
+	get_storage_jobs_config_path,
+	get_storage_jobs_path,
+	get_storage_staging_path,
+	get_storage_state_path,
+	get_storage_targets,
+	get_storage_tokens,
+	get_todos_host,
+	get_todos_path,
+	get_todos_port,
+	get_todos_secret,
+	get_storage_frame_length,
+};
+
+use crate::alerts::{ AlertContext, AlertSink, Alerts, get_default_rules };
+use crate::commands::{ Commands, is_authorized };
+use crate::jobs::{ Jobs, JobsConfig, run_job };
+use crate::probe::SystemProbe;
+use crate::smart::Smart;
+use crate::snapshots::{ Restores, run_restores };
+use crate::status::Storage;
+
+// a socket gets text lines until it says hello, so clients from before the binary protocol keep working
+fn encode_message(status: &StorageStatus, binary: bool) -> Vec<u8> {
+	if binary {
+		encode_storage_status(status)
 	} else {
-		return None;
-	}
-}
-
-fn get_zpool_error() -> Result<ZPoolStatus, Error> {
-	let stdout = run_command(
-		Command::new("zpool")
-			.arg("status")
-	)?;
-
-	// analyze the output
-	for line in stdout.split('\n') {
-		// parse scrubbing information
-		if line.contains("scan:") && line.contains("scrub in progress") {
-			return Ok(ZPoolStatus::Scrubbing);
-		}
-
-		// parse hard drive information
-		match get_hard_drive_status(line) {
-			Some(HardDriveStatus::Online(read_errors, write_errors, checksum_errors)) => {
-				// return error if we have error counts set
-				if read_errors == -1 || write_errors == -1 || checksum_errors == -1 {
-					return Ok(ZPoolStatus::HardDriveParseError);
-				} else if read_errors != 0 || write_errors != 0 || checksum_errors != 0 {
-					return Ok(ZPoolStatus::HardDriveReadWriteChecksumError);
-				}
-			},
-			None => {},
-			_ => {
-				return Ok(ZPoolStatus::HardDriveStateError);
-			},
-		}
-
-		if line.contains("errors:") && line != "errors: No known data errors" {
-			return Ok(ZPoolStatus::Error)
-		}
-	}
-
-	Ok(ZPoolStatus::Safe)
-}
-
-fn get_disk_usage() -> Result<(u64, u64), Error> {
-	let stdout = run_command(
-		Command::new("df")
-			.arg("-B1")
-	)?;
-
-	// analyze the output
-	let mut used_size = 0;
-	let mut total_size = 0;
-	for line in stdout.split('\n') {
-		if line.contains("bansheerubber") {
-			let items: Vec<String> = line.split(" ")
-				.filter(|i| i.len() > 0)
-				.map(|i| i.to_string())
-				.collect();
-
-			used_size = match items[2].parse() {
-				Err(error) => {
-					return Err(Error::DiskUsage(format!("used size parse error: {:?}", error)));
-				},
-				Ok(size) => size,
-			};
-
-			total_size = match items[1].parse() {
-				Err(error) => {
-					return Err(Error::DiskUsage(format!("total size parse error: {:?}", error)));
-				},
-				Ok(size) => size,
-			};
-		}
-	}
-
-	Ok((used_size, total_size))
-}
-
-fn get_btrfs_disk_usage() -> Result<(u64, u64), Error> {
-	let stdout = run_command(
-		Command::new("btrfs")
-			.arg("fi")
-			.arg("usage")
-			.arg("-b")
-			.arg("/bansheebtrfs/")
-	)?;
-
-	let get_number = |line: &str| {
-		let mut number = String::new();
-		for character in line.chars() {
-			if character.is_ascii_digit() {
-				number.push(character);
-			}
-		}
-		return number;
-	};
-
-	let mut used_size = 0;
-	let mut total_size = 0;
-	for line in stdout.split('\n') {
-		if line.contains("Device size:") {
-			total_size = get_number(line).parse::<u64>().unwrap();
-		} else if line.contains("Used:\t") {
-			used_size = get_number(line).parse::<u64>().unwrap();
-		}
-	}
-
-	Ok((used_size, total_size))
-}
-
-fn get_btrfs_backup_count() -> Result<u64, Error> {
-	let mut total = 0;
-
-	for path in std::fs::read_dir("/bansheebtrfs/").unwrap() {
-		if path.unwrap().path().to_str().unwrap().contains("home_backup") {
-			total += 1;
-		}
-	}
-
-	Ok(total)
-}
-
-fn get_backups_count() -> Result<(u8, u8), Error> {
-	let read_count = |file_name: &str| {
-		let value = match std::fs::read_to_string(format!("/home/me/bansheestorage/{}-count", file_name)) {
-			Err(error) => {
-				return Err(Error::LocalInfo(format!("{} count read error: {:?}", file_name, error)));
-			},
-			Ok(value) => value,
-		};
-
-		match value.trim().parse::<u8>() {
-			Err(error) => {
-				return Err(Error::LocalInfo(format!("{} parse error: {:?}", file_name, error)));
-			},
-			Ok(value) => Ok(value),
-		}
-	};
-
-	Ok((read_count("dailies")?, read_count("weeklies")?))
-}
-
-fn get_job_flags() -> Result<JobStatusFlags, Error> {
-	let mut result = JobStatusFlags::IDLE;
-
-	match get_zpool_error() { // handle zpool error by indicating it on the bansheelong
-		Err(_) => {
-			result |= JobStatusFlags::ZPOOL_ERROR;
-		},
-		Ok(ZPoolStatus::Error) => {
-			result |= JobStatusFlags::ZPOOL_ERROR;
-		},
-		Ok(ZPoolStatus::HardDriveParseError) => {
-			result |= JobStatusFlags::ZPOOL_HARD_DRIVE_PARSE_ERROR;
-		},
-		Ok(ZPoolStatus::HardDriveReadWriteChecksumError) => {
-			result |= JobStatusFlags::ZPOOL_HARD_DRIVE_RW_ERROR;
-		},
-		Ok(ZPoolStatus::HardDriveStateError) => {
-			result |= JobStatusFlags::ZPOOL_HARD_DRIVE_STATE_ERROR;
-		},
-		Ok(ZPoolStatus::Scrubbing) => {
-			result |= JobStatusFlags::ZPOOL_SCRUBBING;
-		},
-		Ok(ZPoolStatus::Safe) => {},
-	};
-
-	// check daily backup
-	if Path::new("/home/me/bansheestorage/writing-daily-backup").exists() {
-		result |= JobStatusFlags::DOWNLOADING_DAILY;
-	}
-
-	// check weekly backup
-	if Path::new("/home/me/bansheestorage/writing-weekly-backup").exists() {
-		result |= JobStatusFlags::CREATING_WEEKLY;
-	}
-
-	// check monthly backup
-	if Path::new("/home/me/bansheestorage/writing-monthly-backup").exists() {
-		result |= JobStatusFlags::CREATING_MONTHLY;
-	}
-
-	// check git backup
-	if Path::new("/home/me/bansheestorage/writing-git-backup").exists() {
-		result |= JobStatusFlags::SYNCING_GITHUB;
-	}
-
-	// check removing daily backup
-	if Path::new("/home/me/bansheestorage/removing-daily-backup").exists() {
-		result |= JobStatusFlags::REMOVING_DAILY;
+		status.to_legacy_line().into_bytes()
 	}
-
-	// check removing weekly backup
-	if Path::new("/home/me/bansheestorage/removing-weekly-backup").exists() {
-		result |= JobStatusFlags::REMOVING_WEEKLY;
-	}
-
-	// check btrbk status
-	if Path::new("/home/me/bansheestorage/writing-btrbk").exists() {
-		result |= JobStatusFlags::WRITING_BTRBK;
-	}
-
-	Ok(result)
 }
 
 async fn read_socket(
-	socket: Arc<OwnedReadHalf>
+	socket: Arc<OwnedReadHalf>,
+	writable: Arc<Mutex<OwnedWriteHalf>>,
+	binary: Arc<AtomicBool>,
+	message: Arc<Mutex<StorageStatus>>,
+	history: Arc<Mutex<StorageHistory>>,
+	restores: Arc<Mutex<Restores>>,
+	commands: Arc<Commands<SystemProbe>>
 ) -> Result<(), tokio::io::Error> {
+	let mut buffer = Vec::new();
 	loop { // keep reading forever, until socket closes
 		socket.readable().await?;
 
-		let mut buffer = Vec::new();
 		match socket.try_read_buf(&mut buffer) {
 			Ok(0) => break,
 			Ok(n) => {
 				println!("read {} bytes", n);
+
+				// one read can hold several frames, or only the start of one
+				loop {
+					match decode_storage_frame(&buffer) {
+						Ok(Some((frame, used))) => {
+							buffer.drain(..used);
+							match frame {
+								StorageFrame::Hello if !binary.swap(true, Ordering::SeqCst) => {
+									// the client was sent a text line when it connected, so follow it up with a frame
+									let status = message.lock().await.clone();
+									write_socket(writable.clone(), encode_message(&status, true)).await?;
+								},
+								StorageFrame::HistoryQuery(query) => {
+									let reply = history.lock().await.reply(&query);
+									write_socket(writable.clone(), encode_storage_history(&reply)).await?;
+								},
+								// snapshots are found by running commands, which block
+								StorageFrame::SnapshotsQuery => {
+									let storage = commands.storage.clone();
+									let snapshots = tokio::task::spawn_blocking(move || storage.get_snapshots()).await.unwrap_or_default();
+									write_socket(writable.clone(), encode_storage_snapshots(&snapshots)).await?;
+								},
+								StorageFrame::SnapshotDiffQuery(mut query) => {
+									let authorized = is_authorized(&query.secret, &commands.shared_secret, &commands.tokens);
+									query.secret.clear();
+
+									if !authorized {
+										let diff = SnapshotDiff {
+											changes: Vec::new(),
+											error: Some(String::from("not authorized")),
+											query,
+											truncated: false,
+										};
+										write_socket(writable.clone(), encode_storage_snapshot_diff(&diff)).await?;
+										continue;
+									}
+
+									let storage = commands.storage.clone();
+									let diff = tokio::task::spawn_blocking(move || storage.diff_snapshots(&query)).await;
+									match diff {
+										Ok(diff) => write_socket(writable.clone(), encode_storage_snapshot_diff(&diff)).await?,
+										Err(error) => eprintln!("snapshot diff error {:?}", error),
+									}
+								},
+								StorageFrame::Command(request) => {
+									let command = request.command.get_description();
+									let response = commands.run(request).await;
+									match response.result.as_ref() {
+										Ok(message) => println!("command {}: {}", command, message),
+										Err(error) => eprintln!("command {} error {}", command, error),
+									}
+
+									write_socket(writable.clone(), encode_storage_command_response(&response)).await?;
+								},
+								// the restore shows up in the status, so there is nothing to reply with
+								StorageFrame::RestoreRequest(mut request) => {
+									let authorized = is_authorized(&request.secret, &commands.shared_secret, &commands.tokens);
+									request.secret.clear();
+
+									if !authorized {
+										eprintln!("restore of {} from {} is not authorized", request.path, request.snapshot);
+										continue;
+									}
+
+									let storage = commands.storage.clone();
+									let snapshots = tokio::task::spawn_blocking(move || storage.get_snapshots()).await.unwrap_or_default();
+									if restores.lock().await.queue(request, &snapshots) {
+										tokio::spawn(run_restores(restores.clone()));
+									}
+								},
+								_ => {},
+							}
+						},
+						Ok(None) => break,
+						Err(error) => {
+							eprintln!("storage protocol error {}", error);
+
+							// without the magic there is no telling where the next frame starts, otherwise skip the bad one
+							match get_storage_frame_length(&buffer) {
+								Some(length) if error != StorageProtocolError::BadMagic => {
+									buffer.drain(..length.min(buffer.len()));
+								},
+								_ => buffer.clear(),
+							}
+						},
+					}
+				}
 			},
 			Err(ref error) if error.kind() == tokio::io::ErrorKind::WouldBlock => {
 				continue;
@@ -337,33 +188,72 @@ async fn read_socket(
 	Ok(())
 }
 
+// replies and status broadcasts share the socket, so the lock is held until the whole frame is out
 async fn write_socket(
-	socket: Arc<OwnedWriteHalf>,
-	message: String,
+	socket: Arc<Mutex<OwnedWriteHalf>>,
+	message: Vec<u8>,
 ) -> Result<(), tokio::io::Error> {
-	loop { // keep looping until we can write
-		socket.writable().await?;
-
-		match socket.try_write(message.as_bytes()) {
-			Ok(_) => {
-				break;
-			},
-			Err(ref error) if error.kind() == tokio::io::ErrorKind::WouldBlock => {
-				continue;
-			}
-			Err(error) => {
-				return Err(error)
-			}
-		}
-	}
-
-	Ok(())
+	socket.lock().await.write_all(&message).await
 }
 
 #[tokio::main]
 async fn main() {
 	let sockets = Arc::new(Mutex::new(Vec::new()));
-	let message = Arc::new(Mutex::new(String::new()));
+	let message = Arc::new(Mutex::new(StorageStatus::default()));
+
+	// start over if there is no history yet, or it cannot be read
+	let history_path = get_storage_history_path();
+	let history = Arc::new(Mutex::new(match StorageHistory::read(&history_path) {
+		Ok(history) => history,
+		Err(error) => {
+			eprintln!("could not read history {}", error.message);
+			StorageHistory::default()
+		},
+	}));
+
+
+	let restores = Arc::new(Mutex::new(Restores {
+		staging_path: get_storage_staging_path(),
+		..Restores::default()
+	}));
+
+	let alerts_path = get_storage_alerts_path();
+	let mut alerts = Alerts {
+		rules: get_default_rules(),
+		..Alerts::default()
+	};
+
+	if let Err(error) = alerts.read_firing(&alerts_path) {
+		eprintln!("could not read firing alerts {:?}", error);
+	}
+
+	let alerts = Arc::new(Mutex::new(alerts));
+
+	// backups are only run from here if there is a config for them
+	let jobs = get_storage_jobs_config_path().and_then(|path| match JobsConfig::read(&path) {
+		Ok(config) => Some(Arc::new(Mutex::new(Jobs::read(config, &get_storage_jobs_path())))),
+		Err(error) => {
+			eprintln!("could not read jobs config {:?}", error);
+			None
+		},
+	});
+
+	let refresh = Arc::new(Notify::new());
+	let commands = Arc::new(Commands {
+		alerts: alerts.clone(),
+		jobs: jobs.clone(),
+		message: message.clone(),
+		refresh: refresh.clone(),
+		shared_secret: get_todos_secret(),
+		// requests from clients get their own storage, the status loop has one too
+		storage: Arc::new(Storage {
+			btrfs_path: get_storage_btrfs_path(),
+			probe: SystemProbe,
+			state_path: get_storage_state_path(),
+			targets: get_storage_targets(),
+		}),
+		tokens: get_storage_tokens(),
+	});
 
 	future::join(
 		async { // server listener
@@ -387,20 +277,27 @@ async fn main() {
 
 				// move to longer lifetime
 				let mut locked = sockets_reference.lock().await;
-				locked.push((Arc::new(split.0), Arc::new(split.1)));
+				locked.push((Arc::new(split.0), Arc::new(Mutex::new(split.1)), Arc::new(AtomicBool::new(false))));
 
 				// send status message right away
 				let index = locked.len() - 1;
 				let locked_message = message.lock().await;
-				if let Err(error) = write_socket(locked[index].1.clone(), locked_message.clone()).await {
+				if let Err(error) = write_socket(locked[index].1.clone(), encode_message(&locked_message, false)).await {
 					eprintln!("socket write error {:?}", error);
 				}
+				drop(locked_message);
 
 				// spawn read task
 				let read_half = locked[index].0.clone();
+				let write_half = locked[index].1.clone();
+				let binary = locked[index].2.clone();
+				let message = message.clone();
+				let history = history.clone();
+				let restores = restores.clone();
+				let commands = commands.clone();
 				let sockets_reference = sockets_reference.clone();
 				tokio::spawn(async move {
-					if let Err(error) = read_socket(read_half).await {
+					if let Err(error) = read_socket(read_half, write_half, binary, message, history, restores, commands).await {
 						eprintln!("socket read error {:?}", error);
 					}
 
@@ -411,88 +308,139 @@ async fn main() {
 		},
 		async { // status getter
 			let sockets_reference = sockets.clone();
+			let storage = Arc::new(Storage {
+				btrfs_path: get_storage_btrfs_path(),
+				probe: SystemProbe,
+				state_path: get_storage_state_path(),
+				targets: get_storage_targets(),
+			});
+
+			// sinks are only used if they are set up
+			let mut sinks = Vec::new();
+			if let Some(path) = get_storage_alert_mail_spool() {
+				sinks.push(AlertSink::MailSpool(path));
+			}
 
+			if let Some(url) = get_storage_alert_webhook() {
+				sinks.push(AlertSink::Webhook(url));
+			}
+
+			if get_storage_alert_todos() {
+				sinks.push(AlertSink::Todos(Resource {
+					reference: format!("https://{}{}{}", get_todos_host(), get_todos_port(), get_todos_path()),
+				}));
+			}
+
+			let mut smart = Smart::default();
 			let mut sleep_time = 0;
+			let mut last_write: Option<Instant> = None;
 			loop {
-				sleep(Duration::from_secs(sleep_time)).await;
+				// commands from clients cut the wait short, so their effects show up right away
+				tokio::select! {
+					_ = sleep(Duration::from_secs(sleep_time)) => {},
+					_ = refresh.notified() => {},
+				}
 				sleep_time = 5;
 
-				// get server job status
-				let job_status = match get_job_flags() {
-					Err(error) => {
-						eprintln!("job status error: {:?}", error);
-						0
-					},
-					Ok(value) => value.bits(),
-				};
+				let mut status = storage.get_status();
+				let mut last_daily = storage.get_last_daily().ok();
+
+				// jobs we run ourselves are reported from their history instead of the files the scripts leave around
+				if let Some(jobs) = jobs.as_ref() {
+					let now = Local::now().naive_local();
+					let mut locked_jobs = jobs.lock().await;
+					if let Some(kind) = locked_jobs.get_due(now) {
+						let name = locked_jobs.start(kind, now);
+						println!("starting {}", name);
+						tokio::spawn(run_job(jobs.clone(), kind, name));
+					}
 
-				// get df
-				let (used_size, total_size) = match get_disk_usage() {
-					Err(error) => {
-						eprintln!("disk usage error: {:?}", error);
-						(0, 0)
-					},
-					Ok(value) => value
-				};
+					status.jobs = locked_jobs.get_recent();
+					status.job_flags |= locked_jobs.get_flags();
 
-				// get btrfs disk usage
-				let (btrfs_used_size, btrfs_total_size) = match get_btrfs_disk_usage() {
-					Err(error) => {
-						eprintln!("btrfs usage error: {:?}", error);
-						(0, 0)
-					},
-					Ok(value) => value
-				};
+					if locked_jobs.config.daily.is_some() {
+						status.dailies = locked_jobs.get_kept(BackupKind::Daily).len().min(u8::MAX as usize) as u8;
+						last_daily = locked_jobs.get_last_success(BackupKind::Daily);
+					}
 
-				// get btrfs backup count
-				let btrfs_backup_count = match get_btrfs_backup_count() {
-					Err(error) => {
-						eprintln!("btrfs backup count error: {:?}", error);
-						return 0;
-					},
-					Ok(value) => value,
-				};
+					if locked_jobs.config.weekly.is_some() {
+						status.weeklies = locked_jobs.get_kept(BackupKind::Weekly).len().min(u8::MAX as usize) as u8;
+					}
+				}
+
+				status.restores = restores.lock().await.restores.clone();
+
+				// samples are taken every time, but only written out once a minute to go easy on the disk
+				let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs() as i64);
+				let mut locked_history = history.lock().await;
+				if let Some(sample) = StorageSample::from_status(&status, time) {
+					locked_history.record(sample);
+				}
+				if last_write.is_none_or(|last_write| last_write.elapsed() >= Duration::from_secs(60)) {
+					if let Err(error) = locked_history.write(&history_path) {
+						eprintln!("could not write history {}", error.message);
+					}
 
-				// get dailies/weeklies count
-				let (dailies, weeklies) = match get_backups_count() {
+					last_write = Some(Instant::now());
+				}
+				drop(locked_history);
+
+				// smartctl runs once per drive and can take a while, so it stays off the async threads like the snapshot queries
+				let smart_storage = storage.clone();
+				let pools = status.pools.clone();
+				smart = match tokio::task::spawn_blocking(move || {
+					smart.update(&smart_storage, &pools, time);
+					smart
+				}).await {
+					Ok(smart) => smart,
 					Err(error) => {
-						eprintln!("dailies/weeklies error: {:?}", error);
-						(0, 0)
+						eprintln!("smart error {:?}", error);
+						Smart::default()
 					},
-					Ok(value) => value
 				};
+				status.smart = smart.get_drives();
+
+				// the last daily is only missing if no daily ever finished, which the rule reports on its own
+				let mut locked_alerts = alerts.lock().await;
+				let events = locked_alerts.update(&AlertContext {
+					last_daily,
+					now: time,
+					status: &status,
+				});
 
-				// update message
-				let mut locked_message = message.lock().await;
-				*locked_message = format!(
-					"{} {} {} {} {} {} {} {}\n",
-					job_status,
-					used_size,
-					total_size,
-					btrfs_used_size,
-					btrfs_total_size,
-					btrfs_backup_count,
-					dailies,
-					weeklies
-				);
-
-				let whitespace_count = locked_message.chars()
-					.fold(0, |prev, c| {
-						prev + if c.is_whitespace() {
-							1
-						} else {
-							0
+				status.alerts = locked_alerts.get_storage_alerts();
+				if !events.is_empty() {
+					if let Err(error) = locked_alerts.write_firing(&alerts_path) {
+						eprintln!("could not write firing alerts {:?}", error);
+					}
+				}
+				drop(locked_alerts);
+
+				if !events.is_empty() {
+
+					// sinks can be slow, so they do not hold up the status loop
+					let sinks = sinks.clone();
+					tokio::spawn(async move {
+						for event in events.iter() {
+							println!("alert {:?}", event);
+							for sink in sinks.iter() {
+								if let Err(error) = sink.send(event, time).await {
+									eprintln!("alert sink error {:?}", error);
+								}
+							}
 						}
 					});
-
-				if whitespace_count != STORAGE_MESSAGE_COUNT {
-					eprintln!("error: unexpected sent message word length {}", whitespace_count);
 				}
 
-				// send to all sockets
+				// update message
+				let mut locked_message = message.lock().await;
+				*locked_message = status;
+
+				// send to all sockets, in whichever format they asked for
 				let locked = sockets_reference.lock().await;
-				for (_, writable) in locked.iter() {
-					if let Err(error) = write_socket(writable.clone(), locked_message.clone()).await {
+				for (_, writable, binary) in locked.iter() {
+					let encoded = encode_message(&locked_message, binary.load(Ordering::SeqCst));
+					if let Err(error) = write_socket(writable.clone(), encoded).await {
 						eprintln!("socket write error {:?}", error);
 					}
 				}
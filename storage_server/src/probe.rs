@@ -0,0 +1,196 @@
+use std::path::Path;
+use std::process::{ Command, Stdio };
+use std::time::UNIX_EPOCH;
+
+#[cfg(test)]
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub(crate) enum Error {
+	Command(String),
+	DiskUsage(String),
+	LocalInfo(String),
+	Sink(String),
+	Smart(String),
+}
+
+impl Error {
+	// what went wrong, for showing to people instead of logging
+	pub(crate) fn get_message(&self) -> &str {
+		match self {
+			Error::Command(message)
+			| Error::DiskUsage(message)
+			| Error::LocalInfo(message)
+			| Error::Sink(message)
+			| Error::Smart(message) => message,
+		}
+	}
+}
+
+// everything the status getter needs to know about the machine goes through here, so it can be run against captured
+// output instead of the real NAS
+pub(crate) trait Probe {
+	// run a command and return the stdout
+	fn run(&self, program: &str, arguments: &[&str]) -> Result<String, Error>;
+	// the same, for commands like smartctl that report what they found through their exit code
+	fn run_ignoring_status(&self, program: &str, arguments: &[&str]) -> Result<String, Error>;
+	fn exists(&self, path: &str) -> bool;
+	fn read_to_string(&self, path: &str) -> Result<String, Error>;
+	// when the file was last written, in unix seconds
+	fn modified(&self, path: &str) -> Result<i64, Error>;
+	// the full paths of everything in the directory
+	fn read_dir(&self, path: &str) -> Result<Vec<String>, Error>;
+}
+
+pub(crate) struct SystemProbe;
+
+impl SystemProbe {
+	fn output(&self, program: &str, arguments: &[&str], check_status: bool) -> Result<String, Error> {
+		let child = Command::new(program)
+			.args(arguments)
+			.stdout(Stdio::piped())
+			.spawn();
+
+		// make sure the command spawned
+		let child = match child {
+			Err(error) => {
+				return Err(Error::Command(format!("{} spawn error: {:?}", program, error)));
+			},
+			Ok(child) => child,
+		};
+
+		// make sure we got an output
+		let child = match child.wait_with_output() {
+			Err(error) => {
+				return Err(Error::Command(format!("{} wait error: {:?}", program, error)));
+			},
+			Ok(child) => child,
+		};
+
+		if check_status && !child.status.success() {
+			return Err(Error::Command(format!("{} returned non-zero exit code: {:?}", program, child.status.code())));
+		}
+
+		// make sure we decode the utf8 correctly
+		match String::from_utf8(child.stdout) {
+			Err(error) => Err(Error::Command(format!("{} utf8 decode error: {:?}", program, error))),
+			Ok(stdout) => Ok(stdout),
+		}
+	}
+}
+
+impl Probe for SystemProbe {
+	fn run(&self, program: &str, arguments: &[&str]) -> Result<String, Error> {
+		self.output(program, arguments, true)
+	}
+
+	fn run_ignoring_status(&self, program: &str, arguments: &[&str]) -> Result<String, Error> {
+		self.output(program, arguments, false)
+	}
+
+	fn exists(&self, path: &str) -> bool {
+		Path::new(path).exists()
+	}
+
+	fn read_to_string(&self, path: &str) -> Result<String, Error> {
+		std::fs::read_to_string(path).map_err(|error| Error::LocalInfo(format!("{} read error: {:?}", path, error)))
+	}
+
+	fn modified(&self, path: &str) -> Result<i64, Error> {
+		let modified = std::fs::metadata(path)
+			.and_then(|metadata| metadata.modified())
+			.map_err(|error| Error::LocalInfo(format!("{} metadata error: {:?}", path, error)))?;
+
+		match modified.duration_since(UNIX_EPOCH) {
+			Ok(duration) => Ok(duration.as_secs() as i64),
+			Err(error) => Err(Error::LocalInfo(format!("{} modified before the epoch: {:?}", path, error))),
+		}
+	}
+
+	fn read_dir(&self, path: &str) -> Result<Vec<String>, Error> {
+		let entries = std::fs::read_dir(path).map_err(|error| Error::LocalInfo(format!("{} read error: {:?}", path, error)))?;
+
+		let mut paths = Vec::new();
+		for entry in entries {
+			match entry {
+				Ok(entry) => paths.push(entry.path().to_string_lossy().to_string()),
+				Err(error) => return Err(Error::LocalInfo(format!("{} read error: {:?}", path, error))),
+			}
+		}
+
+		Ok(paths)
+	}
+}
+
+// replays captured command output and pretends files exist. commands are looked up by their program and arguments
+// joined with spaces, anything left out fails like it would on a machine without it
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct FixtureProbe {
+	commands: HashMap<String, String>,
+	directories: HashMap<String, Vec<String>>,
+	files: HashMap<String, String>,
+	modified: HashMap<String, i64>,
+}
+
+#[cfg(test)]
+impl FixtureProbe {
+	pub(crate) fn command(mut self, command: &str, output: &str) -> Self {
+		self.commands.insert(String::from(command), String::from(output));
+		self
+	}
+
+	pub(crate) fn directory(mut self, path: &str, entries: &[&str]) -> Self {
+		self.directories.insert(
+			String::from(path),
+			entries.iter().map(|entry| format!("{}/{}", path.trim_end_matches('/'), entry)).collect()
+		);
+		self
+	}
+
+	pub(crate) fn file(mut self, path: &str, contents: &str) -> Self {
+		self.files.insert(String::from(path), String::from(contents));
+		self
+	}
+
+	pub(crate) fn modified(mut self, path: &str, time: i64) -> Self {
+		self.modified.insert(String::from(path), time);
+		self
+	}
+}
+
+#[cfg(test)]
+impl Probe for FixtureProbe {
+	fn run(&self, program: &str, arguments: &[&str]) -> Result<String, Error> {
+		let command = std::iter::once(program).chain(arguments.iter().copied()).collect::<Vec<&str>>().join(" ");
+		self.commands.get(&command)
+			.cloned()
+			.ok_or_else(|| Error::Command(format!("{} spawn error: no fixture", command)))
+	}
+
+	fn run_ignoring_status(&self, program: &str, arguments: &[&str]) -> Result<String, Error> {
+		self.run(program, arguments)
+	}
+
+	fn exists(&self, path: &str) -> bool {
+		self.files.contains_key(path) || self.directories.contains_key(path)
+	}
+
+	fn read_to_string(&self, path: &str) -> Result<String, Error> {
+		self.files.get(path)
+			.cloned()
+			.ok_or_else(|| Error::LocalInfo(format!("{} read error: no fixture", path)))
+	}
+
+	fn modified(&self, path: &str) -> Result<i64, Error> {
+		self.modified.get(path)
+			.copied()
+			.ok_or_else(|| Error::LocalInfo(format!("{} metadata error: no fixture", path)))
+	}
+
+	fn read_dir(&self, path: &str) -> Result<Vec<String>, Error> {
+		self.directories.get(path)
+			.cloned()
+			.ok_or_else(|| Error::LocalInfo(format!("{} read error: no fixture", path)))
+	}
+}
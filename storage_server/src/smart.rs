@@ -0,0 +1,61 @@
+use std::collections::{ BTreeMap, BTreeSet };
+
+use bansheelong_types::{ SmartDrive, ZPool, parse_smartctl };
+
+use crate::probe::{ Error, Probe };
+use crate::status::Storage;
+
+// smartctl is slow and wakes drives up, so it runs far less often than the rest of the status
+pub(crate) const SMART_INTERVAL: i64 = 10 * 60;
+
+impl<P: Probe> Storage<P> {
+	// `-n standby` leaves sleeping drives alone, which comes back as an error
+	pub(crate) fn get_smart(&self, device: &str, now: i64) -> Result<SmartDrive, Error> {
+		let output = self.probe.run_ignoring_status("smartctl", &["--json", "-a", "-n", "standby", device])?;
+		parse_smartctl(&output, now).map_err(|error| Error::Smart(format!("{} {}", device, error)))
+	}
+}
+
+// the last reading of every pool member, by device path. a drive that could not be read keeps its last reading, and
+// clients can tell how old it is from when it was checked
+#[derive(Debug, Default)]
+pub(crate) struct Smart {
+	pub(crate) drives: BTreeMap<String, SmartDrive>,
+	pub(crate) last_check: Option<i64>, // unix seconds
+}
+
+impl Smart {
+	// returns true if the drives were checked
+	pub(crate) fn update<P: Probe>(&mut self, storage: &Storage<P>, pools: &[ZPool], now: i64) -> bool {
+		if self.last_check.is_some_and(|last_check| now - last_check < SMART_INTERVAL) {
+			return false;
+		}
+
+		self.last_check = Some(now);
+
+		let devices = pools.iter()
+			.flat_map(|pool| pool.get_drives())
+			.map(|drive| drive.get_device_path())
+			.collect::<BTreeSet<String>>();
+
+		// forget drives that left the pools. no pools at all means zpool could not be read, not that every drive is gone
+		if !pools.is_empty() {
+			self.drives.retain(|device, _| devices.contains(device));
+		}
+
+		for device in devices {
+			match storage.get_smart(&device, now) {
+				Ok(drive) => {
+					self.drives.insert(device, drive);
+				},
+				Err(error) => eprintln!("smartctl error {:?}", error),
+			}
+		}
+
+		true
+	}
+
+	pub(crate) fn get_drives(&self) -> Vec<SmartDrive> {
+		self.drives.values().cloned().collect()
+	}
+}
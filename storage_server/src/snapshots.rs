@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+use bansheelong_types::{
+	Restore,
+	RestoreRequest,
+	RestoreState,
+	SNAPSHOT_DIFF_MAX_CHANGES,
+	Snapshot,
+	SnapshotDiff,
+	SnapshotDiffQuery,
+	SnapshotFile,
+	diff_file_lists,
+	is_safe_relative_path,
+	parse_btrfs_snapshots,
+	parse_file_list,
+	parse_zfs_snapshots,
+};
+
+use crate::probe::{ Error, Probe };
+use crate::status::Storage;
+
+// how many finished restores clients get to see
+const RESTORE_HISTORY_LENGTH: usize = 20;
+
+fn join_path(root: &str, path: &str) -> String {
+	if path.is_empty() {
+		root.trim_end_matches('/').to_string()
+	} else {
+		format!("{}/{}", root.trim_end_matches('/'), path.trim_end_matches('/'))
+	}
+}
+
+impl<P: Probe> Storage<P> {
+	// zfs and btrfs snapshots, oldest first. a filesystem we cannot list is left out
+	pub(crate) fn get_snapshots(&self) -> Vec<Snapshot> {
+		let mut snapshots = Vec::new();
+		match self.probe.run("zfs", &["list", "-H", "-p", "-t", "snapshot", "-o", "name,creation,used"]) {
+			Ok(list) => {
+				let mountpoints = self.probe.run("zfs", &["list", "-H", "-o", "name,mountpoint", "-t", "filesystem"])
+					.unwrap_or_else(|error| {
+						eprintln!("zfs mountpoint error: {:?}", error);
+						String::new()
+					});
+
+				snapshots.extend(parse_zfs_snapshots(&list, &mountpoints));
+			},
+			Err(error) => eprintln!("zfs snapshot error: {:?}", error),
+		}
+
+		match self.probe.run("btrfs", &["subvolume", "list", "-s", &self.btrfs_path]) {
+			Ok(list) => {
+				// quotas being off just means there are no sizes
+				let qgroups = self.probe.run("btrfs", &["qgroup", "show", "--raw", &self.btrfs_path]).unwrap_or_default();
+				snapshots.extend(parse_btrfs_snapshots(&list, &qgroups, &self.btrfs_path));
+			},
+			Err(error) => eprintln!("btrfs snapshot error: {:?}", error),
+		}
+
+		snapshots.sort_by_key(|snapshot| snapshot.created_at);
+		snapshots
+	}
+
+	pub(crate) fn get_file_list(&self, snapshot: &Snapshot, path: &str) -> Result<Vec<SnapshotFile>, Error> {
+		let directory = join_path(&snapshot.path, path);
+		Ok(parse_file_list(&self.probe.run("find", &[&directory, "-type", "f", "-printf", "%P\\t%s\\t%T@\\n"])?))
+	}
+
+	pub(crate) fn diff_snapshots(&self, query: &SnapshotDiffQuery) -> SnapshotDiff {
+		let result = (|| {
+			if !is_safe_relative_path(&query.path) {
+				return Err(format!("{} is not a path inside the snapshot", query.path));
+			}
+
+			let snapshots = self.get_snapshots();
+			let find = |name: &str| {
+				snapshots.iter()
+					.find(|snapshot| snapshot.name == name && !snapshot.path.is_empty())
+					.ok_or_else(|| format!("could not find snapshot {}", name))
+			};
+
+			let from = self.get_file_list(find(&query.from)?, &query.path).map_err(|error| format!("{:?}", error))?;
+			let to = self.get_file_list(find(&query.to)?, &query.path).map_err(|error| format!("{:?}", error))?;
+			Ok(diff_file_lists(&from, &to))
+		})();
+
+		match result {
+			Ok(mut changes) => {
+				let truncated = changes.len() > SNAPSHOT_DIFF_MAX_CHANGES;
+				changes.truncate(SNAPSHOT_DIFF_MAX_CHANGES);
+				SnapshotDiff {
+					changes,
+					error: None,
+					query: query.clone(),
+					truncated,
+				}
+			},
+			Err(error) => SnapshotDiff {
+				changes: Vec::new(),
+				error: Some(error),
+				query: query.clone(),
+				truncated: false,
+			},
+		}
+	}
+}
+
+// restores run one at a time, copying out of the snapshot into the staging directory
+#[derive(Debug, Default)]
+pub(crate) struct Restores {
+	pub(crate) next_id: u64,
+	pub(crate) restores: Vec<Restore>,
+	pub(crate) running: bool, // whether something is working through the queue
+	pub(crate) sources: HashMap<u64, (String, String)>, // the snapshot each queued restore copies out of, and from where
+	pub(crate) staging_path: String,
+}
+
+impl Restores {
+	// requests are checked against the snapshots we know about. ones that cannot be done are still listed as failed,
+	// so the client hears about it. returns true if the queue needs something to start working through it
+	pub(crate) fn queue(&mut self, request: RestoreRequest, snapshots: &[Snapshot]) -> bool {
+		let id = self.next_id;
+		self.next_id += 1;
+
+		let snapshot = snapshots.iter().find(|snapshot| snapshot.name == request.snapshot && !snapshot.path.is_empty());
+		let error = if request.path.is_empty() || !is_safe_relative_path(&request.path) {
+			Some(format!("{} is not a path inside the snapshot", request.path))
+		} else if snapshot.is_none() {
+			Some(format!("could not find snapshot {}", request.snapshot))
+		} else {
+			None
+		};
+
+		// every restore gets its own directory, so restoring the same thing twice does not nest one inside the other
+		let folder = format!("{}-{}", id, request.snapshot.replace(['/', '@'], "-"));
+		let destination = join_path(&join_path(&self.staging_path, &folder), &request.path);
+
+		if let Some(snapshot) = snapshot.filter(|_| error.is_none()) {
+			self.sources.insert(id, (snapshot.path.clone(), join_path(&snapshot.path, &request.path)));
+		}
+
+		self.restores.push(Restore {
+			destination,
+			id,
+			message: error.clone(),
+			request,
+			state: if error.is_some() { RestoreState::Failed } else { RestoreState::Queued },
+		});
+
+		// forget the oldest finished restores
+		let mut extra = self.restores.len().saturating_sub(RESTORE_HISTORY_LENGTH);
+		self.restores.retain(|restore| {
+			if extra > 0 && matches!(restore.state, RestoreState::Done | RestoreState::Failed) {
+				extra -= 1;
+				false
+			} else {
+				true
+			}
+		});
+
+		if error.is_none() && !self.running {
+			self.running = true;
+			true
+		} else {
+			false
+		}
+	}
+
+	// marks the next queued restore as running, along with the snapshot it copies out of and where it copies from and to
+	fn start_next(&mut self) -> Option<(u64, String, String, String)> {
+		let restore = self.restores.iter_mut().find(|restore| restore.state == RestoreState::Queued)?;
+		restore.state = RestoreState::Running;
+		let (snapshot, source) = self.sources.remove(&restore.id)?;
+		Some((restore.id, snapshot, source, restore.destination.clone()))
+	}
+
+	fn finish(&mut self, id: u64, result: Result<(), String>) {
+		if let Some(restore) = self.restores.iter_mut().find(|restore| restore.id == id) {
+			match result {
+				Ok(()) => restore.state = RestoreState::Done,
+				Err(message) => {
+					restore.message = Some(message);
+					restore.state = RestoreState::Failed;
+				},
+			}
+		}
+	}
+}
+
+// paths are checked for .. when they are queued, but a symlink inside the snapshot could still lead anywhere
+async fn check_source(snapshot: &str, source: &str) -> Result<(), String> {
+	let snapshot = tokio::fs::canonicalize(snapshot).await.map_err(|error| format!("{} read error: {:?}", snapshot, error))?;
+	let canonical = tokio::fs::canonicalize(source).await.map_err(|error| format!("{} read error: {:?}", source, error))?;
+	if canonical.starts_with(&snapshot) {
+		Ok(())
+	} else {
+		Err(format!("{} leads outside of the snapshot", source))
+	}
+}
+
+async fn copy(source: &str, destination: &str) -> Result<(), String> {
+	if let Some(parent) = Path::new(destination).parent() {
+		tokio::fs::create_dir_all(parent).await.map_err(|error| format!("{} create error: {:?}", parent.display(), error))?;
+	}
+
+	// reflinks make copies out of btrfs snapshots nearly free, cp falls back to a real copy elsewhere
+	let output = Command::new("cp")
+		.args(["-a", "--reflink=auto", source, destination])
+		.output()
+		.await
+		.map_err(|error| format!("cp spawn error: {:?}", error))?;
+
+	if output.status.success() {
+		Ok(())
+	} else {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		Err(stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("cp failed").trim().to_string())
+	}
+}
+
+// works through the queue until it is empty
+pub(crate) async fn run_restores(restores: Arc<Mutex<Restores>>) {
+	loop {
+		let next = {
+			let mut locked = restores.lock().await;
+			let next = locked.start_next();
+			if next.is_none() {
+				locked.running = false;
+			}
+
+			next
+		};
+
+		let (id, snapshot, source, destination) = match next {
+			Some(next) => next,
+			None => break,
+		};
+
+		let result = match check_source(&snapshot, &source).await {
+			Ok(()) => copy(&source, &destination).await,
+			Err(error) => Err(error),
+		};
+		if let Err(error) = result.as_ref() {
+			eprintln!("restore {} error {}", id, error);
+		}
+
+		restores.lock().await.finish(id, result);
+	}
+}
@@ -0,0 +1,293 @@
+use bansheelong_types::{
+	JobStatusFlags,
+	StorageStatus,
+	StorageTarget,
+	StorageTargetKind,
+	StorageUsage,
+	ZPool,
+	parse_zpool_status,
+};
+
+use crate::probe::{ Error, Probe };
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum ZPoolStatus {
+	Error,
+	HardDriveReadWriteChecksumError,
+	HardDriveParseError,
+	HardDriveStateError,
+	Scrubbing,
+	Safe,
+}
+
+pub(crate) fn get_zpool_error(pools: &[ZPool]) -> ZPoolStatus {
+	for pool in pools.iter() {
+		if pool.scan.as_ref().is_some_and(|scan| scan.in_progress) {
+			return ZPoolStatus::Scrubbing;
+		}
+
+		// the pool and its vdevs count too, a degraded raidz is as bad as a faulted drive
+		for device in pool.get_devices() {
+			match device.state.as_str() {
+				"ONLINE" => {
+					// return error if we have error counts set
+					if device.has_unreadable_counts() {
+						return ZPoolStatus::HardDriveParseError;
+					} else if device.has_errors() {
+						return ZPoolStatus::HardDriveReadWriteChecksumError;
+					}
+				},
+				"DEGRADED" | "FAULTED" | "OFFLINE" | "UNAVAIL" => {
+					return ZPoolStatus::HardDriveStateError;
+				},
+				_ => {},
+			}
+		}
+
+		if pool.has_data_errors() {
+			return ZPoolStatus::Error;
+		}
+	}
+
+	ZPoolStatus::Safe
+}
+
+// files the backup scripts leave in the state directory while they work, and the flag each one turns on
+static JOB_FILES: [(&str, JobStatusFlags); 7] = [
+	("writing-daily-backup", JobStatusFlags::DOWNLOADING_DAILY),
+	("writing-weekly-backup", JobStatusFlags::CREATING_WEEKLY),
+	("writing-monthly-backup", JobStatusFlags::CREATING_MONTHLY),
+	("writing-git-backup", JobStatusFlags::SYNCING_GITHUB),
+	("removing-daily-backup", JobStatusFlags::REMOVING_DAILY),
+	("removing-weekly-backup", JobStatusFlags::REMOVING_WEEKLY),
+	("writing-btrbk", JobStatusFlags::WRITING_BTRBK),
+];
+
+pub(crate) struct Storage<P: Probe> {
+	pub(crate) btrfs_path: String, // where btrbk keeps its snapshots
+	pub(crate) probe: P,
+	pub(crate) state_path: String,
+	pub(crate) targets: Vec<StorageTarget>,
+}
+
+impl<P: Probe> Storage<P> {
+	fn get_state_file(&self, file_name: &str) -> String {
+		format!("{}/{}", self.state_path.trim_end_matches('/'), file_name)
+	}
+
+	pub(crate) fn get_zpools(&self) -> Result<Vec<ZPool>, Error> {
+		Ok(parse_zpool_status(&self.probe.run("zpool", &["status"])?))
+	}
+
+	// pools are found by the dataset at their root, everything else by where it is mounted
+	pub(crate) fn get_disk_usage(&self, target: &StorageTarget) -> Result<(u64, u64), Error> {
+		let stdout = self.probe.run("df", &["-B1"])?;
+
+		// analyze the output
+		for line in stdout.split('\n').skip(1) {
+			let items: Vec<String> = line.split(" ")
+				.filter(|i| !i.is_empty())
+				.map(|i| i.to_string())
+				.collect();
+
+			let found = match target.kind {
+				StorageTargetKind::Pool => items.first().is_some_and(|filesystem| *filesystem == target.path),
+				_ => items.last().is_some_and(|mount_point| mount_point == target.get_name()),
+			};
+
+			if !found {
+				continue;
+			}
+
+			if items.len() < 3 {
+				return Err(Error::DiskUsage(format!("not enough columns in '{}'", line)));
+			}
+
+			let used_size = match items[2].parse() {
+				Err(error) => {
+					return Err(Error::DiskUsage(format!("used size parse error: {:?}", error)));
+				},
+				Ok(size) => size,
+			};
+
+			let total_size = match items[1].parse() {
+				Err(error) => {
+					return Err(Error::DiskUsage(format!("total size parse error: {:?}", error)));
+				},
+				Ok(size) => size,
+			};
+
+			return Ok((used_size, total_size));
+		}
+
+		Err(Error::DiskUsage(format!("{} is not mounted", target.get_name())))
+	}
+
+	pub(crate) fn get_btrfs_disk_usage(&self, path: &str) -> Result<(u64, u64), Error> {
+		let stdout = self.probe.run("btrfs", &["fi", "usage", "-b", path])?;
+
+		let get_number = |line: &str| {
+			let number = line.chars()
+				.filter(|character| character.is_ascii_digit())
+				.collect::<String>();
+
+			number.parse::<u64>()
+				.map_err(|error| Error::DiskUsage(format!("btrfs size parse error in '{}': {:?}", line.trim(), error)))
+		};
+
+		let mut used_size = 0;
+		let mut total_size = 0;
+		for line in stdout.split('\n') {
+			if line.contains("Device size:") {
+				total_size = get_number(line)?;
+			} else if line.contains("Used:\t") {
+				used_size = get_number(line)?;
+			}
+		}
+
+		Ok((used_size, total_size))
+	}
+
+	// every target gets a row, even the ones that could not be read
+	pub(crate) fn get_usage(&self) -> Vec<StorageUsage> {
+		self.targets.iter()
+			.map(|target| {
+				let usage = match target.kind {
+					StorageTargetKind::Btrfs => self.get_btrfs_disk_usage(&target.path),
+					StorageTargetKind::Mount | StorageTargetKind::Pool => self.get_disk_usage(target),
+				};
+
+				match usage {
+					Ok((used_size, total_size)) => StorageUsage {
+						error: None,
+						target: target.clone(),
+						total_size,
+						used_size,
+					},
+					Err(error) => {
+						eprintln!("{} usage error: {:?}", target.get_name(), error);
+						StorageUsage {
+							error: Some(error.get_message().to_string()),
+							target: target.clone(),
+							total_size: 0,
+							used_size: 0,
+						}
+					},
+				}
+			})
+			.collect()
+	}
+
+	pub(crate) fn get_btrfs_backup_count(&self) -> Result<u64, Error> {
+		Ok(
+			self.probe.read_dir(&self.btrfs_path)?.iter()
+				.filter(|path| path.contains("home_backup"))
+				.count() as u64
+		)
+	}
+
+	pub(crate) fn get_backups_count(&self) -> Result<(u8, u8), Error> {
+		let read_count = |file_name: &str| {
+			let value = self.probe.read_to_string(&self.get_state_file(&format!("{}-count", file_name)))?;
+			match value.trim().parse::<u8>() {
+				Err(error) => Err(Error::LocalInfo(format!("{} parse error: {:?}", file_name, error))),
+				Ok(value) => Ok(value),
+			}
+		};
+
+		Ok((read_count("dailies")?, read_count("weeklies")?))
+	}
+
+	// the backup script rewrites the count after every daily it downloads
+	pub(crate) fn get_last_daily(&self) -> Result<i64, Error> {
+		self.probe.modified(&self.get_state_file("dailies-count"))
+	}
+
+	pub(crate) fn get_job_flags(&self, pools: &Result<Vec<ZPool>, Error>) -> JobStatusFlags {
+		let mut result = JobStatusFlags::IDLE;
+
+		match pools.as_ref().map(|pools| get_zpool_error(pools)) { // handle zpool error by indicating it on the bansheelong
+			Err(_) => {
+				result |= JobStatusFlags::ZPOOL_ERROR;
+			},
+			Ok(ZPoolStatus::Error) => {
+				result |= JobStatusFlags::ZPOOL_ERROR;
+			},
+			Ok(ZPoolStatus::HardDriveParseError) => {
+				result |= JobStatusFlags::ZPOOL_HARD_DRIVE_PARSE_ERROR;
+			},
+			Ok(ZPoolStatus::HardDriveReadWriteChecksumError) => {
+				result |= JobStatusFlags::ZPOOL_HARD_DRIVE_RW_ERROR;
+			},
+			Ok(ZPoolStatus::HardDriveStateError) => {
+				result |= JobStatusFlags::ZPOOL_HARD_DRIVE_STATE_ERROR;
+			},
+			Ok(ZPoolStatus::Scrubbing) => {
+				result |= JobStatusFlags::ZPOOL_SCRUBBING;
+			},
+			Ok(ZPoolStatus::Safe) => {},
+		};
+
+		for (file_name, flag) in JOB_FILES.iter() {
+			if self.probe.exists(&self.get_state_file(file_name)) {
+				result |= *flag;
+			}
+		}
+
+		result
+	}
+
+	// everything we send to clients. whatever could not be read is logged and left at zero
+	pub(crate) fn get_status(&self) -> StorageStatus {
+		// get zpool status, which goes out to clients in full as well as being boiled down into the job flags
+		let pools = self.get_zpools();
+		if let Err(error) = pools.as_ref() {
+			eprintln!("zpool status error: {:?}", error);
+		}
+
+		// get server job status
+		let job_flags = self.get_job_flags(&pools);
+
+		// get the usage of everything we were told to watch
+		let usage = self.get_usage();
+
+		// clients from before there were targets only know about one pool and one btrfs filesystem
+		let get_first = |kind: StorageTargetKind| {
+			usage.iter()
+				.find(|usage| usage.target.kind == kind)
+				.map_or((0, 0), |usage| (usage.used_size, usage.total_size))
+		};
+
+		let (used_size, total_size) = get_first(StorageTargetKind::Pool);
+		let (btrfs_used_size, btrfs_total_size) = get_first(StorageTargetKind::Btrfs);
+
+		// get btrfs backup count
+		let btrfs_backup_count = self.get_btrfs_backup_count().unwrap_or_else(|error| {
+			eprintln!("btrfs backup count error: {:?}", error);
+			0
+		});
+
+		// get dailies/weeklies count
+		let (dailies, weeklies) = self.get_backups_count().unwrap_or_else(|error| {
+			eprintln!("dailies/weeklies error: {:?}", error);
+			(0, 0)
+		});
+
+		StorageStatus {
+			job_flags,
+			used_size,
+			total_size,
+			btrfs_used_size,
+			btrfs_total_size,
+			btrfs_backup_count,
+			dailies,
+			weeklies,
+			pools: pools.unwrap_or_default(),
+			jobs: Vec::new(),
+			restores: Vec::new(),
+			alerts: Vec::new(),
+			smart: Vec::new(),
+			usage,
+		}
+	}
+}
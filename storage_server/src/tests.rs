@@ -0,0 +1,1178 @@
+#[cfg(test)]
+mod fixtures {
+	use crate::probe::FixtureProbe;
+	use crate::status::Storage;
+
+	pub(super) const FINISHED_SCRUB: &str = include_str!("../../types/fixtures/zpool-mock1");
+	pub(super) const RUNNING_SCRUB: &str = include_str!("../../types/fixtures/zpool-mock2");
+
+	// a healthy pool, taken from the finished scrub with its errors cleared
+	pub(super) fn healthy_zpool() -> String {
+		FINISHED_SCRUB
+			.replace("ONLINE       0     0     4", "ONLINE       0     0     0")
+			.replace("ONLINE      16   103     0", "ONLINE       0     0     0")
+			.replace("ONLINE      42   202     0", "ONLINE       0     0     0")
+	}
+
+	// a server with its btrfs filesystem at /btrfs/, its state in /state and no usage targets
+	pub(super) fn storage(probe: FixtureProbe) -> Storage<FixtureProbe> {
+		Storage {
+			btrfs_path: String::from("/btrfs/"),
+			probe,
+			state_path: String::from("/state"),
+			targets: Vec::new(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod status {
+	use bansheelong_types::{
+		JobStatusFlags,
+		STORAGE_PROTOCOL_VERSION,
+		StorageFrame,
+		StorageStatus,
+		StorageTarget,
+		StorageTargetKind,
+		decode_storage_frame,
+		encode_storage_status,
+		parse_storage_targets,
+	};
+
+	use crate::probe::FixtureProbe;
+	use crate::status::{ Storage, ZPoolStatus, get_zpool_error };
+
+	use super::fixtures::{ FINISHED_SCRUB, RUNNING_SCRUB, healthy_zpool, storage };
+
+	const DF: &str = include_str!("../fixtures/df");
+	const BTRFS_USAGE: &str = include_str!("../fixtures/btrfs-usage");
+	fn targets() -> Vec<StorageTarget> {
+		parse_storage_targets("pool:bansheerubber,btrfs:/btrfs/").unwrap()
+	}
+
+	fn setup(zpool: &str) -> Storage<FixtureProbe> {
+		let probe = FixtureProbe::default()
+			.command("zpool status", zpool)
+			.command("df -B1", DF)
+			.command("btrfs fi usage -b /btrfs/", BTRFS_USAGE)
+			.directory("/btrfs/", &["home_backup.20220911", "home_backup.20220912", "root", "home_backup.20220913"])
+			.file("/state/dailies-count", "7\n")
+			.file("/state/weeklies-count", "4\n");
+
+		Storage {
+			targets: targets(),
+			..storage(probe)
+		}
+	}
+
+	fn flags(zpool: &str) -> JobStatusFlags {
+		let storage = setup(zpool);
+		storage.get_job_flags(&storage.get_zpools())
+	}
+
+	#[test]
+	fn reads_everything() {
+		let status = setup(&healthy_zpool()).get_status();
+		assert_eq!(status.job_flags, JobStatusFlags::IDLE);
+		assert_eq!((status.used_size, status.total_size), (4123456789504, 10469387730944));
+		assert_eq!((status.btrfs_used_size, status.btrfs_total_size), (1100000000000, 4000787030016));
+		assert_eq!(status.btrfs_backup_count, 3);
+		assert_eq!((status.dailies, status.weeklies), (7, 4));
+		assert_eq!(status.pools.len(), 1);
+		assert_eq!(status.usage.len(), 2);
+		assert!(status.usage.iter().all(|usage| usage.error.is_none()));
+	}
+
+	#[test]
+	fn leaves_missing_things_at_zero() {
+		let probe = FixtureProbe::default()
+			.command("zpool status", &healthy_zpool())
+			.command("btrfs fi usage -b /btrfs/", "    Device size:\t\tlots\n")
+			.file("/state/dailies-count", "seven");
+
+		let storage = Storage {
+			targets: targets(),
+			..storage(probe)
+		};
+
+		let status = storage.get_status();
+		assert_eq!(status.job_flags, JobStatusFlags::IDLE);
+		assert_eq!((status.used_size, status.btrfs_total_size, status.btrfs_backup_count, status.dailies), (0, 0, 0, 0));
+		assert!(storage.get_disk_usage(&targets()[0]).is_err());
+		assert!(storage.get_btrfs_disk_usage("/btrfs/").is_err());
+		assert!(storage.get_backups_count().is_err());
+	}
+
+	#[test]
+	fn reads_every_target() {
+		let storage = Storage {
+			targets: parse_storage_targets("mount:/, pool:bansheerubber,btrfs:/btrfs/,mount:/bansheebtrfs/,mount:/nowhere").unwrap(),
+			..setup(&healthy_zpool())
+		};
+
+		let status = storage.get_status();
+		let usage = status.usage.iter()
+			.map(|usage| (usage.target.get_name(), usage.used_size, usage.total_size))
+			.collect::<Vec<(&str, u64, u64)>>();
+		assert_eq!(usage, vec![
+			("/", 121348296704, 491173273600),
+			("bansheerubber", 4123456789504, 10469387730944),
+			("/btrfs", 1100000000000, 4000787030016),
+			("/bansheebtrfs", 1100000000000, 4000787030016),
+			("/nowhere", 0, 0),
+		]);
+		assert_eq!(status.usage[4].error.as_deref(), Some("/nowhere is not mounted"));
+		assert_eq!(status.usage[2].target.kind, StorageTargetKind::Btrfs);
+
+		// older clients get the first pool and btrfs filesystem, not whatever came first
+		assert_eq!((status.used_size, status.total_size), (4123456789504, 10469387730944));
+		assert_eq!((status.btrfs_used_size, status.btrfs_total_size), (1100000000000, 4000787030016));
+
+		// a pool is not the same as a filesystem that happens to share its name
+		let mount = StorageTarget {
+			kind: StorageTargetKind::Mount,
+			path: String::from("bansheerubber"),
+		};
+		assert!(storage.get_disk_usage(&mount).is_err());
+
+		let frame = encode_storage_status(&status);
+		assert_eq!(decode_storage_frame(&frame).unwrap().unwrap().0, StorageFrame::Status(status));
+	}
+
+	#[test]
+	fn flags_zpool_problems() {
+		assert_eq!(flags(&healthy_zpool()), JobStatusFlags::IDLE);
+		assert_eq!(flags(FINISHED_SCRUB), JobStatusFlags::ZPOOL_HARD_DRIVE_RW_ERROR);
+		assert_eq!(flags(RUNNING_SCRUB), JobStatusFlags::ZPOOL_SCRUBBING);
+
+		let faulted = healthy_zpool().replace("ZK203MKK  ONLINE ", "ZK203MKK  FAULTED");
+		assert_eq!(flags(&faulted), JobStatusFlags::ZPOOL_HARD_DRIVE_STATE_ERROR);
+
+		let unreadable = healthy_zpool().replace("ZK203MKK  ONLINE       0", "ZK203MKK  ONLINE       ?");
+		assert_eq!(flags(&unreadable), JobStatusFlags::ZPOOL_HARD_DRIVE_PARSE_ERROR);
+
+		let data_errors = healthy_zpool().replace("No known data errors", "1 data errors, use '-v' for a list");
+		assert_eq!(flags(&data_errors), JobStatusFlags::ZPOOL_ERROR);
+
+		// no zpool at all
+		let storage = Storage {
+			probe: FixtureProbe::default(),
+			..setup("")
+		};
+		assert_eq!(storage.get_job_flags(&storage.get_zpools()), JobStatusFlags::ZPOOL_ERROR);
+		assert_eq!(get_zpool_error(&[]), ZPoolStatus::Safe);
+	}
+
+	#[test]
+	fn flags_backup_jobs() {
+		let jobs = [
+			("writing-daily-backup", JobStatusFlags::DOWNLOADING_DAILY),
+			("writing-weekly-backup", JobStatusFlags::CREATING_WEEKLY),
+			("writing-monthly-backup", JobStatusFlags::CREATING_MONTHLY),
+			("writing-git-backup", JobStatusFlags::SYNCING_GITHUB),
+			("removing-daily-backup", JobStatusFlags::REMOVING_DAILY),
+			("removing-weekly-backup", JobStatusFlags::REMOVING_WEEKLY),
+			("writing-btrbk", JobStatusFlags::WRITING_BTRBK),
+		];
+
+		for (file_name, flag) in jobs.iter() {
+			let mut storage = setup(&healthy_zpool());
+			storage.probe = storage.probe.file(&format!("/state/{}", file_name), "");
+			assert_eq!(storage.get_job_flags(&storage.get_zpools()), *flag, "{}", file_name);
+		}
+
+		// jobs run alongside each other and alongside zpool problems
+		let mut storage = setup(RUNNING_SCRUB);
+		storage.probe = storage.probe
+			.file("/state/writing-daily-backup", "")
+			.file("/state/writing-btrbk", "");
+		assert_eq!(
+			storage.get_job_flags(&storage.get_zpools()),
+			JobStatusFlags::DOWNLOADING_DAILY | JobStatusFlags::WRITING_BTRBK | JobStatusFlags::ZPOOL_SCRUBBING
+		);
+	}
+
+	#[test]
+	fn flags_unknown_jobs_as_errors() {
+		// a newer server with flags this build does not know about
+		let mut frame = encode_storage_status(&StorageStatus::default());
+		assert_eq!(frame[2], STORAGE_PROTOCOL_VERSION);
+		frame[11..17].copy_from_slice(&[0xff; 6]);
+
+		match decode_storage_frame(&frame).unwrap().unwrap().0 {
+			StorageFrame::Status(status) => assert_eq!(status.job_flags, JobStatusFlags::GENERAL_ERROR),
+			frame => panic!("{:?}", frame),
+		}
+
+		let status = StorageStatus::from_legacy_line(&format!("{} 0 0 0 0 0 0 0", 1u64 << 40)).unwrap();
+		assert_eq!(status.job_flags, JobStatusFlags::GENERAL_ERROR);
+	}
+}
+
+#[cfg(test)]
+mod alerts {
+	use bansheelong_types::{
+		Date,
+		JobStatusFlags,
+		MealsDatabase,
+		Priority,
+		Resource,
+		StorageStatus,
+		StorageTarget,
+		StorageUsage,
+		TodosDatabase,
+		WriteDatabase,
+		parse_smartctl,
+		parse_zpool_status,
+		read_database,
+		write_database,
+	};
+
+	use crate::alerts::{ Alert, AlertContext, AlertEvent, AlertRule, AlertSink, Alerts, get_default_rules, get_mail };
+	use crate::probe::FixtureProbe;
+
+	use super::fixtures::{ FINISHED_SCRUB, RUNNING_SCRUB, healthy_zpool, storage };
+
+	const HOUR: i64 = 60 * 60;
+	const DAY: i64 = 24 * HOUR;
+
+	const SMART_HEALTHY: &str = include_str!("../../types/fixtures/smartctl-ata-healthy");
+	const SMART_FAILING: &str = include_str!("../../types/fixtures/smartctl-ata-failing");
+
+	fn healthy_status() -> StorageStatus {
+		StorageStatus {
+			used_size: 50,
+			total_size: 100,
+			pools: parse_zpool_status(&healthy_zpool()),
+			smart: vec![parse_smartctl(SMART_HEALTHY, 0).unwrap()],
+			..StorageStatus::default()
+		}
+	}
+
+	// a day after the fixture's scrub finished, with a daily from an hour ago
+	fn check(rule: AlertRule, status: &StorageStatus) -> Vec<Alert> {
+		let now = status.pools.first().and_then(|pool| pool.scan.as_ref()).and_then(|scan| scan.finished_at).unwrap_or(0) + DAY;
+		rule.check(&AlertContext {
+			last_daily: Some(now - HOUR),
+			now,
+			status,
+		})
+	}
+
+	fn keys(alerts: &[Alert]) -> Vec<&str> {
+		alerts.iter().map(|alert| alert.key.as_str()).collect()
+	}
+
+	#[test]
+	fn healthy_storage_is_quiet() {
+		for rule in get_default_rules() {
+			assert_eq!(check(rule.clone(), &healthy_status()), Vec::new(), "{:?}", rule);
+		}
+	}
+
+	#[test]
+	fn flags_pools_and_drives() {
+		let mut status = healthy_status();
+		status.pools = parse_zpool_status(&FINISHED_SCRUB.replace(" state: ONLINE", " state: DEGRADED"));
+
+		let alerts = check(AlertRule::PoolDegraded, &status);
+		assert_eq!(keys(&alerts), vec!["pool-degraded:bansheerubber"]);
+		assert_eq!(alerts[0].message, "pool bansheerubber is degraded");
+
+		// every drive with errors is its own alert
+		let alerts = check(AlertRule::DriveErrors, &status);
+		assert_eq!(keys(&alerts), vec!["drive-errors:ZFL36PYH", "drive-errors:ZFL370EW", "drive-errors:ZK203SFZ"]);
+		assert_eq!(alerts[2].message, "drive ZK203SFZ is online with 42 read, 202 write and 0 checksum errors");
+
+		status.pools = parse_zpool_status(&FINISHED_SCRUB.replace("No known data errors", "2 data errors"));
+		assert_eq!(check(AlertRule::PoolDegraded, &status)[0].message, "pool bansheerubber has data errors: 2 data errors");
+
+		// no pools and a zpool error means zpool status could not be read
+		let status = StorageStatus {
+			job_flags: JobStatusFlags::ZPOOL_ERROR | JobStatusFlags::GENERAL_ERROR,
+			..StorageStatus::default()
+		};
+		assert_eq!(keys(&check(AlertRule::StatusErrors, &status)), vec!["status-errors:zpool", "status-errors:general"]);
+	}
+
+	#[test]
+	fn flags_smart_drives() {
+		let mut status = healthy_status();
+		status.smart.push(parse_smartctl(SMART_FAILING, 0).unwrap());
+
+		let alerts = check(AlertRule::SmartFailing, &status);
+		assert_eq!(keys(&alerts), vec!["smart-failed:ZFL370EW"]);
+		assert_eq!(alerts[0].message, "drive ZFL370EW is failing its SMART health check");
+
+		let alerts = check(AlertRule::BadSectors(0), &status);
+		assert_eq!(keys(&alerts), vec!["smart-sectors:ZFL370EW"]);
+		assert_eq!(alerts[0].message, "drive ZFL370EW has 2984 reallocated and 136 pending sectors");
+		assert!(check(AlertRule::BadSectors(5000), &status).is_empty());
+
+		let alerts = check(AlertRule::TemperatureAbove(50), &status);
+		assert_eq!(keys(&alerts), vec!["smart-temperature:ZFL370EW"]);
+		assert_eq!(alerts[0].message, "drive ZFL370EW is at 53°C, over the 50°C limit");
+		assert_eq!(check(AlertRule::TemperatureAbove(30), &status).len(), 2);
+
+		// a drive that says it is fine can still fail a self-test
+		status.smart[1].passed = Some(true);
+		assert_eq!(
+			check(AlertRule::SmartFailing, &status)[0].message,
+			"drive ZFL370EW failed its last short offline self-test: Completed: read failure"
+		);
+	}
+
+	#[test]
+	fn flags_usage_and_stale_jobs() {
+		let mut status = healthy_status();
+		status.used_size = 91;
+		assert_eq!(check(AlertRule::UsageAbove(0.9), &status)[0].message, "pool is 91% full, over the 90% limit");
+
+		// with targets, each one is checked on its own
+		let usage = |target: &str, used_size: u64| StorageUsage {
+			error: None,
+			target: StorageTarget::parse(target).unwrap(),
+			total_size: 100,
+			used_size,
+		};
+		let mut targets = healthy_status();
+		targets.used_size = 91;
+		targets.usage = vec![usage("pool:bansheerubber", 50), usage("mount:/", 95), usage("btrfs:/bansheebtrfs/", 92)];
+		let alerts = check(AlertRule::UsageAbove(0.9), &targets);
+		assert_eq!(keys(&alerts), vec!["usage:/", "usage:/bansheebtrfs"]);
+		assert_eq!(alerts[1].message, "/bansheebtrfs is 92% full, over the 90% limit");
+
+		let finished_at = status.pools[0].scan.as_ref().unwrap().finished_at.unwrap();
+		let context = |now: i64, last_daily: Option<i64>| AlertContext {
+			last_daily,
+			now,
+			status: &status,
+		};
+
+		assert!(AlertRule::NoDailyBackup(36 * HOUR).check(&context(finished_at, Some(finished_at - 30 * HOUR))).is_empty());
+		assert_eq!(
+			AlertRule::NoDailyBackup(36 * HOUR).check(&context(finished_at, Some(finished_at - 40 * HOUR)))[0].message,
+			"no new daily backup in 40 hours"
+		);
+		assert_eq!(keys(&AlertRule::NoDailyBackup(36 * HOUR).check(&context(finished_at, None))), vec!["no-daily-backup"]);
+
+		assert!(AlertRule::NoScrub(35 * DAY).check(&context(finished_at + 34 * DAY, None)).is_empty());
+		assert_eq!(
+			AlertRule::NoScrub(35 * DAY).check(&context(finished_at + 36 * DAY, None))[0].message,
+			"pool bansheerubber has not been scrubbed in 36 days"
+		);
+
+		// a scrub that is running is as good as a fresh one
+		let status = StorageStatus {
+			pools: parse_zpool_status(RUNNING_SCRUB),
+			..StorageStatus::default()
+		};
+		assert!(AlertRule::NoScrub(35 * DAY).check(&AlertContext { last_daily: None, now: finished_at + 100 * DAY, status: &status }).is_empty());
+	}
+
+	#[test]
+	fn reads_the_last_daily() {
+		let storage = storage(FixtureProbe::default().modified("/state/dailies-count", 1_660_000_000));
+		assert_eq!(storage.get_last_daily().ok(), Some(1_660_000_000));
+	}
+
+	#[test]
+	fn fires_once_and_resolves() {
+		let mut alerts = Alerts {
+			rules: vec![AlertRule::UsageAbove(0.9)],
+			..Alerts::default()
+		};
+
+		let mut status = healthy_status();
+		let mut update = |used_size: u64| {
+			status.used_size = used_size;
+			alerts.update(&AlertContext {
+				last_daily: None,
+				now: 0,
+				status: &status,
+			})
+		};
+
+		assert!(update(50).is_empty());
+
+		let events = update(95);
+		assert!(matches!(events.as_slice(), [AlertEvent::Firing(alert)] if alert.key == "usage"));
+		assert!(update(96).is_empty());
+
+		let events = update(50);
+		assert!(matches!(events.as_slice(), [AlertEvent::Resolved(alert)] if alert.key == "usage"));
+	}
+
+	#[test]
+	fn remembers_what_is_firing() {
+		let path = std::env::temp_dir().join(format!("bansheelong-alerts-{}", std::process::id()));
+		let path = path.to_str().unwrap();
+
+		let mut alerts = Alerts {
+			rules: vec![AlertRule::UsageAbove(0.9)],
+			..Alerts::default()
+		};
+
+		let mut status = healthy_status();
+		status.used_size = 95;
+		let context = AlertContext {
+			last_daily: None,
+			now: 0,
+			status: &status,
+		};
+		alerts.update(&context);
+		alerts.write_firing(path).unwrap();
+
+		// a restarted server does not send the alert again
+		let mut restarted = Alerts {
+			rules: vec![AlertRule::UsageAbove(0.9)],
+			..Alerts::default()
+		};
+		restarted.read_firing(path).unwrap();
+		assert!(restarted.update(&context).is_empty());
+
+		std::fs::remove_file(path).unwrap();
+	}
+
+	#[tokio::test]
+	async fn writes_to_the_mail_spool() {
+		let path = std::env::temp_dir().join(format!("bansheelong-spool-{}", std::process::id()));
+		let path = path.to_str().unwrap().to_string();
+
+		let alert = Alert {
+			key: String::from("usage"),
+			message: String::from("pool is 95% full"),
+		};
+
+		let sink = AlertSink::MailSpool(path.clone());
+		sink.send(&AlertEvent::Firing(alert.clone()), 1_660_000_000).await.unwrap();
+		sink.send(&AlertEvent::Resolved(alert.clone()), 1_660_000_000).await.unwrap();
+
+		let spool = std::fs::read_to_string(&path).unwrap();
+		assert_eq!(spool.matches("From bansheelong@localhost ").count(), 2);
+		assert!(spool.starts_with("From bansheelong@localhost "));
+		assert!(spool.contains("Subject: [bansheelong] firing: pool is 95% full\n"));
+		assert!(spool.contains("Subject: [bansheelong] resolved: pool is 95% full\n"));
+		assert!(spool.ends_with("\n\n"));
+
+		let mail = get_mail(&AlertEvent::Firing(alert), "me", 1_660_000_000);
+		assert!(mail.contains("\nTo: me\n"));
+
+		std::fs::remove_file(&path).unwrap();
+	}
+
+	#[tokio::test]
+	async fn writes_urgent_todos() {
+		let path = std::env::temp_dir().join(format!("bansheelong-alert-todos-{}", std::process::id()));
+		let resource = Resource {
+			reference: path.to_str().unwrap().to_string(),
+		};
+
+		write_database(
+			WriteDatabase::Full {
+				meals: &MealsDatabase::default(),
+				todos: &TodosDatabase::default(),
+			},
+			resource.clone()
+		).await.unwrap();
+
+		let alert = Alert {
+			key: String::from("usage"),
+			message: String::from("pool is 95% full"),
+		};
+
+		AlertSink::Todos(resource.clone()).send(&AlertEvent::Firing(alert), 1_660_000_000).await.unwrap();
+
+		let (todos, _) = read_database(resource).await.unwrap();
+		let item = &todos.mapping[&Some(Date::today())].items[0];
+		assert_eq!(item.description, "! storage: pool is 95% full");
+		assert_eq!(item.priority, Priority::Urgent);
+
+		std::fs::remove_file(&path).unwrap();
+	}
+}
+
+#[cfg(test)]
+mod jobs {
+	use std::sync::Arc;
+
+	use bansheelong_types::{ BackupJobState, BackupKind, JobStatusFlags };
+	use chrono::{ NaiveDate, NaiveDateTime };
+	use tokio::sync::Mutex;
+
+	use crate::jobs::{ JobConfig, Jobs, JobsConfig, get_scheduled_time, run_job, to_timestamp };
+
+	// a sunday
+	fn time(day: u32, hour: u32) -> NaiveDateTime {
+		NaiveDate::from_ymd(2022, 9, day).and_hms(hour, 0, 0)
+	}
+
+	// local commands stand in for btrbk and zfs
+	fn config(create: &str, remove: &str, keep: usize) -> JobConfig {
+		JobConfig {
+			create: vec![String::from("sh"), String::from("-c"), String::from(create), String::from("sh"), String::from("{name}")],
+			hour: 3,
+			keep,
+			remove: vec![String::from("sh"), String::from("-c"), String::from(remove), String::from("sh"), String::from("{name}")],
+		}
+	}
+
+	fn setup(daily: JobConfig) -> Arc<Mutex<Jobs>> {
+		Arc::new(Mutex::new(Jobs {
+			config: JobsConfig {
+				daily: Some(daily),
+				..JobsConfig::default()
+			},
+			..Jobs::default()
+		}))
+	}
+
+	async fn run(jobs: &Arc<Mutex<Jobs>>, now: NaiveDateTime) {
+		let name = jobs.lock().await.start(BackupKind::Daily, now);
+		run_job(jobs.clone(), BackupKind::Daily, name).await;
+	}
+
+	#[test]
+	fn schedules_jobs() {
+		assert_eq!(get_scheduled_time(BackupKind::Daily, 3, time(13, 2)), time(12, 3));
+		assert_eq!(get_scheduled_time(BackupKind::Daily, 3, time(13, 3)), time(13, 3));
+		assert_eq!(get_scheduled_time(BackupKind::Weekly, 4, time(13, 12)), time(11, 4));
+		assert_eq!(get_scheduled_time(BackupKind::Weekly, 4, time(11, 3)), time(4, 4));
+		assert_eq!(get_scheduled_time(BackupKind::Monthly, 5, time(13, 12)), time(1, 5));
+		assert_eq!(get_scheduled_time(BackupKind::Monthly, 5, time(1, 4)), NaiveDate::from_ymd(2022, 8, 1).and_hms(5, 0, 0));
+	}
+
+	#[test]
+	fn runs_one_due_job_at_a_time() {
+		let mut jobs = Jobs {
+			config: JobsConfig {
+				daily: Some(config("true", "true", 7)),
+				monthly: Some(config("true", "true", 12)),
+				weekly: Some(config("true", "true", 4)),
+			},
+			..Jobs::default()
+		};
+
+		// monthlies go first, and nothing else starts while one is running
+		assert_eq!(jobs.get_due(time(13, 12)), Some(BackupKind::Monthly));
+		jobs.start(BackupKind::Monthly, time(13, 12));
+		assert_eq!(jobs.get_due(time(13, 12)), None);
+		assert_eq!(jobs.get_flags(), JobStatusFlags::CREATING_MONTHLY);
+		assert_eq!(jobs.history[0].get_duration(to_timestamp(time(13, 12)) + 60), 60);
+
+		jobs.history[0].state = BackupJobState::Succeeded;
+		assert_eq!(jobs.get_due(time(13, 12)), Some(BackupKind::Weekly));
+
+		// failed jobs wait for their next turn
+		jobs.start(BackupKind::Weekly, time(13, 12));
+		jobs.history[1].state = BackupJobState::Failed;
+		assert_eq!(jobs.get_due(time(13, 12)), Some(BackupKind::Daily));
+		jobs.start(BackupKind::Daily, time(13, 12));
+		jobs.history[2].state = BackupJobState::Succeeded;
+		assert_eq!(jobs.get_due(time(13, 12)), None);
+		assert_eq!(jobs.get_due(time(14, 2)), None);
+		assert_eq!(jobs.get_due(time(14, 3)), Some(BackupKind::Daily));
+
+		assert_eq!(jobs.history[2].name, "daily-2022-09-13");
+		assert_eq!(jobs.history[2].started_at, to_timestamp(time(13, 12)));
+	}
+
+	#[tokio::test]
+	async fn reports_progress_and_exit_status() {
+		let jobs = setup(config("echo progress 40; echo progress 80; echo almost >&2; echo made $1", "true", 7));
+		run(&jobs, time(13, 3)).await;
+
+		let locked = jobs.lock().await;
+		let job = &locked.history[0];
+		assert_eq!(job.state, BackupJobState::Succeeded);
+		assert_eq!(job.exit_code, Some(0));
+		assert_eq!(job.progress, Some(100.0));
+		assert_eq!(job.message.as_deref(), Some("almost"));
+		assert!(job.finished_at.is_some());
+		assert_eq!(locked.get_flags(), JobStatusFlags::IDLE);
+		assert_eq!(locked.get_last_success(BackupKind::Daily), job.finished_at);
+		drop(locked);
+
+		let jobs = setup(config("echo progress 30; echo disk full >&2; exit 3", "true", 7));
+		run(&jobs, time(13, 3)).await;
+
+		let locked = jobs.lock().await;
+		let job = &locked.history[0];
+		assert_eq!(job.state, BackupJobState::Failed);
+		assert_eq!(job.exit_code, Some(3));
+		assert_eq!(job.progress, Some(30.0));
+		assert_eq!(job.message.as_deref(), Some("disk full"));
+		assert_eq!(locked.get_last_success(BackupKind::Daily), None);
+		assert!(locked.get_kept(BackupKind::Daily).is_empty());
+
+		let jobs = setup(JobConfig {
+			create: vec![String::from("/does/not/exist")],
+			..config("true", "true", 7)
+		});
+		run(&jobs, time(13, 3)).await;
+		assert_eq!(jobs.lock().await.history[0].state, BackupJobState::Failed);
+		assert!(jobs.lock().await.history[0].message.as_ref().unwrap().contains("spawn error"));
+	}
+
+	#[tokio::test]
+	async fn removes_backups_past_retention() {
+		let path = std::env::temp_dir().join(format!("bansheelong-removed-{}", std::process::id()));
+		let path = path.to_str().unwrap().to_string();
+
+		let jobs = setup(config("true", &format!("echo $1 >> {}", path), 2));
+		for day in 11..=14 {
+			run(&jobs, time(day, 3)).await;
+		}
+
+		let removed = std::fs::read_to_string(&path).unwrap();
+		assert_eq!(removed, "daily-2022-09-11\ndaily-2022-09-12\n");
+
+		let locked = jobs.lock().await;
+		let kept = locked.get_kept(BackupKind::Daily).iter().map(|job| job.name.as_str()).collect::<Vec<&str>>();
+		assert_eq!(kept, vec!["daily-2022-09-13", "daily-2022-09-14"]);
+		assert_eq!(locked.get_recent().len(), 1);
+		assert_eq!(locked.get_recent()[0].name, "daily-2022-09-14");
+		drop(locked);
+
+		// a backup that could not be removed is kept and tried again next time
+		let jobs = setup(config("true", "echo busy >&2; exit 1", 1));
+		run(&jobs, time(11, 3)).await;
+		run(&jobs, time(12, 3)).await;
+
+		let locked = jobs.lock().await;
+		assert_eq!(locked.history[1].state, BackupJobState::Succeeded);
+		assert_eq!(locked.history[1].message.as_deref(), Some("could not remove daily-2022-09-11, exit code 1: busy"));
+		assert_eq!(locked.get_expired(BackupKind::Daily), vec![String::from("daily-2022-09-11")]);
+
+		std::fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn fails_jobs_that_were_running_on_restart() {
+		let path = std::env::temp_dir().join(format!("bansheelong-jobs-{}", std::process::id()));
+		let path = path.to_str().unwrap();
+
+		let mut jobs = Jobs {
+			path: Some(String::from(path)),
+			..Jobs::default()
+		};
+		jobs.start(BackupKind::Weekly, time(11, 4));
+
+		let jobs = Jobs::read(JobsConfig::default(), path);
+		assert_eq!(jobs.history[0].state, BackupJobState::Failed);
+		assert!(jobs.get_running().is_none());
+
+		std::fs::remove_file(path).unwrap();
+	}
+}
+
+#[cfg(test)]
+mod smart {
+	use bansheelong_types::{ ZPool, parse_zpool_status };
+
+	use crate::probe::FixtureProbe;
+	use crate::smart::{ SMART_INTERVAL, Smart };
+	use crate::status::Storage;
+
+	use super::fixtures::{ FINISHED_SCRUB, storage };
+
+	const HEALTHY: &str = include_str!("../../types/fixtures/smartctl-ata-healthy");
+	const FAILING: &str = include_str!("../../types/fixtures/smartctl-ata-failing");
+	const STANDBY: &str = include_str!("../../types/fixtures/smartctl-standby");
+
+	fn command(serial: &str) -> String {
+		format!("smartctl --json -a -n standby /dev/disk/by-id/ata-ST2000DM008-2FR102_{}", serial)
+	}
+
+	// a pool down to the two drives we have captures for
+	fn pools() -> Vec<ZPool> {
+		let zpool = FINISHED_SCRUB.lines()
+			.filter(|line| !line.contains("ata-") || line.contains("ZFL36QCV") || line.contains("ZFL370EW"))
+			.collect::<Vec<&str>>()
+			.join("\n");
+
+		parse_zpool_status(&zpool)
+	}
+
+	fn setup(failing: &str) -> Storage<FixtureProbe> {
+		storage(FixtureProbe::default()
+			.command(&command("ZFL36QCV"), HEALTHY)
+			.command(&command("ZFL370EW"), failing))
+	}
+
+	#[test]
+	fn reads_every_pool_member() {
+		let mut smart = Smart::default();
+		assert!(smart.update(&setup(FAILING), &pools(), 1000));
+
+		let drives = smart.get_drives();
+		assert_eq!(drives.iter().map(|drive| drive.serial.as_str()).collect::<Vec<&str>>(), ["ZFL36QCV", "ZFL370EW"]);
+		assert_eq!(drives[0].checked_at, 1000);
+		assert!(!drives[0].is_failing());
+		assert!(drives[1].is_failing());
+	}
+
+	#[test]
+	fn waits_between_checks() {
+		let mut smart = Smart::default();
+		assert!(smart.update(&setup(FAILING), &pools(), 1000));
+		assert!(!smart.update(&setup(FAILING), &pools(), 1000 + SMART_INTERVAL - 1));
+		assert!(smart.update(&setup(FAILING), &pools(), 1000 + SMART_INTERVAL));
+		assert_eq!(smart.get_drives()[0].checked_at, 1000 + SMART_INTERVAL);
+	}
+
+	#[test]
+	fn keeps_sleeping_drives() {
+		let mut smart = Smart::default();
+		smart.update(&setup(FAILING), &pools(), 1000);
+
+		// the drive spun down, so the reading from before is the best we have
+		smart.update(&setup(STANDBY), &pools(), 1000 + SMART_INTERVAL);
+		let drives = smart.get_drives();
+		assert_eq!(drives.len(), 2);
+		assert_eq!(drives[0].checked_at, 1000 + SMART_INTERVAL);
+		assert_eq!((drives[1].serial.as_str(), drives[1].checked_at), ("ZFL370EW", 1000));
+
+		// zpool not answering is not a reason to forget drives, but a drive leaving the pool is
+		smart.update(&setup(STANDBY), &[], 1000 + 2 * SMART_INTERVAL);
+		assert_eq!(smart.get_drives().len(), 2);
+
+		let mut pools = pools();
+		pools[0].devices[0].children[0].children.pop();
+		smart.update(&setup(STANDBY), &pools, 1000 + 3 * SMART_INTERVAL);
+		assert_eq!(smart.get_drives().iter().map(|drive| drive.serial.as_str()).collect::<Vec<&str>>(), ["ZFL36QCV"]);
+	}
+}
+
+#[cfg(test)]
+mod snapshots {
+	use std::sync::Arc;
+
+	use bansheelong_types::{ RestoreRequest, RestoreState, Snapshot, SnapshotChange, SnapshotDiffQuery, SnapshotSource };
+	use tokio::sync::Mutex;
+
+	use crate::probe::FixtureProbe;
+	use crate::snapshots::{ Restores, run_restores };
+	use crate::status::Storage;
+
+	use super::fixtures::storage;
+
+	const BTRFS_SNAPSHOTS: &str = include_str!("../../types/fixtures/snapshot-btrfs-snapshots");
+	const BTRFS_QGROUPS: &str = include_str!("../../types/fixtures/snapshot-btrfs-qgroups");
+	const ZFS_SNAPSHOTS: &str = include_str!("../../types/fixtures/snapshot-zfs-snapshots");
+	const ZFS_MOUNTPOINTS: &str = include_str!("../../types/fixtures/snapshot-zfs-mountpoints");
+	const FIND_FROM: &str = include_str!("../../types/fixtures/snapshot-find-from");
+	const FIND_TO: &str = include_str!("../../types/fixtures/snapshot-find-to");
+
+	fn find(directory: &str) -> String {
+		format!("find {} -type f -printf %P\\t%s\\t%T@\\n", directory)
+	}
+
+	fn setup() -> Storage<FixtureProbe> {
+		storage(FixtureProbe::default()
+			.command("zfs list -H -p -t snapshot -o name,creation,used", ZFS_SNAPSHOTS)
+			.command("zfs list -H -o name,mountpoint -t filesystem", ZFS_MOUNTPOINTS)
+			.command("btrfs subvolume list -s /btrfs/", BTRFS_SNAPSHOTS)
+			.command("btrfs qgroup show --raw /btrfs/", BTRFS_QGROUPS)
+			.command(&find("/btrfs/home_backup.20220911"), FIND_FROM)
+			.command(&find("/btrfs/home_backup.20220913"), FIND_TO))
+	}
+
+	fn request(snapshot: &str, path: &str) -> RestoreRequest {
+		RestoreRequest {
+			path: String::from(path),
+			secret: String::new(),
+			snapshot: String::from(snapshot),
+		}
+	}
+
+	#[test]
+	fn enumerates_snapshots() {
+		let snapshots = setup().get_snapshots();
+		assert_eq!(snapshots.len(), 7);
+		assert!(snapshots.windows(2).all(|pair| pair[0].created_at <= pair[1].created_at));
+		assert_eq!(snapshots.iter().filter(|snapshot| snapshot.source == SnapshotSource::Btrfs).count(), 3);
+
+		// a machine without zfs still lists its btrfs snapshots
+		let storage = storage(FixtureProbe::default().command("btrfs subvolume list -s /btrfs/", BTRFS_SNAPSHOTS));
+
+		let snapshots = storage.get_snapshots();
+		assert_eq!(snapshots.len(), 3);
+		assert!(snapshots.iter().all(|snapshot| snapshot.size.is_none()));
+	}
+
+	#[test]
+	fn diffs_snapshots() {
+		let storage = setup();
+		let query = |from: &str, path: &str| SnapshotDiffQuery {
+			from: String::from(from),
+			path: String::from(path),
+			secret: String::new(),
+			to: String::from("home_backup.20220913"),
+		};
+
+		let diff = storage.diff_snapshots(&query("home_backup.20220911", ""));
+		assert_eq!(diff.error, None);
+		assert!(!diff.truncated);
+		assert_eq!(diff.changes, vec![
+			(SnapshotChange::Changed, String::from("notes/todo.md")),
+			(SnapshotChange::Removed, String::from("old.txt")),
+			(SnapshotChange::Added, String::from("photos/dog with spaces.jpg")),
+		]);
+
+		assert!(storage.diff_snapshots(&query("home_backup.20220901", "")).error.unwrap().contains("could not find"));
+		assert!(storage.diff_snapshots(&query("home_backup.20220911", "../..")).error.is_some());
+
+		// snapshots that cannot be browsed cannot be diffed either
+		assert!(storage.diff_snapshots(&query("bansheerubber/scratch@manual", "")).error.is_some());
+
+		// find failing comes back as an error instead of everything being removed
+		assert!(storage.diff_snapshots(&query("home_backup.20220912", "")).error.is_some());
+	}
+
+	#[test]
+	fn validates_restores() {
+		let snapshots = setup().get_snapshots();
+		let mut restores = Restores {
+			staging_path: String::from("/staging/"),
+			..Restores::default()
+		};
+
+		assert!(!restores.queue(request("home_backup.20220911", "../../etc/passwd"), &snapshots));
+		assert!(!restores.queue(request("home_backup.20220911", ""), &snapshots));
+		assert!(!restores.queue(request("home_backup.20220901", "notes"), &snapshots));
+		assert!(!restores.queue(request("bansheerubber/scratch@manual", "notes"), &snapshots));
+		assert!(restores.restores.iter().all(|restore| restore.state == RestoreState::Failed && restore.message.is_some()));
+		assert!(restores.sources.is_empty());
+
+		// only the first good request needs something to work through the queue
+		assert!(restores.queue(request("bansheerubber/home@daily-2022-09-13", "notes/todo.md"), &snapshots));
+		assert!(!restores.queue(request("home_backup.20220912", "notes"), &snapshots));
+
+		assert_eq!(restores.restores[4].state, RestoreState::Queued);
+		assert_eq!(restores.restores[4].destination, "/staging/4-bansheerubber-home-daily-2022-09-13/notes/todo.md");
+		assert_eq!(restores.sources[&4].1, "/bansheerubber/home/.zfs/snapshot/daily-2022-09-13/notes/todo.md");
+		assert_eq!(restores.sources[&5], (String::from("/btrfs/home_backup.20220912"), String::from("/btrfs/home_backup.20220912/notes")));
+
+		// old finished restores are forgotten, queued ones are not
+		for _ in 0..30 {
+			restores.queue(request("home_backup.20220911", ""), &snapshots);
+		}
+
+		assert_eq!(restores.restores.len(), 20);
+		assert_eq!(restores.restores.iter().filter(|restore| restore.state == RestoreState::Queued).count(), 2);
+	}
+
+	#[tokio::test]
+	async fn runs_restores() {
+		let root = std::env::temp_dir().join(format!("bansheelong-restores-{}", std::process::id()));
+		let snapshot = root.join("snapshot");
+		std::fs::create_dir_all(snapshot.join("notes")).unwrap();
+		std::fs::write(snapshot.join("notes/todo.md"), "soup\n").unwrap();
+		std::fs::write(root.join("secret"), "hunter2\n").unwrap();
+		std::os::unix::fs::symlink(&root, snapshot.join("outside")).unwrap();
+
+		let snapshots = vec![Snapshot {
+			created_at: 0,
+			name: String::from("home_backup.20220911"),
+			path: snapshot.to_string_lossy().to_string(),
+			size: None,
+			source: SnapshotSource::Btrfs,
+		}];
+
+		let staging = root.join("staging");
+		let restores = Arc::new(Mutex::new(Restores {
+			staging_path: staging.to_string_lossy().to_string(),
+			..Restores::default()
+		}));
+
+		{
+			let mut locked = restores.lock().await;
+			assert!(locked.queue(request("home_backup.20220911", "notes"), &snapshots));
+			assert!(!locked.queue(request("home_backup.20220911", "notes/missing.md"), &snapshots));
+			assert!(!locked.queue(request("home_backup.20220911", "outside/secret"), &snapshots));
+		}
+
+		run_restores(restores.clone()).await;
+
+		let locked = restores.lock().await;
+		assert!(!locked.running);
+		assert_eq!(locked.restores[0].state, RestoreState::Done);
+		assert_eq!(std::fs::read_to_string(staging.join("0-home_backup.20220911/notes/todo.md")).unwrap(), "soup\n");
+		assert_eq!(locked.restores[1].state, RestoreState::Failed);
+		assert!(locked.restores[1].message.is_some());
+
+		// symlinks cannot be used to copy things from outside of the snapshot
+		assert_eq!(locked.restores[2].state, RestoreState::Failed);
+		assert!(locked.restores[2].message.as_ref().unwrap().contains("outside of the snapshot"));
+		assert!(!staging.join("2-home_backup.20220911/outside/secret").exists());
+
+		std::fs::remove_dir_all(root).unwrap();
+	}
+}
+
+#[cfg(test)]
+mod commands {
+	use std::sync::Arc;
+
+	use bansheelong_types::{
+		BackupKind,
+		StorageCommand,
+		StorageCommandRequest,
+		StorageStatus,
+		parse_zpool_status,
+	};
+	use tokio::sync::{ Mutex, Notify };
+	use tokio::time::{ Duration, timeout };
+
+	use crate::alerts::{ AlertContext, AlertRule, Alerts };
+	use crate::commands::{ Commands, is_authorized };
+	use crate::jobs::{ JobConfig, Jobs, JobsConfig };
+	use crate::probe::FixtureProbe;
+
+	use super::fixtures::{ FINISHED_SCRUB, RUNNING_SCRUB, storage };
+
+	fn setup(zpool: &str, probe: FixtureProbe, jobs: Option<Jobs>) -> Commands<FixtureProbe> {
+		Commands {
+			alerts: Arc::new(Mutex::new(Alerts {
+				rules: vec![AlertRule::UsageAbove(0.9)],
+				..Alerts::default()
+			})),
+			jobs: jobs.map(|jobs| Arc::new(Mutex::new(jobs))),
+			message: Arc::new(Mutex::new(StorageStatus {
+				pools: parse_zpool_status(zpool),
+				..StorageStatus::default()
+			})),
+			refresh: Arc::new(Notify::new()),
+			shared_secret: String::from("hunter2"),
+			storage: Arc::new(storage(probe)),
+			tokens: vec![String::from("dashboard-token")],
+		}
+	}
+
+	fn request(command: StorageCommand) -> StorageCommandRequest {
+		StorageCommandRequest {
+			command,
+			id: 7,
+			secret: String::from("hunter2"),
+		}
+	}
+
+	#[test]
+	fn authorizes() {
+		let tokens = vec![String::from("dashboard-token")];
+		assert!(is_authorized("hunter2", "hunter2", &tokens));
+		assert!(is_authorized("dashboard-token", "hunter2", &tokens));
+		assert!(!is_authorized("hunter3", "hunter2", &tokens));
+		assert!(!is_authorized("", "hunter2", &tokens));
+
+		// no secret set up means no commands
+		assert!(!is_authorized("", "", &[]));
+	}
+
+	#[tokio::test]
+	async fn refuses_bad_secrets() {
+		let commands = setup(FINISHED_SCRUB, FixtureProbe::default().command("zpool scrub bansheerubber", ""), None);
+		let response = commands.run(StorageCommandRequest {
+			secret: String::from("hunter3"),
+			..request(StorageCommand::RefreshStatus)
+		}).await;
+
+		assert_eq!(response.id, 7);
+		assert_eq!(response.result, Err(String::from("not authorized")));
+
+		// nothing happened, so the status loop is not woken up
+		assert!(timeout(Duration::from_millis(50), commands.refresh.notified()).await.is_err());
+
+		let response = commands.run(StorageCommandRequest {
+			secret: String::from("dashboard-token"),
+			..request(StorageCommand::RefreshStatus)
+		}).await;
+
+		assert!(response.result.is_ok());
+		assert!(timeout(Duration::from_millis(50), commands.refresh.notified()).await.is_ok());
+	}
+
+	#[tokio::test]
+	async fn starts_scrubs() {
+		let commands = setup(FINISHED_SCRUB, FixtureProbe::default().command("zpool scrub bansheerubber", ""), None);
+		let response = commands.run(request(StorageCommand::StartScrub(String::from("bansheerubber")))).await;
+		assert_eq!(response.result, Ok(String::from("started scrubbing bansheerubber")));
+
+		// pools have to be ones zpool told us about
+		let response = commands.run(request(StorageCommand::StartScrub(String::from("-s bansheerubber")))).await;
+		assert!(response.result.unwrap_err().contains("no pool"));
+
+		let commands = setup(RUNNING_SCRUB, FixtureProbe::default().command("zpool scrub bansheerubber", ""), None);
+		let response = commands.run(request(StorageCommand::StartScrub(String::from("bansheerubber")))).await;
+		assert!(response.result.unwrap_err().contains("already"));
+
+		// zpool failing comes back to the client
+		let commands = setup(FINISHED_SCRUB, FixtureProbe::default(), None);
+		let response = commands.run(request(StorageCommand::StartScrub(String::from("bansheerubber")))).await;
+		assert!(response.result.unwrap_err().contains("could not scrub"));
+	}
+
+	#[tokio::test]
+	async fn acknowledges_alerts() {
+		let commands = setup(FINISHED_SCRUB, FixtureProbe::default(), None);
+		let full = StorageStatus {
+			used_size: 95,
+			total_size: 100,
+			..StorageStatus::default()
+		};
+
+		let empty = StorageStatus::default();
+		let context = |status| AlertContext {
+			last_daily: None,
+			now: 0,
+			status,
+		};
+
+		commands.alerts.lock().await.update(&context(&full));
+
+		let response = commands.run(request(StorageCommand::AcknowledgeAlert(String::from("missing")))).await;
+		assert!(response.result.is_err());
+
+		let response = commands.run(request(StorageCommand::AcknowledgeAlert(String::from("usage")))).await;
+		assert!(response.result.is_ok());
+
+		let mut alerts = commands.alerts.lock().await;
+		assert_eq!(alerts.get_storage_alerts().len(), 1);
+		assert!(alerts.get_storage_alerts()[0].acknowledged);
+
+		// still firing keeps the acknowledgement, firing again after resolving does not
+		alerts.update(&context(&full));
+		assert!(alerts.get_storage_alerts()[0].acknowledged);
+
+		alerts.update(&context(&empty));
+		alerts.update(&context(&full));
+		assert!(!alerts.get_storage_alerts()[0].acknowledged);
+	}
+
+	#[tokio::test]
+	async fn runs_backups() {
+		let commands = setup(FINISHED_SCRUB, FixtureProbe::default(), None);
+		let response = commands.run(request(StorageCommand::RunBackup(BackupKind::Daily))).await;
+		assert!(response.result.unwrap_err().contains("does not run backups"));
+
+		let script = |script: &str| vec![String::from("sh"), String::from("-c"), String::from(script), String::from("sh"), String::from("{name}")];
+		let jobs = Jobs {
+			config: JobsConfig {
+				daily: Some(JobConfig {
+					create: script("sleep 0.2"),
+					hour: 3,
+					keep: 7,
+					remove: Vec::new(),
+				}),
+				..JobsConfig::default()
+			},
+			..Jobs::default()
+		};
+
+		let commands = setup(FINISHED_SCRUB, FixtureProbe::default(), Some(jobs));
+		let response = commands.run(request(StorageCommand::RunBackup(BackupKind::Weekly))).await;
+		assert!(response.result.unwrap_err().contains("no weekly"));
+
+		let response = commands.run(request(StorageCommand::RunBackup(BackupKind::Daily))).await;
+		assert!(response.result.is_ok());
+
+		// one job at a time
+		let response = commands.run(request(StorageCommand::RunBackup(BackupKind::Daily))).await;
+		assert!(response.result.unwrap_err().contains("still running"));
+
+		let jobs = commands.jobs.as_ref().unwrap();
+		for _ in 0..50 {
+			if jobs.lock().await.get_running().is_none() {
+				break;
+			}
+
+			tokio::time::sleep(Duration::from_millis(20)).await;
+		}
+
+		// running it again the same day gets its own name
+		let response = commands.run(request(StorageCommand::RunBackup(BackupKind::Daily))).await;
+		assert!(response.result.is_ok());
+
+		let locked = jobs.lock().await;
+		assert_eq!(locked.history.len(), 2);
+		assert_ne!(locked.history[0].name, locked.history[1].name);
+	}
+}
+
+#[cfg(test)]
+mod socket {
+	use std::sync::Arc;
+
+	use bansheelong_types::{
+		STORAGE_MAX_PAYLOAD_LENGTH,
+		SnapshotDiff,
+		SnapshotDiffQuery,
+		StorageFrame,
+		StorageStatus,
+		decode_storage_frame,
+		encode_storage_snapshot_diff,
+		encode_storage_status,
+	};
+	use tokio::io::AsyncReadExt;
+	use tokio::net::{ TcpListener, TcpStream };
+	use tokio::sync::Mutex;
+
+	use crate::write_socket;
+
+	// pads the error out so the payload is exactly as long as a frame is allowed to be
+	fn get_largest_diff() -> SnapshotDiff {
+		let mut diff = SnapshotDiff {
+			changes: Vec::new(),
+			error: Some(String::new()),
+			query: SnapshotDiffQuery {
+				from: String::from("daily-2022-08-01"),
+				path: String::from("/home"),
+				secret: String::new(),
+				to: String::from("daily-2022-08-02"),
+			},
+			truncated: false,
+		};
+
+		let length = encode_storage_snapshot_diff(&diff).len() - 7;
+		diff.error = Some("a".repeat(STORAGE_MAX_PAYLOAD_LENGTH as usize - length));
+		diff
+	}
+
+	#[tokio::test]
+	async fn writes_largest_frames_whole() {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+		let writable = Arc::new(Mutex::new(listener.accept().await.unwrap().0.into_split().1));
+
+		let diff = get_largest_diff();
+		let encoded = encode_storage_snapshot_diff(&diff);
+		assert_eq!(encoded.len(), 7 + STORAGE_MAX_PAYLOAD_LENGTH as usize);
+
+		// a status broadcast going out at the same time as the reply
+		let status = StorageStatus {
+			used_size: 5,
+			..StorageStatus::default()
+		};
+
+		let reply = tokio::spawn(write_socket(writable.clone(), encoded));
+		let broadcast = tokio::spawn(write_socket(writable.clone(), encode_storage_status(&status)));
+
+		let mut buffer = Vec::new();
+		let mut frames = Vec::new();
+		while frames.len() < 2 {
+			assert_ne!(client.read_buf(&mut buffer).await.unwrap(), 0);
+			while let Some((frame, used)) = decode_storage_frame(&buffer).unwrap() {
+				buffer.drain(..used);
+				frames.push(frame);
+			}
+		}
+
+		reply.await.unwrap().unwrap();
+		broadcast.await.unwrap().unwrap();
+
+		assert!(buffer.is_empty());
+		assert!(frames.contains(&StorageFrame::SnapshotDiff(diff)));
+		assert!(frames.contains(&StorageFrame::Status(status)));
+	}
+}
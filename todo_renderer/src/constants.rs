@@ -18,6 +18,8 @@ pub(crate) const TIMESHEET_HOUR_HEIGHT: u32 = TIMESHEET_HEIGHT / 24;
 pub(crate) const TIMESHEET_HEIGHT_PADDING: u32 = 4;
 pub(crate) const TIMESHEET_TEXT_COLOR: Rgba<u8> = Rgba([233, 217, 233, 255]);
 pub(crate) const TIMESHEET_TIME_LINE: Rgba<u8> = Rgba([88, 60, 99, 255]);
+pub(crate) const TIMESHEET_TRACKED_COLOR: Rgba<u8> = Rgba([233, 217, 233, 255]);
+pub(crate) const TIMESHEET_TRACKED_WIDTH: u32 = FONT_WIDTH / 2 + 2;
 pub(crate) const TIMESHEET_WIDTH_PADDING: u32 = FONT_WIDTH * 3;
 
 pub(crate) const TIMESHEET_BLUE: Rgba<u8> = Rgba([138, 118, 224, 255]);
@@ -33,6 +35,9 @@ pub(crate) static TIMESHEET_COLORS: [Rgba<u8>; 4] = [
 ];
 
 pub(crate) const TODO_LIST_TEXT_COLOR: Rgba<u8> = Rgba([183, 172, 183, 255]);
+pub(crate) const TODO_LIST_LOW_COLOR: Rgba<u8> = Rgba([120, 108, 120, 255]);
+pub(crate) const TODO_LIST_HIGH_COLOR: Rgba<u8> = Rgba([224, 170, 89, 255]);
+pub(crate) const TODO_LIST_URGENT_COLOR: Rgba<u8> = Rgba([224, 89, 89, 255]);
 
 pub(crate) static VALID_STARTING_CHARACTERS: [char; 4] = ['-', '!', '%', 'z'];
 
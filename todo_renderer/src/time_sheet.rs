@@ -1,5 +1,5 @@
-use bansheelong_types::{ Date, IO, Item, Weekday };
-use chrono::{ Datelike, Local, Timelike };
+use bansheelong_types::{ Date, IO, ScheduledItem, Time };
+use chrono::{ Local, Timelike };
 use image::RgbaImage;
 use imageproc::drawing::{ draw_filled_rect_mut, draw_text_mut };
 use imageproc::rect::Rect;
@@ -16,6 +16,8 @@ use crate::constants:: {
 	TIMESHEET_HEIGHT_PADDING,
 	TIMESHEET_HOUR_HEIGHT,
 	TIMESHEET_TIME_LINE,
+	TIMESHEET_TRACKED_COLOR,
+	TIMESHEET_TRACKED_WIDTH,
 	TIMESHEET_WIDTH_PADDING
 };
 
@@ -44,24 +46,62 @@ fn time_to_position(hour: u8, minute: u8) -> i32 {
 	}
 }
 
-fn draw_item(image: &mut RgbaImage, item: &Item, color_index: &mut usize) {
-	let time = item.time.unwrap();
+fn draw_item(image: &mut RgbaImage, scheduled: &ScheduledItem, color_index: &mut usize) {
+	let time = scheduled.time;
 
-	let start_time = time_to_position(time.start_hour, time.start_minute);
+	// cut events off at the top of the time sheet
+	let (start_hour, start_minute) = if (time.start_hour as HourMinute) < START_TIME {
+		(START_TIME as u8, 0)
+	} else {
+		(time.start_hour, time.start_minute)
+	};
+
+	if (time.end_hour, time.end_minute) <= (start_hour, start_minute) {
+		return;
+	}
+
+	let start_time = time_to_position(start_hour, start_minute);
 	let end_time = time_to_position(time.end_hour, time.end_minute);
 
+	// overlapping items split the width between them, but always keep at least a character each
+	let characters_per_row = (CHARACTERS_PER_ROW / scheduled.columns as u32).max(1);
+	let x_offset = TIMESHEET_WIDTH_PADDING as i32 + (FONT_WIDTH * characters_per_row * scheduled.column as u32) as i32;
+
 	draw_filled_rect_mut(
 		image,
-		Rect::at(TIMESHEET_WIDTH_PADDING as i32, start_time)
-			.of_size(FONT_WIDTH * CHARACTERS_PER_ROW, (end_time - start_time) as u32),
+		Rect::at(x_offset, start_time)
+			.of_size(FONT_WIDTH * characters_per_row, (end_time - start_time).max(1) as u32),
 		TIMESHEET_COLORS[*color_index]
 	);
 
-	draw_timesheet_line(image, item.description.clone(), start_time);
+	draw_timesheet_line(image, scheduled.item.description.clone(), x_offset, characters_per_row, start_time);
 
 	*color_index = (*color_index + 1) % TIMESHEET_COLORS.len();
 }
 
+// tracked time goes in a thin strip down the right edge, so it lines up against what was planned
+fn draw_tracked(image: &mut RgbaImage, time: Time) {
+	let (start_hour, start_minute) = if (time.start_hour as HourMinute) < START_TIME {
+		(START_TIME as u8, 0)
+	} else {
+		(time.start_hour, time.start_minute)
+	};
+
+	if (time.end_hour, time.end_minute) <= (start_hour, start_minute) {
+		return;
+	}
+
+	let start_time = time_to_position(start_hour, start_minute);
+	let end_time = time_to_position(time.end_hour, time.end_minute);
+
+	draw_filled_rect_mut(
+		image,
+		Rect::at((TIMESHEET_WIDTH_PADDING + FONT_WIDTH * CHARACTERS_PER_ROW + 2) as i32, start_time)
+			.of_size(TIMESHEET_TRACKED_WIDTH, (end_time - start_time).max(1) as u32),
+		TIMESHEET_TRACKED_COLOR
+	);
+}
+
 pub fn draw_time_sheet(database: &IO, file_name: String) {
 	let width = FONT_WIDTH * CHARACTERS_PER_ROW + TIMESHEET_WIDTH_PADDING + (TIMESHEET_WIDTH_PADDING / 2 as u32);
 	let height = TIMESHEET_HEIGHT + TIMESHEET_HEIGHT_PADDING - TIMESHEET_HOUR_HEIGHT * (23 - (END_TIME - START_TIME));
@@ -72,48 +112,14 @@ pub fn draw_time_sheet(database: &IO, file_name: String) {
 	let mut color_index = 0;
 	let time = Local::now();
 
-	let weekday = match time.weekday() {
-		chrono::Weekday::Mon => { Weekday::Monday },
-		chrono::Weekday::Tue => { Weekday::Tuesday },
-		chrono::Weekday::Wed => { Weekday::Wednesday },
-		chrono::Weekday::Thu => { Weekday::Thursday },
-		chrono::Weekday::Fri => { Weekday::Friday },
-		chrono::Weekday::Sat => { Weekday::Saturday },
-		chrono::Weekday::Sun => { Weekday::Sunday },
-	};
-
-	let current_date = Some(Date {
-		day: time.day() as u8,
-		month: time.month() as u8,
-		year: (time.year() % 100) as u8,
-	});
-
-	// draw day-to-day events
-	for (date, day) in database.todos_database.mapping.iter() {
-		if date != &current_date {
-			continue;
-		}
-		
-		for item in day.items.iter() {
-			if let None = item.time {
-				continue;
-			}
-
-			draw_item(&mut image, item, &mut color_index);
-		}
+	// draw day-to-day and recurring events, side by side where they overlap
+	for scheduled in database.todos_database.schedule(Date::today()).iter() {
+		draw_item(&mut image, scheduled, &mut color_index);
 	}
 
-	// draw recurring events
-	if database.todos_database.mapping.contains_key(&None) {
-		for item in database.todos_database.mapping[&None].items.iter() {
-			if let None = item.time {
-				continue;
-			}
-
-			if item.time.unwrap().day == Some(weekday) {
-				draw_item(&mut image, item, &mut color_index);
-			}
-		}
+	// draw what actually happened next to the plan
+	for (_, tracked) in database.todos_database.get_sessions(Date::today(), time.naive_local()) {
+		draw_tracked(&mut image, tracked);
 	}
 
 	// draw time line
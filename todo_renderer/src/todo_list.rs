@@ -1,6 +1,6 @@
-use bansheelong_types::{ Date, IO };
+use bansheelong_types::{ Date, IO, Item, Priority };
 use chrono::{ Datelike, Local, TimeZone, Utc, Weekday };
-use image::RgbaImage;
+use image::{ Rgba, RgbaImage };
 use image::imageops::crop;
 use imageproc::drawing::{ draw_filled_circle_mut, draw_filled_rect_mut };
 use imageproc::rect::Rect;
@@ -11,10 +11,40 @@ use crate::constants:: {
 	FONT_HEIGHT,
 	FONT_WIDTH,
 	TIMESHEET_COLORS,
+	TODO_LIST_HIGH_COLOR,
+	TODO_LIST_LOW_COLOR,
 	TODO_LIST_TEXT_COLOR,
+	TODO_LIST_URGENT_COLOR,
 };
 use crate::util::{ draw_todo_line, row_to_y };
 
+fn get_item_color(item: &Item, current_date: &Option<Date>) -> Rgba<u8> {
+	// anything past its due date is as urgent as it gets
+	if item.due.is_some() && item.due < *current_date {
+		return TODO_LIST_URGENT_COLOR;
+	}
+
+	match item.priority {
+		Priority::Low => TODO_LIST_LOW_COLOR,
+		Priority::Normal => TODO_LIST_TEXT_COLOR,
+		Priority::High => TODO_LIST_HIGH_COLOR,
+		Priority::Urgent => TODO_LIST_URGENT_COLOR,
+	}
+}
+
+// draws children as indented checkboxes, returns the row after the last one
+fn draw_children(image: &mut RgbaImage, item: &Item, depth: usize, mut row: f32, current_date: &Option<Date>) -> f32 {
+	for child in item.children.iter() {
+		let checkbox = if child.is_done() { "[x]" } else { "[ ]" };
+		let description = format!("{}{} {}", "  ".repeat(depth), checkbox, child.get_text());
+
+		row = draw_todo_line(image, description, row, get_item_color(child, current_date));
+		row = draw_children(image, child, depth + 1, row, current_date);
+	}
+
+	row
+}
+
 pub fn draw_todo_list(database: &IO, file_name: String) {
 	let mut image = RgbaImage::new(FONT_WIDTH * CHARACTERS_PER_ROW, 1000);
 	draw_filled_rect_mut(&mut image, Rect::at(0, 0).of_size(FONT_WIDTH * CHARACTERS_PER_ROW, 1000), BACKGROUND_COLOR);
@@ -51,12 +81,12 @@ pub fn draw_todo_list(database: &IO, file_name: String) {
 				TODO_LIST_TEXT_COLOR
 			);
 
-			row = draw_todo_line(&mut image, date_string.clone(), row);
+			row = draw_todo_line(&mut image, date_string.clone(), row, TODO_LIST_TEXT_COLOR);
 			row += 0.4; // padding for dates
 		}
 
 		let mut color_index = 0;
-		for item in day.items.iter() {
+		for item in day.items_by_priority() {
 			if item.time.is_some() && date.is_none() { // do not display recurring events
 				continue;
 			}
@@ -84,7 +114,15 @@ pub fn draw_todo_list(database: &IO, file_name: String) {
 					color_index += 1;
 				}
 				
-				row = draw_todo_line(&mut image, description, row);
+				let description = if item.children.is_empty() {
+					description
+				} else {
+					let (done, total) = item.progress();
+					format!("{} ({}/{})", description, done, total)
+				};
+
+				row = draw_todo_line(&mut image, description, row, get_item_color(item, &current_date));
+				row = draw_children(&mut image, item, 1, row, &current_date);
 			}
 		}
 	}
@@ -0,0 +1,66 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::JobStatusFlags;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum BackupKind {
+	Daily,
+	Weekly,
+	Monthly,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum BackupJobState {
+	Running,
+	Pruning, // the backup is made, older ones past retention are being removed
+	Succeeded,
+	Failed,
+}
+
+// one run of a backup job. times are unix seconds
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BackupJob {
+	pub exit_code: Option<i32>,
+	pub finished_at: Option<i64>,
+	pub kind: BackupKind,
+	pub message: Option<String>, // the last thing the job wrote to stderr, or why it could not run
+	pub name: String,
+	pub progress: Option<f64>, // percent, if the job reports it
+	#[serde(default)]
+	pub removed: bool, // retention has removed the backup it made
+	pub started_at: i64,
+	pub state: BackupJobState,
+}
+
+impl BackupKind {
+	pub fn get_name(&self) -> &'static str {
+		match self {
+			BackupKind::Daily => "daily",
+			BackupKind::Weekly => "weekly",
+			BackupKind::Monthly => "monthly",
+		}
+	}
+
+	// the flags clients from before structured jobs look at
+	pub fn get_flags(&self, state: BackupJobState) -> JobStatusFlags {
+		match (self, state) {
+			(BackupKind::Daily, BackupJobState::Running) => JobStatusFlags::DOWNLOADING_DAILY,
+			(BackupKind::Weekly, BackupJobState::Running) => JobStatusFlags::CREATING_WEEKLY,
+			(BackupKind::Monthly, BackupJobState::Running) => JobStatusFlags::CREATING_MONTHLY,
+			(BackupKind::Daily, BackupJobState::Pruning) => JobStatusFlags::REMOVING_DAILY,
+			(BackupKind::Weekly, BackupJobState::Pruning) => JobStatusFlags::REMOVING_WEEKLY,
+			_ => JobStatusFlags::IDLE,
+		}
+	}
+}
+
+impl BackupJob {
+	pub fn is_active(&self) -> bool {
+		matches!(self.state, BackupJobState::Running | BackupJobState::Pruning)
+	}
+
+	// how long the job took, or has been going for
+	pub fn get_duration(&self, now: i64) -> i64 {
+		self.finished_at.unwrap_or(now) - self.started_at
+	}
+}
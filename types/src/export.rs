@@ -0,0 +1,210 @@
+use crate::{
+	Error,
+	IO,
+	Item,
+	MealsDatabase,
+	Recipe,
+	Time,
+	TodosDatabase,
+	Weekday,
+};
+use crate::io::{ INFO_REGEX, NAME_REGEX, get_time_from_line };
+
+impl IO {
+	// inverse of parse_from_human_readable
+	pub fn write_to_human_readable(&self, todo_list: String, recipe_list: String) -> Result<(), Error> {
+		// find out if the recipes can be written before touching either file
+		let recipes = recipes_to_human_readable(&self.meals_database)?;
+
+		if let Err(error) = std::fs::write(&todo_list, todos_to_human_readable(&self.todos_database)) {
+			return Err(Error {
+				message: format!("{:?}", error),
+				..Error::default()
+			});
+		}
+
+		if let Err(error) = std::fs::write(&recipe_list, recipes) {
+			return Err(Error {
+				message: format!("{:?}", error),
+				..Error::default()
+			});
+		}
+
+		Ok(())
+	}
+}
+
+pub fn todos_to_human_readable(database: &TodosDatabase) -> String {
+	let mut lines = Vec::new();
+
+	// the general list has no date, so it sits at the top of the file before any date line
+	for (date, day) in database.mapping.iter() {
+		if let Some(date) = date {
+			lines.push(format!("{}:", date.to_string()));
+		}
+
+		for item in day.items.iter() {
+			lines.extend(item_to_human_readable_lines(item));
+		}
+	}
+
+	// every line is an item to the parser, so a trailing newline would add an empty item
+	lines.join("\n")
+}
+
+// the item followed by its children, each indented one tab further than its parent
+pub fn item_to_human_readable_lines(item: &Item) -> Vec<String> {
+	let mut lines = vec![item_to_human_readable(item)];
+	for child in item.children.iter() {
+		lines.extend(item_to_human_readable_lines(child).into_iter().map(|line| format!("\t{}", line)));
+	}
+
+	lines
+}
+
+pub fn item_to_human_readable(item: &Item) -> String {
+	let time = match item.time {
+		Some(time) => time,
+		None => return item.description.clone(),
+	};
+
+	// keep the user's own formatting if it already parses to the same time
+	if let Ok(Some(parsed)) = get_time_from_line(item.description.clone()) {
+		if parsed == time {
+			return item.description.clone();
+		}
+	}
+
+	if item.description.is_empty() {
+		format!("@{}", time_to_human_readable(&time))
+	} else {
+		format!("@{} {}", time_to_human_readable(&time), item.description)
+	}
+}
+
+// always writes am/pm explicitly so the parser never has to guess
+pub fn time_to_human_readable(time: &Time) -> String {
+	let day = match time.day {
+		Some(Weekday::Monday) => "m",
+		Some(Weekday::Tuesday) => "t",
+		Some(Weekday::Wednesday) => "w",
+		Some(Weekday::Thursday) => "th",
+		Some(Weekday::Friday) => "f",
+		Some(Weekday::Saturday) => "s",
+		Some(Weekday::Sunday) => "su",
+		None => "",
+	};
+
+	let format_hour = |hour: u8, minute: u8| {
+		let (hour, ampm) = match hour {
+			0 => (12, "am"),
+			1..=11 => (hour, "am"),
+			12 => (12, "pm"),
+			_ => (hour - 12, "pm"),
+		};
+
+		if minute == 0 {
+			format!("{}{}", hour, ampm)
+		} else {
+			format!("{}:{:02}{}", hour, minute, ampm)
+		}
+	};
+
+	if time.is_all_day() {
+		return format!("{}allday", day);
+	}
+
+	let start_minutes = time.start_hour as u32 * 60 + time.start_minute as u32;
+	let end_minutes = time.end_hour as u32 * 60 + time.end_minute as u32;
+	let duration = end_minutes.saturating_sub(start_minutes);
+
+	let start = format_hour(time.start_hour, time.start_minute);
+	if duration > 0 && duration < 24 * 60 { // ranges past midnight wrap around, which the parser understands
+		format!("{}{}-{}", day, start, format_hour(time.end_hour % 24, time.end_minute))
+	} else { // a range of a whole day would look empty, so write it as a duration
+		let (hours, minutes) = (duration / 60, duration % 60);
+		if minutes == 0 {
+			format!("{}{}+{}h", day, start, hours)
+		} else {
+			format!("{}{}+{}h{}m", day, start, hours, minutes)
+		}
+	}
+}
+
+pub fn recipes_to_human_readable(database: &MealsDatabase) -> Result<String, Error> {
+	Ok(
+		database.recipes.iter()
+			.map(recipe_to_human_readable)
+			.collect::<Result<Vec<String>, Error>>()?
+			.join("\n")
+	)
+}
+
+// the format has no way of escaping anything, so a recipe the parser would read back differently is an error instead
+pub fn recipe_to_human_readable(recipe: &Recipe) -> Result<String, Error> {
+	let error = |what: &str| Error {
+		message: format!("recipe '{}' has {} that the recipe list cannot hold", recipe.name, what),
+		..Error::default()
+	};
+
+	let name = match (&recipe.image_url, recipe.minutes) {
+		(Some(image_url), Some(minutes)) => format!("{} [{}, {}]:", recipe.name, image_url, minutes),
+		(Some(image_url), None) => format!("{} [{}]:", recipe.name, image_url),
+		(None, Some(minutes)) => format!("{} [{}]:", recipe.name, minutes),
+		(None, None) => format!("{}:", recipe.name),
+	};
+
+	let read_back = NAME_REGEX.captures(&name).is_some_and(|captures| {
+		let get = |index| captures.get(index).map(|capture| capture.as_str().to_string());
+		let (image_url, minutes) = match (get(2), get(3)) {
+			(Some(bracket), None) if bracket.chars().all(|character| character.is_ascii_digit()) => (None, Some(bracket)),
+			parts => parts,
+		};
+
+		get(1).as_deref() == Some(recipe.name.as_str())
+			&& image_url == recipe.image_url
+			&& minutes == recipe.minutes.map(|minutes| minutes.to_string())
+	});
+
+	if !read_back {
+		return Err(error("a name or image url"));
+	}
+
+	let mut string = format!("{}\n", name);
+
+	// a line and the two parts the parser should find in it
+	let mut push_line = |character: char, name: &str, extra: &Option<String>, what: &str| {
+		let line = match extra {
+			Some(extra) => format!("{} {} [{}]", character, name, extra),
+			None => format!("{} {}", character, name),
+		};
+
+		let read_back = INFO_REGEX.captures(&line).is_some_and(|captures| {
+			captures.get(0).map(|capture| capture.start()) == Some(0)
+				&& captures.get(2).map(|capture| capture.as_str()) == Some(name)
+				&& captures.get(3).map(|capture| capture.as_str()) == extra.as_deref()
+		});
+
+		if !read_back {
+			return Err(error(what));
+		}
+
+		string += &line;
+		string += "\n";
+		Ok(())
+	};
+
+	for ingredient in recipe.ingredients.iter() {
+		push_line('-', &ingredient.name, &ingredient.quantity, "an ingredient")?;
+	}
+
+	for step in recipe.preparation_steps.iter() {
+		push_line('#', &step.name, &step.extra_information, "a preparation step")?;
+	}
+
+	for step in recipe.cooking_steps.iter() {
+		push_line('$', &step.name, &step.extra_information, "a cooking step")?;
+	}
+
+	Ok(string)
+}
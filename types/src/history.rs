@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use crate::{ Date, MealStatus, MealsDatabase };
+
+impl MealsDatabase {
+	// moves planned meals that no longer cover today into the history log, returns how many were moved
+	pub fn archive_planned_meals(&mut self, today: Date) -> usize {
+		let expired = self.planned_meal_mapping.iter()
+			.filter(|(_, meal)| meal.last_covered_date() < today)
+			.map(|(date, _)| *date)
+			.collect::<Vec<Date>>();
+
+		for date in expired.iter() {
+			let mut meal = self.planned_meal_mapping.remove(date).unwrap();
+
+			// nobody marked the meal, so assume we ate it
+			if meal.status == MealStatus::Planned {
+				meal.status = MealStatus::Cooked;
+			}
+
+			self.history.push(meal);
+		}
+
+		self.history.sort_by_key(|meal| meal.date);
+
+		expired.len()
+	}
+
+	pub fn times_cooked(&self, recipe_name: &str) -> usize {
+		self.history.iter()
+			.filter(|meal| was_cooked(meal.status) && meal.recipe.name.to_lowercase() == recipe_name.to_lowercase())
+			.count()
+	}
+
+	pub fn last_cooked(&self, recipe_name: &str) -> Option<Date> {
+		self.history.iter()
+			.filter(|meal| was_cooked(meal.status) && meal.recipe.name.to_lowercase() == recipe_name.to_lowercase())
+			.map(|meal| meal.date)
+			.max()
+	}
+
+	pub fn most_cooked_recipe(&self) -> Option<(String, usize)> {
+		let mut counts: HashMap<String, (String, usize)> = HashMap::new();
+		for meal in self.history.iter() {
+			if !was_cooked(meal.status) {
+				continue;
+			}
+
+			let entry = counts.entry(meal.recipe.name.to_lowercase())
+				.or_insert((meal.recipe.name.clone(), 0));
+			entry.1 += 1;
+		}
+
+		counts.into_values()
+			.max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+	}
+}
+
+fn was_cooked(status: MealStatus) -> bool {
+	match status {
+		MealStatus::Cooked | MealStatus::Leftovers(_) => true,
+		MealStatus::Planned | MealStatus::Skipped => false,
+	}
+}
@@ -0,0 +1,506 @@
+use chrono::{ Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc };
+
+use crate::import::parse_duration;
+use crate::{ Date, Day, Error, IO, Item, MealsDatabase, Time, TodosDatabase, Weekday };
+
+type Property = (String, String, String);
+
+const WEEKDAYS: [(Weekday, &str); 7] = [
+	(Weekday::Monday, "MO"),
+	(Weekday::Tuesday, "TU"),
+	(Weekday::Wednesday, "WE"),
+	(Weekday::Thursday, "TH"),
+	(Weekday::Friday, "FR"),
+	(Weekday::Saturday, "SA"),
+	(Weekday::Sunday, "SU"),
+];
+
+// exports the schedule as an RFC 5545 calendar. timed items become events, recurring items become weekly events
+// starting the week of today, everything else becomes a todo
+pub fn to_ical(todos: &TodosDatabase, meals: &MealsDatabase, today: Date) -> String {
+	let mut lines = vec![
+		String::from("BEGIN:VCALENDAR"),
+		String::from("VERSION:2.0"),
+		String::from("PRODID:-//bansheelong//todos//EN"),
+		String::from("CALSCALE:GREGORIAN"),
+	];
+
+	let stamp = format!("DTSTAMP:{}T000000Z", format_date(&today));
+
+	for (date, day) in todos.mapping.iter() {
+		for item in day.items.iter().filter(|item| !item.get_text().trim().is_empty()) {
+			lines.extend(item_to_component(date, item, today, &stamp));
+		}
+	}
+
+	for (date, meal) in meals.planned_meal_mapping.iter() {
+		lines.push(String::from("BEGIN:VEVENT"));
+		lines.push(format!("UID:{}", get_uid(&Some(*date), &format!("meal {}", meal.recipe.name))));
+		lines.push(stamp.clone());
+		lines.push(format!("DTSTART;VALUE=DATE:{}", format_date(date)));
+		lines.push(format!("DTEND;VALUE=DATE:{}", format_date(&meal.last_covered_date().add_days(1))));
+		lines.push(format!("SUMMARY:{}", escape(&meal.recipe.name)));
+
+		if !meal.ingredients.is_empty() {
+			lines.push(format!(
+				"DESCRIPTION:{}",
+				escape(&meal.ingredients.iter()
+					.map(|planned_ingredient| planned_ingredient.ingredient.name.clone())
+					.collect::<Vec<String>>()
+					.join("\n"))
+			));
+		}
+
+		lines.push(String::from("END:VEVENT"));
+	}
+
+	lines.push(String::from("END:VCALENDAR"));
+	to_content_lines(&lines)
+}
+
+// a calendar holding just the one item, for serving it on its own
+pub fn item_to_ical(date: &Option<Date>, item: &Item, today: Date) -> String {
+	let mut lines = vec![
+		String::from("BEGIN:VCALENDAR"),
+		String::from("VERSION:2.0"),
+		String::from("PRODID:-//bansheelong//todos//EN"),
+	];
+
+	lines.extend(item_to_component(date, item, today, &format!("DTSTAMP:{}T000000Z", format_date(&today))));
+	lines.push(String::from("END:VCALENDAR"));
+	to_content_lines(&lines)
+}
+
+// items a calendar app has put keep the uid it gave them. the rest get one derived from the item itself, so
+// editing them in the text files gives them a new one
+pub fn item_uid(date: &Option<Date>, item: &Item) -> String {
+	match &item.uid {
+		Some(uid) => uid.clone(),
+		None => get_uid(date, &item.description),
+	}
+}
+
+// changes whenever anything about the item does, including its children
+pub fn item_etag(date: &Option<Date>, item: &Item) -> String {
+	format!("{:016x}", fnv1a(format!("{:?}/{:?}", date, item).as_bytes()))
+}
+
+// reads the events out of a calendar. weekly events that repeat on one day become recurring items in the general
+// list, everything else lands on the day it starts
+pub fn parse_ical(contents: &str) -> Result<Vec<Day>, Error> {
+	let mut days: Vec<Day> = Vec::new();
+	for (_, properties) in parse_components(contents)?.iter().filter(|(kind, _)| kind == "VEVENT") {
+		if let Some((date, item)) = event_to_item(properties) {
+			match days.iter_mut().find(|day| day.date == date) {
+				Some(day) => day.items.push(item),
+				None => days.push(Day {
+					date,
+					items: vec![item],
+				}),
+			}
+		}
+	}
+
+	days.sort_by_key(|day| day.date);
+	Ok(days)
+}
+
+// reads the first event or todo out of a calendar, which is what CalDAV clients send us
+pub fn parse_ical_item(contents: &str) -> Result<(Option<Date>, Item), Error> {
+	let item = parse_components(contents)?.iter()
+		.find_map(|(kind, properties)| match kind.as_str() {
+			"VEVENT" => event_to_item(properties),
+			"VTODO" => todo_to_item(properties),
+			_ => None,
+		});
+
+	item.ok_or(Error {
+		message: String::from("Calendar has no usable event or todo"),
+		..Error::default()
+	})
+}
+
+impl TodosDatabase {
+	pub fn find_by_uid(&self, uid: &str) -> Option<(Option<Date>, &Item)> {
+		self.mapping.iter()
+			.flat_map(|(date, day)| day.items.iter().map(move |item| (*date, item)))
+			.find(|(date, item)| item_uid(date, item) == uid)
+	}
+
+	// the text files have nowhere to keep uids, so carry them over from the other copy for items that did not change
+	pub fn keep_uids_from(&mut self, other: &TodosDatabase) {
+		for (date, day) in self.mapping.iter_mut() {
+			let other_items = match other.mapping.get(date) {
+				Some(other_day) => &other_day.items,
+				None => continue,
+			};
+
+			for item in day.items.iter_mut().filter(|item| item.uid.is_none()) {
+				item.uid = other_items.iter()
+					.find(|other_item| other_item.description == item.description)
+					.and_then(|other_item| other_item.uid.clone());
+			}
+		}
+	}
+}
+
+impl IO {
+	// adds the calendar's events to the todos, skipping ones we already have. returns how many were added
+	pub fn import_ical(&mut self, contents: &str) -> Result<usize, Error> {
+		let mut count = 0;
+		for day in parse_ical(contents)? {
+			for item in day.items {
+				let exists = self.todos_database.mapping.get(&day.date)
+					.is_some_and(|existing| existing.items.iter().any(|other| other.description == item.description && other.time == item.time));
+
+				if !exists {
+					self.add_to_todos_database(item, day.date)?;
+					count += 1;
+				}
+			}
+		}
+
+		Ok(count)
+	}
+
+	// updates the item with the given uid from what the calendar app sent, or adds it if there is none. returns
+	// whether it was added
+	pub fn put_ical_item(&mut self, uid: &str, date: Option<Date>, mut item: Item) -> Result<bool, Error> {
+		let existing = self.todos_database.find_by_uid(uid)
+			.map(|(date, item)| (date, item.clone()));
+
+		item.uid = Some(String::from(uid));
+
+		match existing {
+			Some((old_date, old)) => {
+				// todos with a due date are exported with it as their DUE, which reads back as the day they are on
+				let date = if old.due.is_some() && item.time.is_none() {
+					old_date
+				} else {
+					date
+				};
+
+				self.remove_from_todos_database(old_date, &old.description)?;
+				self.add_to_todos_database(merge_ical_item(old, item), date)?;
+				Ok(false)
+			},
+			None => {
+				self.add_to_todos_database(item, date)?;
+				Ok(true)
+			},
+		}
+	}
+
+	// returns whether there was anything to delete
+	pub fn delete_ical_item(&mut self, uid: &str) -> Result<bool, Error> {
+		let existing = self.todos_database.find_by_uid(uid)
+			.map(|(date, item)| (date, item.description.clone()));
+
+		match existing {
+			Some((date, description)) => {
+				self.remove_from_todos_database(date, &description)?;
+				Ok(true)
+			},
+			None => Ok(false),
+		}
+	}
+}
+
+// calendars only know about the text, time and whether a todo is done, so everything else comes from the item we
+// already had
+fn merge_ical_item(old: Item, new: Item) -> Item {
+	let text = new.description.strip_prefix("- ").unwrap_or(&new.description);
+	let mut item = Item::from_description(format!("{} {}", old.priority.to_marker(), text), new.time.or(old.time));
+	item.children = old.children;
+	item.uid = new.uid;
+	item
+}
+
+// splits a calendar into its components and their properties, as (name, parameters, value)
+fn parse_components(contents: &str) -> Result<Vec<(String, Vec<Property>)>, Error> {
+	// unfold continuation lines first
+	let unfolded = contents.replace("\r\n", "\n").replace("\n ", "").replace("\n\t", "");
+
+	if !unfolded.lines().any(|line| line.trim() == "BEGIN:VCALENDAR") {
+		return Err(Error {
+			message: String::from("Not an iCalendar file"),
+			..Error::default()
+		});
+	}
+
+	let mut components = Vec::new();
+	let mut component: Option<(String, Vec<Property>)> = None;
+	for line in unfolded.lines() {
+		let line = line.trim_end();
+		match line.split_once(':') {
+			Some(("BEGIN", kind)) if kind == "VEVENT" || kind == "VTODO" => {
+				component = Some((String::from(kind), Vec::new()));
+				continue;
+			},
+			Some(("END", kind)) if component.as_ref().is_some_and(|(current, _)| current == kind) => {
+				components.push(component.take().unwrap());
+				continue;
+			},
+			_ => {},
+		}
+
+		// NAME;PARAM=VALUE:value
+		if let (Some((_, properties)), Some((key, value))) = (component.as_mut(), line.split_once(':')) {
+			let (name, parameters) = key.split_once(';').unwrap_or((key, ""));
+			properties.push((name.to_uppercase(), parameters.to_uppercase(), unescape(value)));
+		}
+	}
+
+	Ok(components)
+}
+
+// a timed item is an event, a recurring item a weekly event starting the week of today, everything else a todo
+fn item_to_component(date: &Option<Date>, item: &Item, today: Date, stamp: &str) -> Vec<String> {
+	let mut lines = Vec::new();
+	let uid = item_uid(date, item);
+
+	match (date, item.time) {
+		(Some(date), Some(time)) => {
+			lines.push(String::from("BEGIN:VEVENT"));
+			lines.push(format!("UID:{}", uid));
+			lines.push(String::from(stamp));
+			lines.extend(get_event_times(date, &time));
+			lines.push(format!("SUMMARY:{}", escape(item.get_text())));
+			lines.push(String::from("END:VEVENT"));
+		},
+		(None, Some(time)) if time.day.is_some() => {
+			let weekday = time.day.unwrap();
+			let start = get_weekday_in_week(today, weekday);
+
+			lines.push(String::from("BEGIN:VEVENT"));
+			lines.push(format!("UID:{}", uid));
+			lines.push(String::from(stamp));
+			lines.extend(get_event_times(&start, &time));
+			lines.push(format!(
+				"RRULE:FREQ=WEEKLY;BYDAY={}",
+				WEEKDAYS.iter().find(|(day, _)| *day == weekday).unwrap().1
+			));
+			lines.push(format!("SUMMARY:{}", escape(item.get_text())));
+			lines.push(String::from("END:VEVENT"));
+		},
+		_ => {
+			lines.push(String::from("BEGIN:VTODO"));
+			lines.push(format!("UID:{}", uid));
+			lines.push(String::from(stamp));
+
+			if let Some(due) = item.due.or(*date) {
+				lines.push(format!("DUE;VALUE=DATE:{}", format_date(&due)));
+			}
+
+			if item.is_done() {
+				lines.push(String::from("STATUS:COMPLETED"));
+			} else {
+				lines.push(String::from("STATUS:NEEDS-ACTION"));
+			}
+
+			if !item.tags.is_empty() {
+				lines.push(format!("CATEGORIES:{}", item.tags.iter().map(|tag| escape(tag)).collect::<Vec<String>>().join(",")));
+			}
+
+			lines.push(format!("SUMMARY:{}", escape(item.get_text())));
+			lines.push(String::from("END:VTODO"));
+		},
+	}
+
+	lines
+}
+
+fn event_to_item(properties: &[Property]) -> Option<(Option<Date>, Item)> {
+	let get = |name: &str| properties.iter().find(|(key, _, _)| key == name);
+
+	let summary = get("SUMMARY").map_or("", |(_, _, value)| value.as_str());
+	let (_, start_parameters, start) = get("DTSTART")?;
+	let (start, all_day) = parse_date_time(start, start_parameters)?;
+
+	let end = match get("DTEND") {
+		Some((_, parameters, end)) => parse_date_time(end, parameters).map(|(end, _)| end),
+		None => get("DURATION")
+			.and_then(|(_, _, duration)| parse_duration(duration))
+			.map(|minutes| start + Duration::minutes(minutes as i64)),
+	};
+
+	let date = Date::from_naive(start.date());
+	let description = format!("- {}", summary.split_whitespace().collect::<Vec<&str>>().join(" "));
+
+	// weekly events on a single day repeat forever in the general list
+	let weekday = get("RRULE").and_then(|(_, _, rule)| {
+		if !rule.contains("FREQ=WEEKLY") || rule.contains("UNTIL") || rule.contains("COUNT") {
+			return None;
+		}
+
+		let days = rule.split(';')
+			.find_map(|part| part.strip_prefix("BYDAY="))
+			.unwrap_or("");
+
+		if days.contains(',') {
+			return None;
+		}
+
+		WEEKDAYS.iter()
+			.find(|(_, name)| *name == days)
+			.map(|(weekday, _)| *weekday)
+			.or_else(|| Some(WEEKDAYS[start.date().weekday().num_days_from_monday() as usize].0))
+	});
+
+	let time = if all_day {
+		Time::all_day(weekday)
+	} else {
+		let end = end.unwrap_or(start + Duration::hours(1));
+		let start_minutes = start.time().signed_duration_since(chrono::NaiveTime::from_hms(0, 0, 0)).num_minutes();
+		let length = (end - start).num_minutes().clamp(1, 24 * 60);
+		let end_minutes = start_minutes + length;
+
+		Time {
+			day: weekday,
+			start_hour: (start_minutes / 60) as u8,
+			start_minute: (start_minutes % 60) as u8,
+			end_hour: (end_minutes / 60) as u8,
+			end_minute: (end_minutes % 60) as u8,
+		}
+	};
+
+	let date = if weekday.is_some() {
+		None
+	} else {
+		Some(date)
+	};
+
+	Some((date, Item::from_description(description, Some(time))))
+}
+
+fn todo_to_item(properties: &[Property]) -> Option<(Option<Date>, Item)> {
+	let get = |name: &str| properties.iter().find(|(key, _, _)| key == name);
+
+	let summary = get("SUMMARY").map_or("", |(_, _, value)| value.as_str());
+	let date = get("DUE")
+		.or_else(|| get("DTSTART"))
+		.and_then(|(_, parameters, value)| parse_date_time(value, parameters))
+		.map(|(due, _)| Date::from_naive(due.date()));
+
+	let checkbox = if get("STATUS").is_some_and(|(_, _, status)| status == "COMPLETED") {
+		"[x] "
+	} else {
+		""
+	};
+
+	let mut description = format!("- {}{}", checkbox, summary.split_whitespace().collect::<Vec<&str>>().join(" "));
+
+	// our tags live in the description, so carry over any the client added
+	if let Some((_, _, categories)) = get("CATEGORIES") {
+		for tag in categories.split(',').map(|tag| tag.trim().replace(' ', "-")).filter(|tag| !tag.is_empty()) {
+			if !description.contains(&format!("#{}", tag)) {
+				description += &format!(" #{}", tag);
+			}
+		}
+	}
+
+	Some((date, Item::from_description(description, None)))
+}
+
+// returns the local date and time, and whether it was a date without a time
+fn parse_date_time(value: &str, parameters: &str) -> Option<(NaiveDateTime, bool)> {
+	if parameters.contains("VALUE=DATE") && !parameters.contains("VALUE=DATE-TIME") || value.len() == 8 {
+		return NaiveDate::parse_from_str(value, "%Y%m%d").ok().map(|date| (date.and_hms(0, 0, 0), true));
+	}
+
+	// times in UTC get converted to our time zone, times with a TZID are assumed to already be in it
+	match value.strip_suffix('Z') {
+		Some(value) => {
+			let utc = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+			Some((Utc.from_utc_datetime(&utc).with_timezone(&Local).naive_local(), false))
+		},
+		None => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok().map(|time| (time, false)),
+	}
+}
+
+fn get_event_times(date: &Date, time: &Time) -> Vec<String> {
+	if time.is_all_day() {
+		return vec![
+			format!("DTSTART;VALUE=DATE:{}", format_date(date)),
+			format!("DTEND;VALUE=DATE:{}", format_date(&date.add_days(1))),
+		];
+	}
+
+	// end times past midnight belong to the next day
+	let end_date = date.add_days(time.end_hour as i64 / 24);
+	vec![
+		format!("DTSTART:{}T{:02}{:02}00", format_date(date), time.start_hour, time.start_minute),
+		format!("DTEND:{}T{:02}{:02}00", format_date(&end_date), time.end_hour % 24, time.end_minute),
+	]
+}
+
+fn get_weekday_in_week(today: Date, weekday: Weekday) -> Date {
+	let today_index = today.to_naive().map_or(0, |date| date.weekday().num_days_from_monday() as i64);
+	let index = WEEKDAYS.iter().position(|(day, _)| *day == weekday).unwrap() as i64;
+	today.add_days(index - today_index)
+}
+
+fn get_uid(date: &Option<Date>, description: &str) -> String {
+	let key = format!("{}/{}", date.as_ref().map_or(String::new(), format_date), description);
+	format!("{:016x}@bansheelong", fnv1a(key.as_bytes()))
+}
+
+// 64-bit FNV-1a. calendar apps hold on to our uids and tags, so unlike DefaultHasher this has to give the same answer on
+// every rust release
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+	bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn to_content_lines(lines: &[String]) -> String {
+	// content lines end in CRLF and are folded at 75 octets
+	lines.iter()
+		.map(|line| fold(line))
+		.collect::<Vec<String>>()
+		.join("\r\n") + "\r\n"
+}
+
+fn format_date(date: &Date) -> String {
+	format!("{:04}{:02}{:02}", 2000 + date.year as u32, date.month, date.day)
+}
+
+fn escape(text: &str) -> String {
+	text.replace('\\', "\\\\")
+		.replace(';', "\\;")
+		.replace(',', "\\,")
+		.replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+	let mut result = String::new();
+	let mut characters = text.chars();
+	while let Some(character) = characters.next() {
+		if character != '\\' {
+			result.push(character);
+			continue;
+		}
+
+		match characters.next() {
+			Some('n') | Some('N') => result.push(' '),
+			Some(character) => result.push(character),
+			None => {},
+		}
+	}
+
+	result
+}
+
+fn fold(line: &str) -> String {
+	let mut folded = String::new();
+	let mut length = 0;
+	for character in line.chars() {
+		if length + character.len_utf8() > 75 {
+			folded += "\r\n ";
+			length = 1;
+		}
+
+		folded.push(character);
+		length += character.len_utf8();
+	}
+
+	folded
+}
@@ -0,0 +1,388 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{ Error, Ingredient, Recipe, RecipeStep };
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RecipeFormat {
+	JsonLd,
+	Mealie,
+	Paprika,
+}
+
+// figures out what kind of file we were handed and converts every recipe in it
+pub fn parse_recipes(contents: &str) -> Result<Vec<Recipe>, Error> {
+	lazy_static! {
+		// group 1: contents of a JSON-LD script tag
+		static ref SCRIPT_REGEX: Regex = Regex::new(
+			r#"(?is)<script[^>]*type\s*=\s*["']application/ld\+json["'][^>]*>(.*?)</script>"#
+		).unwrap();
+	}
+
+	let trimmed = contents.trim_start();
+	if !trimmed.starts_with('{') && !trimmed.starts_with('[') { // assume we got a saved HTML page
+		// pages carry all sorts of JSON-LD, so a broken block only matters if no other block had a recipe in it
+		let mut recipes = Vec::new();
+		let mut errors = Vec::new();
+		for captures in SCRIPT_REGEX.captures_iter(contents) {
+			match serde_json::from_str::<Value>(captures.get(1).unwrap().as_str().trim()) {
+				Ok(value) => recipes.append(&mut parse_value(&value, RecipeFormat::JsonLd)),
+				Err(error) => errors.push(format!("could not parse JSON-LD block: {:?}", error)),
+			}
+		}
+
+		if recipes.is_empty() {
+			let mut message = String::from("Could not find a schema.org Recipe in the HTML file");
+			if !errors.is_empty() {
+				message = format!("{} ({})", message, errors.join(", "));
+			}
+
+			return Err(Error {
+				message,
+				..Error::default()
+			});
+		}
+
+		return Ok(recipes);
+	}
+
+	let value = match serde_json::from_str::<Value>(trimmed) {
+		Ok(value) => value,
+		Err(error) => return Err(Error {
+			message: format!("Could not deserialize JSON: {:?}", error),
+			..Error::default()
+		}),
+	};
+
+	let recipes = match detect_format(&value) {
+		Some(format) => parse_value(&value, format),
+		None => Vec::new(),
+	};
+
+	if recipes.is_empty() {
+		return Err(Error {
+			message: String::from("Could not find any recipes in the JSON file"),
+			..Error::default()
+		});
+	}
+
+	Ok(recipes)
+}
+
+pub fn detect_format(value: &Value) -> Option<RecipeFormat> {
+	match value {
+		Value::Array(array) => array.iter().find_map(detect_format),
+		Value::Object(object) => {
+			if object.contains_key("@context") || object.contains_key("@type") || object.contains_key("@graph") {
+				Some(RecipeFormat::JsonLd)
+			} else if object.contains_key("recipeIngredient") || object.contains_key("recipeInstructions") {
+				Some(RecipeFormat::Mealie)
+			} else if object.contains_key("directions") || matches!(object.get("ingredients"), Some(Value::String(_))) {
+				Some(RecipeFormat::Paprika)
+			} else {
+				None
+			}
+		},
+		_ => None,
+	}
+}
+
+fn parse_value(value: &Value, format: RecipeFormat) -> Vec<Recipe> {
+	match value {
+		Value::Array(array) => array.iter()
+			.flat_map(|value| parse_value(value, format))
+			.collect(),
+		Value::Object(object) => {
+			match format {
+				RecipeFormat::JsonLd => {
+					if let Some(graph) = object.get("@graph") {
+						return parse_value(graph, format);
+					}
+
+					if is_schema_recipe(value) {
+						parse_json_ld(value).into_iter().collect()
+					} else {
+						Vec::new()
+					}
+				},
+				RecipeFormat::Mealie => parse_mealie(value).into_iter().collect(),
+				RecipeFormat::Paprika => parse_paprika(value).into_iter().collect(),
+			}
+		},
+		_ => Vec::new(),
+	}
+}
+
+fn is_schema_recipe(value: &Value) -> bool {
+	match value.get("@type") {
+		Some(Value::String(kind)) => kind == "Recipe",
+		Some(Value::Array(kinds)) => kinds.iter().any(|kind| kind.as_str() == Some("Recipe")),
+		_ => false,
+	}
+}
+
+fn parse_json_ld(value: &Value) -> Option<Recipe> {
+	let name = clean_text(value.get("name")?.as_str()?);
+
+	let ingredients = match value.get("recipeIngredient").or_else(|| value.get("ingredients")) {
+		Some(Value::Array(array)) => array.iter()
+			.filter_map(Value::as_str)
+			.filter_map(parse_ingredient)
+			.collect(),
+		Some(Value::String(string)) => string.lines().filter_map(parse_ingredient).collect(),
+		_ => Vec::new(),
+	};
+
+	let mut cooking_steps = Vec::new();
+	if let Some(instructions) = value.get("recipeInstructions") {
+		collect_instructions(instructions, &mut cooking_steps);
+	}
+
+	let minutes = value.get("totalTime")
+		.or_else(|| value.get("cookTime"))
+		.and_then(Value::as_str)
+		.and_then(parse_duration);
+
+	Some(Recipe {
+		cooking_steps,
+		image_url: value.get("image").and_then(get_image_url),
+		ingredients,
+		minutes,
+		name,
+		preparation_steps: Vec::new(),
+	})
+}
+
+fn parse_mealie(value: &Value) -> Option<Recipe> {
+	let name = clean_text(value.get("name")?.as_str()?);
+
+	let ingredients = match value.get("recipeIngredient") {
+		Some(Value::Array(array)) => array.iter()
+			.filter_map(|ingredient| {
+				match ingredient {
+					Value::String(string) => parse_ingredient(string),
+					Value::Object(_) => parse_mealie_ingredient(ingredient),
+					_ => None,
+				}
+			})
+			.collect(),
+		_ => Vec::new(),
+	};
+
+	let mut cooking_steps = Vec::new();
+	if let Some(instructions) = value.get("recipeInstructions") {
+		collect_instructions(instructions, &mut cooking_steps);
+	}
+
+	let minutes = value.get("totalTime")
+		.or_else(|| value.get("performTime"))
+		.and_then(Value::as_str)
+		.and_then(parse_duration);
+
+	Some(Recipe {
+		cooking_steps,
+		image_url: value.get("image").and_then(get_image_url),
+		ingredients,
+		minutes,
+		name,
+		preparation_steps: Vec::new(),
+	})
+}
+
+fn parse_mealie_ingredient(value: &Value) -> Option<Ingredient> {
+	let get_name = |key: &str| {
+		match value.get(key) {
+			Some(Value::String(string)) => Some(string.clone()),
+			Some(Value::Object(object)) => object.get("name").and_then(Value::as_str).map(str::to_string),
+			_ => None,
+		}
+	};
+
+	// structured ingredients have a food, older exports only fill out the note
+	let food = match get_name("food") {
+		Some(food) => food,
+		None => {
+			let text = value.get("note")
+				.or_else(|| value.get("display"))
+				.or_else(|| value.get("originalText"))
+				.and_then(Value::as_str)?;
+			return parse_ingredient(text);
+		},
+	};
+
+	let quantity = match value.get("quantity").and_then(Value::as_f64) {
+		Some(quantity) if quantity > 0.0 => Some(format!("{}", quantity)),
+		_ => None,
+	};
+
+	let quantity = match (quantity, get_name("unit")) {
+		(Some(quantity), Some(unit)) => Some(format!("{} {}", quantity, unit)),
+		(Some(quantity), None) => Some(quantity),
+		(None, Some(unit)) => Some(unit),
+		(None, None) => None,
+	};
+
+	Some(Ingredient::new(clean_text(&food), quantity))
+}
+
+fn parse_paprika(value: &Value) -> Option<Recipe> {
+	let name = clean_text(value.get("name")?.as_str()?);
+
+	let ingredients = value.get("ingredients")
+		.and_then(Value::as_str)
+		.map(|string| string.lines().filter_map(parse_ingredient).collect())
+		.unwrap_or_default();
+
+	let cooking_steps = value.get("directions")
+		.and_then(Value::as_str)
+		.map(|string| {
+			string.lines()
+				.map(clean_text)
+				.filter(|line| !line.is_empty())
+				.map(|line| RecipeStep {
+					extra_information: None,
+					name: line,
+				})
+				.collect()
+		})
+		.unwrap_or_default();
+
+	let minutes = value.get("total_time")
+		.or_else(|| value.get("cook_time"))
+		.and_then(Value::as_str)
+		.and_then(parse_duration);
+
+	Some(Recipe {
+		cooking_steps,
+		image_url: value.get("image_url").and_then(Value::as_str).map(str::to_string),
+		ingredients,
+		minutes,
+		name,
+		preparation_steps: Vec::new(),
+	})
+}
+
+// instructions can be a string, a list of strings, HowToSteps, or HowToSections full of HowToSteps
+fn collect_instructions(value: &Value, steps: &mut Vec<RecipeStep>) {
+	match value {
+		Value::String(string) => {
+			for line in string.lines().map(clean_text).filter(|line| !line.is_empty()) {
+				steps.push(RecipeStep {
+					extra_information: None,
+					name: line,
+				});
+			}
+		},
+		Value::Array(array) => {
+			for value in array {
+				collect_instructions(value, steps);
+			}
+		},
+		Value::Object(object) => {
+			if let Some(elements) = object.get("itemListElement") {
+				collect_instructions(elements, steps);
+			} else if let Some(text) = object.get("text").or_else(|| object.get("name")).and_then(Value::as_str) {
+				collect_instructions(&Value::String(text.to_string()), steps);
+			}
+		},
+		_ => {},
+	}
+}
+
+fn get_image_url(value: &Value) -> Option<String> {
+	match value {
+		Value::String(url) => Some(url.clone()),
+		Value::Array(array) => array.iter().find_map(get_image_url),
+		Value::Object(object) => object.get("url").and_then(Value::as_str).map(str::to_string),
+		_ => None,
+	}
+}
+
+// splits "1 1/2 cups flour" into the quantity and the ingredient name
+pub fn parse_ingredient(line: &str) -> Option<Ingredient> {
+	lazy_static! {
+		// group 1: amount, including ranges and fractions
+		// group 2: unit (optional)
+		// group 3: ingredient name
+		static ref INGREDIENT_REGEX: Regex = Regex::new(
+			r"(?i)^((?:\d+(?:[./]\d+)?|[¼½¾⅓⅔⅛])(?:\s*(?:-|to)?\s*(?:\d+(?:[./]\d+)?|[¼½¾⅓⅔⅛]))*)\s*((?:cups?|tbsps?|tablespoons?|tsps?|teaspoons?|oz|ounces?|lbs?|pounds?|grams?|g|kg|ml|liters?|l|cloves?|cans?|pinch(?:es)?|slices?)\.?)?\s+(.+)$"
+		).unwrap();
+	}
+
+	let line = clean_text(line);
+	let line = line.trim_start_matches(['-', '*', '•']).trim();
+	if line.is_empty() {
+		return None;
+	}
+
+	match INGREDIENT_REGEX.captures(line) {
+		Some(captures) => {
+			let amount = captures.get(1).unwrap().as_str().trim();
+			let quantity = match captures.get(2) {
+				Some(unit) => format!("{} {}", amount, unit.as_str()),
+				None => String::from(amount),
+			};
+
+			Some(Ingredient::new(String::from(captures.get(3).unwrap().as_str().trim()), Some(quantity)))
+		},
+		None => Some(Ingredient::new(String::from(line), None)),
+	}
+}
+
+// understands ISO 8601 durations like "PT1H30M" and human ones like "1 hr 30 mins"
+pub fn parse_duration(duration: &str) -> Option<u32> {
+	lazy_static! {
+		// group 1: days, group 2: hours, group 3: minutes
+		static ref ISO_REGEX: Regex = Regex::new(
+			r"(?i)^P(?:(\d+)D)?(?:T(?:(\d+)H)?(?:(\d+)M)?(?:\d+(?:\.\d+)?S)?)?$"
+		).unwrap();
+
+		static ref HOURS_REGEX: Regex = Regex::new(r"(?i)(\d+)\s*(?:h|hr|hrs|hour|hours)\b").unwrap();
+		static ref MINUTES_REGEX: Regex = Regex::new(r"(?i)(\d+)\s*(?:m|min|mins|minute|minutes)\b").unwrap();
+	}
+
+	let duration = duration.trim();
+
+	// numbers too big for a u32 are as good as no duration at all
+	let get_number = |capture: Option<regex::Match>| {
+		capture.map_or(Some(0), |capture| capture.as_str().parse::<u32>().ok())
+	};
+
+	let to_minutes = |days: Option<u32>, hours: Option<u32>, minutes: Option<u32>| {
+		days?.checked_mul(24)?
+			.checked_add(hours?)?
+			.checked_mul(60)?
+			.checked_add(minutes?)
+	};
+
+	let minutes = if let Some(captures) = ISO_REGEX.captures(duration) {
+		to_minutes(get_number(captures.get(1)), get_number(captures.get(2)), get_number(captures.get(3)))
+	} else {
+		let hours = HOURS_REGEX.captures(duration).map_or(Some(0), |captures| get_number(captures.get(1)));
+		let minutes = MINUTES_REGEX.captures(duration).map_or(Some(0), |captures| get_number(captures.get(1)));
+
+		if hours == Some(0) && minutes == Some(0) {
+			duration.parse::<u32>().ok() // paprika sometimes just stores a number of minutes
+		} else {
+			to_minutes(Some(0), hours, minutes)
+		}
+	};
+
+	minutes.filter(|minutes| *minutes != 0)
+}
+
+// websites love to leave HTML entities and stray whitespace in their JSON-LD
+fn clean_text(text: &str) -> String {
+	text.replace("&amp;", "&")
+		.replace("&quot;", "\"")
+		.replace("&#39;", "'")
+		.replace("&#039;", "'")
+		.replace("&apos;", "'")
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&nbsp;", " ")
+		.split_whitespace()
+		.collect::<Vec<&str>>()
+		.join(" ")
+}
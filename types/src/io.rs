@@ -2,8 +2,10 @@ use lazy_static::lazy_static;
 use regex::Regex;
 
 use crate::{
+	CONFLICT_NOTE,
 	Date,
 	Day,
+	Diagnostic,
 	Dirty,
 	Error,
 	ErrorTag,
@@ -20,6 +22,7 @@ use crate::{
 	TodosDatabase,
 	Weekday,
 	WriteDatabase,
+	get_todos_pm_before_hour,
 	read_database,
 	write_database,
 };
@@ -68,6 +71,15 @@ impl IO {
 		Ok(&self.meals_database)
 	}
 
+	pub fn archive_planned_meals(&mut self, today: Date) -> usize {
+		let count = self.meals_database.archive_planned_meals(today);
+		if count > 0 {
+			self.dirty = Dirty::Write;
+		}
+
+		count
+	}
+
 	pub fn remove_planned_meal_log(&self, date: Date) -> PlannedMealsRemoveLog {
 		let mut log = self.planned_meals_remove_log.clone();
 		log.push(date);
@@ -89,6 +101,26 @@ impl IO {
 		Ok(&self.todos_database)
 	}
 
+	pub fn remove_from_todos_database(&mut self, date: Option<Date>, description: &str) -> Result<&TodosDatabase, Error> {
+		let day = self.todos_database.mapping.get_mut(&date);
+		match day.as_ref().and_then(|day| day.items.iter().position(|item| item.description == description)) {
+			Some(index) => {
+				let day = day.unwrap();
+				day.items.remove(index);
+				if day.items.is_empty() {
+					self.todos_database.mapping.remove(&date);
+				}
+
+				self.dirty = Dirty::Write;
+				Ok(&self.todos_database)
+			},
+			None => Err(Error {
+				message: format!("Could not find todo '{}'", description),
+				..Error::default()
+			}),
+		}
+	}
+
 	pub async fn write_database(&mut self) -> Result<(), Error> {
 		match write_database(
 			WriteDatabase::Full {
@@ -99,6 +131,8 @@ impl IO {
 		).await {
 			Ok(_) => {
 				self.planned_meals_write_log.clear();
+				self.todos_complete_log.clear();
+				self.todos_tracking_log.clear();
 				self.todos_write_log.clear();
 				self.dirty = Dirty::None;
 				Ok(())
@@ -121,13 +155,41 @@ impl IO {
 		Ok(())
 	}
 
-	pub fn parse_from_human_readable(&mut self, todo_list: String, recipe_list: String) -> Result<(), Error> {
+	// adds a child under the last item of the day, depth levels down its chain of last children
+	fn add_child_to_todos_database(&mut self, item: Item, date: Option<Date>, depth: usize) {
+		let mut parent = match self.todos_database.mapping.get_mut(&date).and_then(|day| day.items.last_mut()) {
+			Some(parent) => parent,
+			None => return,
+		};
+
+		for _ in 0..depth {
+			if parent.children.is_empty() {
+				break;
+			}
+
+			parent = parent.children.last_mut().unwrap();
+		}
+
+		parent.children.push(item);
+
+		// keep the copy of the top level item in the write log up to date
+		let top = self.todos_database.mapping[&date].items.last().unwrap().clone();
+		if let Some((logged, _)) = self.todos_write_log.last_mut() {
+			*logged = top;
+		}
+	}
+
+	// parses the todo and recipe lists, returning everything we could not understand. lines with problems are
+	// skipped where possible so one typo does not take down the whole file
+	pub fn parse_from_human_readable(&mut self, todo_list: String, recipe_list: String) -> Result<Vec<Diagnostic>, Error> {
 		self.meals_database = MealsDatabase::default();
 		self.todos_database = TodosDatabase::default();
+
+		let mut diagnostics = Vec::new();
 	
 		// read the todos
 		{
-			let string = match std::fs::read_to_string(todo_list) {
+			let string = match std::fs::read_to_string(&todo_list) {
 				Ok(string) => string,
 				Err(error) => return Err(Error {
 					message: format!("{:?}", error),
@@ -139,49 +201,98 @@ impl IO {
 				// group 1: month
 				// group 2: day
 				// group 3: year
-				static ref DATE_REGEX: Regex = Regex::new(r"([0-9]+)/([0-9]+)/([0-9]+)").unwrap();
+				static ref DATE_REGEX: Regex = Regex::new(r"^([0-9]+)/([0-9]+)/([0-9]+)").unwrap();
 			}
 			
 			let lines: Vec<String> = string.split("\n").map(str::to_string).collect();
 			let mut date: Option<Date> = None;
-			for mut line in lines {
-				if let Some(captures) = DATE_REGEX.captures(&line) {
-					let month = String::from(captures.get(1).unwrap().as_str());
-					let day = String::from(captures.get(2).unwrap().as_str());
-					let year = String::from(captures.get(3).unwrap().as_str());
-
-					date = Some(Date {
-						day: day.parse().unwrap(),
-						month: month.parse().unwrap(),
-						year: year.parse().unwrap(),
-					});
+			let mut indentations: Vec<usize> = Vec::new(); // indentation of each child level under the last item
+			for (index, mut line) in lines.into_iter().enumerate() {
+				let diagnostic = |column: usize, message: String| Diagnostic {
+					column,
+					file: todo_list.clone(),
+					line: index + 1,
+					message,
+				};
+
+				// conflict notes from the last sync are for the person editing the file, not todos
+				if line.starts_with(CONFLICT_NOTE) {
+					diagnostics.push(diagnostic(1, String::from("unresolved sync conflict, keep the version you want and delete this line")));
+				} else if let Some(captures) = DATE_REGEX.captures(&line) {
+					let mut parts = [0; 3];
+					let mut valid = true;
+					for (part, group) in parts.iter_mut().zip(1..=3) {
+						let capture = captures.get(group).unwrap();
+						match capture.as_str().parse::<u8>() {
+							Ok(number) => *part = number,
+							Err(_) => {
+								diagnostics.push(diagnostic(
+									get_column(&line, capture.start()),
+									format!("date component '{}' is out of range", capture.as_str())
+								));
+								valid = false;
+								break;
+							},
+						}
+					}
+
+					if valid {
+						date = Some(Date {
+							day: parts[1],
+							month: parts[0],
+							year: parts[2],
+						});
+					}
+
+					indentations.clear();
 				} else {
-					let time = get_time_from_line(line.clone());
+					let time = match get_time_from_line(line.clone()) {
+						Ok(time) => time,
+						Err(error) => {
+							let lowercase = line.to_lowercase();
+							let start = TIME_REGEX.find(&lowercase).map_or(0, |m| m.start());
+							diagnostics.push(diagnostic(get_column(&lowercase, start), error.to_string()));
+							None
+						},
+					};
 
-					let time = if let Err(error) = time {
-						eprintln!("Could not parse time for item: {:?}", error);
-						None
+					// indented lines belong to the item above them, unless a blank line separates them
+					let indentation = get_indentation(&line);
+					let has_parent = self.todos_database.mapping.get(&date)
+						.and_then(|day| day.items.last())
+						.map_or(false, |item| !item.description.is_empty());
+
+					let depth = if indentation > 0 && has_parent && !line.trim().is_empty() {
+						while indentations.last().map_or(false, |last| *last >= indentation) {
+							indentations.pop();
+						}
+
+						indentations.push(indentation);
+						line = line.trim_start().to_string();
+						Some(indentations.len() - 1)
 					} else {
-						time.unwrap()
+						indentations.clear();
+						None
 					};
 
-					if line.len() > 0 && line.chars().nth(0).unwrap() == '@' {
+					if line.starts_with('@') {
 						line = line.split(" ").skip(1).collect::<Vec<&str>>().join(" ");
 					}
 
-					let item = Item {
-						description: line,
-						time,
-					};
+					let item = Item::from_description(line, time);
 
-					self.add_to_todos_database(item, date)?;
+					if let Some(depth) = depth {
+						self.add_child_to_todos_database(item, date, depth);
+					} else {
+						self.add_to_todos_database(item, date)?;
+					}
 				}
 			}
 		}
 
 		// read the recipes
 		{
-			let string = match std::fs::read_to_string(recipe_list) {
+			let string = match std::fs::read_to_string(&recipe_list) {
 				Ok(string) => string,
 				Err(error) => return Err(Error {
 					message: format!("{:?}", error),
@@ -189,23 +300,6 @@ impl IO {
 				}),
 			};
 
-			lazy_static! {
-				// group 1: recipe name
-
-				// group 2 & 3 are optional and dependent on each other
-				// group 2: url of picture
-				// group 3: minutes it takes to complete meal
-				static ref NAME_REGEX: Regex = Regex::new(
-					r"^([a-zA-Z\s\-0-9,.()]+)(?:\s+?\[([a-zA-Z0-9\-._~:/?#\[\]@!$&'()*+,;=]+),\s*?([0-9]+)\])?:$"
-				).unwrap();
-
-				// group 1: markup character delineating ingredient/steps/etc
-				// group 2: text description of ingredient/step/etc
-				static ref INFO_REGEX: Regex = Regex::new(
-					r"([$#-]) ([a-zA-Z\s\-0-9,.()]+)(?:\s+?\[([a-zA-Z0-9\-._~:/?#\[\]@!$&'()*+,;=\s]+)\])?$"
-				).unwrap();
-			}
-
 			let lines: Vec<String> = string.split("\n").map(str::to_string).collect();
 
 			let mut name = None;
@@ -217,8 +311,17 @@ impl IO {
 			let mut minutes = None;
 			let mut preparation_steps = Vec::new();
 
-			for line in lines {
-				if let Some(captures) = NAME_REGEX.captures(&line) {
+			for (index, line) in lines.into_iter().enumerate() {
+				let diagnostic = |column: usize, message: &str| Diagnostic {
+					column,
+					file: recipe_list.clone(),
+					line: index + 1,
+					message: String::from(message),
+				};
+
+				if line.starts_with(CONFLICT_NOTE) {
+					diagnostics.push(diagnostic(1, "unresolved sync conflict, keep the version you want and delete this line"));
+				} else if let Some(captures) = NAME_REGEX.captures(&line) {
 					if name != None {
 						self.add_recipe(Recipe {
 							cooking_steps: cooking_steps.clone(),
@@ -236,27 +339,37 @@ impl IO {
 					
 					name = Some(String::from(captures.get(1).unwrap().as_str()));
 					
-					image_url = if let Some(capture) = captures.get(2) {
-						Some(capture.as_str().to_string())
-					} else {
-						None
+					// brackets with only a number in them are the minutes
+					let (image_url_capture, minutes_capture) = match (captures.get(2), captures.get(3)) {
+						(Some(capture), None) if capture.as_str().chars().all(|character| character.is_ascii_digit()) => {
+							(None, Some(capture))
+						},
+						captures => captures,
 					};
 
-					minutes = if let Some(capture) = captures.get(3) {
-						match capture.as_str().parse::<u32>() {
+					image_url = image_url_capture.map(|capture| capture.as_str().to_string());
+
+					minutes = match minutes_capture {
+						Some(capture) => match capture.as_str().parse::<u32>() {
 							Ok(minutes) => Some(minutes),
-							Err(_) => None,
-						}
-					} else {
-						None
+							Err(_) => {
+								diagnostics.push(diagnostic(
+									get_column(&line, capture.start()), "recipe minutes are out of range"
+								));
+								None
+							},
+						},
+						None => None,
 					};
 				} else if let Some(captures) = INFO_REGEX.captures(&line) {
+					if name.is_none() {
+						diagnostics.push(diagnostic(1, "ingredient or step does not belong to a recipe"));
+						continue;
+					}
+
 					let first_character = captures.get(1).unwrap().as_str();
 					let rest = String::from(captures.get(2).unwrap().as_str());
-					let extra = match captures.get(3) {
-						Some(m) => Some(String::from(m.as_str())),
-						None => None,
-					};
+					let extra = captures.get(3).map(|m| String::from(m.as_str()));
 
 					match first_character {
 						"-" => { // ingredient markup
@@ -279,6 +392,16 @@ impl IO {
 						},
 						_ => {},
 					}
+				} else if !line.trim().is_empty() {
+					// point at the first character the regexes do not allow, if we can find one
+					let column = line.chars()
+						.position(|character| !(character.is_ascii_alphanumeric() || " \t-,.()$#[]:/".contains(character)))
+						.map_or(1, |position| position + 1);
+
+					diagnostics.push(diagnostic(column, match line.trim_start().chars().next() {
+						Some('-') | Some('#') | Some('$') => "could not understand ingredient or step",
+						_ => "expected a recipe name ending in ':' or a line starting with '-', '#' or '$'",
+					}));
 				}
 			}
 
@@ -294,12 +417,26 @@ impl IO {
 			}
 		}
 
-		Ok(())
+		Ok(diagnostics)
 	}
 }
 
-#[derive(Debug)]
-enum TimeError {
+// tabs count as four spaces
+fn get_indentation(line: &str) -> usize {
+	line.chars()
+		.take_while(|character| character.is_whitespace())
+		.map(|character| if character == '\t' { 4 } else { 1 })
+		.sum()
+}
+
+// converts a byte offset into a 1-based character column
+fn get_column(line: &str, byte_offset: usize) -> usize {
+	line.get(..byte_offset).map_or(0, |prefix| prefix.chars().count()) + 1
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TimeError {
+	BadDuration,
 	BadEndHours,
 	BadStartHours,
 	BadEndMinutes,
@@ -310,169 +447,257 @@ enum TimeError {
 	NeededEndTime,
 }
 
-fn get_time_from_line(line: String) -> Result<Option<Time>, TimeError> {
-	lazy_static! {
-		// group 1: day (used for recurring events)
-		// group 2: start hours
-		// group 3: start minutes (optional)
-		// group 4: start time am/pm (optional, inferred if not specified)
-		// group 5: either a - for specifying a time range, or a + for a calculated time range
-		// group 6: end hours (optional, required for time range)
-		// group 7: end minutes (optional)
-		// group 8: end time am/pm (optional, only for time range)
-		static ref TIME_REGEX: Regex = Regex::new(
-			r"(m|t|w|th|f|s|su)?(\d{1,2})(:\d{2})?(am|pm)?(-|\+)(\d{0,2})(:\d{2})?(am|pm)?"
-		).unwrap();
+impl std::fmt::Display for TimeError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.write_str(match self {
+			TimeError::BadDuration => "could not read duration, expected something like '+1h30m' or '+1:30'",
+			TimeError::BadEndHours => "could not read end hours",
+			TimeError::BadStartHours => "could not read start hours",
+			TimeError::BadEndMinutes => "could not read end minutes",
+			TimeError::BadStartMinutes => "could not read start minutes",
+			TimeError::InvalidEndAmPm => "am/pm is not allowed after a '+' duration",
+			TimeError::InvalidTimeRange => "time range is empty or longer than a day",
+			TimeError::NeededEndHours => "time range needs end hours after the '-'",
+			TimeError::NeededEndTime => "duration needs hours or minutes after the '+'",
+		})
 	}
-	
-	if let Some(captures) = TIME_REGEX.captures(&line.as_str().to_lowercase()) {
-		// get operator
-		let operator = String::from(captures.get(5).unwrap().as_str());
+}
 
-		if operator == "-" { // time range error checking
-			if captures.get(6).unwrap().as_str() == "" {
-				return Err(TimeError::NeededEndHours);
-			}
-		} else if captures.get(8).is_some() { // calculated time range error checking
-			return Err(TimeError::InvalidEndAmPm);
-		} else if captures.get(6).unwrap().as_str() == "" && captures.get(7).is_none() {
-			return Err(TimeError::NeededEndTime);
-		}
-		
-		// decode day
-		let day = if let None = captures.get(1) {
-			None
-		} else {
-			Some(match captures.get(1).unwrap().as_str() {
-				"m" => Weekday::Monday,
-				"t" => Weekday::Tuesday,
-				"w" => Weekday::Wednesday,
-				"th" => Weekday::Thursday,
-				"f" => Weekday::Friday,
-				"s" => Weekday::Saturday,
-				"su" => Weekday::Sunday,
-				&_ => Weekday::Monday,
-			})
-		};
-		
-		// decode start hours
-		let mut start_hour = if let None = captures.get(2) {
-			0
-		} else {
-			match String::from(captures.get(2).unwrap().as_str()).parse::<u8>() {
-			  Ok(number) => {
-					number
-				},
-				Err(_) => {
-					return Err(TimeError::BadStartHours);
-				}
-			}
-		};
+lazy_static! {
+	// group 1: recipe name
+
+	// group 2 & 3 are both optional
+	// group 2: url of picture, or the minutes if there is nothing else in the brackets
+	// group 3: minutes it takes to complete meal
+	pub(crate) static ref NAME_REGEX: Regex = Regex::new(
+		r"^([a-zA-Z\s\-0-9,.()]+)(?:\s+?\[([a-zA-Z0-9\-._~:/?#\[\]@!$&'()*+,;=]+?)(?:,\s*?([0-9]+))?\])?:$"
+	).unwrap();
+
+	// group 1: markup character delineating ingredient/steps/etc
+	// group 2: text description of ingredient/step/etc
+	pub(crate) static ref INFO_REGEX: Regex = Regex::new(
+		r"^\s*([$#-]) ([a-zA-Z\s\-0-9,.()]+)(?:\s+?\[([a-zA-Z0-9\-._~:/?#\[\]@!$&'()*+,;=\s]+)\])?$"
+	).unwrap();
+
+	// group 1: day (used for recurring events)
+	// group 2: all day marker
+	// group 3: start time, either noon/midnight or hours with optional minutes and am/pm
+	// group 4: either a - for specifying a time range, or a + for a calculated time range
+	// group 5: end time for ranges, or a duration like 1h30m, 45m or 1:30 for calculated ranges
+	static ref TIME_REGEX: Regex = Regex::new(
+		r"\b(m|t|w|th|f|s|su)?(?:(all-?day)\b|(noon|midnight|\d{1,2}(?::\d{2})?(?:am|pm)?)(-|\+)(\d+h(?:\d+m(?:in)?)?|\d+m(?:in)?|noon|midnight|\d{0,2}(?::\d{2})?(?:am|pm)?))"
+	).unwrap();
+}
 
-		// decode start minutes
-		let start_minute = if let None = captures.get(3) {
-			0
-		} else {
-			let string = captures.get(3).unwrap().as_str();
-			match String::from(&string[1..string.len()]).parse::<u8>() {
-			  Ok(number) => {
-					number
-				},
-				Err(_) => {
-					return Err(TimeError::BadStartMinutes);
-				}
-			}
-		};
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Clock {
+	Am,
+	Pm,
+	TwentyFour,
+	Guess,
+}
 
-		// decode end hours
-		let mut end_hour = if captures.get(6).unwrap().as_str() == "" {
-			0
-		} else {
-			match String::from(captures.get(6).unwrap().as_str()).parse::<u8>() {
-			  Ok(number) => {
-					number
-				},
-				Err(_) => {
-					return Err(TimeError::BadEndHours);
-				}
-			}
-		};
+#[derive(Clone, Copy, Debug)]
+struct TimeOfDay {
+	clock: Clock,
+	hour: u8,
+	minute: u8,
+}
 
-		// decode end minutes
-		let mut end_minute = if let None = captures.get(7) {
-			0
-		} else {
-			let string = captures.get(7).unwrap().as_str();
-			match String::from(&string[1..string.len()]).parse::<u8>() {
-			  Ok(number) => {
-					number
-				},
-				Err(_) => {
-					eprintln!("{:?}", captures.get(7).unwrap().as_str());
-					return Err(TimeError::BadEndMinutes);
-				}
-			}
+impl TimeOfDay {
+	fn to_minutes(self, clock: Clock) -> u32 {
+		let hour = match clock {
+			Clock::Am if self.hour == 12 => 0,
+			Clock::Pm if self.hour != 12 => self.hour + 12,
+			_ => self.hour,
 		};
 
-		// decode am/pm
-		let start_ampm = if let None = captures.get(4) {
-			if start_hour < 8 || start_hour == 12 {
-				String::from("pm")
-			} else {
-				String::from("am")
-			}
-		} else {
-			String::from(captures.get(4).unwrap().as_str())
+		hour as u32 * 60 + self.minute as u32
+	}
+}
+
+fn parse_time_of_day(string: &str, bad_hours: TimeError, bad_minutes: TimeError) -> Result<TimeOfDay, TimeError> {
+	match string {
+		"noon" => return Ok(TimeOfDay { clock: Clock::TwentyFour, hour: 12, minute: 0 }),
+		"midnight" => return Ok(TimeOfDay { clock: Clock::TwentyFour, hour: 0, minute: 0 }),
+		_ => {},
+	}
+
+	let (string, clock) = if let Some(string) = string.strip_suffix("am") {
+		(string, Clock::Am)
+	} else if let Some(string) = string.strip_suffix("pm") {
+		(string, Clock::Pm)
+	} else {
+		(string, Clock::Guess)
+	};
+
+	let (hours, minutes) = match string.split_once(':') {
+		Some((hours, minutes)) => (hours, Some(minutes)),
+		None => (string, None),
+	};
+
+	let hour = hours.parse::<u8>().map_err(|_| bad_hours)?;
+	let minute = match minutes {
+		Some(minutes) => minutes.parse::<u8>().map_err(|_| bad_minutes)?,
+		None => 0,
+	};
+
+	if minute >= 60 {
+		return Err(bad_minutes);
+	}
+
+	// 13:00 and 07:00 can only be 24 hour times
+	let clock = if clock == Clock::Guess && (hour == 0 || hour > 12 || hours.starts_with('0')) {
+		Clock::TwentyFour
+	} else {
+		clock
+	};
+
+	match clock {
+		Clock::Am | Clock::Pm if hour == 0 || hour > 12 => Err(bad_hours),
+		_ if hour > 24 || (hour == 24 && minute != 0) => Err(bad_hours),
+		_ => Ok(TimeOfDay { clock, hour, minute }),
+	}
+}
+
+// reads durations like 1h30m, 90m, 90min, 2 and 1:30
+fn parse_duration(string: &str) -> Result<u32, TimeError> {
+	if string.ends_with("am") || string.ends_with("pm") {
+		return Err(TimeError::InvalidEndAmPm);
+	} else if string.is_empty() {
+		return Err(TimeError::NeededEndTime);
+	}
+
+	let string = string.strip_suffix("in").unwrap_or(string);
+	if string.ends_with('h') || string.ends_with('m') {
+		let (hours, minutes) = match string.split_once('h') {
+			Some((hours, minutes)) => (hours, minutes),
+			None => ("", string),
 		};
 
-		let end_ampm = if let None = captures.get(8) {
-			if end_hour < 8 || end_hour == 12 {
-				String::from("pm")
-			} else {
-				String::from("am")
-			}
-		} else {
-			String::from(captures.get(8).unwrap().as_str())
+		let hours = if hours.is_empty() { 0 } else { hours.parse::<u32>().map_err(|_| TimeError::BadDuration)? };
+		let minutes = match minutes.strip_suffix('m') {
+			Some(minutes) => minutes.parse::<u32>().map_err(|_| TimeError::BadDuration)?,
+			None if minutes.is_empty() => 0,
+			None => return Err(TimeError::BadDuration),
 		};
 
-		// handle start hours conversions
-		if start_ampm == "pm" && start_hour != 12 {
-			start_hour += 12;
-		}
+		return hours.checked_mul(60).and_then(|hours| hours.checked_add(minutes)).ok_or(TimeError::BadDuration);
+	}
 
-		if start_ampm == "am" && start_hour == 12 {
-			start_hour = 0;
-		}
+	let (hours, minutes) = match string.split_once(':') {
+		Some((hours, minutes)) => (hours, minutes),
+		None => (string, ""),
+	};
 
-		// handle operators
-		if end_ampm == "pm" && end_hour != 12 && operator != "+" {
-			end_hour += 12;
-		}
+	let hours = if hours.is_empty() { 0 } else { hours.parse::<u32>().map_err(|_| TimeError::BadEndHours)? };
+	let minutes = if minutes.is_empty() { 0 } else { minutes.parse::<u32>().map_err(|_| TimeError::BadEndMinutes)? };
+
+	hours.checked_mul(60).and_then(|hours| hours.checked_add(minutes)).ok_or(TimeError::BadDuration)
+}
 
-		if operator == "+" {
-			end_hour = start_hour + end_hour;
-			end_minute = start_minute + end_minute;
+pub(crate) fn get_time_from_line(line: String) -> Result<Option<Time>, TimeError> {
+	parse_time(&line, get_todos_pm_before_hour())
+}
 
-			if end_minute >= 60 {
-				end_hour += 1;
-				end_minute = end_minute % 60;
-			}
+// finds a time in a todo line. hours without am/pm that are below pm_before_hour are assumed to be in the
+// afternoon, so "3-4" means 3pm to 4pm
+pub fn parse_time(line: &str, pm_before_hour: u8) -> Result<Option<Time>, TimeError> {
+	let line = line.to_lowercase();
+	let captures = match TIME_REGEX.captures(&line) {
+		Some(captures) => captures,
+		None => return Ok(None),
+	};
+
+	// decode day
+	let day = captures.get(1).map(|day| match day.as_str() {
+		"m" => Weekday::Monday,
+		"t" => Weekday::Tuesday,
+		"w" => Weekday::Wednesday,
+		"th" => Weekday::Thursday,
+		"f" => Weekday::Friday,
+		"s" => Weekday::Saturday,
+		"su" => Weekday::Sunday,
+		&_ => Weekday::Monday,
+	});
+
+	if captures.get(2).is_some() {
+		return Ok(Some(Time::all_day(day)));
+	}
+
+	let start = parse_time_of_day(captures.get(3).unwrap().as_str(), TimeError::BadStartHours, TimeError::BadStartMinutes)?;
+	let operator = captures.get(4).unwrap().as_str();
+	let end = captures.get(5).unwrap().as_str();
+
+	let guess = |time: &TimeOfDay| {
+		if time.hour == 12 || time.hour < pm_before_hour {
+			Clock::Pm
+		} else {
+			Clock::Am
 		}
+	};
 
-		if start_hour > end_hour {
-			return Err(TimeError::InvalidTimeRange);
-		} else if start_hour == end_hour && start_minute >= end_minute {
-			return Err(TimeError::InvalidTimeRange);
+	let (start_minutes, end_minutes) = if operator == "-" {
+		if end.is_empty() {
+			return Err(TimeError::NeededEndHours);
 		}
 
-		Ok(Some(Time {
-			day,
-			start_hour,
-			start_minute,
-			end_hour,
-			end_minute,
-		}))
+		let end = parse_time_of_day(end, TimeError::BadEndHours, TimeError::BadEndMinutes)?;
+
+		// "9-10pm" means 9pm, but "11-1pm" means 11am
+		let start_minutes = match (start.clock, end.clock) {
+			(Clock::Guess, Clock::Am | Clock::Pm) => {
+				let same = start.to_minutes(end.clock);
+				if same < end.to_minutes(end.clock) {
+					same
+				} else {
+					start.to_minutes(if end.clock == Clock::Am { Clock::Pm } else { Clock::Am })
+				}
+			},
+			(Clock::Guess, _) => start.to_minutes(guess(&start)),
+			(clock, _) => start.to_minutes(clock),
+		};
+
+		// pick whichever half of the day comes next after the start
+		let end_minutes = match end.clock {
+			Clock::Guess => {
+				let am = end.to_minutes(Clock::Am);
+				let pm = end.to_minutes(Clock::Pm);
+				if am > start_minutes {
+					am
+				} else if pm > start_minutes {
+					pm
+				} else {
+					am
+				}
+			},
+			clock => end.to_minutes(clock),
+		};
+
+		// ranges that end before they start cross midnight
+		if end_minutes < start_minutes {
+			(start_minutes, end_minutes + 24 * 60)
+		} else {
+			(start_minutes, end_minutes)
+		}
 	} else {
-		Ok(None)
+		let start_minutes = match start.clock {
+			Clock::Guess => start.to_minutes(guess(&start)),
+			clock => start.to_minutes(clock),
+		};
+
+		(start_minutes, start_minutes.checked_add(parse_duration(end)?).ok_or(TimeError::BadDuration)?)
+	};
+
+	if end_minutes <= start_minutes || end_minutes - start_minutes > 24 * 60 {
+		return Err(TimeError::InvalidTimeRange);
 	}
+
+	Ok(Some(Time {
+		day,
+		start_hour: (start_minutes / 60) as u8,
+		start_minute: (start_minutes % 60) as u8,
+		end_hour: (end_minutes / 60) as u8,
+		end_minute: (end_minutes % 60) as u8,
+	}))
 }
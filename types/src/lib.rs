@@ -1,35 +1,167 @@
 use std::env;
 use bitflags::bitflags;
 
+pub(crate) mod backup;
+pub(crate) mod export;
+pub(crate) mod history;
+pub(crate) mod ical;
+pub(crate) mod import;
 pub(crate) mod io;
+pub(crate) mod markup;
+pub(crate) mod merge;
 pub(crate) mod read_write;
+pub(crate) mod reminders;
+pub(crate) mod rollover;
+pub(crate) mod scheduling;
+pub(crate) mod smart;
+pub(crate) mod snapshot;
+pub(crate) mod storage;
+pub(crate) mod storage_command;
+pub(crate) mod storage_history;
+pub(crate) mod storage_target;
+pub(crate) mod subtasks;
 pub(crate) mod tests;
+pub(crate) mod tracking;
 pub(crate) mod types;
+pub(crate) mod zpool;
 
 pub use types::Date;
 pub use types::Day;
+pub use types::Diagnostic;
 pub use types::Dirty;
 pub use types::Error;
 pub use types::ErrorTag;
 pub use types::IO;
 pub use types::Ingredient;
 pub use types::Item;
+pub use types::MealStatus;
 pub use types::MealsDatabase;
 pub use types::PlannedIngredient;
 pub use types::PlannedMeal;
 pub use types::PlannedMealsRemoveLog;
 pub use types::PlannedMealsWriteLog;
+pub use types::Priority;
 pub use types::Recipe;
 pub use types::RecipeStep;
 pub use types::Resource;
 pub use types::Time;
+pub use types::TodoCompletion;
+pub use types::TodosCompleteLog;
 pub use types::TodosDatabase;
+pub use types::TodosTrackingLog;
+pub use types::TrackingEvent;
+pub use types::TrackingSession;
 pub use types::Weekday;
 pub use types::WriteDatabase;
 
+pub use backup::BackupJob;
+pub use backup::BackupJobState;
+pub use backup::BackupKind;
+
+pub use export::item_to_human_readable;
+pub use export::item_to_human_readable_lines;
+pub use export::recipe_to_human_readable;
+pub use export::recipes_to_human_readable;
+pub use export::time_to_human_readable;
+pub use export::todos_to_human_readable;
+
+pub use ical::fnv1a;
+pub use ical::item_etag;
+pub use ical::item_to_ical;
+pub use ical::item_uid;
+pub use ical::parse_ical;
+pub use ical::parse_ical_item;
+pub use ical::to_ical;
+
+pub use io::TimeError;
+pub use io::parse_time;
+
+pub use import::RecipeFormat;
+pub use import::parse_recipes;
+
+pub use merge::CONFLICT_NOTE;
+pub use merge::MergeResult;
+pub use merge::get_first_sync_base;
+pub use merge::keep_unwritable_recipes;
+pub use merge::merge_human_readable;
+pub use merge::merge_lines;
+
+pub use read_write::import_recipes;
 pub use read_write::read_database;
 pub use read_write::write_database;
 
+pub use reminders::Reminder;
+
+pub use rollover::RolloverMode;
+
+pub use scheduling::FreeBusy;
+pub use scheduling::ScheduledItem;
+
+pub use smart::SmartDrive;
+pub use smart::SmartSelfTest;
+pub use smart::parse_smartctl;
+
+pub use snapshot::Restore;
+pub use snapshot::RestoreRequest;
+pub use snapshot::RestoreState;
+pub use snapshot::SNAPSHOT_DIFF_MAX_CHANGES;
+pub use snapshot::Snapshot;
+pub use snapshot::SnapshotChange;
+pub use snapshot::SnapshotDiff;
+pub use snapshot::SnapshotDiffQuery;
+pub use snapshot::SnapshotFile;
+pub use snapshot::SnapshotSource;
+pub use snapshot::diff_file_lists;
+pub use snapshot::is_safe_relative_path;
+pub use snapshot::parse_btrfs_snapshots;
+pub use snapshot::parse_file_list;
+pub use snapshot::parse_zfs_snapshots;
+
+pub use storage::STORAGE_MAX_PAYLOAD_LENGTH;
+pub use storage::STORAGE_PROTOCOL_MAGIC;
+pub use storage::STORAGE_PROTOCOL_VERSION;
+pub use storage::StorageFrame;
+pub use storage::StorageProtocolError;
+pub use storage::StorageStatus;
+pub use storage::decode_storage_frame;
+pub use storage::encode_storage_command;
+pub use storage::encode_storage_command_response;
+pub use storage::encode_storage_hello;
+pub use storage::encode_storage_restore_request;
+pub use storage::encode_storage_snapshot_diff;
+pub use storage::encode_storage_snapshot_diff_query;
+pub use storage::encode_storage_snapshots;
+pub use storage::encode_storage_snapshots_query;
+pub use storage::encode_storage_history;
+pub use storage::encode_storage_history_query;
+pub use storage::encode_storage_status;
+pub use storage::get_storage_frame_length;
+pub use storage::is_storage_frame;
+
+pub use storage_command::StorageAlert;
+pub use storage_command::StorageCommand;
+pub use storage_command::StorageCommandRequest;
+pub use storage_command::StorageCommandResponse;
+
+pub use storage_history::STORAGE_HISTORY_MAX_SAMPLES;
+pub use storage_history::StorageHistory;
+pub use storage_history::StorageHistoryQuery;
+pub use storage_history::StorageHistoryReply;
+pub use storage_history::StorageSample;
+
+pub use storage_target::StorageTarget;
+pub use storage_target::StorageTargetKind;
+pub use storage_target::StorageUsage;
+pub use storage_target::parse_storage_targets;
+
+pub use tracking::WeeklyTotal;
+
+pub use zpool::ZPool;
+pub use zpool::ZPoolDevice;
+pub use zpool::ZPoolScan;
+pub use zpool::parse_zpool_number;
+pub use zpool::parse_zpool_status;
+
 pub fn get_todos_server_port() -> u16 {
 	match env::var("BANSHEELONG_TODOS_SERVER_PORT") {
 		Ok(port) => port.parse().unwrap(),
@@ -71,6 +203,49 @@ pub fn get_todos_secret() -> String {
 	}
 }
 
+// the calendar feed lives at /calendar/<secret>.ics, an empty secret turns it off
+pub fn get_todos_calendar_secret() -> String {
+	match env::var("BANSHEELONG_TODOS_CALENDAR_SECRET") {
+		Ok(secret) => secret,
+		Err(_) => String::from(""),
+	}
+}
+
+// how many minutes before timed items start to remind us about them, comma separated. "off" turns reminders off
+pub fn get_todos_reminders() -> Vec<u32> {
+	match env::var("BANSHEELONG_TODOS_REMINDERS") {
+		Ok(reminders) => reminders.split(',')
+			.filter_map(|minutes| minutes.trim().parse().ok())
+			.collect(),
+		Err(_) => vec![10],
+	}
+}
+
+// also show reminders as desktop notifications on machines running a notification daemon
+pub fn get_desktop_notifications() -> bool {
+	match env::var("BANSHEELONG_DESKTOP_NOTIFICATIONS") {
+		Ok(enabled) => enabled == "1" || enabled == "true",
+		Err(_) => false,
+	}
+}
+
+// hours without am/pm in the todo list that are below this hour are assumed to be in the afternoon
+pub fn get_todos_pm_before_hour() -> u8 {
+	match env::var("BANSHEELONG_TODOS_PM_BEFORE_HOUR") {
+		Ok(hour) => hour.parse().unwrap_or(8),
+		Err(_) => 8,
+	}
+}
+
+// what happens to unfinished todos on past days: "today", "overdue" or "off"
+pub fn get_todos_rollover_mode() -> RolloverMode {
+	match env::var("BANSHEELONG_TODOS_ROLLOVER") {
+		Ok(mode) if mode == "off" => RolloverMode::Off,
+		Ok(mode) if mode == "overdue" => RolloverMode::Overdue,
+		_ => RolloverMode::Today,
+	}
+}
+
 pub fn get_todos_https_cert() -> String {
 	match env::var("BANSHEELONG_TODOS_HTTPS_CERT") {
 		Ok(cert) => cert,
@@ -99,6 +274,118 @@ pub fn get_storage_host() -> String {
 	}
 }
 
+// where the backup scripts leave their counts and the files that say what they are working on
+pub fn get_storage_state_path() -> String {
+	match env::var("BANSHEELONG_STORAGE_STATE_PATH") {
+		Ok(path) => path,
+		Err(_) => String::from("/home/me/bansheestorage"),
+	}
+}
+
+// where the storage server keeps its samples between restarts
+pub fn get_storage_history_path() -> String {
+	match env::var("BANSHEELONG_STORAGE_HISTORY_PATH") {
+		Ok(path) => path,
+		Err(_) => String::from("/home/me/bansheestorage-history"),
+	}
+}
+
+// which alerts are already firing, so a restart does not send them all over again
+pub fn get_storage_alerts_path() -> String {
+	match env::var("BANSHEELONG_STORAGE_ALERTS_PATH") {
+		Ok(path) => path,
+		Err(_) => String::from("/home/me/bansheestorage-alerts"),
+	}
+}
+
+// an mbox file to append alerts to, like /var/mail/me
+pub fn get_storage_alert_mail_spool() -> Option<String> {
+	match env::var("BANSHEELONG_STORAGE_ALERT_MAIL_SPOOL") {
+		Ok(path) => Some(path),
+		Err(_) => None,
+	}
+}
+
+pub fn get_storage_alert_webhook() -> Option<String> {
+	match env::var("BANSHEELONG_STORAGE_ALERT_WEBHOOK") {
+		Ok(url) => Some(url),
+		Err(_) => None,
+	}
+}
+
+// turns alerts into todos on the todos server
+pub fn get_storage_alert_todos() -> bool {
+	env::var("BANSHEELONG_STORAGE_ALERT_TODOS").is_ok()
+}
+
+// what the storage server runs for each backup job and how many it keeps. without it the backup scripts are left to
+// run themselves
+pub fn get_storage_jobs_config_path() -> Option<String> {
+	match env::var("BANSHEELONG_STORAGE_JOBS_CONFIG") {
+		Ok(path) => Some(path),
+		Err(_) => None,
+	}
+}
+
+pub fn get_storage_jobs_path() -> String {
+	match env::var("BANSHEELONG_STORAGE_JOBS_PATH") {
+		Ok(path) => path,
+		Err(_) => String::from("/home/me/bansheestorage-jobs"),
+	}
+}
+
+// where restores from snapshots are copied to, so nothing in use is overwritten
+pub fn get_storage_staging_path() -> String {
+	match env::var("BANSHEELONG_STORAGE_STAGING_PATH") {
+		Ok(path) => path,
+		Err(_) => String::from("/home/me/bansheestorage-restores"),
+	}
+}
+
+// tokens that can send commands to the storage server on top of the todos secret, comma separated
+pub fn get_storage_tokens() -> Vec<String> {
+	match env::var("BANSHEELONG_STORAGE_TOKENS") {
+		Ok(tokens) => tokens.split(',')
+			.map(|token| token.trim().to_string())
+			.filter(|token| !token.is_empty())
+			.collect(),
+		Err(_) => Vec::new(),
+	}
+}
+
+// what clients send commands to the storage server with
+pub fn get_storage_token() -> String {
+	match env::var("BANSHEELONG_STORAGE_TOKEN") {
+		Ok(token) => token,
+		Err(_) => get_todos_secret(),
+	}
+}
+
+pub fn get_storage_btrfs_path() -> String {
+	match env::var("BANSHEELONG_STORAGE_BTRFS_PATH") {
+		Ok(path) => path,
+		Err(_) => String::from("/bansheebtrfs/"),
+	}
+}
+
+// what the storage server reports the usage of, like `pool:bansheerubber,mount:/,btrfs:/bansheebtrfs/`. the first pool
+// and the first btrfs filesystem are the ones older clients see
+pub fn get_storage_targets() -> Vec<StorageTarget> {
+	match env::var("BANSHEELONG_STORAGE_TARGETS") {
+		Ok(targets) => parse_storage_targets(&targets).unwrap(),
+		Err(_) => vec![
+			StorageTarget {
+				kind: StorageTargetKind::Pool,
+				path: String::from("bansheerubber"),
+			},
+			StorageTarget {
+				kind: StorageTargetKind::Btrfs,
+				path: get_storage_btrfs_path(),
+			},
+		],
+	}
+}
+
 pub fn get_static_path() -> Option<String> {
 	match env::var("BANSHEELONG_TODOS_HTTP_ROOT") {
 		Ok(root) => Some(root),
@@ -106,7 +393,7 @@ pub fn get_static_path() -> Option<String> {
 	}
 }
 
-// how many words we send to clients using the storage server
+// how many words we send to clients that only understand the old text lines from the storage server
 pub const STORAGE_MESSAGE_COUNT: u8 = 8;
 
 bitflags! {
@@ -0,0 +1,115 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::{ Date, Day, Item, Priority, Time };
+
+lazy_static! {
+	// group 1: tag name
+	static ref TAG_REGEX: Regex = Regex::new(r"(?:^|\s)#([a-zA-Z0-9_\-]+)").unwrap();
+
+	// group 1: month
+	// group 2: day
+	// group 3: year
+	static ref DUE_REGEX: Regex = Regex::new(r"\bdue:([0-9]{1,2})/([0-9]{1,2})/([0-9]{1,2})\b").unwrap();
+}
+
+impl Priority {
+	pub fn from_marker(character: char) -> Option<Priority> {
+		match character {
+			'z' => Some(Priority::Low),
+			'-' => Some(Priority::Normal),
+			'%' => Some(Priority::High),
+			'!' => Some(Priority::Urgent),
+			_ => None,
+		}
+	}
+
+	pub fn to_marker(self) -> char {
+		match self {
+			Priority::Low => 'z',
+			Priority::Normal => '-',
+			Priority::High => '%',
+			Priority::Urgent => '!',
+		}
+	}
+}
+
+impl Item {
+	// builds an item and reads its priority, tags and due date out of the description
+	pub fn from_description(description: String, time: Option<Time>) -> Item {
+		let mut item = Item {
+			children: Vec::new(),
+			completed: false,
+			description,
+			due: None,
+			priority: Priority::default(),
+			tags: Vec::new(),
+			time,
+			uid: None,
+		};
+
+		item.parse_markup();
+		item
+	}
+
+	pub fn parse_markup(&mut self) {
+		// the marker only counts if it is followed by a space, so words like "zoo" stay normal priority
+		let mut characters = self.description.chars();
+		self.priority = match (characters.next(), characters.next()) {
+			(Some(character), Some(' ')) => Priority::from_marker(character).unwrap_or_default(),
+			_ => Priority::default(),
+		};
+
+		let text = self.strip_marker();
+		self.completed = text.starts_with("[x]") || text.starts_with("[X]");
+
+		self.tags = TAG_REGEX.captures_iter(&self.description)
+			.map(|captures| captures.get(1).unwrap().as_str().to_lowercase())
+			.collect();
+
+		self.due = DUE_REGEX.captures(&self.description).and_then(|captures| {
+			Some(Date {
+				day: captures.get(2)?.as_str().parse().ok()?,
+				month: captures.get(1)?.as_str().parse().ok()?,
+				year: captures.get(3)?.as_str().parse().ok()?,
+			})
+		});
+	}
+
+	// the description without its priority marker or checkbox, used when those are drawn separately
+	pub fn get_text(&self) -> &str {
+		let text = self.strip_marker();
+		match text.get(..3) {
+			Some("[x]") | Some("[X]") | Some("[ ]") => text[3..].trim_start(),
+			_ => text,
+		}
+	}
+
+	fn strip_marker(&self) -> &str {
+		let mut characters = self.description.chars();
+		match (characters.next(), characters.next()) {
+			(Some(character), Some(' ')) if Priority::from_marker(character).is_some() => &self.description[2..],
+			_ => &self.description,
+		}
+	}
+}
+
+impl Day {
+	// stable sorts items by priority, but only between blank lines so separated groups stay where they are
+	pub fn items_by_priority(&self) -> Vec<&Item> {
+		let mut items = Vec::new();
+		for group in self.items.split_inclusive(|item| item.description.is_empty()) {
+			let (blank, group) = match group.split_last() {
+				Some((last, rest)) if last.description.is_empty() => (Some(last), rest),
+				_ => (None, group),
+			};
+
+			let mut group = group.iter().collect::<Vec<&Item>>();
+			group.sort_by_key(|item| std::cmp::Reverse(item.priority));
+			items.extend(group);
+			items.extend(blank);
+		}
+
+		items
+	}
+}
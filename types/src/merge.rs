@@ -0,0 +1,209 @@
+use std::collections::{ BTreeSet, HashMap };
+
+use crate::{ Date, Day, MealsDatabase, TodosDatabase, item_to_human_readable_lines, recipe_to_human_readable };
+
+// start of the lines we annotate conflicts with. the parser skips them so they never become todos
+pub const CONFLICT_NOTE: &str = "!! conflict";
+
+#[derive(Clone, Debug, Default)]
+pub struct MergeResult {
+	pub conflicts: usize,
+	pub recipe_list: String,
+	pub todo_list: String,
+}
+
+// three-way merges the last synced databases with the text files and the server's copy. produces new
+// contents for the text files, with conflicts annotated next to the lines we kept
+pub fn merge_human_readable(
+	base: (&TodosDatabase, &MealsDatabase),
+	local: (&TodosDatabase, &MealsDatabase),
+	remote: (&TodosDatabase, &MealsDatabase)
+) -> MergeResult {
+	let mut result = MergeResult::default();
+
+	// merge todos day by day so date lines never end up inside of a conflict
+	let dates = base.0.mapping.keys()
+		.chain(local.0.mapping.keys())
+		.chain(remote.0.mapping.keys())
+		.cloned()
+		.collect::<BTreeSet<Option<Date>>>();
+
+	let day_lines = |database: &TodosDatabase, date: &Option<Date>| {
+		match database.mapping.get(date) {
+			Some(day) => day.items.iter().flat_map(item_to_human_readable_lines).collect(),
+			None => Vec::new(),
+		}
+	};
+
+	let mut lines = Vec::new();
+	for date in dates.iter() {
+		let (merged, conflicts) = merge_lines(
+			&day_lines(base.0, date),
+			&day_lines(local.0, date),
+			&day_lines(remote.0, date)
+		);
+
+		result.conflicts += conflicts;
+
+		if merged.is_empty() {
+			continue;
+		}
+
+		if let Some(date) = date {
+			lines.push(format!("{}:", date.to_string()));
+		}
+
+		lines.extend(merged);
+	}
+	result.todo_list = lines.join("\n");
+
+	// recipes the text format cannot hold stay out of the text files, see keep_unwritable_recipes
+	let recipe_lines = |database: &MealsDatabase| -> Vec<String> {
+		database.recipes.iter()
+			.filter_map(|recipe| recipe_to_human_readable(recipe).ok())
+			.collect::<Vec<String>>()
+			.join("\n")
+			.split('\n')
+			.map(str::to_string)
+			.collect()
+	};
+
+	let (merged, conflicts) = merge_lines(&recipe_lines(base.1), &recipe_lines(local.1), &recipe_lines(remote.1));
+	result.conflicts += conflicts;
+	result.recipe_list = merged.join("\n");
+
+	result
+}
+
+// imported recipes can have text the recipe list has no way of writing down. they never make it into the text
+// files, so the copy parsed back out of them gets the server's instead
+pub fn keep_unwritable_recipes(meals: &mut MealsDatabase, remote: &MealsDatabase) {
+	for recipe in remote.recipes.iter().filter(|recipe| recipe_to_human_readable(recipe).is_err()) {
+		if !meals.recipes.contains(recipe) {
+			meals.recipes.push(recipe.clone());
+		}
+	}
+}
+
+// merge base for the first sync, when we have no snapshot yet: whatever both sides already agree on. everything
+// else counts as added on one side, so nothing only one side has looks like a deletion
+pub fn get_first_sync_base(
+	local: (&TodosDatabase, &MealsDatabase),
+	remote: (&TodosDatabase, &MealsDatabase)
+) -> (TodosDatabase, MealsDatabase) {
+	let mut todos = TodosDatabase::default();
+	for (date, day) in local.0.mapping.iter() {
+		let remote_items = match remote.0.mapping.get(date) {
+			Some(remote_day) => &remote_day.items,
+			None => continue,
+		};
+
+		// by their text, since the server's copy also has the calendar uids
+		let remote_lines = remote_items.iter().map(item_to_human_readable_lines).collect::<Vec<Vec<String>>>();
+		todos.mapping.insert(*date, Day {
+			date: *date,
+			items: day.items.iter()
+				.filter(|item| remote_lines.contains(&item_to_human_readable_lines(item)))
+				.cloned()
+				.collect(),
+		});
+	}
+
+	// recipes compare by name, so look at the whole text instead
+	let remote_recipes = remote.1.recipes.iter()
+		.filter_map(|recipe| recipe_to_human_readable(recipe).ok())
+		.collect::<Vec<String>>();
+
+	let meals = MealsDatabase {
+		recipes: local.1.recipes.iter()
+			.filter(|recipe| recipe_to_human_readable(recipe).is_ok_and(|text| remote_recipes.contains(&text)))
+			.cloned()
+			.collect(),
+		..MealsDatabase::default()
+	};
+
+	(todos, meals)
+}
+
+// diff3 over lines. returns the merged lines and how many conflicts we annotated
+pub fn merge_lines(base: &[String], local: &[String], remote: &[String]) -> (Vec<String>, usize) {
+	let local_matches = get_matches(base, local);
+	let remote_matches = get_matches(base, remote);
+
+	let mut merged = Vec::new();
+	let mut conflicts = 0;
+
+	let (mut i, mut j, mut k) = (0, 0, 0);
+	loop {
+		// find the next base line that both sides left alone
+		let stable = (i..base.len()).find(|index| local_matches.contains_key(index) && remote_matches.contains_key(index));
+		let (base_end, local_end, remote_end) = match stable {
+			Some(index) => (index, local_matches[&index], remote_matches[&index]),
+			None => (base.len(), local.len(), remote.len()),
+		};
+
+		let base_chunk = &base[i..base_end];
+		let local_chunk = &local[j..local_end];
+		let remote_chunk = &remote[k..remote_end];
+
+		if base_chunk == local_chunk {
+			merged.extend_from_slice(remote_chunk);
+		} else if base_chunk == remote_chunk || local_chunk == remote_chunk {
+			merged.extend_from_slice(local_chunk);
+		} else if base_chunk.is_empty() { // both sides only added lines, so keep all of them
+			merged.extend_from_slice(local_chunk);
+			merged.extend_from_slice(remote_chunk);
+		} else {
+			conflicts += 1;
+			merged.extend_from_slice(local_chunk);
+
+			if remote_chunk.is_empty() {
+				merged.extend(base_chunk.iter().map(|line| format!("{}, server removed: \"{}\"", CONFLICT_NOTE, line)));
+			} else {
+				merged.extend(remote_chunk.iter().map(|line| format!("{}, server has: \"{}\"", CONFLICT_NOTE, line)));
+			}
+		}
+
+		match stable {
+			Some(index) => {
+				merged.push(base[index].clone());
+				i = base_end + 1;
+				j = local_end + 1;
+				k = remote_end + 1;
+			},
+			None => break,
+		}
+	}
+
+	(merged, conflicts)
+}
+
+// longest common subsequence between two lists of lines, as a map from the first list's indices to the second's
+fn get_matches(a: &[String], b: &[String]) -> HashMap<usize, usize> {
+	let mut lengths = vec![vec![0; b.len() + 1]; a.len() + 1];
+	for i in (0..a.len()).rev() {
+		for j in (0..b.len()).rev() {
+			lengths[i][j] = if a[i] == b[j] {
+				lengths[i + 1][j + 1] + 1
+			} else {
+				std::cmp::max(lengths[i + 1][j], lengths[i][j + 1])
+			};
+		}
+	}
+
+	let mut matches = HashMap::new();
+	let (mut i, mut j) = (0, 0);
+	while i < a.len() && j < b.len() {
+		if a[i] == b[j] {
+			matches.insert(i, j);
+			i += 1;
+			j += 1;
+		} else if lengths[i + 1][j] >= lengths[i][j + 1] {
+			i += 1;
+		} else {
+			j += 1;
+		}
+	}
+
+	matches
+}
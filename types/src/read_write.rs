@@ -9,6 +9,7 @@ use crate::{
 	Error,
 	ErrorTag,
 	MealsDatabase,
+	parse_recipes,
 	Resource,
 	TodosDatabase,
 	WriteDatabase,
@@ -106,6 +107,8 @@ pub async fn write_database<'a>(
     	WriteDatabase::Partial {
 				planned_meals_remove_log,
 				planned_meals_write_log,
+				todos_complete_log,
+				todos_tracking_log,
 				todos_write_log
 			} => {
 				let mut result = None;
@@ -140,6 +143,26 @@ pub async fn write_database<'a>(
 					);
 				}
 
+				if todos_complete_log.len() > 0 {
+					result = Some(
+						client.post(format!("{}/complete-todos/", resource.reference))
+							.header("Secret", get_todos_secret())
+							.body(serde_json::to_string(todos_complete_log).unwrap())
+							.send()
+							.await
+					);
+				}
+
+				if todos_tracking_log.len() > 0 {
+					result = Some(
+						client.post(format!("{}/track-todos/", resource.reference))
+							.header("Secret", get_todos_secret())
+							.body(serde_json::to_string(todos_tracking_log).unwrap())
+							.send()
+							.await
+					);
+				}
+
 				result
 			},
 		};
@@ -178,6 +201,8 @@ pub async fn write_database<'a>(
     	WriteDatabase::Partial {
 				planned_meals_remove_log,
 				planned_meals_write_log,
+				todos_complete_log,
+				todos_tracking_log,
 				todos_write_log
 			} => {
 				let databases = read_database(resource.clone()).await?;
@@ -205,6 +230,14 @@ pub async fn write_database<'a>(
 					}
 				}
 
+				for completion in todos_complete_log {
+					read_databases.0.as_mut().unwrap().complete_todo(completion);
+				}
+
+				for event in todos_tracking_log {
+					read_databases.0.as_mut().unwrap().track(event);
+				}
+
 				(read_databases.0.as_ref().unwrap(), read_databases.1.as_ref().unwrap())
 			},
 		};
@@ -226,3 +259,62 @@ pub async fn write_database<'a>(
 		}
 	}
 }
+
+// sends a recipe file to the server for importing, or imports it straight into a local database
+pub async fn import_recipes(contents: String, resource: Resource) -> Result<usize, Error> {
+	if resource.reference.contains("http") {
+		let client = reqwest::Client::new();
+		let response = match client.post(format!("{}/import-recipes/", resource.reference))
+			.header("Secret", get_todos_secret())
+			.body(contents)
+			.send()
+			.await
+		{
+			Ok(response) => response,
+			Err(error) => return Err(Error {
+				message: format!("{:?}", error),
+				..Error::default()
+			}),
+		};
+
+		if response.status() != reqwest::StatusCode::OK {
+			return Err(Error {
+				message: response.text().await.unwrap_or_default(),
+				..Error::default()
+			});
+		}
+
+		match response.json::<usize>().await {
+			Ok(count) => Ok(count),
+			Err(error) => Err(Error {
+				message: format!("Could not deserialize JSON: {:?}", error),
+				..Error::default()
+			}),
+		}
+	} else {
+		let recipes = parse_recipes(&contents)?;
+		let (todos, mut meals) = read_database(resource.clone()).await?;
+
+		// recipes we already have are skipped, and left out of the count
+		let mut count = 0;
+		for recipe in recipes {
+			if meals.recipes.contains(&recipe) {
+				continue;
+			}
+
+			meals.recipes.push(recipe);
+			count += 1;
+		}
+		meals.recipes.sort();
+
+		write_database(
+			WriteDatabase::Full {
+				meals: &meals,
+				todos: &todos,
+			},
+			resource
+		).await?;
+
+		Ok(count)
+	}
+}
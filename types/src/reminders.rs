@@ -0,0 +1,66 @@
+use chrono::{ Duration, NaiveDateTime };
+use serde::{ Serialize, Deserialize };
+
+use crate::{ Date, Time, TodosDatabase };
+
+// sent to the clients when a timed item is lead_minutes away from starting. the description has its markup removed
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Reminder {
+	pub date: Date,
+	pub description: String,
+	pub lead_minutes: u32,
+	pub time: Time,
+}
+
+impl Reminder {
+	pub fn get_text(&self) -> String {
+		let description = &self.description;
+		match self.lead_minutes {
+			0 => format!("{} is starting", description),
+			1 => format!("{} in 1 minute", description),
+			minutes if minutes % 60 == 0 => format!("{} in {} hour(s)", description, minutes / 60),
+			minutes => format!("{} in {} minutes", description, minutes),
+		}
+	}
+}
+
+impl TodosDatabase {
+	// every reminder that came due after since, up to and including now. each lead time gets its own reminder
+	pub fn get_reminders(&self, since: NaiveDateTime, now: NaiveDateTime, lead_times: &[u32]) -> Vec<Reminder> {
+		let longest = lead_times.iter().max().copied().unwrap_or(0);
+		let last = Date::from_naive((now + Duration::minutes(longest as i64)).date());
+
+		let mut reminders = Vec::new();
+		let mut date = Date::from_naive(since.date());
+		while date <= last {
+			let naive = match date.to_naive() {
+				Some(naive) => naive,
+				None => break,
+			};
+
+			for (item, time) in self.get_timed_items(date) {
+				// the part of an event spilling over from last night already got its reminders
+				if item.time.map(|original| original.start_minutes()) != Some(time.start_minutes()) {
+					continue;
+				}
+
+				let start = naive.and_hms(0, 0, 0) + Duration::minutes(time.start_minutes() as i64);
+				for lead_minutes in lead_times.iter() {
+					let due = start - Duration::minutes(*lead_minutes as i64);
+					if since < due && due <= now {
+						reminders.push(Reminder {
+							date,
+							description: item.get_text().to_string(),
+							lead_minutes: *lead_minutes,
+							time: item.time.unwrap(),
+						});
+					}
+				}
+			}
+
+			date = date.add_days(1);
+		}
+
+		reminders
+	}
+}
@@ -0,0 +1,86 @@
+use crate::{ Date, Day, Dirty, IO, Item, TodosDatabase };
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum RolloverMode {
+	Off,
+	#[default]
+	Today, // move unfinished items to the end of today
+	Overdue, // move unfinished items to the top of today, marked with the day they were due
+}
+
+impl TodosDatabase {
+	// archives every day before today, carrying unfinished todos over to today. returns how many items moved
+	pub fn rollover(&mut self, today: Date, mode: RolloverMode) -> usize {
+		if mode == RolloverMode::Off {
+			return 0;
+		}
+
+		let past = self.mapping.keys()
+			.filter(|date| matches!(date, Some(date) if *date < today))
+			.cloned()
+			.collect::<Vec<Option<Date>>>();
+
+		let mut moved = Vec::new();
+		for date in past.iter() {
+			let mut day = self.mapping.remove(date).unwrap();
+
+			// events already happened and finished todos are done, everything else still needs doing
+			let (unfinished, finished): (Vec<Item>, Vec<Item>) = day.items.drain(..)
+				.filter(|item| !item.description.is_empty())
+				.partition(|item| item.time.is_none() && !item.is_done());
+
+			for mut item in unfinished {
+				if mode == RolloverMode::Overdue && item.due.is_none() {
+					item.description = format!("{} due:{}", item.description, date.unwrap().to_string());
+					item.parse_markup();
+				}
+
+				moved.push(item);
+			}
+
+			if !finished.is_empty() {
+				day.items = finished;
+				self.archive.push(day);
+			}
+		}
+
+		self.archive.sort_by_key(|day| day.date);
+
+		if moved.is_empty() {
+			return 0;
+		}
+
+		let count = moved.len();
+		let day = self.mapping.entry(Some(today)).or_insert(Day {
+			date: Some(today),
+			items: Vec::new(),
+		});
+
+		if mode == RolloverMode::Overdue { // keep overdue items in their own group above today's items
+			if !day.items.is_empty() {
+				moved.push(Item::default());
+			}
+
+			day.items.splice(0..0, moved);
+		} else {
+			day.items.extend(moved);
+		}
+
+		count
+	}
+}
+
+impl IO {
+	pub fn rollover_todos(&mut self, today: Date, mode: RolloverMode) -> usize {
+		let past_days = self.todos_database.mapping.keys()
+			.filter(|date| matches!(date, Some(date) if *date < today))
+			.count();
+
+		let count = self.todos_database.rollover(today, mode);
+		if past_days > 0 && mode != RolloverMode::Off {
+			self.dirty = Dirty::Write;
+		}
+
+		count
+	}
+}
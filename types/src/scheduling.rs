@@ -0,0 +1,199 @@
+use chrono::Datelike;
+use serde::{ Serialize, Deserialize };
+
+use crate::{ Date, Item, Time, TodosDatabase, Weekday };
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+// a timed item on one day, along with where it goes when drawn next to the items it overlaps. the time is cut to
+// the day, so events that cross midnight show up on both days they touch
+#[derive(Clone, Debug)]
+pub struct ScheduledItem<'a> {
+	pub column: usize,
+	pub columns: usize,
+	pub item: &'a Item,
+	pub time: Time,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FreeBusy {
+	pub busy: Vec<Time>,
+	pub conflicts: Vec<(String, String)>,
+	pub free: Vec<Time>,
+}
+
+impl Time {
+	pub fn start_minutes(&self) -> u32 {
+		self.start_hour as u32 * 60 + self.start_minute as u32
+	}
+
+	pub fn end_minutes(&self) -> u32 {
+		self.end_hour as u32 * 60 + self.end_minute as u32
+	}
+
+	// touching end to start does not count
+	pub fn overlaps(&self, other: &Time) -> bool {
+		self.start_minutes() < other.end_minutes() && other.start_minutes() < self.end_minutes()
+	}
+
+	pub(crate) fn from_minutes(day: Option<Weekday>, start: u32, end: u32) -> Time {
+		Time {
+			day,
+			start_hour: (start / 60) as u8,
+			start_minute: (start % 60) as u8,
+			end_hour: (end / 60) as u8,
+			end_minute: (end % 60) as u8,
+		}
+	}
+}
+
+impl Date {
+	pub fn weekday(&self) -> Option<Weekday> {
+		self.to_naive().map(|date| match date.weekday() {
+			chrono::Weekday::Mon => Weekday::Monday,
+			chrono::Weekday::Tue => Weekday::Tuesday,
+			chrono::Weekday::Wed => Weekday::Wednesday,
+			chrono::Weekday::Thu => Weekday::Thursday,
+			chrono::Weekday::Fri => Weekday::Friday,
+			chrono::Weekday::Sat => Weekday::Saturday,
+			chrono::Weekday::Sun => Weekday::Sunday,
+		})
+	}
+}
+
+impl TodosDatabase {
+	// every timed item that takes up part of the date, including recurring ones on its weekday and whatever spills
+	// over from the night before. all day items do not block any time, so they are left out
+	pub fn get_timed_items(&self, date: Date) -> Vec<(&Item, Time)> {
+		let weekday = date.weekday();
+		let yesterday = date.add_days(-1);
+
+		let mut items = Vec::new();
+		for (item_date, day) in self.mapping.iter() {
+			for item in day.items.iter() {
+				let time = match item.time {
+					Some(time) if !time.is_all_day() => time,
+					_ => continue,
+				};
+
+				let (today, spilled) = match item_date {
+					Some(item_date) => (*item_date == date, *item_date == yesterday),
+					None if time.day.is_some() => (
+						time.day == weekday,
+						time.day == yesterday.weekday(),
+					),
+					None => (false, false),
+				};
+
+				if today {
+					let end = time.end_minutes().min(MINUTES_PER_DAY);
+					items.push((item, Time::from_minutes(time.day, time.start_minutes(), end)));
+				}
+
+				if spilled && time.end_minutes() > MINUTES_PER_DAY {
+					items.push((item, Time::from_minutes(time.day, 0, time.end_minutes() - MINUTES_PER_DAY)));
+				}
+			}
+		}
+
+		items.sort_by_key(|(_, time)| (time.start_minutes(), time.end_minutes()));
+		items
+	}
+
+	// every pair of timed items on the date that overlap, in start order
+	pub fn get_conflicts(&self, date: Date) -> Vec<(&Item, &Item)> {
+		let items = self.get_timed_items(date);
+
+		let mut conflicts = Vec::new();
+		for (index, (item, time)) in items.iter().enumerate() {
+			for (other, other_time) in items[index + 1..].iter() {
+				if time.overlaps(other_time) {
+					conflicts.push((*item, *other));
+				}
+			}
+		}
+
+		conflicts
+	}
+
+	// items that overlap are put in side by side columns. a group of items that overlap each other, even through
+	// other items, all get the same number of columns so they line up
+	pub fn schedule(&self, date: Date) -> Vec<ScheduledItem<'_>> {
+		let mut scheduled: Vec<ScheduledItem> = Vec::new();
+		let mut group_start = 0;
+		let mut group_end = 0;
+		for (item, time) in self.get_timed_items(date) {
+			if time.start_minutes() >= group_end {
+				finish_group(&mut scheduled[group_start..]);
+				group_start = scheduled.len();
+			}
+
+			// take the first column that is free by the time this item starts
+			let column = (0..)
+				.find(|column| {
+					!scheduled[group_start..].iter()
+						.any(|other| other.column == *column && other.time.overlaps(&time))
+				})
+				.unwrap();
+
+			group_end = group_end.max(time.end_minutes());
+			scheduled.push(ScheduledItem {
+				column,
+				columns: 1,
+				item,
+				time,
+			});
+		}
+
+		finish_group(&mut scheduled[group_start..]);
+		scheduled
+	}
+
+	// free and busy time on the date between the start and end hours, with busy time merged together
+	pub fn free_busy(&self, date: Date, start_hour: u8, end_hour: u8) -> FreeBusy {
+		let start = (start_hour as u32 * 60).min(MINUTES_PER_DAY);
+		let end = (end_hour as u32 * 60).min(MINUTES_PER_DAY);
+
+		let mut busy: Vec<(u32, u32)> = Vec::new();
+		for (_, time) in self.get_timed_items(date) {
+			let (item_start, item_end) = (time.start_minutes().max(start), time.end_minutes().min(end));
+			if item_start >= item_end {
+				continue;
+			}
+
+			match busy.last_mut() {
+				Some((_, last_end)) if item_start <= *last_end => *last_end = (*last_end).max(item_end),
+				_ => busy.push((item_start, item_end)),
+			}
+		}
+
+		let mut free = Vec::new();
+		let mut cursor = start;
+		for (busy_start, busy_end) in busy.iter() {
+			if *busy_start > cursor {
+				free.push(Time::from_minutes(None, cursor, *busy_start));
+			}
+
+			cursor = *busy_end;
+		}
+
+		if cursor < end {
+			free.push(Time::from_minutes(None, cursor, end));
+		}
+
+		FreeBusy {
+			busy: busy.iter().map(|(start, end)| Time::from_minutes(None, *start, *end)).collect(),
+			conflicts: self.get_conflicts(date).iter()
+				.map(|(item, other)| (item.description.clone(), other.description.clone()))
+				.collect(),
+			free,
+		}
+	}
+}
+
+fn finish_group(group: &mut [ScheduledItem]) {
+	let columns = group.iter().map(|scheduled| scheduled.column + 1).max().unwrap_or(1);
+	for scheduled in group.iter_mut() {
+		scheduled.columns = columns;
+	}
+}
@@ -0,0 +1,121 @@
+use serde::{ Serialize, Deserialize };
+use serde_json::Value;
+
+// ata attributes, by id
+const REALLOCATED_SECTORS: u64 = 5;
+const POWER_ON_HOURS: u64 = 9;
+const TEMPERATURE: u64 = 194;
+const PENDING_SECTORS: u64 = 197;
+
+// smartctl's exit status is a bit mask. these two mean it could not read the drive at all
+const EXIT_COMMAND_LINE: u64 = 1 << 0;
+const EXIT_DEVICE_OPEN: u64 = 1 << 1;
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SmartSelfTest {
+	pub hours: Option<u64>, // power on hours when it ran
+	pub kind: String, // short, extended and so on
+	pub passed: bool,
+	pub status: String, // what smartctl says about how it went
+}
+
+// what `smartctl --json -a` had to say about a drive. anything the drive does not report is None
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SmartDrive {
+	pub checked_at: i64, // unix seconds
+	pub device: String, // what smartctl was pointed at
+	pub model: Option<String>,
+	pub passed: Option<bool>, // the drive's own verdict on its health
+	pub pending_sectors: Option<u64>,
+	pub power_on_hours: Option<u64>,
+	pub reallocated_sectors: Option<u64>,
+	pub self_test: Option<SmartSelfTest>, // the most recent one
+	pub serial: String,
+	pub temperature: Option<u64>, // celsius
+}
+
+impl SmartDrive {
+	pub fn get_bad_sectors(&self) -> u64 {
+		self.reallocated_sectors.unwrap_or(0) + self.pending_sectors.unwrap_or(0)
+	}
+
+	pub fn is_failing(&self) -> bool {
+		self.passed == Some(false) || self.self_test.as_ref().is_some_and(|test| !test.passed)
+	}
+}
+
+fn get_attribute(json: &Value, id: u64) -> Option<&Value> {
+	json["ata_smart_attributes"]["table"].as_array()?
+		.iter()
+		.find(|attribute| attribute["id"].as_u64() == Some(id))
+}
+
+// raw values pack other things into the upper bytes, like the min and max temperature, so the first number in the
+// string is what we are after
+fn get_raw_value(json: &Value, id: u64) -> Option<u64> {
+	let raw = &get_attribute(json, id)?["raw"];
+	raw["string"].as_str()
+		.and_then(|string| string.split_whitespace().next())
+		.and_then(|number| number.parse().ok())
+		.or_else(|| raw["value"].as_u64())
+}
+
+fn get_self_test(json: &Value) -> Option<SmartSelfTest> {
+	// ata drives list their tests newest first
+	if let Some(test) = json["ata_smart_self_test_log"]["standard"]["table"].as_array().and_then(|table| table.first()) {
+		return Some(SmartSelfTest {
+			hours: test["lifetime_hours"].as_u64(),
+			kind: test["type"]["string"].as_str().unwrap_or("unknown").to_string(),
+			passed: test["status"]["passed"].as_bool().unwrap_or(false),
+			status: test["status"]["string"].as_str().unwrap_or("unknown").to_string(),
+		});
+	}
+
+	// nvme drives do too, and a result of 0 means no errors
+	let test = json["nvme_self_test_log"]["table"].as_array().and_then(|table| table.first())?;
+	Some(SmartSelfTest {
+		hours: test["power_on_hours"].as_u64(),
+		kind: test["self_test_code"]["string"].as_str().unwrap_or("unknown").to_string(),
+		passed: test["self_test_result"]["value"].as_u64() == Some(0),
+		status: test["self_test_result"]["string"].as_str().unwrap_or("unknown").to_string(),
+	})
+}
+
+// reads `smartctl --json -a` for one drive. smartctl still prints json when it cannot read the drive, like when it is
+// asleep and `-n standby` told it to leave it that way, in which case its messages are the error
+pub fn parse_smartctl(output: &str, checked_at: i64) -> Result<SmartDrive, String> {
+	let json: Value = serde_json::from_str(output).map_err(|error| format!("smartctl parse error: {}", error))?;
+
+	let exit_status = json["smartctl"]["exit_status"].as_u64().unwrap_or(0);
+	let serial = json["serial_number"].as_str();
+	if exit_status & (EXIT_COMMAND_LINE | EXIT_DEVICE_OPEN) != 0 || serial.is_none() {
+		let messages = json["smartctl"]["messages"].as_array()
+			.map(|messages| {
+				messages.iter()
+					.filter_map(|message| message["string"].as_str())
+					.collect::<Vec<&str>>()
+					.join(", ")
+			})
+			.filter(|messages| !messages.is_empty());
+
+		return Err(messages.unwrap_or_else(|| format!("smartctl exited with {}", exit_status)));
+	}
+
+	let nvme = &json["nvme_smart_health_information_log"];
+	Ok(SmartDrive {
+		checked_at,
+		device: json["device"]["name"].as_str().unwrap_or("").to_string(),
+		model: json["model_name"].as_str().map(|model| model.to_string()),
+		passed: json["smart_status"]["passed"].as_bool(),
+		pending_sectors: get_raw_value(&json, PENDING_SECTORS),
+		power_on_hours: json["power_on_time"]["hours"].as_u64()
+			.or_else(|| get_raw_value(&json, POWER_ON_HOURS))
+			.or_else(|| nvme["power_on_hours"].as_u64()),
+		reallocated_sectors: get_raw_value(&json, REALLOCATED_SECTORS),
+		self_test: get_self_test(&json),
+		serial: serial.unwrap().to_string(),
+		temperature: json["temperature"]["current"].as_u64()
+			.or_else(|| get_raw_value(&json, TEMPERATURE))
+			.or_else(|| nvme["temperature"].as_u64()),
+	})
+}
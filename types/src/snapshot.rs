@@ -0,0 +1,217 @@
+use chrono::{ Local, NaiveDateTime, TimeZone };
+use serde::{ Serialize, Deserialize };
+use std::collections::{ BTreeMap, HashMap };
+
+// diffs of whole backups can run to millions of files, clients only get this many
+pub const SNAPSHOT_DIFF_MAX_CHANGES: usize = 2000;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum SnapshotSource {
+	Btrfs,
+	Zfs,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Snapshot {
+	pub created_at: i64, // unix seconds
+	pub name: String, // what clients ask for it by
+	pub path: String, // where its files are on the storage server, empty if they cannot be browsed
+	pub size: Option<u64>, // bytes only this snapshot holds on to, if the filesystem keeps track
+	pub source: SnapshotSource,
+}
+
+// a line of `find -printf "%P\t%s\t%T@\n"`
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SnapshotFile {
+	pub modified: i64,
+	pub path: String,
+	pub size: u64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum SnapshotChange {
+	Added,
+	Changed,
+	Removed,
+}
+
+// compares the files under path, which is relative to the root of both snapshots
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SnapshotDiffQuery {
+	pub from: String,
+	pub path: String,
+	#[serde(default)]
+	pub secret: String, // same as a command's, cleared before the query is echoed back
+	pub to: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SnapshotDiff {
+	pub changes: Vec<(SnapshotChange, String)>,
+	pub error: Option<String>,
+	pub query: SnapshotDiffQuery,
+	pub truncated: bool, // there were more than SNAPSHOT_DIFF_MAX_CHANGES
+}
+
+// copies path out of the snapshot into the staging directory
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RestoreRequest {
+	pub path: String,
+	#[serde(default)]
+	pub secret: String, // same as a command's, cleared before the request shows up in the status
+	pub snapshot: String,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum RestoreState {
+	Queued,
+	Running,
+	Done,
+	Failed,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Restore {
+	pub destination: String,
+	pub id: u64,
+	pub message: Option<String>,
+	pub request: RestoreRequest,
+	pub state: RestoreState,
+}
+
+fn from_local_time(time: NaiveDateTime) -> i64 {
+	Local.from_local_datetime(&time).earliest().map_or(time.timestamp(), |time| time.timestamp())
+}
+
+// `zfs list -H -p -t snapshot -o name,creation,used` along with `zfs list -H -o name,mountpoint -t filesystem`, so
+// snapshots can be browsed through the dataset's .zfs directory
+pub fn parse_zfs_snapshots(snapshots: &str, mountpoints: &str) -> Vec<Snapshot> {
+	let mountpoints = mountpoints.lines()
+		.filter_map(|line| line.split_once('\t'))
+		.filter(|(_, mountpoint)| mountpoint.starts_with('/'))
+		.map(|(dataset, mountpoint)| (dataset.trim(), mountpoint.trim()))
+		.collect::<HashMap<&str, &str>>();
+
+	let mut result = Vec::new();
+	for line in snapshots.lines() {
+		let fields = line.split('\t').collect::<Vec<&str>>();
+		if fields.len() < 3 {
+			continue;
+		}
+
+		let (dataset, snapshot) = match fields[0].split_once('@') {
+			Some(split) => split,
+			None => continue,
+		};
+
+		let created_at = match fields[1].trim().parse::<i64>() {
+			Ok(created_at) => created_at,
+			Err(_) => continue,
+		};
+
+		result.push(Snapshot {
+			created_at,
+			name: fields[0].to_string(),
+			path: mountpoints.get(dataset)
+				.map_or(String::new(), |mountpoint| format!("{}/.zfs/snapshot/{}", mountpoint.trim_end_matches('/'), snapshot)),
+			size: fields[2].trim().parse().ok(),
+			source: SnapshotSource::Zfs,
+		});
+	}
+
+	result.sort_by_key(|snapshot| snapshot.created_at);
+	result
+}
+
+// `btrfs subvolume list -s` along with `btrfs qgroup show --raw`, which fails if quotas are off. snapshot paths are
+// relative to the top of the filesystem, which is mounted at root
+pub fn parse_btrfs_snapshots(list: &str, qgroups: &str, root: &str) -> Vec<Snapshot> {
+	// exclusive sizes by subvolume id
+	let sizes = qgroups.lines()
+		.filter_map(|line| {
+			let fields = line.split_whitespace().collect::<Vec<&str>>();
+			let id = fields.first()?.strip_prefix("0/")?;
+			Some((id.to_string(), fields.get(2)?.parse::<u64>().ok()?))
+		})
+		.collect::<HashMap<String, u64>>();
+
+	let mut result = Vec::new();
+	for line in list.lines() {
+		let fields = line.split_whitespace().collect::<Vec<&str>>();
+		let get = |key: &str| fields.iter().position(|field| *field == key).and_then(|index| fields.get(index + 1));
+
+		let (id, date, time) = match (get("ID"), get("otime"), fields.iter().position(|field| *field == "otime")) {
+			(Some(id), Some(date), Some(index)) => (id, date, fields.get(index + 2)),
+			_ => continue,
+		};
+
+		// the path is the rest of the line, and could have spaces in it
+		let path = match line.split_once(" path ") {
+			Some((_, path)) => path.trim(),
+			None => continue,
+		};
+
+		let created_at = match time.and_then(|time| NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S").ok()) {
+			Some(created_at) => from_local_time(created_at),
+			None => continue,
+		};
+
+		result.push(Snapshot {
+			created_at,
+			name: path.to_string(),
+			path: format!("{}/{}", root.trim_end_matches('/'), path),
+			size: sizes.get(*id).copied(),
+			source: SnapshotSource::Btrfs,
+		});
+	}
+
+	result.sort_by_key(|snapshot| snapshot.created_at);
+	result
+}
+
+pub fn parse_file_list(output: &str) -> Vec<SnapshotFile> {
+	output.lines()
+		.filter_map(|line| {
+			let mut fields = line.rsplitn(3, '\t');
+			let modified = fields.next()?.split('.').next()?.parse::<i64>().ok()?;
+			let size = fields.next()?.parse::<u64>().ok()?;
+			let path = fields.next()?;
+			Some(SnapshotFile {
+				modified,
+				path: path.to_string(),
+				size,
+			})
+		})
+		.collect()
+}
+
+// what happened to each file between the two lists, in path order. files count as changed if their size or
+// modification time did
+pub fn diff_file_lists(from: &[SnapshotFile], to: &[SnapshotFile]) -> Vec<(SnapshotChange, String)> {
+	let mut files: BTreeMap<&str, (Option<&SnapshotFile>, Option<&SnapshotFile>)> = BTreeMap::new();
+	for file in from.iter() {
+		files.entry(&file.path).or_default().0 = Some(file);
+	}
+
+	for file in to.iter() {
+		files.entry(&file.path).or_default().1 = Some(file);
+	}
+
+	files.into_iter()
+		.filter_map(|(path, files)| match files {
+			(None, Some(_)) => Some((SnapshotChange::Added, path.to_string())),
+			(Some(_), None) => Some((SnapshotChange::Removed, path.to_string())),
+			(Some(from), Some(to)) if from.size != to.size || from.modified != to.modified => {
+				Some((SnapshotChange::Changed, path.to_string()))
+			},
+			_ => None,
+		})
+		.collect()
+}
+
+// paths from clients have to stay inside the snapshot or staging directory they are joined onto
+pub fn is_safe_relative_path(path: &str) -> bool {
+	!path.starts_with('/')
+		&& !path.contains('\0')
+		&& path.split('/').all(|component| component != ".." && component != ".")
+}
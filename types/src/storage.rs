@@ -0,0 +1,446 @@
+use crate::{
+	BackupJob,
+	JobStatusFlags,
+	Restore,
+	RestoreRequest,
+	STORAGE_MESSAGE_COUNT,
+	SmartDrive,
+	Snapshot,
+	SnapshotDiff,
+	SnapshotDiffQuery,
+	StorageAlert,
+	StorageCommandRequest,
+	StorageCommandResponse,
+	StorageHistoryQuery,
+	StorageHistoryReply,
+	StorageUsage,
+	ZPool,
+};
+
+// every frame is the magic, a version, the payload length as a big endian u32 and then the payload. the payload is
+// a list of fields, each a tag, a kind and a value, so a receiver can skip fields it does not know about. adding a
+// metric only needs a new tag, the version only changes if the framing itself does
+pub const STORAGE_PROTOCOL_MAGIC: [u8; 2] = *b"BL";
+pub const STORAGE_PROTOCOL_VERSION: u8 = 1;
+pub const STORAGE_MAX_PAYLOAD_LENGTH: u32 = 1024 * 1024; // history replies can run to a few hundred kilobytes
+
+const HEADER_LENGTH: usize = 7;
+
+const KIND_U64: u8 = 0;
+const KIND_BYTES: u8 = 1;
+
+const TAG_JOB_FLAGS: u8 = 1;
+const TAG_USED_SIZE: u8 = 2;
+const TAG_TOTAL_SIZE: u8 = 3;
+const TAG_BTRFS_USED_SIZE: u8 = 4;
+const TAG_BTRFS_TOTAL_SIZE: u8 = 5;
+const TAG_BTRFS_BACKUP_COUNT: u8 = 6;
+const TAG_DAILIES: u8 = 7;
+const TAG_WEEKLIES: u8 = 8;
+const TAG_POOLS: u8 = 9; // json, since it is a whole tree
+const TAG_HISTORY_QUERY: u8 = 10; // json, sent by clients
+const TAG_HISTORY: u8 = 11; // json, the reply to a query
+const TAG_JOBS: u8 = 12; // json
+const TAG_SNAPSHOTS_QUERY: u8 = 13; // sent by clients, the value is ignored
+const TAG_SNAPSHOTS: u8 = 14; // json
+const TAG_SNAPSHOT_DIFF_QUERY: u8 = 15; // json, sent by clients
+const TAG_SNAPSHOT_DIFF: u8 = 16; // json
+const TAG_RESTORE_REQUEST: u8 = 17; // json, sent by clients
+const TAG_RESTORES: u8 = 18; // json
+const TAG_COMMAND: u8 = 19; // json, sent by clients
+const TAG_COMMAND_RESPONSE: u8 = 20; // json
+const TAG_ALERTS: u8 = 21; // json
+const TAG_SMART: u8 = 22; // json
+const TAG_USAGE: u8 = 23; // json
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StorageStatus {
+	pub job_flags: JobStatusFlags,
+
+	pub used_size: u64,
+	pub total_size: u64,
+
+	pub btrfs_used_size: u64,
+	pub btrfs_total_size: u64,
+	pub btrfs_backup_count: u64,
+
+	pub dailies: u8,
+	pub weeklies: u8,
+
+	pub pools: Vec<ZPool>,
+
+	// running jobs and the last run of each kind, if the storage server runs the backups itself
+	pub jobs: Vec<BackupJob>,
+
+	pub restores: Vec<Restore>,
+
+	pub alerts: Vec<StorageAlert>,
+
+	// the last smartctl reading of every drive in the pools
+	pub smart: Vec<SmartDrive>,
+
+	// every configured pool and filesystem. the sizes above are the first pool and btrfs filesystem among them
+	pub usage: Vec<StorageUsage>,
+}
+
+impl Default for StorageStatus {
+	fn default() -> Self {
+		StorageStatus {
+			job_flags: JobStatusFlags::IDLE,
+			used_size: 0,
+			total_size: 0,
+			btrfs_used_size: 0,
+			btrfs_total_size: 0,
+			btrfs_backup_count: 0,
+			dailies: 0,
+			weeklies: 0,
+			pools: Vec::new(),
+			jobs: Vec::new(),
+			restores: Vec::new(),
+			alerts: Vec::new(),
+			smart: Vec::new(),
+			usage: Vec::new(),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StorageProtocolError {
+	BadMagic,
+	MalformedField(u8),
+	PayloadTooLong(u32),
+	Truncated,
+	UnknownKind(u8),
+	UnsupportedVersion(u8),
+}
+
+impl std::fmt::Display for StorageProtocolError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			StorageProtocolError::BadMagic => f.write_str("frame does not start with the storage protocol magic"),
+			StorageProtocolError::MalformedField(tag) => write!(f, "could not read field {}", tag),
+			StorageProtocolError::PayloadTooLong(length) => write!(f, "payload of {} bytes is too long", length),
+			StorageProtocolError::Truncated => f.write_str("field runs past the end of the payload"),
+			StorageProtocolError::UnknownKind(kind) => write!(f, "unknown field kind {}", kind),
+			StorageProtocolError::UnsupportedVersion(version) => write!(f, "unsupported protocol version {}", version),
+		}
+	}
+}
+
+// what a decoder found at the front of a buffer
+#[derive(Clone, Debug, PartialEq)]
+pub enum StorageFrame {
+	Command(StorageCommandRequest),
+	CommandResponse(StorageCommandResponse),
+	Hello,
+	History(StorageHistoryReply),
+	HistoryQuery(StorageHistoryQuery),
+	RestoreRequest(RestoreRequest),
+	SnapshotDiff(SnapshotDiff),
+	SnapshotDiffQuery(SnapshotDiffQuery),
+	Snapshots(Vec<Snapshot>),
+	SnapshotsQuery,
+	Status(StorageStatus),
+}
+
+fn encode_frame(payload: &[u8]) -> Vec<u8> {
+	let mut frame = Vec::with_capacity(HEADER_LENGTH + payload.len());
+	frame.extend_from_slice(&STORAGE_PROTOCOL_MAGIC);
+	frame.push(STORAGE_PROTOCOL_VERSION);
+	frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+	frame.extend_from_slice(payload);
+	frame
+}
+
+fn push_u64(payload: &mut Vec<u8>, tag: u8, value: u64) {
+	payload.push(tag);
+	payload.push(KIND_U64);
+	payload.extend_from_slice(&value.to_be_bytes());
+}
+
+fn push_bytes(payload: &mut Vec<u8>, tag: u8, value: &[u8]) {
+	payload.push(tag);
+	payload.push(KIND_BYTES);
+	payload.extend_from_slice(&(value.len() as u32).to_be_bytes());
+	payload.extend_from_slice(value);
+}
+
+// clients send an empty frame to ask for frames instead of text lines
+pub fn encode_storage_hello() -> Vec<u8> {
+	encode_frame(&[])
+}
+
+pub fn encode_storage_status(status: &StorageStatus) -> Vec<u8> {
+	let mut payload = Vec::new();
+	push_u64(&mut payload, TAG_JOB_FLAGS, status.job_flags.bits());
+	push_u64(&mut payload, TAG_USED_SIZE, status.used_size);
+	push_u64(&mut payload, TAG_TOTAL_SIZE, status.total_size);
+	push_u64(&mut payload, TAG_BTRFS_USED_SIZE, status.btrfs_used_size);
+	push_u64(&mut payload, TAG_BTRFS_TOTAL_SIZE, status.btrfs_total_size);
+	push_u64(&mut payload, TAG_BTRFS_BACKUP_COUNT, status.btrfs_backup_count);
+	push_u64(&mut payload, TAG_DAILIES, status.dailies as u64);
+	push_u64(&mut payload, TAG_WEEKLIES, status.weeklies as u64);
+
+	if !status.pools.is_empty() {
+		push_bytes(&mut payload, TAG_POOLS, serde_json::to_string(&status.pools).unwrap().as_bytes());
+	}
+
+	if !status.jobs.is_empty() {
+		push_bytes(&mut payload, TAG_JOBS, serde_json::to_string(&status.jobs).unwrap().as_bytes());
+	}
+
+	if !status.restores.is_empty() {
+		push_bytes(&mut payload, TAG_RESTORES, serde_json::to_string(&status.restores).unwrap().as_bytes());
+	}
+
+	if !status.alerts.is_empty() {
+		push_bytes(&mut payload, TAG_ALERTS, serde_json::to_string(&status.alerts).unwrap().as_bytes());
+	}
+
+	if !status.smart.is_empty() {
+		push_bytes(&mut payload, TAG_SMART, serde_json::to_string(&status.smart).unwrap().as_bytes());
+	}
+
+	if !status.usage.is_empty() {
+		push_bytes(&mut payload, TAG_USAGE, serde_json::to_string(&status.usage).unwrap().as_bytes());
+	}
+
+	encode_frame(&payload)
+}
+
+pub fn encode_storage_history_query(query: &StorageHistoryQuery) -> Vec<u8> {
+	let mut payload = Vec::new();
+	push_bytes(&mut payload, TAG_HISTORY_QUERY, serde_json::to_string(query).unwrap().as_bytes());
+	encode_frame(&payload)
+}
+
+pub fn encode_storage_history(reply: &StorageHistoryReply) -> Vec<u8> {
+	let mut payload = Vec::new();
+	push_bytes(&mut payload, TAG_HISTORY, serde_json::to_string(reply).unwrap().as_bytes());
+	encode_frame(&payload)
+}
+
+pub fn encode_storage_snapshots_query() -> Vec<u8> {
+	let mut payload = Vec::new();
+	push_u64(&mut payload, TAG_SNAPSHOTS_QUERY, 0);
+	encode_frame(&payload)
+}
+
+pub fn encode_storage_snapshots(snapshots: &[Snapshot]) -> Vec<u8> {
+	let mut payload = Vec::new();
+	push_bytes(&mut payload, TAG_SNAPSHOTS, serde_json::to_string(snapshots).unwrap().as_bytes());
+	encode_frame(&payload)
+}
+
+pub fn encode_storage_snapshot_diff_query(query: &SnapshotDiffQuery) -> Vec<u8> {
+	let mut payload = Vec::new();
+	push_bytes(&mut payload, TAG_SNAPSHOT_DIFF_QUERY, serde_json::to_string(query).unwrap().as_bytes());
+	encode_frame(&payload)
+}
+
+pub fn encode_storage_snapshot_diff(diff: &SnapshotDiff) -> Vec<u8> {
+	let mut payload = Vec::new();
+	push_bytes(&mut payload, TAG_SNAPSHOT_DIFF, serde_json::to_string(diff).unwrap().as_bytes());
+	encode_frame(&payload)
+}
+
+pub fn encode_storage_restore_request(request: &RestoreRequest) -> Vec<u8> {
+	let mut payload = Vec::new();
+	push_bytes(&mut payload, TAG_RESTORE_REQUEST, serde_json::to_string(request).unwrap().as_bytes());
+	encode_frame(&payload)
+}
+
+pub fn encode_storage_command(request: &StorageCommandRequest) -> Vec<u8> {
+	let mut payload = Vec::new();
+	push_bytes(&mut payload, TAG_COMMAND, serde_json::to_string(request).unwrap().as_bytes());
+	encode_frame(&payload)
+}
+
+pub fn encode_storage_command_response(response: &StorageCommandResponse) -> Vec<u8> {
+	let mut payload = Vec::new();
+	push_bytes(&mut payload, TAG_COMMAND_RESPONSE, serde_json::to_string(response).unwrap().as_bytes());
+	encode_frame(&payload)
+}
+
+pub fn is_storage_frame(buffer: &[u8]) -> bool {
+	buffer.len() >= STORAGE_PROTOCOL_MAGIC.len() && buffer[..STORAGE_PROTOCOL_MAGIC.len()] == STORAGE_PROTOCOL_MAGIC
+}
+
+// how many bytes the frame at the front of the buffer takes up, once its header has arrived
+pub fn get_storage_frame_length(buffer: &[u8]) -> Option<usize> {
+	if buffer.len() < HEADER_LENGTH || !is_storage_frame(buffer) {
+		return None;
+	}
+
+	Some(HEADER_LENGTH + u32::from_be_bytes([buffer[3], buffer[4], buffer[5], buffer[6]]) as usize)
+}
+
+// decodes the frame at the front of the buffer, along with how many bytes it took up. returns None if the frame has
+// not fully arrived yet
+pub fn decode_storage_frame(buffer: &[u8]) -> Result<Option<(StorageFrame, usize)>, StorageProtocolError> {
+	if buffer.len() < HEADER_LENGTH {
+		if !buffer.is_empty() && !STORAGE_PROTOCOL_MAGIC.starts_with(&buffer[..buffer.len().min(2)]) {
+			return Err(StorageProtocolError::BadMagic);
+		}
+
+		return Ok(None);
+	}
+
+	if !is_storage_frame(buffer) {
+		return Err(StorageProtocolError::BadMagic);
+	}
+
+	if buffer[2] != STORAGE_PROTOCOL_VERSION {
+		return Err(StorageProtocolError::UnsupportedVersion(buffer[2]));
+	}
+
+	let length = u32::from_be_bytes([buffer[3], buffer[4], buffer[5], buffer[6]]);
+	if length > STORAGE_MAX_PAYLOAD_LENGTH {
+		return Err(StorageProtocolError::PayloadTooLong(length));
+	}
+
+	let end = HEADER_LENGTH + length as usize;
+	if buffer.len() < end {
+		return Ok(None);
+	}
+
+	let payload = &buffer[HEADER_LENGTH..end];
+	if payload.is_empty() {
+		return Ok(Some((StorageFrame::Hello, end)));
+	}
+
+	Ok(Some((decode_payload(payload)?, end)))
+}
+
+// a payload with a request or reply field is that request or reply, anything else is a status
+fn decode_payload(mut payload: &[u8]) -> Result<StorageFrame, StorageProtocolError> {
+	let mut status = StorageStatus::default();
+	let mut frame = None;
+	while !payload.is_empty() {
+		if payload.len() < 2 {
+			return Err(StorageProtocolError::Truncated);
+		}
+
+		let (tag, kind) = (payload[0], payload[1]);
+		payload = &payload[2..];
+
+		match kind {
+			KIND_U64 => {
+				if payload.len() < 8 {
+					return Err(StorageProtocolError::Truncated);
+				}
+
+				let mut bytes = [0; 8];
+				bytes.copy_from_slice(&payload[..8]);
+				payload = &payload[8..];
+
+				let value = u64::from_be_bytes(bytes);
+				match tag {
+					// flags we do not know about mean something went wrong we cannot show
+					TAG_JOB_FLAGS => status.job_flags = JobStatusFlags::from_bits(value).unwrap_or(JobStatusFlags::GENERAL_ERROR),
+					TAG_USED_SIZE => status.used_size = value,
+					TAG_TOTAL_SIZE => status.total_size = value,
+					TAG_BTRFS_USED_SIZE => status.btrfs_used_size = value,
+					TAG_BTRFS_TOTAL_SIZE => status.btrfs_total_size = value,
+					TAG_BTRFS_BACKUP_COUNT => status.btrfs_backup_count = value,
+					TAG_DAILIES => status.dailies = value.min(u8::MAX as u64) as u8,
+					TAG_WEEKLIES => status.weeklies = value.min(u8::MAX as u64) as u8,
+					TAG_SNAPSHOTS_QUERY => frame = Some(StorageFrame::SnapshotsQuery),
+					_ => {},
+				}
+			},
+			KIND_BYTES => {
+				if payload.len() < 4 {
+					return Err(StorageProtocolError::Truncated);
+				}
+
+				let length = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]) as usize;
+				payload = &payload[4..];
+				if payload.len() < length {
+					return Err(StorageProtocolError::Truncated);
+				}
+
+				let value = &payload[..length];
+				payload = &payload[length..];
+
+				let malformed = |_| StorageProtocolError::MalformedField(tag);
+				match tag {
+					// a pool list we cannot read is left empty rather than losing the rest of the status
+					TAG_POOLS => status.pools = serde_json::from_slice(value).unwrap_or_default(),
+					TAG_JOBS => status.jobs = serde_json::from_slice(value).unwrap_or_default(),
+					TAG_RESTORES => status.restores = serde_json::from_slice(value).unwrap_or_default(),
+					TAG_ALERTS => status.alerts = serde_json::from_slice(value).unwrap_or_default(),
+					TAG_SMART => status.smart = serde_json::from_slice(value).unwrap_or_default(),
+					TAG_USAGE => status.usage = serde_json::from_slice(value).unwrap_or_default(),
+					TAG_HISTORY_QUERY => frame = Some(StorageFrame::HistoryQuery(serde_json::from_slice(value).map_err(malformed)?)),
+					TAG_HISTORY => frame = Some(StorageFrame::History(serde_json::from_slice(value).map_err(malformed)?)),
+					TAG_SNAPSHOTS => frame = Some(StorageFrame::Snapshots(serde_json::from_slice(value).map_err(malformed)?)),
+					TAG_SNAPSHOT_DIFF_QUERY => frame = Some(
+						StorageFrame::SnapshotDiffQuery(serde_json::from_slice(value).map_err(malformed)?)
+					),
+					TAG_SNAPSHOT_DIFF => frame = Some(StorageFrame::SnapshotDiff(serde_json::from_slice(value).map_err(malformed)?)),
+					TAG_RESTORE_REQUEST => frame = Some(
+						StorageFrame::RestoreRequest(serde_json::from_slice(value).map_err(malformed)?)
+					),
+					TAG_COMMAND => frame = Some(StorageFrame::Command(serde_json::from_slice(value).map_err(malformed)?)),
+					TAG_COMMAND_RESPONSE => frame = Some(
+						StorageFrame::CommandResponse(serde_json::from_slice(value).map_err(malformed)?)
+					),
+					_ => {},
+				}
+			},
+			kind => return Err(StorageProtocolError::UnknownKind(kind)),
+		}
+	}
+
+	Ok(frame.unwrap_or(StorageFrame::Status(status)))
+}
+
+impl StorageStatus {
+	// the space separated line older clients understand
+	pub fn to_legacy_line(&self) -> String {
+		format!(
+			"{} {} {} {} {} {} {} {}\n",
+			self.job_flags.bits(),
+			self.used_size,
+			self.total_size,
+			self.btrfs_used_size,
+			self.btrfs_total_size,
+			self.btrfs_backup_count,
+			self.dailies,
+			self.weeklies
+		)
+	}
+
+	// reads a line from an older server, without its newline
+	pub fn from_legacy_line(line: &str) -> Option<StorageStatus> {
+		let parts = line.split_whitespace()
+			.map(|part| part.parse::<u64>().ok())
+			.collect::<Option<Vec<u64>>>()?;
+
+		if parts.len() != STORAGE_MESSAGE_COUNT as usize {
+			return None;
+		}
+
+		Some(StorageStatus {
+			job_flags: JobStatusFlags::from_bits(parts[0]).unwrap_or(JobStatusFlags::GENERAL_ERROR),
+
+			used_size: parts[1],
+			total_size: parts[2],
+
+			btrfs_used_size: parts[3],
+			btrfs_total_size: parts[4],
+			btrfs_backup_count: parts[5],
+
+			dailies: parts[6].min(u8::MAX as u64) as u8,
+			weeklies: parts[7].min(u8::MAX as u64) as u8,
+
+			pools: Vec::new(),
+			jobs: Vec::new(),
+			restores: Vec::new(),
+			alerts: Vec::new(),
+			smart: Vec::new(),
+			usage: Vec::new(),
+		})
+	}
+}
@@ -0,0 +1,45 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::BackupKind;
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum StorageCommand {
+	AcknowledgeAlert(String), // by the alert's key
+	RefreshStatus,
+	RunBackup(BackupKind),
+	StartScrub(String), // by pool name
+}
+
+// every command carries the secret, so a connection does not need to remember whether it logged in
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StorageCommandRequest {
+	pub command: StorageCommand,
+	pub id: u64, // picked by the client, the response comes back with it
+	pub secret: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StorageCommandResponse {
+	pub id: u64,
+	pub result: Result<String, String>, // what happened, or why nothing did
+}
+
+// alerts the storage server is raising. acknowledged ones stay until they resolve, clients just stop drawing
+// attention to them
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StorageAlert {
+	pub acknowledged: bool,
+	pub key: String,
+	pub message: String,
+}
+
+impl StorageCommand {
+	pub fn get_description(&self) -> String {
+		match self {
+			StorageCommand::AcknowledgeAlert(key) => format!("acknowledge {}", key),
+			StorageCommand::RefreshStatus => String::from("refresh status"),
+			StorageCommand::RunBackup(kind) => format!("run {} backup", kind.get_name()),
+			StorageCommand::StartScrub(pool) => format!("scrub {}", pool),
+		}
+	}
+}
@@ -0,0 +1,262 @@
+use serde::{ Serialize, Deserialize };
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{ Read, Write };
+
+use crate::{ Error, StorageStatus, StorageTargetKind };
+
+const MINUTE: i64 = 60;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+
+// replies are capped so they fit in a frame, a day of minutes is the most anyone should need
+pub const STORAGE_HISTORY_MAX_SAMPLES: usize = 1440;
+
+// how far apart samples are in each tier and how long the tier keeps them, finest first. a bucket keeps the last
+// sample recorded during it
+const TIERS: [(i64, i64); 3] = [(MINUTE, DAY), (HOUR, 30 * DAY), (DAY, 730 * DAY)];
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct StorageSample {
+	pub btrfs_backup_count: u64,
+	pub btrfs_total_size: u64,
+	pub btrfs_used_size: u64,
+	pub dailies: u8,
+	pub device_errors: BTreeMap<String, u64>, // read, write and checksum errors added up, by drive serial
+	pub time: i64, // unix seconds
+	pub total_size: u64,
+	pub used_size: u64,
+	pub weeklies: u8,
+}
+
+// one list of samples per tier, in time order
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct StorageHistory {
+	pub days: Vec<StorageSample>,
+	pub hours: Vec<StorageSample>,
+	pub minutes: Vec<StorageSample>,
+}
+
+// times are unix seconds, resolution is the spacing between samples in seconds
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct StorageHistoryQuery {
+	pub from: i64,
+	pub resolution: i64,
+	pub to: i64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct StorageHistoryReply {
+	pub days_until_full: Option<f64>,
+	pub samples: Vec<StorageSample>,
+}
+
+impl StorageSample {
+	// sizes that could not be read come through as zeros, which would look like the pool emptied out. there is no
+	// sample at all then
+	pub fn from_status(status: &StorageStatus, time: i64) -> Option<Self> {
+		let failed = [StorageTargetKind::Pool, StorageTargetKind::Btrfs].iter().any(|kind| {
+			status.usage.iter()
+				.find(|usage| usage.target.kind == *kind)
+				.is_some_and(|usage| usage.error.is_some())
+		});
+
+		if failed {
+			return None;
+		}
+
+		let mut device_errors = BTreeMap::new();
+		for drive in status.pools.iter().flat_map(|pool| pool.get_drives()) {
+			let errors = [drive.read_errors, drive.write_errors, drive.checksum_errors].iter()
+				.map(|count| count.unwrap_or(0))
+				.sum::<u64>();
+
+			*device_errors.entry(drive.get_serial().to_string()).or_insert(0) += errors;
+		}
+
+		Some(StorageSample {
+			btrfs_backup_count: status.btrfs_backup_count,
+			btrfs_total_size: status.btrfs_total_size,
+			btrfs_used_size: status.btrfs_used_size,
+			dailies: status.dailies,
+			device_errors,
+			time,
+			total_size: status.total_size,
+			used_size: status.used_size,
+			weeklies: status.weeklies,
+		})
+	}
+}
+
+// keeps the last sample in each bucket of the resolution
+fn downsample(samples: impl Iterator<Item = StorageSample>, resolution: i64) -> Vec<StorageSample> {
+	let mut result: Vec<StorageSample> = Vec::new();
+	for sample in samples {
+		match result.last_mut() {
+			Some(last) if last.time.div_euclid(resolution) == sample.time.div_euclid(resolution) => *last = sample,
+			_ => result.push(sample),
+		}
+	}
+
+	result
+}
+
+impl StorageHistory {
+	fn get_tiers(&self) -> [(i64, i64, &Vec<StorageSample>); 3] {
+		[
+			(TIERS[0].0, TIERS[0].1, &self.minutes),
+			(TIERS[1].0, TIERS[1].1, &self.hours),
+			(TIERS[2].0, TIERS[2].1, &self.days),
+		]
+	}
+
+	fn get_tiers_mut(&mut self) -> [(i64, i64, &mut Vec<StorageSample>); 3] {
+		[
+			(TIERS[0].0, TIERS[0].1, &mut self.minutes),
+			(TIERS[1].0, TIERS[1].1, &mut self.hours),
+			(TIERS[2].0, TIERS[2].1, &mut self.days),
+		]
+	}
+
+	pub fn get_latest(&self) -> Option<&StorageSample> {
+		self.get_tiers().into_iter()
+			.filter_map(|(_, _, samples)| samples.last())
+			.max_by_key(|sample| sample.time)
+	}
+
+	// adds the sample to every tier and drops whatever has aged out of them. samples older than the latest one are
+	// ignored
+	pub fn record(&mut self, sample: StorageSample) {
+		if self.get_latest().is_some_and(|latest| latest.time > sample.time) {
+			return;
+		}
+
+		for (resolution, keep, samples) in self.get_tiers_mut() {
+			match samples.last_mut() {
+				Some(last) if last.time.div_euclid(resolution) == sample.time.div_euclid(resolution) => *last = sample.clone(),
+				_ => samples.push(sample.clone()),
+			}
+
+			let cutoff = sample.time - keep;
+			samples.retain(|sample| sample.time >= cutoff);
+		}
+	}
+
+	// samples between the two times, using the finest tier that still goes back far enough
+	pub fn query(&self, query: &StorageHistoryQuery) -> Vec<StorageSample> {
+		let latest = match self.get_latest() {
+			Some(latest) => latest.time,
+			None => return Vec::new(),
+		};
+
+		let tiers = self.get_tiers();
+		let (_, _, samples) = tiers.iter()
+			.find(|(resolution, keep, _)| *resolution <= query.resolution.max(1) && latest - keep <= query.from)
+			.unwrap_or(&tiers[tiers.len() - 1]);
+
+		let mut samples = downsample(
+			samples.iter().filter(|sample| sample.time >= query.from && sample.time <= query.to).cloned(),
+			query.resolution.max(1)
+		);
+
+		if samples.len() > STORAGE_HISTORY_MAX_SAMPLES {
+			samples.drain(..samples.len() - STORAGE_HISTORY_MAX_SAMPLES);
+		}
+
+		samples
+	}
+
+	// fits a line through the last 30 days of pool usage. None if usage is not going up
+	pub fn get_days_until_full(&self) -> Option<f64> {
+		let latest = self.get_latest()?;
+		let samples = self.hours.iter()
+			.filter(|sample| sample.time >= latest.time - 30 * DAY)
+			.collect::<Vec<&StorageSample>>();
+
+		if samples.len() < 2 {
+			return None;
+		}
+
+		// times are relative to the first sample so the squares stay small
+		let start = samples[0].time;
+		let count = samples.len() as f64;
+		let mean_time = samples.iter().map(|sample| (sample.time - start) as f64).sum::<f64>() / count;
+		let mean_used = samples.iter().map(|sample| sample.used_size as f64).sum::<f64>() / count;
+
+		let mut covariance = 0.0;
+		let mut variance = 0.0;
+		for sample in samples.iter() {
+			let time = (sample.time - start) as f64 - mean_time;
+			covariance += time * (sample.used_size as f64 - mean_used);
+			variance += time * time;
+		}
+
+		if variance == 0.0 {
+			return None;
+		}
+
+		let slope = covariance / variance; // bytes per second
+		if slope <= 0.0 {
+			return None;
+		}
+
+		Some(latest.total_size.saturating_sub(latest.used_size) as f64 / slope / DAY as f64)
+	}
+
+	pub fn reply(&self, query: &StorageHistoryQuery) -> StorageHistoryReply {
+		StorageHistoryReply {
+			days_until_full: self.get_days_until_full(),
+			samples: self.query(query),
+		}
+	}
+
+	pub fn read(path: &str) -> Result<Self, Error> {
+		let mut buffer = Vec::new();
+		if let Err(error) = File::open(path).and_then(|mut file| file.read_to_end(&mut buffer)) {
+			return Err(Error {
+				message: format!("{:?}", error),
+				..Error::default()
+			});
+		}
+
+		let root = match flexbuffers::Reader::get_root(buffer.as_slice()) {
+			Ok(root) => root,
+			Err(error) => return Err(Error {
+				message: format!("{:?}", error),
+				..Error::default()
+			}),
+		};
+
+		match StorageHistory::deserialize(root) {
+			Ok(history) => Ok(history),
+			Err(error) => Err(Error {
+				message: format!("{:?}", error),
+				..Error::default()
+			}),
+		}
+	}
+
+	pub fn write(&self, path: &str) -> Result<(), Error> {
+		let mut serializer = flexbuffers::FlexbufferSerializer::new();
+		if let Err(error) = self.serialize(&mut serializer) {
+			return Err(Error {
+				message: format!("{:?}", error),
+				..Error::default()
+			});
+		}
+
+		// write next to the old file and move it over, so a crash never leaves half a history behind
+		let temporary = format!("{}.tmp", path);
+		let result = File::create(&temporary)
+			.and_then(|mut file| file.write_all(serializer.view()))
+			.and_then(|_| std::fs::rename(&temporary, path));
+
+		match result {
+			Ok(_) => Ok(()),
+			Err(error) => Err(Error {
+				message: format!("{:?}", error),
+				..Error::default()
+			}),
+		}
+	}
+}
@@ -0,0 +1,72 @@
+use serde::{ Serialize, Deserialize };
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum StorageTargetKind {
+	Btrfs, // read through `btrfs fi usage`, which knows about raid profiles
+	Mount, // anything df can see, by where it is mounted
+	Pool, // a zfs pool, by the dataset df sees at its root
+}
+
+// something the storage server reports the size of
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StorageTarget {
+	pub kind: StorageTargetKind,
+	pub path: String, // the pool's name, or where the filesystem is mounted
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StorageUsage {
+	pub error: Option<String>, // why the sizes could not be read, in which case they are zero
+	pub target: StorageTarget,
+	pub total_size: u64,
+	pub used_size: u64,
+}
+
+impl StorageTarget {
+	// written as `pool:bansheerubber`, `mount:/` or `btrfs:/bansheebtrfs/`
+	pub fn parse(target: &str) -> Option<Self> {
+		let (kind, path) = target.trim().split_once(':')?;
+		let kind = match kind {
+			"btrfs" => StorageTargetKind::Btrfs,
+			"mount" => StorageTargetKind::Mount,
+			"pool" => StorageTargetKind::Pool,
+			_ => return None,
+		};
+
+		if path.is_empty() {
+			return None;
+		}
+
+		Some(StorageTarget {
+			kind,
+			path: path.to_string(),
+		})
+	}
+
+	pub fn get_name(&self) -> &str {
+		// mount points read better without the trailing slash, unless that is all there is
+		match self.kind {
+			StorageTargetKind::Pool => &self.path,
+			_ if self.path == "/" => &self.path,
+			_ => self.path.trim_end_matches('/'),
+		}
+	}
+}
+
+// a comma separated list of targets
+pub fn parse_storage_targets(targets: &str) -> Result<Vec<StorageTarget>, String> {
+	targets.split(',')
+		.filter(|target| !target.trim().is_empty())
+		.map(|target| StorageTarget::parse(target).ok_or_else(|| format!("could not read storage target '{}'", target.trim())))
+		.collect()
+}
+
+impl StorageUsage {
+	pub fn get_fraction(&self) -> f64 {
+		if self.total_size == 0 {
+			0.0
+		} else {
+			self.used_size as f64 / self.total_size as f64
+		}
+	}
+}
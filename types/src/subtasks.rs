@@ -0,0 +1,92 @@
+use crate::{ Date, Dirty, Error, IO, Item, Priority, TodoCompletion, TodosCompleteLog, TodosDatabase };
+
+impl Item {
+	// how many children are done out of how many there are
+	pub fn progress(&self) -> (usize, usize) {
+		let done = self.children.iter()
+			.filter(|child| child.is_done())
+			.count();
+
+		(done, self.children.len())
+	}
+
+	// an item is done if it was checked off, or if all of its children are done
+	pub fn is_done(&self) -> bool {
+		self.completed || (!self.children.is_empty() && self.children.iter().all(Item::is_done))
+	}
+
+	// checks or unchecks the box in the description, adding one after the priority marker if there is none
+	pub fn set_completed(&mut self, completed: bool) {
+		let marker_length = match self.description.chars().next() {
+			Some(character) if Priority::from_marker(character).is_some() && self.description[1..].starts_with(' ') => 2,
+			_ => 0,
+		};
+
+		let checkbox = if completed { "[x]" } else { "[ ]" };
+		let rest = &self.description[marker_length..];
+		self.description = if rest.starts_with("[x]") || rest.starts_with("[X]") || rest.starts_with("[ ]") {
+			format!("{}{}{}", &self.description[..marker_length], checkbox, &rest[3..])
+		} else {
+			format!("{}{} {}", &self.description[..marker_length], checkbox, rest)
+		};
+
+		self.completed = completed;
+	}
+
+	fn find_mut(&mut self, path: &[String]) -> Option<&mut Item> {
+		match path.split_first() {
+			None => Some(self),
+			Some((description, rest)) => self.children.iter_mut()
+				.find(|child| &child.description == description || child.get_text() == description)?
+				.find_mut(rest),
+		}
+	}
+}
+
+impl TodosDatabase {
+	// returns false if the item could not be found
+	pub fn complete_todo(&mut self, completion: &TodoCompletion) -> bool {
+		let (description, rest) = match completion.path.split_first() {
+			Some(split) => split,
+			None => return false,
+		};
+
+		let item = self.mapping.get_mut(&completion.date)
+			.and_then(|day| {
+				day.items.iter_mut().find(|item| &item.description == description || item.get_text() == description)
+			})
+			.and_then(|item| item.find_mut(rest));
+
+		match item {
+			Some(item) => {
+				item.set_completed(completion.completed);
+				true
+			},
+			None => false,
+		}
+	}
+}
+
+impl IO {
+	pub fn complete_todo(&mut self, completion: TodoCompletion) -> Result<&TodosDatabase, Error> {
+		if !self.todos_database.complete_todo(&completion) {
+			return Err(Error {
+				message: format!("Could not find todo {:?} on {:?}", completion.path, completion.date),
+				..Error::default()
+			});
+		}
+
+		self.dirty = Dirty::Write;
+		Ok(&self.todos_database)
+	}
+
+	pub fn complete_todo_log(&self, date: Option<Date>, path: Vec<String>, completed: bool) -> TodosCompleteLog {
+		let mut log = self.todos_complete_log.clone();
+		log.push(TodoCompletion {
+			completed,
+			date,
+			path,
+		});
+		log
+	}
+}
@@ -41,6 +41,7 @@ mod tests {
 							end_hour: 0,
 							end_minute: 0,
 						}),
+						..Item::default()
 					},
 					date
 				) {
@@ -52,6 +53,7 @@ mod tests {
 				Item {
 					description: String::from(""),
 					time: None,
+					..Item::default()
 				},
 				date
 			) {
@@ -74,3 +76,2018 @@ mod tests {
 		}
 	}
 }
+
+#[cfg(test)]
+mod recipe_import {
+	use crate::{ Ingredient, parse_recipes };
+	use crate::import::parse_duration;
+
+	#[test]
+	fn json_ld_from_html() {
+		let html = r#"<html><head>
+			<script type="application/ld+json">
+				{
+					"@context": "https://schema.org",
+					"@graph": [
+						{ "@type": "WebPage", "name": "Not a recipe" },
+						{
+							"@type": ["Recipe"],
+							"name": "Garlic Pasta",
+							"image": [{ "@type": "ImageObject", "url": "https://example.com/pasta.png" }],
+							"totalTime": "PT1H5M",
+							"recipeIngredient": ["1 1/2 cups flour", "3 cloves garlic", "Salt to taste"],
+							"recipeInstructions": [
+								{
+									"@type": "HowToSection",
+									"itemListElement": [
+										{ "@type": "HowToStep", "text": "Boil the water" },
+										{ "@type": "HowToStep", "text": "Cook the pasta &amp; garlic" }
+									]
+								}
+							]
+						}
+					]
+				}
+			</script>
+		</head></html>"#;
+
+		let recipes = parse_recipes(html).unwrap();
+		assert_eq!(recipes.len(), 1);
+
+		let recipe = &recipes[0];
+		assert_eq!(recipe.name, "Garlic Pasta");
+		assert_eq!(recipe.minutes, Some(65));
+		assert_eq!(recipe.image_url.as_deref(), Some("https://example.com/pasta.png"));
+		assert_eq!(recipe.ingredients, vec![
+			Ingredient::new(String::from("flour"), Some(String::from("1 1/2 cups"))),
+			Ingredient::new(String::from("garlic"), Some(String::from("3 cloves"))),
+			Ingredient::new(String::from("Salt to taste"), None),
+		]);
+		assert_eq!(recipe.cooking_steps.len(), 2);
+		assert_eq!(recipe.cooking_steps[1].name, "Cook the pasta & garlic");
+	}
+
+	#[test]
+	fn paprika_export() {
+		let json = r#"{
+			"name": "Pancakes",
+			"ingredients": "2 eggs\n1 cup milk\n",
+			"directions": "Mix everything\n\nFry it",
+			"total_time": "1 hr 10 mins",
+			"image_url": "https://example.com/pancakes.png"
+		}"#;
+
+		let recipes = parse_recipes(json).unwrap();
+		assert_eq!(recipes[0].minutes, Some(70));
+		assert_eq!(recipes[0].ingredients[1], Ingredient::new(String::from("milk"), Some(String::from("1 cup"))));
+		assert_eq!(recipes[0].cooking_steps.len(), 2);
+	}
+
+	#[test]
+	fn mealie_export() {
+		let json = r#"[{
+			"name": "Soup",
+			"totalTime": "45 minutes",
+			"recipeIngredient": [
+				{ "quantity": 2, "unit": { "name": "cups" }, "food": { "name": "broth" } },
+				{ "note": "1 onion" }
+			],
+			"recipeInstructions": [{ "title": "", "text": "Simmer" }]
+		}]"#;
+
+		let recipes = parse_recipes(json).unwrap();
+		assert_eq!(recipes[0].minutes, Some(45));
+		assert_eq!(recipes[0].ingredients, vec![
+			Ingredient::new(String::from("broth"), Some(String::from("2 cups"))),
+			Ingredient::new(String::from("onion"), Some(String::from("1"))),
+		]);
+		assert_eq!(recipes[0].cooking_steps[0].name, "Simmer");
+	}
+
+	#[test]
+	fn durations() {
+		assert_eq!(parse_duration("PT1H5M"), Some(65));
+		assert_eq!(parse_duration("P1DT30M"), Some(24 * 60 + 30));
+		assert_eq!(parse_duration("1 hr 10 mins"), Some(70));
+		assert_eq!(parse_duration("25"), Some(25));
+		assert_eq!(parse_duration("PT0M"), None);
+
+		// numbers that do not fit are not a duration, instead of a panic
+		assert_eq!(parse_duration("P9999999D"), None);
+		assert_eq!(parse_duration("PT99999999999M"), None);
+		assert_eq!(parse_duration("80000000 hours"), None);
+	}
+
+	#[test]
+	fn reports_broken_json_ld() {
+		let html = r#"<script type="application/ld+json">{ "@type": "Recipe", </script>"#;
+		let error = parse_recipes(html).unwrap_err();
+		assert!(error.message.starts_with("Could not find a schema.org Recipe in the HTML file (could not parse JSON-LD block"));
+	}
+}
+
+#[cfg(test)]
+mod human_readable {
+	use crate::{ Date, IO, Item, Time, Weekday, recipe_to_human_readable, recipes_to_human_readable, todos_to_human_readable };
+
+	const TODO_LIST: &str = "- general thing
+@m9am-10:30am weekly standup
+- groceries
+
+7/30/22:
+- write tests
+@2pm-3pm dentist
+- lunch 12pm-1pm
+8/1/22:
+- something else";
+
+	const RECIPE_LIST: &str = "Garlic Pasta [https://example.com/pasta.png, 30]:
+- pasta [1 box]
+- garlic
+# mince garlic
+$ boil water [salted]
+$ cook pasta
+
+Toast:
+- bread [2 slices]
+$ toast bread
+";
+
+	fn parse(todo_list: &str, recipe_list: &str, name: &str) -> IO {
+		let temp_dir = std::env::temp_dir();
+		let todo_path = temp_dir.join(format!("bansheelong-{}-todo-list-{}", name, std::process::id())).to_string_lossy().into_owned();
+		let recipe_path = temp_dir.join(format!("bansheelong-{}-recipe-list-{}", name, std::process::id())).to_string_lossy().into_owned();
+		std::fs::write(&todo_path, todo_list).unwrap();
+		std::fs::write(&recipe_path, recipe_list).unwrap();
+
+		let mut io = IO::default();
+		if let Err(error) = io.parse_from_human_readable(todo_path, recipe_path) {
+			panic!("{:?}", error);
+		}
+
+		io
+	}
+
+	#[test]
+	fn round_trip() {
+		let io = parse(TODO_LIST, RECIPE_LIST, "first");
+
+		let todo_list = todos_to_human_readable(&io.todos_database);
+		let recipe_list = recipes_to_human_readable(&io.meals_database).unwrap();
+		assert_eq!(todo_list, TODO_LIST);
+
+		let reparsed = parse(&todo_list, &recipe_list, "second");
+		assert_eq!(reparsed.todos_database.mapping, io.todos_database.mapping);
+		assert_eq!(
+			serde_json::to_string(&reparsed.meals_database.recipes).unwrap(),
+			serde_json::to_string(&io.meals_database.recipes).unwrap()
+		);
+	}
+
+	#[test]
+	fn times_survive_round_trip() {
+		let mut io = IO::default();
+		let date = Some(Date {
+			day: 2,
+			month: 8,
+			year: 22,
+		});
+
+		let times = [
+			(0, 30, 1, 0, None),
+			(12, 0, 13, 15, Some(Weekday::Thursday)),
+			(23, 0, 24, 30, None),
+			(7, 5, 19, 45, Some(Weekday::Sunday)),
+		];
+
+		for (start_hour, start_minute, end_hour, end_minute, day) in times {
+			io.add_to_todos_database(Item {
+				description: String::from("- event"),
+				time: Some(Time {
+					day,
+					start_hour,
+					start_minute,
+					end_hour,
+					end_minute,
+				}),
+				..Item::default()
+			}, date).unwrap();
+		}
+
+		let reparsed = parse(&todos_to_human_readable(&io.todos_database), "", "times");
+		assert_eq!(reparsed.todos_database.mapping, io.todos_database.mapping);
+	}
+
+	#[test]
+	fn recipe_fields_survive_on_their_own() {
+		let io = parse("", "Soup [https://example.com/soup.png]:\n- broth\n\nStew [90]:\n- beef [1 lb]\n", "fields");
+		let find = |io: &IO, name: &str| io.meals_database.recipes.iter().find(|recipe| recipe.name == name).cloned().unwrap();
+
+		let soup = find(&io, "Soup");
+		assert_eq!((soup.image_url.as_deref(), soup.minutes), (Some("https://example.com/soup.png"), None));
+		let stew = find(&io, "Stew");
+		assert_eq!((stew.image_url.as_deref(), stew.minutes), (None, Some(90)));
+
+		let reparsed = parse("", &recipes_to_human_readable(&io.meals_database).unwrap(), "fields-again");
+		for name in ["Soup", "Stew"] {
+			let (recipe, reparsed) = (find(&io, name), find(&reparsed, name));
+			assert_eq!((recipe.image_url, recipe.minutes), (reparsed.image_url, reparsed.minutes));
+		}
+	}
+
+	#[test]
+	fn refuses_recipes_it_cannot_read_back() {
+		let mut recipe = parse("", RECIPE_LIST, "refuses").meals_database.recipes[0].clone();
+		assert!(recipe_to_human_readable(&recipe).is_ok());
+
+		recipe.name = String::from("Grandma's Pie");
+		let error = recipe_to_human_readable(&recipe).unwrap_err();
+		assert_eq!(error.message, "recipe 'Grandma's Pie' has a name or image url that the recipe list cannot hold");
+
+		recipe.name = String::from("Pie");
+		recipe.ingredients[0].quantity = Some(String::from("½ cup"));
+		assert!(recipe_to_human_readable(&recipe).unwrap_err().message.contains("an ingredient"));
+
+		// a picture named after a number would come back as the minutes
+		recipe.ingredients[0].quantity = None;
+		recipe.image_url = Some(String::from("45"));
+		recipe.minutes = None;
+		assert!(recipe_to_human_readable(&recipe).is_err());
+	}
+}
+
+#[cfg(test)]
+mod merge {
+	use crate::{
+		Day,
+		IO,
+		Ingredient,
+		Item,
+		MealsDatabase,
+		Recipe,
+		TodosDatabase,
+		get_first_sync_base,
+		keep_unwritable_recipes,
+		merge_human_readable,
+		merge_lines,
+		parse_recipes,
+		recipe_to_human_readable,
+	};
+
+	fn lines(text: &str) -> Vec<String> {
+		text.split('\n').map(str::to_string).collect()
+	}
+
+	#[test]
+	fn non_overlapping_edits() {
+		let base = lines("a\nb\nc\nd");
+		let local = lines("a\nB\nc\nd");
+		let remote = lines("a\nb\nc\nd\ne");
+
+		let (merged, conflicts) = merge_lines(&base, &local, &remote);
+		assert_eq!(merged, lines("a\nB\nc\nd\ne"));
+		assert_eq!(conflicts, 0);
+	}
+
+	#[test]
+	fn both_sides_add() {
+		let base = lines("a");
+		let local = lines("a\nb");
+		let remote = lines("a\nc");
+
+		let (merged, conflicts) = merge_lines(&base, &local, &remote);
+		assert_eq!(merged, lines("a\nb\nc"));
+		assert_eq!(conflicts, 0);
+	}
+
+	#[test]
+	fn conflicting_edits() {
+		let base = lines("a\nb\nc");
+		let local = lines("a\nlocal\nc");
+		let remote = lines("a\nremote\nc");
+
+		let (merged, conflicts) = merge_lines(&base, &local, &remote);
+		assert_eq!(merged, lines("a\nlocal\n!! conflict, server has: \"remote\"\nc"));
+		assert_eq!(conflicts, 1);
+
+		let (merged, conflicts) = merge_lines(&base, &local, &lines("a\nc"));
+		assert_eq!(merged, lines("a\nlocal\n!! conflict, server removed: \"b\"\nc"));
+		assert_eq!(conflicts, 1);
+	}
+
+	#[test]
+	fn first_sync_keeps_both_sides() {
+		let todos = |descriptions: &[&str]| {
+			let mut database = TodosDatabase::default();
+			database.mapping.insert(None, Day {
+				date: None,
+				items: descriptions.iter().map(|description| Item::from_description(description.to_string(), None)).collect(),
+			});
+			database
+		};
+
+		let meals = |names: &[&str]| MealsDatabase {
+			recipes: names.iter().map(|name| Recipe {
+				cooking_steps: Vec::new(),
+				image_url: None,
+				ingredients: vec![Ingredient {
+					name: String::from("water"),
+					quantity: None,
+				}],
+				minutes: None,
+				name: name.to_string(),
+				preparation_steps: Vec::new(),
+			}).collect(),
+			..MealsDatabase::default()
+		};
+
+		let local = (todos(&["- shared", "- mine"]), meals(&["Toast", "Stew"]));
+		let remote = (todos(&["- shared", "- theirs"]), meals(&["Toast", "Soup"]));
+
+		let base = get_first_sync_base((&local.0, &local.1), (&remote.0, &remote.1));
+		let result = merge_human_readable((&base.0, &base.1), (&local.0, &local.1), (&remote.0, &remote.1));
+
+		assert_eq!(result.conflicts, 0);
+		assert_eq!(result.todo_list, "- shared\n- mine\n- theirs");
+		assert_eq!(result.recipe_list.matches("- water").count(), 3);
+		assert!(result.recipe_list.contains("Stew:\n- water"));
+		assert!(result.recipe_list.contains("Soup:\n- water"));
+	}
+
+	#[test]
+	fn keeps_recipes_the_text_files_cannot_hold() {
+		let html = r#"<script type="application/ld+json">
+			{
+				"@context": "https://schema.org",
+				"@type": "Recipe",
+				"name": "Mom's Crème Brûlée",
+				"recipeIngredient": ["½ cup sugar", "4 egg yolks"],
+				"recipeInstructions": ["Torch the sugar"]
+			}
+		</script>"#;
+
+		let imported = parse_recipes(html).unwrap().remove(0);
+		assert!(recipe_to_human_readable(&imported).is_err());
+
+		let temp_dir = std::env::temp_dir();
+		let todo_path = temp_dir.join(format!("bansheelong-unwritable-todo-list-{}", std::process::id())).to_string_lossy().into_owned();
+		let recipe_path = temp_dir.join(format!("bansheelong-unwritable-recipe-list-{}", std::process::id())).to_string_lossy().into_owned();
+		std::fs::write(&todo_path, "- call mom").unwrap();
+		std::fs::write(&recipe_path, "Toast:\n- bread").unwrap();
+
+		let mut io = IO::default();
+		assert!(io.parse_from_human_readable(todo_path.clone(), recipe_path.clone()).unwrap().is_empty());
+
+		let local = (io.todos_database.clone(), io.meals_database.clone());
+		let mut remote = local.clone();
+		remote.1.recipes.push(imported.clone());
+
+		// the sync goes through, leaving the recipe out of the text files
+		let result = merge_human_readable((&local.0, &local.1), (&local.0, &local.1), (&remote.0, &remote.1));
+		assert_eq!(result.conflicts, 0);
+		assert_eq!(result.recipe_list, "Toast:\n- bread\n");
+
+		// and the database headed back to the server still has it
+		std::fs::write(&recipe_path, &result.recipe_list).unwrap();
+		io.parse_from_human_readable(todo_path, recipe_path).unwrap();
+		keep_unwritable_recipes(&mut io.meals_database, &remote.1);
+		assert_eq!(io.meals_database.recipes.len(), 2);
+		assert_eq!(io.meals_database.recipes[1], imported);
+
+		keep_unwritable_recipes(&mut io.meals_database, &remote.1);
+		assert_eq!(io.meals_database.recipes.len(), 2);
+	}
+}
+
+#[cfg(test)]
+mod diagnostics {
+	use crate::IO;
+
+	#[test]
+	fn reports_line_and_column() {
+		let temp_dir = std::env::temp_dir();
+		let todo_path = temp_dir.join(format!("bansheelong-diagnostics-todo-list-{}", std::process::id())).to_string_lossy().into_owned();
+		let recipe_path = temp_dir.join(format!("bansheelong-diagnostics-recipe-list-{}", std::process::id())).to_string_lossy().into_owned();
+		std::fs::write(&todo_path, "- fine\n900/1/22:\n- meeting 5pm-\n- other").unwrap();
+		std::fs::write(&recipe_path, "- orphan\nToast:\n- bread\nthis is wrong!\n$ toast bread").unwrap();
+
+		let mut io = IO::default();
+		let diagnostics = io.parse_from_human_readable(todo_path.clone(), recipe_path.clone()).unwrap();
+
+		let locations = diagnostics.iter()
+			.map(|diagnostic| (diagnostic.file.as_str(), diagnostic.line, diagnostic.column))
+			.collect::<Vec<(&str, usize, usize)>>();
+
+		assert_eq!(locations, vec![
+			(todo_path.as_str(), 2, 1),
+			(todo_path.as_str(), 3, 11),
+			(recipe_path.as_str(), 1, 1),
+			(recipe_path.as_str(), 4, 14),
+		]);
+
+		// the rest of the files still parse
+		assert_eq!(io.todos_database.mapping[&None].items.len(), 3);
+		assert_eq!(io.meals_database.recipes[0].ingredients.len(), 1);
+		assert_eq!(io.meals_database.recipes[0].cooking_steps.len(), 1);
+	}
+
+	#[test]
+	fn conflict_notes_are_not_todos() {
+		let temp_dir = std::env::temp_dir();
+		let todo_path = temp_dir.join(format!("bansheelong-notes-todo-list-{}", std::process::id())).to_string_lossy().into_owned();
+		let recipe_path = temp_dir.join(format!("bansheelong-notes-recipe-list-{}", std::process::id())).to_string_lossy().into_owned();
+		std::fs::write(&todo_path, "- mine\n!! conflict, server has: \"- theirs\"").unwrap();
+		std::fs::write(&recipe_path, "Toast:\n- bread\n!! conflict, server removed: \"- jam\"\nGrandma's - pie:").unwrap();
+
+		let mut io = IO::default();
+		let diagnostics = io.parse_from_human_readable(todo_path, recipe_path).unwrap();
+		assert_eq!(diagnostics.iter().map(|diagnostic| diagnostic.line).collect::<Vec<usize>>(), vec![2, 3, 4]);
+
+		assert_eq!(io.todos_database.mapping[&None].items.len(), 1);
+		assert_eq!(io.meals_database.recipes.len(), 1);
+		assert_eq!(io.meals_database.recipes[0].ingredients.len(), 1);
+	}
+}
+
+#[cfg(test)]
+mod time_syntax {
+	use crate::{ Time, TimeError, Weekday, parse_time, time_to_human_readable };
+
+	fn time(day: Option<Weekday>, start_hour: u8, start_minute: u8, end_hour: u8, end_minute: u8) -> Option<Time> {
+		Some(Time {
+			day,
+			start_hour,
+			start_minute,
+			end_hour,
+			end_minute,
+		})
+	}
+
+	#[test]
+	fn parses() {
+		let table = [
+			// the old syntax
+			("9am-10:30am standup", 8, time(None, 9, 0, 10, 30)),
+			("m9am-10am", 8, time(Some(Weekday::Monday), 9, 0, 10, 0)),
+			("lunch 12-1", 8, time(None, 12, 0, 13, 0)),
+			("3-4", 8, time(None, 15, 0, 16, 0)),
+			("9-5", 8, time(None, 9, 0, 17, 0)),
+			("2pm+1", 8, time(None, 14, 0, 15, 0)),
+			("2pm+1:30", 8, time(None, 14, 0, 15, 30)),
+			("2pm+:45", 8, time(None, 14, 0, 14, 45)),
+			("no time here", 8, None),
+			("call 555-1234", 8, None),
+
+			// configurable am/pm guessing
+			("7-8", 8, time(None, 19, 0, 20, 0)),
+			("7-8", 6, time(None, 7, 0, 8, 0)),
+			("3-4", 0, time(None, 3, 0, 4, 0)),
+
+			// guessing from the other half of the range
+			("9-10pm", 8, time(None, 21, 0, 22, 0)),
+			("11-1pm", 8, time(None, 11, 0, 13, 0)),
+			("9am-5", 8, time(None, 9, 0, 17, 0)),
+			("9pm-11", 8, time(None, 21, 0, 23, 0)),
+
+			// 24 hour times
+			("13:00-14:30", 8, time(None, 13, 0, 14, 30)),
+			("07:00-08:00", 8, time(None, 7, 0, 8, 0)),
+			("0:30-1", 8, time(None, 0, 30, 1, 0)),
+
+			// noon and midnight
+			("noon-1pm", 8, time(None, 12, 0, 13, 0)),
+			("10am-noon", 8, time(None, 10, 0, 12, 0)),
+			("10pm-midnight", 8, time(None, 22, 0, 24, 0)),
+			("midnight-2am", 8, time(None, 0, 0, 2, 0)),
+
+			// durations
+			("2pm+1h30m", 8, time(None, 14, 0, 15, 30)),
+			("2pm+90min", 8, time(None, 14, 0, 15, 30)),
+			("2pm+45m", 8, time(None, 14, 0, 14, 45)),
+			("noon+2h", 8, time(None, 12, 0, 14, 0)),
+
+			// all day
+			("allday holiday", 8, Some(Time::all_day(None))),
+			("sall-day", 8, Some(Time::all_day(Some(Weekday::Saturday)))),
+
+			// crossing midnight
+			("10pm-2am", 8, time(None, 22, 0, 26, 0)),
+			("11pm-12", 8, time(None, 23, 0, 24, 0)),
+			("11pm+3h", 8, time(None, 23, 0, 26, 0)),
+		];
+
+		for (line, pm_before_hour, expected) in table {
+			assert_eq!(parse_time(line, pm_before_hour), Ok(expected), "{}", line);
+		}
+	}
+
+	#[test]
+	fn rejects() {
+		let table = [
+			("5pm-", TimeError::NeededEndHours),
+			("5pm+", TimeError::NeededEndTime),
+			("5pm+1pm", TimeError::InvalidEndAmPm),
+			("5pm-5pm", TimeError::InvalidTimeRange),
+			("5pm+30h", TimeError::InvalidTimeRange),
+			("13pm-2pm", TimeError::BadStartHours),
+			("1pm-2:75pm", TimeError::BadEndMinutes),
+			("25:00-26:00", TimeError::BadStartHours),
+			("9+99999999h", TimeError::BadDuration),
+			("9+4294967295m", TimeError::BadDuration),
+		];
+
+		for (line, expected) in table {
+			assert_eq!(parse_time(line, 8), Err(expected), "{}", line);
+		}
+	}
+
+	#[test]
+	fn writes_parseable_times() {
+		let table = [
+			time(None, 9, 0, 10, 30),
+			time(Some(Weekday::Thursday), 0, 0, 1, 0),
+			time(None, 22, 0, 26, 0),
+			time(None, 9, 0, 33, 0),
+			Some(Time::all_day(Some(Weekday::Friday))),
+		];
+
+		for time in table {
+			let string = time_to_human_readable(&time.unwrap());
+			assert_eq!(parse_time(&string, 8), Ok(time), "{}", string);
+		}
+	}
+}
+
+#[cfg(test)]
+mod markup {
+	use crate::{ Date, Day, Item, Priority };
+
+	#[test]
+	fn parses_priority_tags_and_due_date() {
+		let item = Item::from_description(String::from("! pay rent #bills #Home due:8/1/22"), None);
+		assert_eq!(item.priority, Priority::Urgent);
+		assert_eq!(item.tags, vec![String::from("bills"), String::from("home")]);
+		assert_eq!(item.due, Some(Date {
+			day: 1,
+			month: 8,
+			year: 22,
+		}));
+		assert_eq!(item.get_text(), "pay rent #bills #Home due:8/1/22");
+
+		let item = Item::from_description(String::from("zoo trip"), None);
+		assert_eq!(item.priority, Priority::Normal);
+		assert_eq!(item.get_text(), "zoo trip");
+
+		assert_eq!(Item::from_description(String::from("z nap"), None).priority, Priority::Low);
+		assert_eq!(Item::from_description(String::from("% laundry"), None).priority, Priority::High);
+	}
+
+	#[test]
+	fn sorts_between_separators() {
+		let day = Day {
+			date: None,
+			items: ["z a", "! b", "- c", "", "- d", "% e"].iter()
+				.map(|description| Item::from_description(description.to_string(), None))
+				.collect(),
+		};
+
+		let order = day.items_by_priority().iter()
+			.map(|item| item.description.as_str())
+			.collect::<Vec<&str>>();
+
+		assert_eq!(order, vec!["! b", "- c", "z a", "", "% e", "- d"]);
+	}
+}
+
+#[cfg(test)]
+mod subtasks {
+	use crate::{ IO, TodoCompletion, todos_to_human_readable };
+
+	const TODO_LIST: &str = "- move apartment
+	- [x] pack kitchen
+	- [ ] pack bedroom
+		- clothes
+		- [x] bed
+	- call movers
+
+- unrelated
+7/30/22:
+- nested
+	- child";
+
+	fn parse(todo_list: &str, name: &str) -> IO {
+		let temp_dir = std::env::temp_dir();
+		let todo_path = temp_dir.join(format!("bansheelong-{}-todo-list-{}", name, std::process::id())).to_string_lossy().into_owned();
+		let recipe_path = temp_dir.join(format!("bansheelong-{}-recipe-list-{}", name, std::process::id())).to_string_lossy().into_owned();
+		std::fs::write(&todo_path, todo_list).unwrap();
+		std::fs::write(&recipe_path, "").unwrap();
+
+		let mut io = IO::default();
+		io.parse_from_human_readable(todo_path, recipe_path).unwrap();
+		io
+	}
+
+	#[test]
+	fn parses_children() {
+		let io = parse(TODO_LIST, "subtasks");
+
+		let items = &io.todos_database.mapping[&None].items;
+		assert_eq!(items.len(), 3);
+		assert_eq!(items[0].children.len(), 3);
+		assert_eq!(items[0].children[1].children.len(), 2);
+		assert_eq!(items[0].progress(), (1, 3));
+		assert!(items[0].children[0].completed);
+		assert_eq!(items[0].children[0].get_text(), "pack kitchen");
+		assert_eq!(items[2].description, "- unrelated");
+
+		assert_eq!(todos_to_human_readable(&io.todos_database), TODO_LIST);
+	}
+
+	#[test]
+	fn completes_children() {
+		let mut io = parse(TODO_LIST, "complete-subtasks");
+
+		let completion = |path: &[&str]| TodoCompletion {
+			completed: true,
+			date: None,
+			path: path.iter().map(|description| description.to_string()).collect(),
+		};
+
+		io.complete_todo(completion(&["- move apartment", "pack bedroom", "clothes"])).unwrap();
+		io.complete_todo(completion(&["move apartment", "- call movers"])).unwrap();
+		assert!(io.complete_todo(completion(&["- move apartment", "missing"])).is_err());
+
+		let item = &io.todos_database.mapping[&None].items[0];
+		assert_eq!(item.children[1].children[0].description, "- [x] clothes");
+		assert_eq!(item.progress(), (3, 3));
+		assert!(item.is_done());
+	}
+}
+
+#[cfg(test)]
+mod rollover {
+	use crate::{ Date, Day, Item, RolloverMode, Time, TodosDatabase };
+
+	fn date(day: u8) -> Date {
+		Date {
+			day,
+			month: 8,
+			year: 22,
+		}
+	}
+
+	fn setup() -> TodosDatabase {
+		let mut database = TodosDatabase::default();
+
+		let event = Item::from_description(String::from("- dentist"), Some(Time {
+			day: None,
+			start_hour: 14,
+			start_minute: 0,
+			end_hour: 15,
+			end_minute: 0,
+		}));
+
+		let mut chores = Item::from_description(String::from("- chores"), None);
+		chores.children.push(Item::from_description(String::from("- [x] dishes"), None));
+
+		database.mapping.insert(Some(date(1)), Day {
+			date: Some(date(1)),
+			items: vec![
+				Item::from_description(String::from("- pay rent"), None),
+				Item::default(),
+				event,
+				chores,
+			],
+		});
+
+		database.mapping.insert(Some(date(3)), Day {
+			date: Some(date(3)),
+			items: vec![Item::from_description(String::from("- today's thing"), None)],
+		});
+
+		database.mapping.insert(None, Day {
+			date: None,
+			items: vec![Item::from_description(String::from("- general"), None)],
+		});
+
+		database
+	}
+
+	#[test]
+	fn moves_to_today() {
+		let mut database = setup();
+		assert_eq!(database.rollover(date(3), RolloverMode::Today), 1);
+
+		assert!(!database.mapping.contains_key(&Some(date(1))));
+		assert_eq!(database.mapping[&None].items.len(), 1);
+
+		let descriptions = database.mapping[&Some(date(3))].items.iter()
+			.map(|item| item.description.as_str())
+			.collect::<Vec<&str>>();
+		assert_eq!(descriptions, vec!["- today's thing", "- pay rent"]);
+
+		assert_eq!(database.archive.len(), 1);
+		assert_eq!(database.archive[0].items.len(), 2);
+
+		// nothing left to do the second time around
+		assert_eq!(database.rollover(date(3), RolloverMode::Today), 0);
+	}
+
+	#[test]
+	fn groups_overdue() {
+		let mut database = setup();
+		assert_eq!(database.rollover(date(3), RolloverMode::Overdue), 1);
+
+		let items = &database.mapping[&Some(date(3))].items;
+		assert_eq!(items[0].description, "- pay rent due:8/1/22");
+		assert_eq!(items[0].due, Some(date(1)));
+		assert_eq!(items[1].description, "");
+		assert_eq!(items[2].description, "- today's thing");
+	}
+
+	#[test]
+	fn off() {
+		let mut database = setup();
+		assert_eq!(database.rollover(date(3), RolloverMode::Off), 0);
+		assert!(database.mapping.contains_key(&Some(date(1))));
+	}
+}
+
+#[cfg(test)]
+mod ical {
+	use crate::{ Date, Day, IO, Item, MealsDatabase, Priority, Time, TodosDatabase, Weekday, item_to_ical, item_uid, parse_ical, parse_ical_item, to_ical };
+
+	fn date(day: u8) -> Date {
+		Date {
+			day,
+			month: 8,
+			year: 22,
+		}
+	}
+
+	fn time(day: Option<Weekday>, start_hour: u8, end_hour: u8) -> Time {
+		Time {
+			day,
+			start_hour,
+			start_minute: 30,
+			end_hour,
+			end_minute: 0,
+		}
+	}
+
+	fn setup() -> TodosDatabase {
+		let mut database = TodosDatabase::default();
+		database.mapping.insert(Some(date(1)), Day {
+			date: Some(date(1)),
+			items: vec![
+				Item::from_description(String::from("- dentist, then groceries"), Some(time(None, 14, 15))),
+				Item::from_description(String::from("- late shift"), Some(time(None, 22, 25))),
+				Item::from_description(String::from("- [x] pay rent"), None),
+			],
+		});
+
+		database.mapping.insert(None, Day {
+			date: None,
+			items: vec![
+				Item::from_description(String::from("- gym"), Some(time(Some(Weekday::Wednesday), 7, 8))),
+			],
+		});
+
+		database
+	}
+
+	#[test]
+	fn exports() {
+		// 8/1/22 was a monday
+		let calendar = to_ical(&setup(), &MealsDatabase::default(), date(1));
+		let lines = calendar.split("\r\n").collect::<Vec<&str>>();
+
+		assert_eq!(lines[0], "BEGIN:VCALENDAR");
+		assert!(lines.contains(&"SUMMARY:dentist\\, then groceries"));
+		assert!(lines.contains(&"DTSTART:20220801T143000"));
+		assert!(lines.contains(&"DTEND:20220802T010000"));
+		assert!(lines.contains(&"STATUS:COMPLETED"));
+		assert!(lines.contains(&"DUE;VALUE=DATE:20220801"));
+		assert!(lines.contains(&"DTSTART:20220803T073000"));
+		assert!(lines.contains(&"RRULE:FREQ=WEEKLY;BYDAY=WE"));
+		assert!(lines.iter().all(|line| line.len() <= 75));
+	}
+
+	#[test]
+	fn round_trip() {
+		let calendar = to_ical(&setup(), &MealsDatabase::default(), date(1));
+		let days = parse_ical(&calendar).unwrap();
+
+		// todos are not events, so they do not come back
+		assert_eq!(days.len(), 2);
+		assert_eq!(days[0].date, None);
+		assert_eq!(days[0].items[0].time, Some(time(Some(Weekday::Wednesday), 7, 8)));
+
+		assert_eq!(days[1].date, Some(date(1)));
+		assert_eq!(days[1].items.len(), 2);
+		assert_eq!(days[1].items[0].description, "- dentist, then groceries");
+		assert_eq!(days[1].items[1].time, Some(time(None, 22, 25)));
+	}
+
+	#[test]
+	fn imports_other_calendars() {
+		let calendar = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART;TZID=America/New_York:20220805T090000\r\n\
+			DURATION:PT1H30M\r\nSUMMARY:stand\r\n up\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20220806\r\n\
+			SUMMARY:trip\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+
+		let days = parse_ical(calendar).unwrap();
+		assert_eq!(days[0].items[0].description, "- standup");
+		assert_eq!(days[0].items[0].time.unwrap().end_hour, 10);
+		assert_eq!(days[0].items[0].time.unwrap().end_minute, 30);
+		assert!(days[1].items[0].time.unwrap().is_all_day());
+
+		assert!(parse_ical("not a calendar").is_err());
+	}
+
+	#[test]
+	fn edits_single_items() {
+		let mut io = IO {
+			todos_database: setup(),
+			..IO::default()
+		};
+
+		let mut todo = Item::from_description(String::from("% water plants #home due:8/5/22"), None);
+		todo.children.push(Item::from_description(String::from("- the fern too"), None));
+		io.add_to_todos_database(todo.clone(), None).unwrap();
+
+		let uid = item_uid(&None, &todo);
+		let calendar = item_to_ical(&None, &todo, date(1)).replace("NEEDS-ACTION", "COMPLETED");
+
+		// the due date reads back as the day the todo is on
+		let (due, completed) = parse_ical_item(&calendar).unwrap();
+		assert_eq!(due, Some(date(5)));
+		assert_eq!(completed.description, "- [x] water plants #home due:8/5/22");
+		assert_eq!(completed.tags, vec![String::from("home")]);
+
+		// completing it from a calendar app keeps its uid, and everything the calendar does not know about
+		assert!(!io.put_ical_item(&uid, due, completed).unwrap());
+		let (date_none, stored) = io.todos_database.find_by_uid(&uid).unwrap();
+		assert_eq!(date_none, None);
+		assert!(stored.is_done());
+		assert_eq!(stored.description, "% [x] water plants #home due:8/5/22");
+		assert_eq!(stored.priority, Priority::High);
+		assert_eq!(stored.due, Some(date(5)));
+		assert_eq!(stored.children.len(), 1);
+		assert_eq!(item_uid(&date_none, stored), uid);
+
+		// unknown uids are new items, and keep the uid the client picked
+		assert!(io.put_ical_item("from-the-phone", Some(date(2)), Item::from_description(String::from("- call mom"), None)).unwrap());
+		assert!(io.todos_database.find_by_uid("from-the-phone").is_some());
+		assert!(!io.put_ical_item("from-the-phone", Some(date(2)), Item::from_description(String::from("- call dad"), None)).unwrap());
+		assert_eq!(io.todos_database.find_by_uid("from-the-phone").unwrap().1.description, "- call dad");
+
+		assert!(io.delete_ical_item("from-the-phone").unwrap());
+		assert!(!io.todos_database.mapping.contains_key(&Some(date(2))));
+		assert!(!io.delete_ical_item("from-the-phone").unwrap());
+	}
+
+	#[test]
+	fn keeps_uids_through_the_text_files() {
+		let mut remote = setup();
+		remote.mapping.get_mut(&None).unwrap().items[0].uid = Some(String::from("from-the-phone"));
+
+		let mut local = setup();
+		local.keep_uids_from(&remote);
+		assert_eq!(item_uid(&None, &local.mapping[&None].items[0]), "from-the-phone");
+		assert_eq!(local.mapping[&Some(date(1))].items[0].uid, None);
+	}
+
+	#[test]
+	fn uids_are_stable() {
+		let todo = Item::from_description(String::from("- water plants #home"), None);
+		assert_eq!(item_uid(&Some(date(2)), &todo), "570b3bff32ae2c82@bansheelong");
+		assert_eq!(item_uid(&None, &todo), "3076162c6932840a@bansheelong");
+	}
+}
+
+#[cfg(test)]
+mod scheduling {
+	use crate::{ Date, Day, Item, Time, TodosDatabase, Weekday };
+
+	// a monday
+	fn date(day: u8) -> Date {
+		Date {
+			day,
+			month: 8,
+			year: 22,
+		}
+	}
+
+	fn time(day: Option<Weekday>, start_hour: u8, end_hour: u8) -> Time {
+		Time {
+			day,
+			start_hour,
+			start_minute: 0,
+			end_hour,
+			end_minute: 0,
+		}
+	}
+
+	fn item(description: &str, time: Time) -> Item {
+		Item::from_description(String::from(description), Some(time))
+	}
+
+	fn setup() -> TodosDatabase {
+		let mut database = TodosDatabase::default();
+		database.mapping.insert(Some(date(1)), Day {
+			date: Some(date(1)),
+			items: vec![
+				item("- meeting", time(None, 9, 11)),
+				item("- lunch", time(None, 10, 12)),
+				item("- dinner", time(None, 18, 19)),
+				item("- late shift", time(None, 22, 26)),
+				item("- holiday", Time::all_day(None)),
+			],
+		});
+
+		database.mapping.insert(None, Day {
+			date: None,
+			items: vec![
+				item("- standup", time(Some(Weekday::Monday), 9, 10)),
+				item("- gym", time(Some(Weekday::Tuesday), 7, 8)),
+			],
+		});
+
+		database
+	}
+
+	#[test]
+	fn finds_conflicts() {
+		let database = setup();
+		let conflicts = database.get_conflicts(date(1)).iter()
+			.map(|(item, other)| (item.description.as_str(), other.description.as_str()))
+			.collect::<Vec<(&str, &str)>>();
+
+		assert_eq!(conflicts, vec![
+			("- standup", "- meeting"),
+			("- meeting", "- lunch"),
+		]);
+	}
+
+	#[test]
+	fn lays_out_side_by_side() {
+		let database = setup();
+		let layout = database.schedule(date(1)).iter()
+			.map(|scheduled| (scheduled.item.description.as_str(), scheduled.column, scheduled.columns))
+			.collect::<Vec<(&str, usize, usize)>>();
+
+		assert_eq!(layout, vec![
+			("- standup", 0, 2),
+			("- meeting", 1, 2),
+			("- lunch", 0, 2),
+			("- dinner", 0, 1),
+			("- late shift", 0, 1),
+		]);
+
+		// the late shift carries over into tuesday morning
+		let tuesday = database.schedule(date(2));
+		assert_eq!(tuesday[0].item.description, "- late shift");
+		assert_eq!(tuesday[0].time, time(None, 0, 2));
+		assert_eq!(tuesday[1].item.description, "- gym");
+	}
+
+	#[test]
+	fn free_busy() {
+		let free_busy = setup().free_busy(date(1), 8, 20);
+		assert_eq!(free_busy.busy, vec![time(None, 9, 12), time(None, 18, 19)]);
+		assert_eq!(free_busy.free, vec![time(None, 8, 9), time(None, 12, 18), time(None, 19, 20)]);
+		assert_eq!(free_busy.conflicts.len(), 2);
+	}
+}
+
+#[cfg(test)]
+mod reminders {
+	use chrono::{ NaiveDate, NaiveDateTime };
+
+	use crate::{ Date, Day, Item, Time, TodosDatabase, Weekday };
+
+	fn time(day: Option<Weekday>, start_hour: u8, start_minute: u8, end_hour: u8) -> Time {
+		Time {
+			day,
+			start_hour,
+			start_minute,
+			end_hour,
+			end_minute: 0,
+		}
+	}
+
+	fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+		NaiveDate::from_ymd(2022, 8, day).and_hms(hour, minute, 0)
+	}
+
+	fn setup() -> TodosDatabase {
+		let date = Date {
+			day: 1,
+			month: 8,
+			year: 22,
+		};
+
+		let mut database = TodosDatabase::default();
+		database.mapping.insert(Some(date), Day {
+			date: Some(date),
+			items: vec![
+				Item::from_description(String::from("! dentist"), Some(time(None, 14, 30, 15))),
+				Item::from_description(String::from("- late shift"), Some(time(None, 23, 0, 26))),
+			],
+		});
+
+		// 8/2/22 was a tuesday
+		database.mapping.insert(None, Day {
+			date: None,
+			items: vec![
+				Item::from_description(String::from("- gym"), Some(time(Some(Weekday::Tuesday), 0, 10, 1))),
+			],
+		});
+
+		database
+	}
+
+	#[test]
+	fn fires_once_per_lead_time() {
+		let database = setup();
+
+		let reminders = database.get_reminders(at(1, 13, 55), at(1, 14, 5), &[10, 30]);
+		assert_eq!(reminders.len(), 1);
+		assert_eq!(reminders[0].description, "dentist");
+		assert_eq!(reminders[0].lead_minutes, 30);
+		assert_eq!(reminders[0].get_text(), "dentist in 30 minutes");
+
+		let reminders = database.get_reminders(at(1, 14, 5), at(1, 14, 20), &[10, 30]);
+		assert_eq!(reminders[0].lead_minutes, 10);
+		assert!(database.get_reminders(at(1, 14, 20), at(1, 14, 40), &[10, 30]).is_empty());
+	}
+
+	#[test]
+	fn looks_past_midnight() {
+		let database = setup();
+
+		// the recurring gym session early tuesday, but not the late shift spilling into it
+		let reminders = database.get_reminders(at(1, 23, 30), at(2, 0, 5), &[10]);
+		assert_eq!(reminders.len(), 1);
+		assert_eq!(reminders[0].description, "gym");
+		assert_eq!(reminders[0].date.day, 2);
+	}
+}
+
+#[cfg(test)]
+mod tracking {
+	use chrono::{ NaiveDate, NaiveDateTime };
+
+	use crate::{ Date, Day, Dirty, IO, Item, Time, TodosDatabase, TrackingEvent };
+
+	fn date(day: u8) -> Date {
+		Date {
+			day,
+			month: 8,
+			year: 22,
+		}
+	}
+
+	fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+		NaiveDate::from_ymd(2022, 8, day).and_hms(hour, minute, 0)
+	}
+
+	fn setup() -> TodosDatabase {
+		let mut database = TodosDatabase::default();
+		database.mapping.insert(Some(date(1)), Day {
+			date: Some(date(1)),
+			items: vec![
+				Item::from_description(String::from("- write report"), Some(Time {
+					day: None,
+					start_hour: 9,
+					start_minute: 0,
+					end_hour: 11,
+					end_minute: 0,
+				})),
+			],
+		});
+
+		database
+	}
+
+	#[test]
+	fn starting_stops_the_running_session() {
+		let mut database = setup();
+
+		database.track(&TrackingEvent::start(String::from("- write report"), at(1, 9, 15)));
+		database.track(&TrackingEvent::start(String::from("- email"), at(1, 10, 0)));
+		assert_eq!(database.sessions[0].end, Some(10 * 60));
+		assert_eq!(database.get_running_session().unwrap().description, "- email");
+
+		assert!(database.track(&TrackingEvent::stop(at(1, 10, 30))));
+		assert!(database.get_running_session().is_none());
+		assert!(!database.track(&TrackingEvent::stop(at(1, 10, 45))));
+	}
+
+	#[test]
+	fn stopping_twice_is_fine() {
+		let mut io = IO {
+			todos_database: setup(),
+			..IO::default()
+		};
+
+		io.track(TrackingEvent::start(String::from("- write report"), at(1, 9, 15))).unwrap();
+		io.track(TrackingEvent::stop(at(1, 10, 0))).unwrap();
+
+		io.dirty = Dirty::None;
+		io.track(TrackingEvent::stop(at(1, 10, 30))).unwrap();
+		assert!(matches!(io.dirty, Dirty::None));
+		assert_eq!(io.todos_database.sessions[0].end, Some(10 * 60));
+	}
+
+	#[test]
+	fn cuts_sessions_to_the_day() {
+		let mut database = setup();
+
+		database.track(&TrackingEvent::start(String::from("- write report"), at(1, 23, 0)));
+
+		// still running, so it ends now
+		let sessions = database.get_sessions(date(2), at(2, 1, 30));
+		assert_eq!(sessions.len(), 1);
+		assert_eq!((sessions[0].1.start_minutes(), sessions[0].1.end_minutes()), (0, 90));
+
+		database.track(&TrackingEvent::stop(at(2, 2, 0)));
+		assert_eq!(database.sessions[0].end, Some(26 * 60));
+
+		let sessions = database.get_sessions(date(1), at(2, 3, 0));
+		assert_eq!((sessions[0].1.start_minutes(), sessions[0].1.end_minutes()), (23 * 60, 24 * 60));
+	}
+
+	#[test]
+	fn totals_planned_against_tracked() {
+		let mut database = setup();
+
+		database.track(&TrackingEvent::start(String::from("- write report"), at(1, 9, 30)));
+		database.track(&TrackingEvent::start(String::from("- email"), at(1, 12, 0)));
+		database.track(&TrackingEvent::stop(at(1, 12, 20)));
+
+		// the week before has nothing in it
+		assert!(database.get_weekly_totals(date(1).add_days(-7), at(1, 13, 0)).is_empty());
+
+		let totals = database.get_weekly_totals(date(1), at(1, 13, 0));
+		assert_eq!(totals.len(), 2);
+		assert_eq!(totals[0].description, "- email");
+		assert_eq!((totals[0].planned_minutes, totals[0].tracked_minutes), (0, 20));
+		assert_eq!(totals[1].description, "- write report");
+		assert_eq!((totals[1].planned_minutes, totals[1].tracked_minutes), (120, 150));
+	}
+}
+
+#[cfg(test)]
+mod storage {
+	use rand::{ Rng, SeedableRng };
+	use rand::rngs::StdRng;
+
+	use crate::{
+		BackupKind,
+		JobStatusFlags,
+		StorageAlert,
+		StorageCommand,
+		StorageCommandRequest,
+		StorageCommandResponse,
+		StorageFrame,
+		StorageProtocolError,
+		StorageStatus,
+		StorageTarget,
+		StorageTargetKind,
+		decode_storage_frame,
+		encode_storage_command,
+		encode_storage_command_response,
+		encode_storage_hello,
+		encode_storage_status,
+		get_storage_frame_length,
+		parse_storage_targets,
+	};
+
+	fn status() -> StorageStatus {
+		StorageStatus {
+			job_flags: JobStatusFlags::DOWNLOADING_DAILY | JobStatusFlags::ZPOOL_SCRUBBING,
+			used_size: 1_200_000_000_000,
+			total_size: 4_000_000_000_000,
+			btrfs_used_size: 300_000_000,
+			btrfs_total_size: 1_000_000_000,
+			btrfs_backup_count: 12,
+			dailies: 7,
+			weeklies: 4,
+			pools: Vec::new(),
+			jobs: Vec::new(),
+			restores: Vec::new(),
+			alerts: Vec::new(),
+			smart: Vec::new(),
+			usage: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn round_trip() {
+		let frame = encode_storage_status(&status());
+		assert_eq!(decode_storage_frame(&frame), Ok(Some((StorageFrame::Status(status()), frame.len()))));
+
+		let hello = encode_storage_hello();
+		assert_eq!(decode_storage_frame(&hello), Ok(Some((StorageFrame::Hello, hello.len()))));
+
+		// frames that arrive back to back are read one at a time
+		let mut buffer = hello.clone();
+		buffer.extend_from_slice(&frame);
+		let (_, used) = decode_storage_frame(&buffer).unwrap().unwrap();
+		assert_eq!(decode_storage_frame(&buffer[used..]).unwrap().unwrap().0, StorageFrame::Status(status()));
+	}
+
+	#[test]
+	fn waits_for_the_whole_frame() {
+		let frame = encode_storage_status(&status());
+		for length in 0..frame.len() {
+			assert_eq!(decode_storage_frame(&frame[..length]), Ok(None));
+		}
+	}
+
+	#[test]
+	fn skips_fields_it_does_not_know() {
+		let frame = encode_storage_status(&status());
+
+		// a newer server with a number and some bytes we have never heard of
+		let mut payload = frame[7..].to_vec();
+		payload.extend_from_slice(&[200, 0, 0, 0, 0, 0, 0, 0, 0, 42]);
+		payload.extend_from_slice(&[201, 1, 0, 0, 0, 3, b'a', b'b', b'c']);
+
+		let mut newer = frame[..3].to_vec();
+		newer.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+		newer.extend_from_slice(&payload);
+
+		assert_eq!(decode_storage_frame(&newer).unwrap().unwrap().0, StorageFrame::Status(status()));
+
+		let mut unsupported = frame.clone();
+		unsupported[2] = 2;
+		assert_eq!(decode_storage_frame(&unsupported), Err(StorageProtocolError::UnsupportedVersion(2)));
+		assert_eq!(decode_storage_frame(b"0 1 2 3 4 5 6 7\n"), Err(StorageProtocolError::BadMagic));
+	}
+
+	#[test]
+	fn finds_frame_boundaries() {
+		let frame = encode_storage_status(&status());
+		let mut buffer = encode_storage_hello();
+		buffer.extend_from_slice(&frame);
+
+		// a frame that has only started arriving is not an error
+		assert_eq!(decode_storage_frame(&buffer[..1]), Ok(None));
+
+		let (hello, used) = decode_storage_frame(&buffer).unwrap().unwrap();
+		assert_eq!(hello, StorageFrame::Hello);
+		assert_eq!(decode_storage_frame(&buffer[used..]).unwrap().unwrap().0, StorageFrame::Status(status()));
+
+		let mut unsupported = frame.clone();
+		unsupported[2] = 2;
+		assert_eq!(get_storage_frame_length(&unsupported), Some(frame.len()));
+		assert_eq!(get_storage_frame_length(b"0 1 2 3 4 5 6 7\n"), None);
+	}
+
+	#[test]
+	fn reads_targets() {
+		let targets = parse_storage_targets("pool:bansheerubber, mount:/,btrfs:/bansheebtrfs/,").unwrap();
+		assert_eq!(targets.iter().map(|target| target.kind).collect::<Vec<StorageTargetKind>>(), [
+			StorageTargetKind::Pool,
+			StorageTargetKind::Mount,
+			StorageTargetKind::Btrfs,
+		]);
+		assert_eq!(targets.iter().map(|target| target.get_name()).collect::<Vec<&str>>(), ["bansheerubber", "/", "/bansheebtrfs"]);
+		assert_eq!(targets[2].path, "/bansheebtrfs/");
+
+		assert_eq!(parse_storage_targets(""), Ok(Vec::new()));
+		assert_eq!(parse_storage_targets("pool:tank,disk:/"), Err(String::from("could not read storage target 'disk:/'")));
+		assert!(StorageTarget::parse("mount:").is_none());
+		assert!(StorageTarget::parse("/mnt").is_none());
+	}
+
+	#[test]
+	fn reads_legacy_lines() {
+		let line = status().to_legacy_line();
+		assert_eq!(line, "2050 1200000000000 4000000000000 300000000 1000000000 12 7 4\n");
+		assert_eq!(StorageStatus::from_legacy_line(line.trim_end()), Some(status()));
+		assert_eq!(StorageStatus::from_legacy_line("1 2 3"), None);
+		assert_eq!(StorageStatus::from_legacy_line("1 2 3 4 5 6 7 eight"), None);
+	}
+
+	#[test]
+	fn fuzz() {
+		let mut rng = StdRng::seed_from_u64(41);
+		let frame = encode_storage_status(&status());
+
+		// random garbage, and valid frames with bytes flipped or cut off, must never panic
+		for _ in 0..10_000 {
+			let mut buffer = if rng.gen_bool(0.5) {
+				(0..rng.gen_range(0, 128)).map(|_| rng.gen()).collect::<Vec<u8>>()
+			} else {
+				frame.clone()
+			};
+
+			for _ in 0..rng.gen_range(0, 4) {
+				if !buffer.is_empty() {
+					let index = rng.gen_range(0, buffer.len());
+					buffer[index] = rng.gen();
+				}
+			}
+
+			buffer.truncate(rng.gen_range(0, buffer.len() + 1));
+
+			if let Ok(Some((_, used))) = decode_storage_frame(&buffer) {
+				assert!(used <= buffer.len());
+			}
+		}
+	}
+
+	#[test]
+	fn commands() {
+		for command in [
+			StorageCommand::AcknowledgeAlert(String::from("usage")),
+			StorageCommand::RefreshStatus,
+			StorageCommand::RunBackup(BackupKind::Weekly),
+			StorageCommand::StartScrub(String::from("bansheerubber")),
+		] {
+			let request = StorageCommandRequest {
+				command,
+				id: 3,
+				secret: String::from("hunter2"),
+			};
+
+			let frame = encode_storage_command(&request);
+			assert_eq!(decode_storage_frame(&frame), Ok(Some((StorageFrame::Command(request), frame.len()))));
+		}
+
+		for result in [Ok(String::from("started scrubbing bansheerubber")), Err(String::from("not authorized"))] {
+			let response = StorageCommandResponse {
+				id: 3,
+				result,
+			};
+
+			let frame = encode_storage_command_response(&response);
+			assert_eq!(decode_storage_frame(&frame).unwrap().unwrap().0, StorageFrame::CommandResponse(response));
+		}
+
+		// alerts ride along with the status
+		let status = StorageStatus {
+			alerts: vec![StorageAlert {
+				acknowledged: true,
+				key: String::from("usage"),
+				message: String::from("pool is 95% full, over the 90% limit"),
+			}],
+			..status()
+		};
+
+		let frame = encode_storage_status(&status);
+		assert_eq!(decode_storage_frame(&frame).unwrap().unwrap().0, StorageFrame::Status(status));
+	}
+}
+
+#[cfg(test)]
+mod zpool {
+	use chrono::{ Local, NaiveDate, TimeZone };
+
+	use crate::{ StorageFrame, StorageStatus, decode_storage_frame, encode_storage_status, parse_zpool_number, parse_zpool_status };
+
+	const FINISHED_SCRUB: &str = include_str!("../fixtures/zpool-mock1");
+	const RUNNING_SCRUB: &str = include_str!("../fixtures/zpool-mock2");
+
+	#[test]
+	fn parses_the_device_tree() {
+		let pools = parse_zpool_status(FINISHED_SCRUB);
+		assert_eq!(pools.len(), 1);
+
+		let pool = &pools[0];
+		assert_eq!(pool.name, "bansheerubber");
+		assert_eq!(pool.state, "ONLINE");
+		assert_eq!(pool.errors, "No known data errors");
+		assert!(!pool.has_data_errors());
+
+		assert_eq!(pool.devices.len(), 1);
+		assert_eq!(pool.devices[0].name, "bansheerubber");
+		assert_eq!(pool.devices[0].children[0].name, "raidz2-0");
+		assert_eq!(pool.get_drives().len(), 8);
+
+		let failing = pool.get_failing_drives();
+		assert_eq!(failing.iter().map(|drive| drive.get_serial()).collect::<Vec<&str>>(), ["ZFL36PYH", "ZFL370EW", "ZK203SFZ"]);
+		assert_eq!((failing[2].read_errors, failing[2].write_errors, failing[2].checksum_errors), (Some(42), Some(202), Some(0)));
+	}
+
+	#[test]
+	fn parses_scrubs() {
+		let scan = parse_zpool_status(FINISHED_SCRUB)[0].scan.clone().unwrap();
+		assert_eq!(scan.kind, "scrub");
+		assert!(!scan.in_progress);
+		assert_eq!(scan.repaired_bytes, parse_zpool_number("5.39M"));
+		assert_eq!(scan.errors, Some(0));
+		assert_eq!(scan.percent_done, None);
+
+		let finished_at = Local.from_local_datetime(&NaiveDate::from_ymd(2022, 7, 13).and_hms(2, 8, 51)).unwrap();
+		assert_eq!(scan.finished_at, Some(finished_at.timestamp()));
+
+		// tabs instead of spaces, and a note after the counts
+		let pool = &parse_zpool_status(RUNNING_SCRUB)[0];
+		let scan = pool.scan.clone().unwrap();
+		assert!(scan.in_progress);
+		assert_eq!(scan.percent_done, Some(8.44));
+		assert_eq!(scan.time_remaining.as_deref(), Some("03:29:08"));
+		assert_eq!(scan.finished_at, None);
+		assert_eq!(scan.repaired_bytes, Some(2_118_123));
+
+		let failing = pool.get_failing_drives();
+		assert_eq!(failing.len(), 1);
+		assert_eq!(failing[0].checksum_errors, Some(231));
+		assert_eq!(failing[0].note.as_deref(), Some("(repairing)"));
+	}
+
+	#[test]
+	fn reads_bad_drives_and_counts() {
+		let output = FINISHED_SCRUB
+			.replace("ZK203MKK  ONLINE       0     0     0", "ZK203MKK  FAULTED      0     0     0")
+			.replace("ZK203LK8  ONLINE       0     0     0", "ZK203LK8  ONLINE       0     ?     0")
+			.replace("No known data errors", "2 data errors, use '-v' for a list");
+
+		let pool = &parse_zpool_status(&output)[0];
+		assert!(pool.has_data_errors());
+
+		let drives = pool.get_drives();
+		assert!(drives[5].has_unreadable_counts());
+		assert_eq!(drives[6].state, "FAULTED");
+		assert!(!drives[6].is_healthy());
+
+		assert_eq!(parse_zpool_number("1.5K"), Some(1536));
+		assert_eq!(parse_zpool_number("12"), Some(12));
+		assert_eq!(parse_zpool_number("-"), None);
+	}
+
+	#[test]
+	fn sends_pools_to_clients() {
+		let status = StorageStatus {
+			pools: parse_zpool_status(RUNNING_SCRUB),
+			..StorageStatus::default()
+		};
+
+		let frame = encode_storage_status(&status);
+		assert_eq!(decode_storage_frame(&frame).unwrap().unwrap().0, StorageFrame::Status(status));
+	}
+}
+
+#[cfg(test)]
+mod storage_history {
+	use crate::{
+		StorageFrame,
+		StorageHistory,
+		StorageHistoryQuery,
+		StorageSample,
+		StorageStatus,
+		StorageTarget,
+		StorageTargetKind,
+		StorageUsage,
+		decode_storage_frame,
+		encode_storage_history,
+		encode_storage_history_query,
+		parse_zpool_status,
+	};
+
+	const HOUR: i64 = 60 * 60;
+	const DAY: i64 = 24 * HOUR;
+	const TERABYTE: u64 = 1_000_000_000_000;
+
+	fn sample(time: i64, used_size: u64) -> StorageSample {
+		StorageSample {
+			time,
+			total_size: 4 * TERABYTE,
+			used_size,
+			..StorageSample::default()
+		}
+	}
+
+	// a sample every five minutes for the number of days, using 10GB more every day
+	fn history(days: i64) -> StorageHistory {
+		let mut history = StorageHistory::default();
+		for time in (0..days * DAY).step_by(5 * 60) {
+			history.record(sample(time, TERABYTE + (time as u64 * 10_000_000_000) / DAY as u64));
+		}
+
+		history
+	}
+
+	#[test]
+	fn downsamples_old_samples() {
+		let history = history(40);
+		let latest = history.get_latest().unwrap().time;
+
+		// minutes only go back a day, hours a month
+		assert_eq!(history.minutes.len(), 24 * 12 + 1);
+		assert!(history.minutes.iter().all(|sample| sample.time >= latest - DAY));
+		assert_eq!(history.hours.len(), 30 * 24 + 1);
+		assert_eq!(history.days.len(), 40);
+
+		// every bucket keeps its last sample
+		assert_eq!(history.days[0].time, DAY - 5 * 60);
+		assert_eq!(history.days[39].time, latest);
+
+		// samples from before the latest one are dropped
+		let mut late = history.clone();
+		late.record(sample(0, 0));
+		assert_eq!(late, history);
+	}
+
+	#[test]
+	fn queries_the_finest_tier_that_fits() {
+		let history = history(40);
+		let latest = history.get_latest().unwrap().time;
+
+		let month = history.query(&StorageHistoryQuery {
+			from: latest - 30 * DAY,
+			resolution: DAY,
+			to: latest,
+		});
+		assert_eq!(month.len(), 31);
+		assert_eq!(month.last().unwrap().time, latest);
+
+		let hour = history.query(&StorageHistoryQuery {
+			from: latest - HOUR,
+			resolution: 60,
+			to: latest,
+		});
+		assert_eq!(hour.len(), 13);
+		assert!(hour.windows(2).all(|pair| pair[1].time - pair[0].time == 5 * 60));
+
+		// only the daily tier goes back this far
+		let year = history.query(&StorageHistoryQuery {
+			from: latest - 365 * DAY,
+			resolution: HOUR,
+			to: latest,
+		});
+		assert_eq!(year.len(), 40);
+
+		assert!(StorageHistory::default().query(&StorageHistoryQuery { from: 0, resolution: 60, to: DAY }).is_empty());
+	}
+
+	#[test]
+	fn predicts_when_the_pool_fills() {
+		// 3TB left at 10GB a day
+		let history = history(40);
+		let latest = history.get_latest().unwrap();
+		let expected = (latest.total_size - latest.used_size) as f64 / 10_000_000_000.0;
+		assert!((history.get_days_until_full().unwrap() - expected).abs() < 1.0);
+
+		// a pool that is not filling up never fills
+		let mut flat = StorageHistory::default();
+		for time in (0..10 * DAY).step_by(HOUR as usize) {
+			flat.record(sample(time, TERABYTE));
+		}
+		assert_eq!(flat.get_days_until_full(), None);
+		assert_eq!(StorageHistory::default().get_days_until_full(), None);
+	}
+
+	#[test]
+	fn samples_device_errors() {
+		let output = include_str!("../fixtures/zpool-mock1")
+			.replace("ZK203MKK  ONLINE       0     0     0", "ZK203MKK  ONLINE       3     1     2");
+
+		let status = StorageStatus {
+			pools: parse_zpool_status(&output),
+			..StorageStatus::default()
+		};
+
+		let sample = StorageSample::from_status(&status, 0).unwrap();
+		assert_eq!(sample.device_errors.len(), 8);
+		assert_eq!(sample.device_errors["ZK203MKK"], 6);
+		assert_eq!(sample.device_errors["ZFL370EW"], 119);
+		assert_eq!(sample.device_errors["ZFL36QCV"], 0);
+		assert_eq!(sample.device_errors.values().sum::<u64>(), 373);
+	}
+
+	#[test]
+	fn skips_sizes_it_could_not_read() {
+		let usage = |kind: StorageTargetKind, error: Option<&str>| StorageUsage {
+			error: error.map(str::to_string),
+			target: StorageTarget {
+				kind,
+				path: String::from("/"),
+			},
+			total_size: 0,
+			used_size: 0,
+		};
+
+		let mut status = StorageStatus {
+			usage: vec![usage(StorageTargetKind::Pool, None), usage(StorageTargetKind::Mount, Some("df failed"))],
+			..StorageStatus::default()
+		};
+		assert!(StorageSample::from_status(&status, 0).is_some());
+
+		status.usage.push(usage(StorageTargetKind::Btrfs, Some("btrfs failed")));
+		assert!(StorageSample::from_status(&status, 0).is_none());
+	}
+
+	#[test]
+	fn sends_queries_and_replies() {
+		let history = history(2);
+		let query = StorageHistoryQuery {
+			from: 0,
+			resolution: HOUR,
+			to: 2 * DAY,
+		};
+
+		let frame = encode_storage_history_query(&query);
+		assert_eq!(decode_storage_frame(&frame).unwrap().unwrap().0, StorageFrame::HistoryQuery(query));
+
+		let reply = history.reply(&query);
+		assert_eq!(reply.samples.len(), 48);
+
+		let frame = encode_storage_history(&reply);
+		assert_eq!(decode_storage_frame(&frame).unwrap().unwrap().0, StorageFrame::History(reply));
+	}
+
+	#[test]
+	fn writes_and_reads_back() {
+		let path = std::env::temp_dir().join(format!("bansheelong-storage-history-{}", std::process::id()));
+		let path = path.to_str().unwrap();
+
+		let history = history(3);
+		history.write(path).unwrap();
+		assert_eq!(StorageHistory::read(path).unwrap(), history);
+
+		std::fs::remove_file(path).unwrap();
+		assert!(StorageHistory::read(path).is_err());
+	}
+}
+
+#[cfg(test)]
+mod snapshot {
+	use chrono::{ Local, NaiveDate, TimeZone };
+
+	use crate::{
+		Restore,
+		RestoreRequest,
+		RestoreState,
+		SnapshotChange,
+		SnapshotDiff,
+		SnapshotDiffQuery,
+		SnapshotSource,
+		StorageFrame,
+		StorageStatus,
+		decode_storage_frame,
+		diff_file_lists,
+		encode_storage_restore_request,
+		encode_storage_snapshot_diff,
+		encode_storage_snapshot_diff_query,
+		encode_storage_snapshots,
+		encode_storage_snapshots_query,
+		encode_storage_status,
+		is_safe_relative_path,
+		parse_btrfs_snapshots,
+		parse_file_list,
+		parse_zfs_snapshots,
+	};
+
+	const BTRFS_SNAPSHOTS: &str = include_str!("../fixtures/snapshot-btrfs-snapshots");
+	const BTRFS_QGROUPS: &str = include_str!("../fixtures/snapshot-btrfs-qgroups");
+	const ZFS_SNAPSHOTS: &str = include_str!("../fixtures/snapshot-zfs-snapshots");
+	const ZFS_MOUNTPOINTS: &str = include_str!("../fixtures/snapshot-zfs-mountpoints");
+	const FIND_FROM: &str = include_str!("../fixtures/snapshot-find-from");
+	const FIND_TO: &str = include_str!("../fixtures/snapshot-find-to");
+
+	#[test]
+	fn zfs_snapshots() {
+		let snapshots = parse_zfs_snapshots(ZFS_SNAPSHOTS, ZFS_MOUNTPOINTS);
+		assert_eq!(
+			snapshots.iter().map(|snapshot| snapshot.name.as_str()).collect::<Vec<&str>>(),
+			vec![
+				"bansheerubber/scratch@manual",
+				"bansheerubber/media@weekly-2022-09-11",
+				"bansheerubber/home@daily-2022-09-12",
+				"bansheerubber/home@daily-2022-09-13",
+			]
+		);
+
+		// datasets without a mountpoint cannot be browsed
+		assert_eq!(snapshots[0].path, "");
+		assert_eq!(snapshots[1].path, "/bansheerubber/media/.zfs/snapshot/weekly-2022-09-11");
+		assert_eq!(snapshots[3].created_at, 1663052400);
+		assert_eq!(snapshots[3].size, Some(3145728));
+		assert!(snapshots.iter().all(|snapshot| snapshot.source == SnapshotSource::Zfs));
+
+		assert!(parse_zfs_snapshots("", ZFS_MOUNTPOINTS).is_empty());
+		assert!(parse_zfs_snapshots("garbage\tline\n", "").is_empty());
+	}
+
+	#[test]
+	fn btrfs_snapshots() {
+		let snapshots = parse_btrfs_snapshots(BTRFS_SNAPSHOTS, BTRFS_QGROUPS, "/btrfs/");
+		assert_eq!(snapshots.len(), 3);
+		assert_eq!(snapshots[0].name, "home_backup.20220911");
+		assert_eq!(snapshots[0].path, "/btrfs/home_backup.20220911");
+		assert_eq!(snapshots[2].size, Some(1073741824));
+		assert!(snapshots.iter().all(|snapshot| snapshot.source == SnapshotSource::Btrfs));
+
+		// otime is in local time
+		let created_at = NaiveDate::from_ymd(2022, 9, 11).and_hms(3, 0, 2);
+		assert_eq!(snapshots[0].created_at, Local.from_local_datetime(&created_at).unwrap().timestamp());
+
+		// quotas being off leaves out sizes, not snapshots
+		let snapshots = parse_btrfs_snapshots(BTRFS_SNAPSHOTS, "", "/btrfs");
+		assert_eq!(snapshots.len(), 3);
+		assert!(snapshots.iter().all(|snapshot| snapshot.size.is_none()));
+		assert_eq!(snapshots[1].path, "/btrfs/home_backup.20220912");
+	}
+
+	#[test]
+	fn file_lists() {
+		let files = parse_file_list(FIND_TO);
+		assert_eq!(files.len(), 4);
+		assert_eq!(files[0].path, "notes/todo.md");
+		assert_eq!(files[0].size, 1390);
+		assert_eq!(files[0].modified, 1662990000);
+		assert_eq!(files[3].path, "photos/dog with spaces.jpg");
+
+		assert_eq!(
+			diff_file_lists(&parse_file_list(FIND_FROM), &files),
+			vec![
+				(SnapshotChange::Changed, String::from("notes/todo.md")),
+				(SnapshotChange::Removed, String::from("old.txt")),
+				(SnapshotChange::Added, String::from("photos/dog with spaces.jpg")),
+			]
+		);
+
+		assert!(diff_file_lists(&files, &files).is_empty());
+	}
+
+	#[test]
+	fn safe_paths() {
+		assert!(is_safe_relative_path(""));
+		assert!(is_safe_relative_path("notes/todo.md"));
+		assert!(is_safe_relative_path("photos/dog with spaces.jpg"));
+		assert!(!is_safe_relative_path("/etc/passwd"));
+		assert!(!is_safe_relative_path("../etc"));
+		assert!(!is_safe_relative_path("notes/../../etc"));
+		assert!(!is_safe_relative_path("notes/./todo.md"));
+		assert!(!is_safe_relative_path("notes\0"));
+	}
+
+	#[test]
+	fn round_trip() {
+		let query = encode_storage_snapshots_query();
+		assert_eq!(decode_storage_frame(&query), Ok(Some((StorageFrame::SnapshotsQuery, query.len()))));
+
+		let snapshots = parse_zfs_snapshots(ZFS_SNAPSHOTS, ZFS_MOUNTPOINTS);
+		let frame = encode_storage_snapshots(&snapshots);
+		assert_eq!(decode_storage_frame(&frame), Ok(Some((StorageFrame::Snapshots(snapshots), frame.len()))));
+
+		let diff_query = SnapshotDiffQuery {
+			from: String::from("home_backup.20220911"),
+			path: String::from("notes"),
+			secret: String::from("hunter2"),
+			to: String::from("home_backup.20220913"),
+		};
+
+		let frame = encode_storage_snapshot_diff_query(&diff_query);
+		assert_eq!(decode_storage_frame(&frame).unwrap().unwrap().0, StorageFrame::SnapshotDiffQuery(diff_query.clone()));
+
+		let diff = SnapshotDiff {
+			changes: diff_file_lists(&parse_file_list(FIND_FROM), &parse_file_list(FIND_TO)),
+			error: None,
+			query: diff_query,
+			truncated: false,
+		};
+
+		let frame = encode_storage_snapshot_diff(&diff);
+		assert_eq!(decode_storage_frame(&frame).unwrap().unwrap().0, StorageFrame::SnapshotDiff(diff));
+
+		let request = RestoreRequest {
+			path: String::from("notes/todo.md"),
+			secret: String::from("hunter2"),
+			snapshot: String::from("home_backup.20220912"),
+		};
+
+		let frame = encode_storage_restore_request(&request);
+		assert_eq!(decode_storage_frame(&frame).unwrap().unwrap().0, StorageFrame::RestoreRequest(request.clone()));
+
+		// restores ride along with the status
+		let status = StorageStatus {
+			restores: vec![Restore {
+				destination: String::from("/staging/0-home_backup.20220912/notes/todo.md"),
+				id: 0,
+				message: None,
+				request,
+				state: RestoreState::Running,
+			}],
+			..StorageStatus::default()
+		};
+
+		let frame = encode_storage_status(&status);
+		assert_eq!(decode_storage_frame(&frame).unwrap().unwrap().0, StorageFrame::Status(status));
+	}
+}
+
+#[cfg(test)]
+mod smart {
+	use crate::{
+		SmartDrive,
+		SmartSelfTest,
+		StorageFrame,
+		StorageStatus,
+		decode_storage_frame,
+		encode_storage_status,
+		parse_smartctl,
+		parse_zpool_status,
+	};
+
+	const ATA_HEALTHY: &str = include_str!("../fixtures/smartctl-ata-healthy");
+	const ATA_FAILING: &str = include_str!("../fixtures/smartctl-ata-failing");
+	const NVME: &str = include_str!("../fixtures/smartctl-nvme");
+	const STANDBY: &str = include_str!("../fixtures/smartctl-standby");
+	const FINISHED_SCRUB: &str = include_str!("../fixtures/zpool-mock1");
+
+	#[test]
+	fn parses_ata_drives() {
+		let drive = parse_smartctl(ATA_HEALTHY, 1663020000).unwrap();
+		assert_eq!(drive, SmartDrive {
+			checked_at: 1663020000,
+			device: String::from("/dev/disk/by-id/ata-ST2000DM008-2FR102_ZFL36QCV"),
+			model: Some(String::from("ST2000DM008-2FR102")),
+			passed: Some(true),
+			pending_sectors: Some(0),
+			power_on_hours: Some(13542),
+			reallocated_sectors: Some(0),
+			self_test: Some(SmartSelfTest {
+				hours: Some(13530),
+				kind: String::from("Short offline"),
+				passed: true,
+				status: String::from("Completed without error"),
+			}),
+			serial: String::from("ZFL36QCV"),
+			temperature: Some(34),
+		});
+		assert!(!drive.is_failing());
+		assert_eq!(drive.get_bad_sectors(), 0);
+	}
+
+	#[test]
+	fn parses_failing_drives() {
+		// smartctl sets exit bits for a failing drive, which still has everything in it
+		let drive = parse_smartctl(ATA_FAILING, 1663020000).unwrap();
+		assert_eq!(drive.serial, "ZFL370EW");
+		assert_eq!(drive.passed, Some(false));
+		assert_eq!((drive.reallocated_sectors, drive.pending_sectors), (Some(2984), Some(136)));
+		assert_eq!(drive.get_bad_sectors(), 3120);
+		assert_eq!((drive.temperature, drive.power_on_hours), (Some(53), Some(20311)));
+
+		let test = drive.self_test.clone().unwrap();
+		assert_eq!((test.passed, test.status.as_str(), test.hours), (false, "Completed: read failure", Some(20300)));
+		assert!(drive.is_failing());
+	}
+
+	#[test]
+	fn parses_nvme_drives() {
+		let drive = parse_smartctl(NVME, 1663020000).unwrap();
+		assert_eq!(drive.serial, "S4EWNX0R123456A");
+		assert_eq!(drive.device, "/dev/nvme0n1");
+		assert_eq!((drive.temperature, drive.power_on_hours), (Some(41), Some(8123)));
+
+		// nvme has no sector counts to speak of
+		assert_eq!((drive.reallocated_sectors, drive.pending_sectors), (None, None));
+
+		let test = drive.self_test.clone().unwrap();
+		assert_eq!((test.kind.as_str(), test.passed), ("Short", true));
+		assert!(!drive.is_failing());
+	}
+
+	#[test]
+	fn reports_drives_it_cannot_read() {
+		assert_eq!(parse_smartctl(STANDBY, 1663020000), Err(String::from("Device is in STANDBY mode, exit(2)")));
+		assert!(parse_smartctl("smartctl: command not found", 1663020000).is_err());
+	}
+
+	#[test]
+	fn finds_pool_members() {
+		let pools = parse_zpool_status(FINISHED_SCRUB);
+		let drives = pools[0].get_drives();
+		assert_eq!(drives[0].get_device_path(), "/dev/disk/by-id/ata-ST2000DM008-2FR102_ZFL36PYH");
+
+		let pools = parse_zpool_status(
+			&FINISHED_SCRUB
+				.replace("ata-ST2000DM008-2FR102_ZFL36PYH ", "ata-ST2000DM008-2FR102_ZFL36PYH-part1")
+				.replace("ata-ST2000DM008-2FR102_ZFL36QCV ", "sdb                            ")
+				.replace("ata-ST2000DM008-2FR102_ZFL36YRL ", "/dev/sdc1                      ")
+		);
+		let paths = pools[0].get_drives().iter().map(|drive| drive.get_device_path()).collect::<Vec<String>>();
+		assert_eq!(paths[..3], [
+			String::from("/dev/disk/by-id/ata-ST2000DM008-2FR102_ZFL36PYH"),
+			String::from("/dev/sdb"),
+			String::from("/dev/sdc1"),
+		]);
+	}
+
+	#[test]
+	fn rides_along_with_the_status() {
+		let status = StorageStatus {
+			smart: vec![parse_smartctl(ATA_FAILING, 1663020000).unwrap(), parse_smartctl(NVME, 1663020000).unwrap()],
+			..StorageStatus::default()
+		};
+
+		let frame = encode_storage_status(&status);
+		assert_eq!(decode_storage_frame(&frame).unwrap().unwrap().0, StorageFrame::Status(status));
+	}
+}
+
+#[cfg(test)]
+mod history {
+	use crate::{ Date, Dirty, IO, MealStatus, MealsDatabase, PlannedMeal, Recipe };
+
+	fn date(day: u8) -> Date {
+		Date {
+			day,
+			month: 8,
+			year: 22,
+		}
+	}
+
+	fn recipe(name: &str) -> Recipe {
+		Recipe {
+			cooking_steps: Vec::new(),
+			ingredients: Vec::new(),
+			image_url: None,
+			minutes: None,
+			name: String::from(name),
+			preparation_steps: Vec::new(),
+		}
+	}
+
+	fn meal(day: u8, name: &str, status: MealStatus) -> PlannedMeal {
+		PlannedMeal {
+			status,
+			..PlannedMeal::new(date(day), recipe(name))
+		}
+	}
+
+	fn setup() -> MealsDatabase {
+		let mut database = MealsDatabase::default();
+		for meal in [
+			meal(5, "Chili", MealStatus::Planned),
+			meal(2, "Pad Thai", MealStatus::Skipped),
+			meal(8, "Chili", MealStatus::Leftovers(3)),
+			meal(9, "Soup", MealStatus::Planned),
+			meal(12, "Pad Thai", MealStatus::Planned),
+		] {
+			database.planned_meal_mapping.insert(meal.date, meal);
+		}
+
+		database
+	}
+
+	#[test]
+	fn archives_past_meals() {
+		let mut database = setup();
+		assert_eq!(database.archive_planned_meals(date(10)), 3);
+
+		// leftovers cover the days after they were cooked, so chili on the 8th stays until the 11th
+		assert_eq!(database.planned_meal_mapping.keys().copied().collect::<Vec<Date>>(), [date(8), date(12)]);
+		assert_eq!(
+			database.history.iter().map(|meal| (meal.date, meal.status)).collect::<Vec<(Date, MealStatus)>>(),
+			[(date(2), MealStatus::Skipped), (date(5), MealStatus::Cooked), (date(9), MealStatus::Cooked)]
+		);
+
+		// archiving again has nothing to do
+		assert_eq!(database.archive_planned_meals(date(10)), 0);
+		assert_eq!(database.archive_planned_meals(date(12)), 1);
+		assert_eq!(database.history.len(), 4);
+		assert_eq!((database.history[2].date, database.history[2].status), (date(8), MealStatus::Leftovers(3)));
+	}
+
+	#[test]
+	fn marks_the_io_dirty() {
+		let mut io = IO {
+			dirty: Dirty::None,
+			meals_database: setup(),
+			..IO::default()
+		};
+
+		assert_eq!(io.archive_planned_meals(date(1)), 0);
+		assert!(matches!(io.dirty, Dirty::None));
+		assert_eq!(io.archive_planned_meals(date(10)), 3);
+		assert!(matches!(io.dirty, Dirty::Write));
+	}
+
+	#[test]
+	fn counts_cooked_meals() {
+		let mut database = setup();
+		database.archive_planned_meals(date(31));
+
+		assert_eq!(database.times_cooked("chili"), 2);
+		assert_eq!(database.times_cooked("Pad Thai"), 1); // skipped ones do not count
+		assert_eq!(database.times_cooked("Lasagna"), 0);
+
+		assert_eq!(database.last_cooked("CHILI"), Some(date(8)));
+		assert_eq!(database.last_cooked("Lasagna"), None);
+
+		assert_eq!(database.most_cooked_recipe(), Some((String::from("Chili"), 2)));
+		assert_eq!(MealsDatabase::default().most_cooked_recipe(), None);
+
+		// ties go to whichever name comes first
+		database.history.push(meal(20, "Soup", MealStatus::Cooked));
+		assert_eq!(database.most_cooked_recipe(), Some((String::from("Chili"), 2)));
+	}
+}
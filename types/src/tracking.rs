@@ -0,0 +1,150 @@
+use chrono::{ NaiveDateTime, Timelike };
+use serde::{ Serialize, Deserialize };
+use std::collections::BTreeMap;
+
+use crate::{ Date, Dirty, Error, IO, Time, TodosDatabase, TodosTrackingLog, TrackingEvent, TrackingSession };
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+// planned time comes from the time sheet, tracked time from the sessions. both are in minutes
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WeeklyTotal {
+	pub description: String,
+	pub planned_minutes: u32,
+	pub tracked_minutes: u32,
+}
+
+// minutes from midnight on the first date to the minute on the second, or None if the second date comes first
+fn minutes_since(date: Date, other: Date, minute: u32) -> Option<u32> {
+	let days = (other.to_naive()? - date.to_naive()?).num_days();
+	if days < 0 {
+		None
+	} else {
+		Some(days as u32 * MINUTES_PER_DAY + minute)
+	}
+}
+
+impl TrackingEvent {
+	pub fn start(description: String, now: NaiveDateTime) -> Self {
+		TrackingEvent::Start {
+			date: Date::from_naive(now.date()),
+			description,
+			minute: now.hour() * 60 + now.minute(),
+		}
+	}
+
+	pub fn stop(now: NaiveDateTime) -> Self {
+		TrackingEvent::Stop {
+			date: Date::from_naive(now.date()),
+			minute: now.hour() * 60 + now.minute(),
+		}
+	}
+}
+
+impl TrackingSession {
+	// the part of the session that falls on the date. running sessions are drawn up until now
+	pub fn get_time(&self, date: Date, now: NaiveDateTime) -> Option<Time> {
+		let end = match self.end {
+			Some(end) => end,
+			None => minutes_since(self.date, Date::from_naive(now.date()), now.hour() * 60 + now.minute())?,
+		};
+
+		let day_start = minutes_since(self.date, date, 0)?;
+		let (start, end) = (self.start.max(day_start), end.min(day_start + MINUTES_PER_DAY));
+		if start >= end {
+			return None;
+		}
+
+		Some(Time::from_minutes(None, start - day_start, end - day_start))
+	}
+}
+
+impl TodosDatabase {
+	pub fn get_running_session(&self) -> Option<&TrackingSession> {
+		self.sessions.iter().rev().find(|session| session.end.is_none())
+	}
+
+	// returns false if there was nothing to stop
+	pub fn track(&mut self, event: &TrackingEvent) -> bool {
+		let (date, minute) = match event {
+			TrackingEvent::Start { date, minute, .. } => (*date, *minute),
+			TrackingEvent::Stop { date, minute } => (*date, *minute),
+		};
+
+		let mut stopped = false;
+		for session in self.sessions.iter_mut().filter(|session| session.end.is_none()) {
+			let end = minutes_since(session.date, date, minute).unwrap_or(session.start);
+			session.end = Some(end.max(session.start));
+			stopped = true;
+		}
+
+		match event {
+			TrackingEvent::Start { description, .. } => {
+				self.sessions.push(TrackingSession {
+					date,
+					description: description.clone(),
+					end: None,
+					start: minute,
+				});
+
+				true
+			},
+			TrackingEvent::Stop { .. } => stopped,
+		}
+	}
+
+	// every session that takes up part of the date, cut to the date, in start order
+	pub fn get_sessions(&self, date: Date, now: NaiveDateTime) -> Vec<(&TrackingSession, Time)> {
+		let mut sessions = self.sessions.iter()
+			.filter_map(|session| session.get_time(date, now).map(|time| (session, time)))
+			.collect::<Vec<(&TrackingSession, Time)>>();
+
+		sessions.sort_by_key(|(_, time)| (time.start_minutes(), time.end_minutes()));
+		sessions
+	}
+
+	// planned against tracked time for every item that had either in the seven days starting at week_start
+	pub fn get_weekly_totals(&self, week_start: Date, now: NaiveDateTime) -> Vec<WeeklyTotal> {
+		let mut totals: BTreeMap<String, WeeklyTotal> = BTreeMap::new();
+		for offset in 0..7 {
+			let date = week_start.add_days(offset);
+			for (item, time) in self.get_timed_items(date) {
+				let total = totals.entry(item.description.clone()).or_insert_with(|| WeeklyTotal {
+					description: item.description.clone(),
+					..WeeklyTotal::default()
+				});
+
+				total.planned_minutes += time.end_minutes() - time.start_minutes();
+			}
+
+			for (session, time) in self.get_sessions(date, now) {
+				let total = totals.entry(session.description.clone()).or_insert_with(|| WeeklyTotal {
+					description: session.description.clone(),
+					..WeeklyTotal::default()
+				});
+
+				total.tracked_minutes += time.end_minutes() - time.start_minutes();
+			}
+		}
+
+		totals.into_values().collect()
+	}
+}
+
+impl IO {
+	// stopping with nothing running does nothing, since clients that were offline can send a stop another client
+	// already made
+	pub fn track(&mut self, event: TrackingEvent) -> Result<&TodosDatabase, Error> {
+		if self.todos_database.track(&event) {
+			self.dirty = Dirty::Write;
+		}
+
+		Ok(&self.todos_database)
+	}
+
+	pub fn track_log(&self, event: TrackingEvent) -> TodosTrackingLog {
+		let mut log = self.todos_tracking_log.clone();
+		log.push(event);
+		log
+	}
+}
@@ -2,6 +2,7 @@ use std::collections::{ BTreeMap, HashMap };
 use std::cmp::Ordering;
 use std::string::ToString;
 
+use chrono::{ Datelike, Duration, Local, NaiveDate };
 use serde::{ Serialize, Deserialize };
 use serde_with::serde_as;
 
@@ -25,6 +26,22 @@ pub struct Time {
 	pub end_minute: u8,
 }
 
+impl Time {
+	pub fn all_day(day: Option<Weekday>) -> Time {
+		Time {
+			day,
+			start_hour: 0,
+			start_minute: 0,
+			end_hour: 24,
+			end_minute: 0,
+		}
+	}
+
+	pub fn is_all_day(&self) -> bool {
+		self.start_hour == 0 && self.start_minute == 0 && self.end_hour == 24 && self.end_minute == 0
+	}
+}
+
 impl Ord for Time {
 	fn cmp(&self, other: &Self) -> Ordering {
 		if self.start_hour < other.start_hour {
@@ -47,10 +64,32 @@ impl PartialOrd for Time {
 	}
 }
 
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+// ordered from least to most important. parsed from the first character of an item's description
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Priority {
+	Low, // z
+	#[default]
+	Normal, // -
+	High, // %
+	Urgent, // !
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Item {
+	#[serde(default)]
+	pub children: Vec<Item>,
+	#[serde(default)]
+	pub completed: bool,
 	pub description: String,
+	#[serde(default)]
+	pub due: Option<Date>,
+	#[serde(default)]
+	pub priority: Priority,
+	#[serde(default)]
+	pub tags: Vec<String>,
 	pub time: Option<Time>,
+	#[serde(default)]
+	pub uid: Option<String>, // set once a calendar app has addressed the item, so its href stays put
 }
 
 impl Ord for Item {
@@ -92,10 +131,7 @@ impl Item {
 			None
 		};
 		
-		Item {
-			description: parameters.get("description").unwrap().to_string(),
-			time,
-		}
+		Item::from_description(parameters.get("description").unwrap().to_string(), time)
 	}
 }
 
@@ -144,6 +180,36 @@ impl Date {
 			None
 		}
 	}
+
+	pub fn today() -> Date {
+		Date::from_naive(Local::today().naive_local())
+	}
+
+	pub fn from_naive(date: NaiveDate) -> Date {
+		Date {
+			day: date.day() as u8,
+			month: date.month() as u8,
+			year: (date.year() % 100) as u8,
+		}
+	}
+
+	pub fn to_naive(&self) -> Option<NaiveDate> {
+		NaiveDate::from_ymd_opt(2000 + self.year as i32, self.month as u32, self.day as u32)
+	}
+
+	pub fn add_days(&self, days: i64) -> Date {
+		match self.to_naive() {
+			Some(date) => Date::from_naive(date + Duration::days(days)),
+			None => *self,
+		}
+	}
+
+	// how long until the next local midnight, for things that run once a day
+	pub fn seconds_until_tomorrow() -> u64 {
+		let now = Local::now().naive_local();
+		let midnight = (now.date() + Duration::days(1)).and_hms(0, 0, 0);
+		(midnight - now).num_seconds().max(1) as u64
+	}
 }
 
 impl ToString for Date {
@@ -181,8 +247,12 @@ impl PartialOrd for Day {
 #[serde_as]
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct TodosDatabase {
+	#[serde(default)]
+	pub archive: Vec<Day>,
 	#[serde_as(as = "Vec<(_, _)>")]
 	pub mapping: BTreeMap<Option<Date>, Day>,
+	#[serde(default)]
+	pub sessions: Vec<TrackingSession>,
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -198,6 +268,21 @@ pub struct Error {
 	pub tag: ErrorTag,
 }
 
+// a problem found while parsing one of the human-readable files. lines and columns start at 1
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+	pub column: usize,
+	pub file: String,
+	pub line: usize,
+	pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}:{}:{}: {}", self.file, self.line, self.column, self.message)
+	}
+}
+
 #[derive(Clone, Debug)]
 pub enum Dirty {
 	None,
@@ -212,8 +297,42 @@ pub struct Resource {
 
 pub type PlannedMealsRemoveLog = Vec<Date>;
 pub type PlannedMealsWriteLog = Vec<PlannedMeal>;
+pub type TodosCompleteLog = Vec<TodoCompletion>;
+pub type TodosTrackingLog = Vec<TrackingEvent>;
 pub type TodosWriteLog = Vec<(Item, Option<Date>)>;
 
+// points at an item by the descriptions leading down to it, which survives other items being added or removed
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TodoCompletion {
+	pub completed: bool,
+	pub date: Option<Date>,
+	pub path: Vec<String>,
+}
+
+// time spent on an item, which is pointed at by its description. times are minutes since midnight on the date the
+// session started, so a session running past midnight ends after 24 * 60. the end is None while it is still running
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TrackingSession {
+	pub date: Date,
+	pub description: String,
+	pub end: Option<u32>,
+	pub start: u32,
+}
+
+// starting a session stops whichever one was running, so only one item is ever tracked at a time
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum TrackingEvent {
+	Start {
+		date: Date,
+		description: String,
+		minute: u32,
+	},
+	Stop {
+		date: Date,
+		minute: u32,
+	},
+}
+
 #[derive(Clone, Debug)]
 pub struct IO {
 	pub count: i32,
@@ -224,6 +343,8 @@ pub struct IO {
 
 	pub planned_meals_remove_log: PlannedMealsRemoveLog,
 	pub planned_meals_write_log: PlannedMealsWriteLog,
+	pub todos_complete_log: TodosCompleteLog,
+	pub todos_tracking_log: TodosTrackingLog,
 	pub todos_write_log: TodosWriteLog,
 }
 
@@ -238,7 +359,9 @@ impl Default for IO {
 			resource: Resource {
 				reference: String::from("todos")
 			},
+			todos_complete_log: Vec::new(),
 			todos_database: TodosDatabase::default(),
+			todos_tracking_log: Vec::new(),
 			todos_write_log: Vec::new(),
 		}
 	}
@@ -250,6 +373,8 @@ pub struct MealsDatabase {
 	pub recipes: Vec<Recipe>,
 	#[serde_as(as = "Vec<(_, _)>")]
 	pub planned_meal_mapping: BTreeMap<Date, PlannedMeal>,
+	#[serde(default)]
+	pub history: Vec<PlannedMeal>, // planned meals whose dates have passed, oldest first
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -307,11 +432,22 @@ pub struct PlannedIngredient {
 	pub ingredient: Ingredient,
 }
 
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum MealStatus {
+	#[default]
+	Planned,
+	Cooked,
+	Skipped,
+	Leftovers(u8), // cooked, and the leftovers cover this many extra days
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PlannedMeal {
 	pub date: Date,
 	pub ingredients: Vec<PlannedIngredient>,
 	pub recipe: Recipe,
+	#[serde(default)]
+	pub status: MealStatus,
 }
 
 impl PlannedMeal {
@@ -327,6 +463,16 @@ impl PlannedMeal {
 				})
 				.collect(),
 			recipe,
+			status: MealStatus::Planned,
+		}
+	}
+
+	// the last day this meal feeds us, including leftovers
+	pub fn last_covered_date(&self) -> Date {
+		if let MealStatus::Leftovers(days) = self.status {
+			self.date.add_days(days as i64)
+		} else {
+			self.date
 		}
 	}
 }
@@ -339,6 +485,8 @@ pub enum WriteDatabase<'a> {
 	Partial {
 		planned_meals_remove_log: &'a PlannedMealsRemoveLog,
 		planned_meals_write_log: &'a PlannedMealsWriteLog,
+		todos_complete_log: &'a TodosCompleteLog,
+		todos_tracking_log: &'a TodosTrackingLog,
 		todos_write_log: &'a TodosWriteLog,
 	},
 }
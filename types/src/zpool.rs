@@ -0,0 +1,307 @@
+use chrono::{ Local, NaiveDateTime, TimeZone };
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{ Serialize, Deserialize };
+
+// one row of the config table in `zpool status`. counts are None if zpool printed something we could not read
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ZPoolDevice {
+	pub checksum_errors: Option<u64>,
+	pub children: Vec<ZPoolDevice>,
+	pub name: String,
+	pub note: Option<String>, // whatever follows the counts, like "(repairing)"
+	pub read_errors: Option<u64>,
+	pub state: String,
+	pub write_errors: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ZPoolScan {
+	pub errors: Option<u64>,
+	#[serde(default)]
+	pub finished_at: Option<i64>, // unix seconds, only for scans that are done
+	pub in_progress: bool,
+	pub kind: String, // scrub or resilver
+	pub percent_done: Option<f64>,
+	pub repaired_bytes: Option<u64>,
+	pub time_remaining: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ZPool {
+	pub devices: Vec<ZPoolDevice>,
+	pub errors: String,
+	pub name: String,
+	pub scan: Option<ZPoolScan>,
+	pub state: String,
+}
+
+lazy_static! {
+	// finished scrubs say "repaired 5.39M", running ones "2.02M repaired"
+	static ref REPAIRED_REGEX: Regex = Regex::new(r"repaired ([0-9.]+[BKMGTPE]?)|([0-9.]+[BKMGTPE]?) repaired").unwrap();
+	static ref ERRORS_REGEX: Regex = Regex::new(r"with ([0-9]+) errors").unwrap();
+	static ref DONE_REGEX: Regex = Regex::new(r"([0-9.]+)% done").unwrap();
+	static ref TO_GO_REGEX: Regex = Regex::new(r"([0-9:]+|[0-9]+ days [0-9:]+) to go").unwrap();
+	static ref FINISHED_REGEX: Regex = Regex::new(r"on ([A-Z][a-z]{2} [A-Z][a-z]{2} +[0-9]+ [0-9:]+ [0-9]{4})").unwrap();
+}
+
+// zpool prints when a scan finished in the machine's local time, like "Wed Jul 13 02:08:51 2022"
+fn parse_finished_at(text: &str) -> Option<i64> {
+	let time = NaiveDateTime::parse_from_str(text, "%a %b %e %H:%M:%S %Y").ok()?;
+	Local.from_local_datetime(&time).earliest().map(|time| time.timestamp())
+}
+
+// zpool rounds sizes and counts to powers of 1024 with a suffix, like 5.39M
+pub fn parse_zpool_number(text: &str) -> Option<u64> {
+	let text = text.trim();
+	let (number, multiplier) = match text.chars().last()? {
+		'B' => (&text[..text.len() - 1], 1),
+		'K' => (&text[..text.len() - 1], 1u64 << 10),
+		'M' => (&text[..text.len() - 1], 1 << 20),
+		'G' => (&text[..text.len() - 1], 1 << 30),
+		'T' => (&text[..text.len() - 1], 1 << 40),
+		'P' => (&text[..text.len() - 1], 1 << 50),
+		'E' => (&text[..text.len() - 1], 1 << 60),
+		_ => (text, 1),
+	};
+
+	if multiplier == 1 {
+		number.parse::<u64>().ok()
+	} else {
+		number.parse::<f64>().ok().map(|number| (number * multiplier as f64) as u64)
+	}
+}
+
+fn parse_scan(text: &str) -> Option<ZPoolScan> {
+	let kind = text.split_whitespace().next()?;
+	if kind != "scrub" && kind != "resilver" {
+		return None; // "none requested"
+	}
+
+	let capture = |regex: &Regex| {
+		regex.captures(text)
+			.and_then(|captures| captures.iter().skip(1).flatten().next().map(|group| group.as_str().to_string()))
+	};
+	Some(ZPoolScan {
+		errors: capture(&ERRORS_REGEX).and_then(|errors| errors.parse().ok()),
+		finished_at: if text.contains("in progress") {
+			None
+		} else {
+			capture(&FINISHED_REGEX).and_then(|finished_at| parse_finished_at(&finished_at))
+		},
+		in_progress: text.contains("in progress"),
+		kind: kind.to_string(),
+		percent_done: capture(&DONE_REGEX).and_then(|percent| percent.parse().ok()),
+		repaired_bytes: capture(&REPAIRED_REGEX).and_then(|repaired| parse_zpool_number(&repaired)),
+		time_remaining: capture(&TO_GO_REGEX),
+	})
+}
+
+fn parse_device(line: &str) -> ZPoolDevice {
+	let fields = line.split_whitespace().collect::<Vec<&str>>();
+	let count = |index: usize| fields.get(index).and_then(|count| parse_zpool_number(count));
+	ZPoolDevice {
+		checksum_errors: count(4),
+		children: Vec::new(),
+		name: fields[0].to_string(),
+		note: if fields.len() > 5 { Some(fields[5..].join(" ")) } else { None },
+		read_errors: count(2),
+		state: fields.get(1).map_or(String::new(), |state| state.to_string()),
+		write_errors: count(3),
+	}
+}
+
+// the table is indented two characters per level below the NAME header. returns the top level devices, which is
+// usually just the pool itself
+fn parse_config(lines: &[&str]) -> Vec<ZPoolDevice> {
+	let mut lines = lines.iter().filter(|line| !line.trim().is_empty());
+	let header = match lines.next() {
+		Some(header) if header.trim_start().starts_with("NAME") => header,
+		_ => return Vec::new(),
+	};
+
+	let base = &header[..header.len() - header.trim_start().len()];
+
+	// devices waiting on their children, along with how deep they are
+	let mut stack: Vec<(usize, ZPoolDevice)> = Vec::new();
+	let mut devices = Vec::new();
+	for line in lines {
+		let rest = line.strip_prefix(base).unwrap_or(line);
+		let depth = (rest.len() - rest.trim_start().len()) / 2;
+		let device = parse_device(rest);
+
+		while stack.last().is_some_and(|(parent_depth, _)| *parent_depth >= depth) {
+			let (_, child) = stack.pop().unwrap();
+			match stack.last_mut() {
+				Some((_, parent)) => parent.children.push(child),
+				None => devices.push(child),
+			}
+		}
+
+		stack.push((depth, device));
+	}
+
+	while let Some((_, child)) = stack.pop() {
+		match stack.last_mut() {
+			Some((_, parent)) => parent.children.push(child),
+			None => devices.push(child),
+		}
+	}
+
+	devices
+}
+
+// parses the whole output of `zpool status`, which has one block per pool
+pub fn parse_zpool_status(output: &str) -> Vec<ZPool> {
+	let mut pools = Vec::new();
+
+	// sections are "key: value" lines, with anything indented under them continuing the value. the config section
+	// keeps its lines as they are since the indentation is the tree
+	let mut sections: Vec<(String, Vec<&str>)> = Vec::new();
+	for line in output.lines() {
+		let key = line.trim_start().split_once(": ").map(|(key, _)| key)
+			.or_else(|| line.trim().strip_suffix(':'));
+
+		let is_section = match key {
+			Some(key) => !key.is_empty()
+				&& key.chars().all(|character| character.is_ascii_lowercase())
+				&& !line.starts_with('\t')
+				&& line.len() - line.trim_start().len() < 8,
+			None => false,
+		};
+
+		if is_section {
+			let key = key.unwrap().to_string();
+			let value = line.trim_start()[key.len() + 1..].trim();
+			if key == "pool" && !sections.is_empty() {
+				pools.push(parse_pool(&sections));
+				sections.clear();
+			}
+
+			sections.push((key, if value.is_empty() { Vec::new() } else { vec![value] }));
+		} else if let Some((_, lines)) = sections.last_mut() {
+			lines.push(line);
+		}
+	}
+
+	if !sections.is_empty() {
+		pools.push(parse_pool(&sections));
+	}
+
+	pools
+}
+
+fn parse_pool(sections: &[(String, Vec<&str>)]) -> ZPool {
+	let get = |key: &str| {
+		sections.iter()
+			.find(|(name, _)| name == key)
+			.map(|(_, lines)| lines.iter().map(|line| line.trim()).collect::<Vec<&str>>().join(" "))
+			.unwrap_or_default()
+	};
+
+	ZPool {
+		devices: sections.iter()
+			.find(|(name, _)| name == "config")
+			.map(|(_, lines)| parse_config(lines))
+			.unwrap_or_default(),
+		errors: get("errors"),
+		name: get("pool"),
+		scan: parse_scan(&get("scan")),
+		state: get("state"),
+	}
+}
+
+impl ZPoolDevice {
+	// drives are named after their model and serial number, and the serial is what is printed on the label
+	pub fn get_serial(&self) -> &str {
+		match self.name.rsplit_once('_') {
+			Some((_, serial)) if !serial.is_empty() => serial,
+			_ => &self.name,
+		}
+	}
+
+	// where smartctl can find the drive. pools built from /dev/disk/by-id name drives after the link, partitions are
+	// read through the drive they are on
+	pub fn get_device_path(&self) -> String {
+		let name = match self.name.rsplit_once("-part") {
+			Some((drive, partition)) if !partition.is_empty() && partition.chars().all(|c| c.is_ascii_digit()) => drive,
+			_ => &self.name,
+		};
+
+		if name.starts_with('/') {
+			name.to_string()
+		} else if name.contains('-') {
+			format!("/dev/disk/by-id/{}", name)
+		} else {
+			format!("/dev/{}", name)
+		}
+	}
+
+	pub fn has_errors(&self) -> bool {
+		[self.read_errors, self.write_errors, self.checksum_errors].iter().any(|count| count.is_some_and(|count| count > 0))
+	}
+
+	pub fn has_unreadable_counts(&self) -> bool {
+		[self.read_errors, self.write_errors, self.checksum_errors].iter().any(|count| count.is_none())
+	}
+
+	// spares sitting around waiting to be used are healthy too
+	pub fn is_healthy(&self) -> bool {
+		matches!(self.state.as_str(), "ONLINE" | "AVAIL" | "INUSE") && !self.has_errors()
+	}
+
+	pub fn is_leaf(&self) -> bool {
+		self.children.is_empty()
+	}
+
+	fn get_all<'a>(&'a self, devices: &mut Vec<&'a ZPoolDevice>) {
+		devices.push(self);
+		for child in self.children.iter() {
+			child.get_all(devices);
+		}
+	}
+
+	fn get_leaves<'a>(&'a self, leaves: &mut Vec<&'a ZPoolDevice>) {
+		if self.is_leaf() {
+			leaves.push(self);
+		}
+
+		for child in self.children.iter() {
+			child.get_leaves(leaves);
+		}
+	}
+}
+
+impl ZPool {
+	// every row in the config table, parents before their children
+	pub fn get_devices(&self) -> Vec<&ZPoolDevice> {
+		let mut devices = Vec::new();
+		for device in self.devices.iter() {
+			device.get_all(&mut devices);
+		}
+
+		devices
+	}
+
+	// the drives themselves, without the pool and vdevs above them
+	pub fn get_drives(&self) -> Vec<&ZPoolDevice> {
+		let mut leaves = Vec::new();
+		for device in self.devices.iter() {
+			for child in device.children.iter() {
+				child.get_leaves(&mut leaves);
+			}
+		}
+
+		leaves
+	}
+
+	pub fn get_failing_drives(&self) -> Vec<&ZPoolDevice> {
+		self.get_drives().into_iter()
+			.filter(|drive| !drive.is_healthy())
+			.collect()
+	}
+
+	pub fn has_data_errors(&self) -> bool {
+		!self.errors.is_empty() && self.errors != "No known data errors"
+	}
+}
@@ -6,7 +6,29 @@ use notify::{ Op, RawEvent, RecursiveMode, Watcher, raw_watcher };
 use tokio::sync::Mutex;
 
 use bansheelong_todo_renderer::{ combine, draw_time_sheet, draw_todo_list };
-use bansheelong_types::{ IO, Resource, WriteDatabase, get_todos_host, get_todos_path, get_todos_port, write_database };
+use bansheelong_types::{
+	CONFLICT_NOTE,
+	Date,
+	Diagnostic,
+	Error,
+	ErrorTag,
+	IO,
+	Resource,
+	WriteDatabase,
+	get_todos_host,
+	get_todos_path,
+	get_todos_port,
+	get_first_sync_base,
+	get_todos_rollover_mode,
+	keep_unwritable_recipes,
+	merge_human_readable,
+	read_database,
+	todos_to_human_readable,
+	write_database,
+};
+
+// copy of the databases from the last time the text files and the server agreed, used as the merge base
+const SYNC_SNAPSHOT: &str = "/home/me/Projects/bansheetodo/.last-sync";
 
 fn reload_feh() {
 	let child = Command::new("feh")
@@ -27,6 +49,132 @@ fn reload_feh() {
 	}
 }
 
+fn redraw(io: &IO) {
+	draw_todo_list(io, String::from("/home/me/Projects/bansheelong/todo-list.png"));
+	draw_time_sheet(io, String::from("/home/me/Projects/bansheelong/time-sheet.png"));
+	combine(
+		String::from("/home/me/.config/background2.png"),
+		String::from("/home/me/Projects/bansheelong/todo-list.png"),
+		String::from("/home/me/Projects/bansheelong/time-sheet.png"),
+		String::from("/home/me/.config/real-background.png"),
+	);
+	reload_feh();
+}
+
+fn print_diagnostics(diagnostics: &[Diagnostic]) {
+	if diagnostics.is_empty() {
+		return;
+	}
+
+	eprintln!("found {} problem(s) in the todo/recipe lists:", diagnostics.len());
+	for diagnostic in diagnostics.iter() {
+		eprintln!("  {}", diagnostic);
+	}
+}
+
+// only touch the file if its contents changed, otherwise we would wake up our own watcher forever. returns
+// whether we wrote anything
+fn write_if_changed(file_name: &str, contents: &str) -> Result<bool, Error> {
+	if std::fs::read_to_string(file_name).ok().as_deref() == Some(contents) {
+		return Ok(false);
+	}
+
+	if let Err(error) = std::fs::write(file_name, contents) {
+		return Err(Error {
+			message: format!("{:?}", error),
+			..Error::default()
+		});
+	}
+
+	Ok(true)
+}
+
+// three-way merges the text files with the server, then writes the result back to both. rolls unfinished todos
+// from past days over to today if asked to
+async fn sync(io: &mut IO, todo_list: &str, recipe_list: &str, rollover: bool) -> Result<(), Error> {
+	// the text files get rewritten from what we parsed, so any line we could not read (including conflict notes from
+	// the last sync) would be lost. leave everything alone until they are fixed
+	let diagnostics = io.parse_from_human_readable(String::from(todo_list), String::from(recipe_list))?;
+	if !diagnostics.is_empty() {
+		print_diagnostics(&diagnostics);
+		return Err(Error {
+			message: String::from("not syncing until the problems in the todo/recipe lists are fixed"),
+			..Error::default()
+		});
+	}
+
+	let local = (io.todos_database.clone(), io.meals_database.clone());
+
+	let remote = read_database(io.resource.clone()).await?;
+
+	let snapshot = Resource {
+		reference: String::from(SYNC_SNAPSHOT),
+	};
+
+	let base = match read_database(snapshot.clone()).await {
+		Ok(base) => base,
+		// first sync, so keep everything from both sides
+		Err(error) if error.tag == ErrorTag::CouldNotFindFile => get_first_sync_base(
+			(&local.0, &local.1),
+			(&remote.0, &remote.1)
+		),
+		Err(error) => return Err(error),
+	};
+
+	let result = merge_human_readable(
+		(&base.0, &base.1),
+		(&local.0, &local.1),
+		(&remote.0, &remote.1)
+	);
+
+	if result.conflicts > 0 {
+		eprintln!("{} sync conflict(s), see the '{}' lines", result.conflicts, CONFLICT_NOTE);
+	}
+
+	let changed = write_if_changed(todo_list, &result.todo_list)? | write_if_changed(recipe_list, &result.recipe_list)?;
+
+	// the merge may have added conflict notes, so only complain again if it did
+	let diagnostics = io.parse_from_human_readable(String::from(todo_list), String::from(recipe_list))?;
+	if changed {
+		print_diagnostics(&diagnostics);
+	}
+
+	// the text files do not know about planned meals, archives, tracked time or calendar uids, so keep whatever the
+	// server has
+	io.todos_database.keep_uids_from(&remote.0);
+	keep_unwritable_recipes(&mut io.meals_database, &remote.1);
+	io.meals_database.planned_meal_mapping = remote.1.planned_meal_mapping;
+	io.meals_database.history = remote.1.history;
+	io.todos_database.archive = remote.0.archive;
+	io.todos_database.sessions = remote.0.sessions;
+	io.todos_write_log.clear();
+
+	if rollover {
+		let count = io.rollover_todos(Date::today(), get_todos_rollover_mode());
+		if count > 0 {
+			println!("Rolled over {} todos", count);
+		}
+
+		write_if_changed(todo_list, &todos_to_human_readable(&io.todos_database))?;
+	}
+
+	write_database(
+		WriteDatabase::Full {
+			meals: &io.meals_database,
+			todos: &io.todos_database,
+		},
+		io.resource.clone()
+	).await?;
+
+	write_database(
+		WriteDatabase::Full {
+			meals: &io.meals_database,
+			todos: &io.todos_database,
+		},
+		snapshot
+	).await
+}
+
 #[tokio::main]
 async fn main() {
 	let todo_list = "/home/me/Projects/bansheetodo/todo-list";
@@ -44,21 +192,11 @@ async fn main() {
 	}));
 
 	let mut locked = io.lock().await;
-	if let Err(error)
-		= locked.parse_from_human_readable(String::from(todo_list), String::from(recipe_list))
-	{
+	if let Err(error) = sync(&mut locked, todo_list, recipe_list, true).await {
 		eprintln!("{:?}", error);
 	}
 
-	draw_todo_list(&locked, String::from("/home/me/Projects/bansheelong/todo-list.png"));
-	draw_time_sheet(&locked, String::from("/home/me/Projects/bansheelong/time-sheet.png"));
-	combine(
-		String::from("/home/me/.config/background2.png"),
-		String::from("/home/me/Projects/bansheelong/todo-list.png"),
-		String::from("/home/me/Projects/bansheelong/time-sheet.png"),
-		String::from("/home/me/.config/real-background.png"),
-	);
-	reload_feh();
+	redraw(&locked);
 
 	drop(locked);
 
@@ -68,16 +206,13 @@ async fn main() {
 			loop {
 				tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
 
-				let locked = io.lock().await;
-				draw_todo_list(&locked, String::from("/home/me/Projects/bansheelong/todo-list.png"));
-				draw_time_sheet(&locked, String::from("/home/me/Projects/bansheelong/time-sheet.png"));
-				combine(
-					String::from("/home/me/.config/background2.png"),
-					String::from("/home/me/Projects/bansheelong/todo-list.png"),
-					String::from("/home/me/Projects/bansheelong/time-sheet.png"),
-					String::from("/home/me/.config/real-background.png"),
-				);
-				reload_feh();
+				// pull in changes made from the other clients
+				let mut locked = io.lock().await;
+				if let Err(error) = sync(&mut locked, todo_list, recipe_list, false).await {
+					eprintln!("{:?}", error);
+				}
+
+				redraw(&locked);
 			}
 		},
 		async {
@@ -89,32 +224,11 @@ async fn main() {
 						if (path.to_str() == Some(todo_list) || path.to_str() == Some(recipe_list)) && op == Op::CLOSE_WRITE {
 							let mut locked = io.lock().await;
 
-							if let Err(error)
-								= locked.parse_from_human_readable(String::from(todo_list), String::from(recipe_list))
-							{
+							if let Err(error) = sync(&mut locked, todo_list, recipe_list, false).await {
 								eprintln!("{:?}", error);
-								continue;
 							}
 
-							draw_todo_list(&locked, String::from("/home/me/Projects/bansheelong/todo-list.png"));
-							draw_time_sheet(&locked, String::from("/home/me/Projects/bansheelong/time-sheet.png"));
-							combine(
-								String::from("/home/me/.config/background2.png"),
-								String::from("/home/me/Projects/bansheelong/todo-list.png"),
-								String::from("/home/me/Projects/bansheelong/time-sheet.png"),
-								String::from("/home/me/.config/real-background.png"),
-							);
-							reload_feh();
-
-							if let Err(error) = write_database(
-								WriteDatabase::Full {
-									meals: &locked.meals_database,
-									todos: &locked.todos_database,
-								},
-								locked.resource.clone()
-							).await {
-								eprintln!("{:?}", error);
-							}
+							redraw(&locked);
 						}
 
 						found = true;